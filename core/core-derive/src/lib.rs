@@ -5,7 +5,10 @@ use syn::{parse_macro_input, DeriveInput};
 
 #[proc_macro_derive(OperationLog)]
 pub fn derive(input: TokenStream) -> TokenStream {
-    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+    let DeriveInput { ident, data, attrs, .. } = parse_macro_input!(input);
+
+    let table = find_table_name(&attrs)
+        .expect("operation log structs must have a diesel table_name attribute pointing to the log table");
 
     let atom = match data {
         syn::Data::Struct(st) => {
@@ -55,19 +58,181 @@ pub fn derive(input: TokenStream) -> TokenStream {
         }
     };
 
+    let table_name = table
+        .segments
+        .last()
+        .expect("Failed to get operation log table name")
+        .ident
+        .to_string();
+
+    let operation_log_table = quote! {
+        impl crate::models::logs::OperationLogTable for #ident {
+            type Atom = #atom_type;
+
+            fn table_name() -> &'static str {
+                #table_name
+            }
+
+            fn parent_id(&self) -> &::bigdecimal::BigDecimal {
+                &self.parent_id
+            }
+
+            fn dataset_version_id(&self) -> &::uuid::Uuid {
+                &self.dataset_version_id
+            }
+
+            fn load_all(conn: &mut ::diesel::pg::PgConnection) -> ::diesel::QueryResult<Vec<Self>> {
+                use ::diesel::prelude::*;
+                #table::table
+                    .order(#table::operation_id.asc())
+                    .select(Self::as_select())
+                    .load::<Self>(conn)
+            }
+
+            fn load_entity(conn: &mut ::diesel::pg::PgConnection, entity_id: &str) -> ::diesel::QueryResult<Vec<Self>> {
+                use ::diesel::prelude::*;
+                #table::table
+                    .filter(#table::entity_id.eq(entity_id))
+                    .order(#table::operation_id.asc())
+                    .select(Self::as_select())
+                    .load::<Self>(conn)
+            }
+
+            fn load_entities(
+                conn: &mut ::diesel::pg::PgConnection,
+                entity_ids: &[String],
+            ) -> ::diesel::QueryResult<Vec<Self>> {
+                use ::diesel::prelude::*;
+                #table::table
+                    .filter(#table::entity_id.eq_any(entity_ids))
+                    .order(#table::operation_id.asc())
+                    .select(Self::as_select())
+                    .load::<Self>(conn)
+            }
+
+            fn entities_until(
+                conn: &mut ::diesel::pg::PgConnection,
+                watermark: &::bigdecimal::BigDecimal,
+                after: &str,
+                limit: i64,
+            ) -> ::diesel::QueryResult<Vec<String>> {
+                use ::diesel::prelude::*;
+                #table::table
+                    .filter(#table::operation_id.le(watermark))
+                    .filter(#table::entity_id.gt(after))
+                    .select(#table::entity_id)
+                    .distinct()
+                    .order(#table::entity_id.asc())
+                    .limit(limit)
+                    .load::<String>(conn)
+            }
+
+            fn load_after(
+                conn: &mut ::diesel::pg::PgConnection,
+                watermark: &::bigdecimal::BigDecimal,
+            ) -> ::diesel::QueryResult<Vec<Self>> {
+                use ::diesel::prelude::*;
+                #table::table
+                    .filter(#table::operation_id.gt(watermark))
+                    .order(#table::operation_id.asc())
+                    .select(Self::as_select())
+                    .load::<Self>(conn)
+            }
+
+            fn delete_all(
+                conn: &mut ::diesel::pg::PgConnection,
+                operation_ids: &[::bigdecimal::BigDecimal],
+                reason: crate::models::logs::DeletionReason,
+            ) -> ::diesel::QueryResult<usize> {
+                use ::diesel::prelude::*;
+                let deleted = ::diesel::delete(#table::table.filter(#table::operation_id.eq_any(operation_ids)))
+                    .returning((#table::operation_id, #table::entity_id))
+                    .get_results::<(::bigdecimal::BigDecimal, String)>(conn)?;
+                crate::models::logs::record_deletions(conn, #table_name, reason, deleted)
+            }
+
+            fn delete_dataset_version(
+                conn: &mut ::diesel::pg::PgConnection,
+                dataset_version_id: &::uuid::Uuid,
+                reason: crate::models::logs::DeletionReason,
+            ) -> ::diesel::QueryResult<usize> {
+                use ::diesel::prelude::*;
+                let deleted = ::diesel::delete(#table::table.filter(#table::dataset_version_id.eq(dataset_version_id)))
+                    .returning((#table::operation_id, #table::entity_id))
+                    .get_results::<(::bigdecimal::BigDecimal, String)>(conn)?;
+                crate::models::logs::record_deletions(conn, #table_name, reason, deleted)
+            }
+
+            fn existing_entities(
+                conn: &mut ::diesel::pg::PgConnection,
+                entity_ids: &[String],
+            ) -> ::diesel::QueryResult<Vec<String>> {
+                use ::diesel::prelude::*;
+                #table::table
+                    .filter(#table::entity_id.eq_any(entity_ids))
+                    .select(#table::entity_id)
+                    .distinct()
+                    .load::<String>(conn)
+            }
+
+            fn insert_all(conn: &mut ::diesel::pg::PgConnection, operations: &[Self]) -> ::diesel::QueryResult<usize> {
+                use ::diesel::prelude::*;
+                ::diesel::insert_into(#table::table)
+                    .values(operations)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+            }
+        }
+    };
+
     quote! {
         #from_data_frame_operation
         #log_operation
+        #operation_log_table
     }
     .into()
 }
 
+/// Find the path in the `#[diesel(table_name = ...)]` attribute
+fn find_table_name(attrs: &[syn::Attribute]) -> Option<syn::Path> {
+    let mut table = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("diesel")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table_name") {
+                table = Some(meta.value()?.parse::<syn::Path>()?);
+            }
+            else {
+                // skip over the other diesel options like belongs_to(..)
+                while !meta.input.is_empty() && !meta.input.peek(syn::Token![,]) {
+                    meta.input.parse::<proc_macro2::TokenTree>()?;
+                }
+            }
+            Ok(())
+        })
+        .expect("Failed to parse diesel attribute");
+    }
+
+    table
+}
+
 
 #[proc_macro_derive(Atom)]
 pub fn derive_atom(input: TokenStream) -> TokenStream {
-    let DeriveInput { ident, .. } = parse_macro_input!(input);
+    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+
+    let variants: Vec<String> = match data {
+        syn::Data::Enum(en) => en.variants.iter().map(|variant| variant.ident.to_string()).collect(),
+        _ => panic!("Only enums are supported as operation log atoms"),
+    };
 
     quote! {
+        impl crate::models::logs::AtomVariants for #ident {
+            fn variant_names() -> &'static [&'static str] {
+                &[#(#variants),*]
+            }
+        }
+
         impl FromSql<Jsonb, Pg> for #ident {
             fn from_sql(value: <Pg as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
                 serde_json::from_value(FromSql::<Jsonb, Pg>::from_sql(value)?).map_err(|e| e.into())
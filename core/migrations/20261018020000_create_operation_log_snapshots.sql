@@ -0,0 +1,28 @@
+-- Create "operation_log_snapshots" table
+CREATE TABLE "public"."operation_log_snapshots" (
+ "log_table" character varying NOT NULL,
+ "entity_id" character varying NOT NULL,
+ "watermark" numeric NOT NULL,
+ "atoms" jsonb NOT NULL DEFAULT '[]',
+ "created_at" timestamptz NOT NULL DEFAULT now(),
+ PRIMARY KEY ("log_table", "entity_id", "watermark")
+);
+-- Create index "operation_log_snapshots_watermark" to table: "operation_log_snapshots"
+CREATE INDEX "operation_log_snapshots_watermark" ON "public"."operation_log_snapshots" ("log_table", "watermark");
+-- Create "operation_log_archives" table
+CREATE TABLE "public"."operation_log_archives" (
+ "log_table" character varying NOT NULL,
+ "operation_id" numeric NOT NULL,
+ "parent_id" numeric NOT NULL,
+ "entity_id" character varying NOT NULL,
+ "dataset_version_id" uuid NOT NULL,
+ "action" "public"."operation_action" NOT NULL,
+ "atom" jsonb NOT NULL DEFAULT '{}',
+ "archived_at" timestamptz NOT NULL DEFAULT now(),
+ PRIMARY KEY ("log_table", "operation_id"),
+ CONSTRAINT "operation_log_archives_dataset_version_id_fkey" FOREIGN KEY ("dataset_version_id") REFERENCES "public"."dataset_versions" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- Create index "operation_log_archives_dataset_version_id" to table: "operation_log_archives"
+CREATE INDEX "operation_log_archives_dataset_version_id" ON "public"."operation_log_archives" ("dataset_version_id");
+-- Create index "operation_log_archives_entity_id" to table: "operation_log_archives"
+CREATE INDEX "operation_log_archives_entity_id" ON "public"."operation_log_archives" ("log_table", "entity_id");
@@ -0,0 +1,27 @@
+-- Create "treatments" table
+CREATE TABLE "public"."treatments" (
+ "entity_id" character varying NOT NULL,
+ "dataset_version_id" uuid NOT NULL,
+ "publication_entity_id" character varying NOT NULL,
+ "treatment_uri" character varying NOT NULL,
+ "lsid" character varying NULL,
+ "scientific_name" character varying NULL,
+ "canonical_name" character varying NULL,
+ PRIMARY KEY ("entity_id"),
+ CONSTRAINT "treatments_dataset_version_id_fkey" FOREIGN KEY ("dataset_version_id") REFERENCES "public"."dataset_versions" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- Create index "treatments_canonical_name" to table: "treatments"
+CREATE INDEX "treatments_canonical_name" ON "public"."treatments" ("canonical_name");
+-- Create index "treatments_publication_entity_id" to table: "treatments"
+CREATE INDEX "treatments_publication_entity_id" ON "public"."treatments" ("publication_entity_id");
+-- Create "treatment_sections" table
+CREATE TABLE "public"."treatment_sections" (
+ "treatment_entity_id" character varying NOT NULL,
+ "position" integer NOT NULL,
+ "section_type" character varying NOT NULL,
+ "text" text NOT NULL,
+ PRIMARY KEY ("treatment_entity_id", "position"),
+ CONSTRAINT "treatment_sections_treatment_entity_id_fkey" FOREIGN KEY ("treatment_entity_id") REFERENCES "public"."treatments" ("entity_id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- Create index "treatment_sections_text" to table: "treatment_sections"
+CREATE INDEX "treatment_sections_text" ON "public"."treatment_sections" USING gin ((to_tsvector('english'::regconfig, text)));
@@ -0,0 +1,28 @@
+-- Create enum type "location_generalisation"
+CREATE TYPE "public"."location_generalisation" AS ENUM ('tenth_degree', 'one_degree', 'withhold');
+-- Modify "collection_events" table
+ALTER TABLE "public"."collection_events" ADD COLUMN "location_generalisation" "public"."location_generalisation" NULL;
+-- Create "sensitive_data_rules" table
+CREATE TABLE "public"."sensitive_data_rules" (
+ "id" uuid NOT NULL,
+ "name_id" uuid NULL,
+ "dataset_id" uuid NULL,
+ "generalisation" "public"."location_generalisation" NOT NULL,
+ "reason" character varying NULL,
+ "created_at" timestamptz NOT NULL DEFAULT now(),
+ PRIMARY KEY ("id"),
+ CONSTRAINT "sensitive_data_rules_dataset_id_fkey" FOREIGN KEY ("dataset_id") REFERENCES "public"."datasets" ("id") ON UPDATE NO ACTION ON DELETE CASCADE,
+ CONSTRAINT "sensitive_data_rules_name_id_fkey" FOREIGN KEY ("name_id") REFERENCES "public"."names" ("id") ON UPDATE NO ACTION ON DELETE CASCADE,
+ CONSTRAINT "sensitive_data_rules_target" CHECK ((name_id IS NOT NULL) OR (dataset_id IS NOT NULL))
+);
+-- Create index "sensitive_data_rules_name_id" to table: "sensitive_data_rules"
+CREATE INDEX "sensitive_data_rules_name_id" ON "public"."sensitive_data_rules" ("name_id");
+-- Create index "sensitive_data_rules_dataset_id" to table: "sensitive_data_rules"
+CREATE INDEX "sensitive_data_rules_dataset_id" ON "public"."sensitive_data_rules" ("dataset_id");
+-- Create "sensitive_locations" table
+CREATE TABLE "public"."sensitive_locations" (
+ "entity_id" character varying NOT NULL,
+ "latitude" double precision NULL,
+ "longitude" double precision NULL,
+ PRIMARY KEY ("entity_id")
+);
@@ -0,0 +1,21 @@
+-- Create "sequence_traces" table
+CREATE TABLE "public"."sequence_traces" (
+ "trace_id" character varying NOT NULL,
+ "process_id" character varying NOT NULL,
+ "sequence_run_id" character varying NULL,
+ "trace_name" character varying NOT NULL,
+ "trace_link" character varying NOT NULL,
+ "a_analyzed" smallint[] NULL,
+ "c_analyzed" smallint[] NULL,
+ "g_analyzed" smallint[] NULL,
+ "t_analyzed" smallint[] NULL,
+ "base_calls" character varying NULL,
+ "quality_scores" smallint[] NULL,
+ "peak_locations" smallint[] NULL,
+ PRIMARY KEY ("trace_id"),
+ CONSTRAINT "sequence_traces_sequence_run_id_fkey" FOREIGN KEY ("sequence_run_id") REFERENCES "public"."sequence_runs" ("entity_id") ON UPDATE NO ACTION ON DELETE SET NULL
+);
+-- Create index "sequence_traces_process_id" to table: "sequence_traces"
+CREATE INDEX "sequence_traces_process_id" ON "public"."sequence_traces" ("process_id");
+-- Create index "sequence_traces_sequence_run_id" to table: "sequence_traces"
+CREATE INDEX "sequence_traces_sequence_run_id" ON "public"."sequence_traces" ("sequence_run_id");
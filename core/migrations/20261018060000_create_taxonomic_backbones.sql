@@ -0,0 +1,21 @@
+-- Create "taxonomic_backbones" table
+CREATE TABLE "public"."taxonomic_backbones" (
+ "dataset_id" uuid NOT NULL,
+ "is_default" boolean NOT NULL DEFAULT false,
+ "created_at" timestamptz NOT NULL DEFAULT now(),
+ PRIMARY KEY ("dataset_id"),
+ CONSTRAINT "taxonomic_backbones_dataset_id_fkey" FOREIGN KEY ("dataset_id") REFERENCES "public"."datasets" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- Create index "taxonomic_backbones_default" to table: "taxonomic_backbones"
+CREATE UNIQUE INDEX "taxonomic_backbones_default" ON "public"."taxonomic_backbones" ("is_default") WHERE is_default;
+-- Create "source_backbones" table
+CREATE TABLE "public"."source_backbones" (
+ "source_id" uuid NOT NULL,
+ "backbone_id" uuid NOT NULL,
+ PRIMARY KEY ("source_id"),
+ CONSTRAINT "source_backbones_backbone_id_fkey" FOREIGN KEY ("backbone_id") REFERENCES "public"."taxonomic_backbones" ("dataset_id") ON UPDATE NO ACTION ON DELETE CASCADE,
+ CONSTRAINT "source_backbones_source_id_fkey" FOREIGN KEY ("source_id") REFERENCES "public"."sources" ("id") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+-- Register the ALA taxonomy as the default backbone
+INSERT INTO "public"."taxonomic_backbones" ("dataset_id", "is_default")
+SELECT "id", true FROM "public"."datasets" WHERE "global_id" = 'ARGA:TL:0001013';
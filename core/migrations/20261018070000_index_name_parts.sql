@@ -0,0 +1,4 @@
+-- Create index "names_genus" to table: "names"
+CREATE INDEX "names_genus" ON "public"."names" ((lower(split_part(canonical_name, ' ', 1))));
+-- Create index "names_epithet" to table: "names"
+CREATE INDEX "names_epithet" ON "public"."names" ((split_part(canonical_name, ' ', 2)));
@@ -0,0 +1,95 @@
+-- Add an insertion sequence to every log table and record deleted operations for the replication feed
+CREATE SEQUENCE operation_log_sequence;
+
+ALTER TABLE accession_event_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE agent_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE annotation_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE assembly_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE collection_event_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE data_product_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE deposition_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE extraction_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE library_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE nomenclatural_act_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE organism_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE project_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE publication_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE sequence_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE sequence_run_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE specimen_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE subsample_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE taxa_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE taxonomic_act_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+ALTER TABLE tissue_logs
+    ADD COLUMN log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    ADD COLUMN logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL;
+
+CREATE INDEX accession_event_logs_log_sequence ON accession_event_logs (log_sequence);
+CREATE INDEX agent_logs_log_sequence ON agent_logs (log_sequence);
+CREATE INDEX annotation_logs_log_sequence ON annotation_logs (log_sequence);
+CREATE INDEX assembly_logs_log_sequence ON assembly_logs (log_sequence);
+CREATE INDEX collection_event_logs_log_sequence ON collection_event_logs (log_sequence);
+CREATE INDEX data_product_logs_log_sequence ON data_product_logs (log_sequence);
+CREATE INDEX deposition_logs_log_sequence ON deposition_logs (log_sequence);
+CREATE INDEX extraction_logs_log_sequence ON extraction_logs (log_sequence);
+CREATE INDEX library_logs_log_sequence ON library_logs (log_sequence);
+CREATE INDEX nomenclatural_act_logs_log_sequence ON nomenclatural_act_logs (log_sequence);
+CREATE INDEX organism_logs_log_sequence ON organism_logs (log_sequence);
+CREATE INDEX project_logs_log_sequence ON project_logs (log_sequence);
+CREATE INDEX publication_logs_log_sequence ON publication_logs (log_sequence);
+CREATE INDEX sequence_logs_log_sequence ON sequence_logs (log_sequence);
+CREATE INDEX sequence_run_logs_log_sequence ON sequence_run_logs (log_sequence);
+CREATE INDEX specimen_logs_log_sequence ON specimen_logs (log_sequence);
+CREATE INDEX subsample_logs_log_sequence ON subsample_logs (log_sequence);
+CREATE INDEX taxa_logs_log_sequence ON taxa_logs (log_sequence);
+CREATE INDEX taxonomic_act_logs_log_sequence ON taxonomic_act_logs (log_sequence);
+CREATE INDEX tissue_logs_log_sequence ON tissue_logs (log_sequence);
+
+-- Operations deleted from a log table by a compaction or a dataset version rollback. The
+-- replication feed includes these so that replicas can remove the same operations.
+CREATE TABLE operation_log_deletions (
+    log_sequence bigint PRIMARY KEY DEFAULT nextval('operation_log_sequence') NOT NULL,
+    log_table varchar NOT NULL,
+    operation_id numeric NOT NULL,
+    entity_id varchar NOT NULL,
+    reason varchar NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Add the serial details of a publication
+ALTER TABLE publications
+    ADD COLUMN journal varchar,
+    ADD COLUMN volume varchar,
+    ADD COLUMN issue varchar,
+    ADD COLUMN pages varchar;
@@ -194,6 +194,13 @@ CREATE TYPE source_content_type as ENUM (
     'ethnobiology'
 );
 
+-- how precisely the location of a sensitive collection can be published
+CREATE TYPE location_generalisation AS ENUM (
+    'tenth_degree',
+    'one_degree',
+    'withhold'
+);
+
 CREATE TYPE publication_type AS ENUM (
   'book',
   'book_chapter',
@@ -271,6 +278,24 @@ CREATE TABLE dataset_versions (
 CREATE UNIQUE INDEX dataset_version_dataset_id_created_at ON dataset_versions (dataset_id, created_at);
 
 
+-- The taxonomy datasets that can be used to classify names. Queries that aggregate
+-- up a hierarchy use the requested backbone, the default of the source being queried,
+-- or the single default backbone in that order
+CREATE TABLE taxonomic_backbones (
+    dataset_id uuid PRIMARY KEY REFERENCES datasets ON DELETE CASCADE,
+    is_default boolean NOT NULL DEFAULT false,
+    created_at timestamp with time zone NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX taxonomic_backbones_default ON taxonomic_backbones (is_default) WHERE is_default;
+
+-- The backbone used by default when querying the datasets of a source
+CREATE TABLE source_backbones (
+    source_id uuid PRIMARY KEY REFERENCES sources ON DELETE CASCADE,
+    backbone_id uuid NOT NULL REFERENCES taxonomic_backbones ON DELETE CASCADE
+);
+
+
 -- The central names table. Most tables link to this
 CREATE TABLE names (
     id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
@@ -283,6 +308,9 @@ COMMENT ON TABLE names IS 'All taxa names. Unique names used to associate attrib
 
 CREATE UNIQUE INDEX names_scientific_name ON names (scientific_name);
 CREATE INDEX names_canonical_name ON names (canonical_name);
+-- the genus and first epithet used to find candidates when matching names
+CREATE INDEX names_genus ON names ((lower(split_part(canonical_name, ' ', 1))));
+CREATE INDEX names_epithet ON names ((split_part(canonical_name, ' ', 2)));
 
 
 -- A publication for any record
@@ -306,7 +334,13 @@ CREATE TABLE publications (
     record_updated_at timestamp with time zone,
 
     created_at timestamp with time zone NOT NULL,
-    updated_at timestamp with time zone NOT NULL
+    updated_at timestamp with time zone NOT NULL,
+
+    -- where the publication was published when it's part of a serial
+    journal varchar,
+    volume varchar,
+    issue varchar,
+    pages varchar
 );
 
 -- each entity is a globally unique publication so we ensure that there is only one
@@ -314,6 +348,34 @@ CREATE TABLE publications (
 CREATE UNIQUE INDEX publications_entity_id ON publications (entity_id);
 
 
+-- Taxonomic treatments from the literature. A treatment is the part of a publication that
+-- describes a taxon and is linked to the publication and name via their entity ids.
+CREATE TABLE treatments (
+    entity_id varchar PRIMARY KEY NOT NULL,
+    dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
+    publication_entity_id varchar NOT NULL,
+    treatment_uri varchar NOT NULL,
+    lsid varchar,
+    scientific_name varchar,
+    canonical_name varchar
+);
+
+CREATE INDEX treatments_publication_entity_id ON treatments (publication_entity_id);
+CREATE INDEX treatments_canonical_name ON treatments (canonical_name);
+
+-- The text of every section in a treatment such as the description or materials examined.
+-- The text is indexed for full text search so that treatments can be found by their content.
+CREATE TABLE treatment_sections (
+    treatment_entity_id varchar REFERENCES treatments ON DELETE CASCADE NOT NULL,
+    position int NOT NULL,
+    section_type varchar NOT NULL,
+    text text NOT NULL,
+    PRIMARY KEY (treatment_entity_id, position)
+);
+
+CREATE INDEX treatment_sections_text ON treatment_sections USING gin (to_tsvector('english', text));
+
+
 -- Agent data. An agent is a person that is referenced in various tables. We want to associate data with
 -- and agent via the full name or some other identifier and for that we use our entity hash approach so that
 -- lookups aren't necessary when importing.
@@ -535,6 +597,30 @@ CREATE TABLE sequence_runs (
     sra_run_accession varchar
 );
 
+-- Sanger chromatograms from BOLD. Runs are linked by the BOLD process id and the channels
+-- are stored in ACGT order regardless of the order in the trace file
+CREATE TABLE sequence_traces (
+    trace_id varchar PRIMARY KEY NOT NULL,
+    process_id varchar NOT NULL,
+    sequence_run_id varchar REFERENCES sequence_runs ON DELETE SET NULL,
+    trace_name varchar NOT NULL,
+    trace_link varchar NOT NULL,
+
+    a_analyzed smallint[],
+    c_analyzed smallint[],
+    g_analyzed smallint[],
+    t_analyzed smallint[],
+
+    base_calls varchar,
+    -- phred quality score of each base call
+    quality_scores smallint[],
+    -- the scan number of the peak for each base call
+    peak_locations smallint[]
+);
+
+CREATE INDEX sequence_traces_process_id ON sequence_traces (process_id);
+CREATE INDEX sequence_traces_sequence_run_id ON sequence_traces (sequence_run_id);
+
 
 -- Assembly data
 CREATE TABLE assemblies (
@@ -947,7 +1033,10 @@ CREATE TABLE collection_events (
 
     strain varchar,
     isolate varchar,
-    field_notes varchar
+    field_notes varchar,
+
+    -- set when the latitude and longitude have been generalised by a sensitive data rule
+    location_generalisation location_generalisation
 );
 
 CREATE INDEX collection_events_specimen_id ON collection_events (specimen_id);
@@ -956,6 +1045,32 @@ CREATE INDEX collection_events_organism_id ON collection_events (organism_id);
 CREATE INDEX collection_events_field_collecting_id ON collection_events (field_collecting_id);
 
 
+-- Rules for generalising the location of sensitive species or datasets. A collection event
+-- matching multiple rules uses the most restrictive generalisation
+CREATE TABLE sensitive_data_rules (
+    id uuid PRIMARY KEY NOT NULL,
+    name_id uuid REFERENCES names ON DELETE CASCADE,
+    dataset_id uuid REFERENCES datasets ON DELETE CASCADE,
+    generalisation location_generalisation NOT NULL,
+    reason varchar,
+    created_at timestamp with time zone NOT NULL DEFAULT now(),
+
+    CONSTRAINT sensitive_data_rules_target CHECK (name_id IS NOT NULL OR dataset_id IS NOT NULL)
+);
+
+CREATE INDEX sensitive_data_rules_name_id ON sensitive_data_rules (name_id);
+CREATE INDEX sensitive_data_rules_dataset_id ON sensitive_data_rules (dataset_id);
+
+-- The precise coordinates of generalised collection events. These must only ever be
+-- available to administrators. They are saved when reducing the collection event logs,
+-- before the events are loaded, so the entity id isn't a foreign key
+CREATE TABLE sensitive_locations (
+    entity_id varchar PRIMARY KEY NOT NULL,
+    latitude float,
+    longitude float
+);
+
+
 -- Accession events
 CREATE TABLE accession_events (
     entity_id varchar PRIMARY KEY NOT NULL,
@@ -1122,18 +1237,30 @@ CREATE INDEX deposition_events_sequence_id ON deposition_events (sequence_id);
 -- Operation logs
 ---------------------------
 
+-- The order operations were inserted into or deleted from any log table. Operation ids are
+-- hybrid timestamps which can be older than existing operations when an importer uses the
+-- timestamps of the source, so replicas follow this sequence instead to not miss any.
+-- The logged_at column records the time the sequence value was taken, not the start of the
+-- transaction, which lets the replication feed hold back rows that a transaction still in
+-- progress could commit a lower sequence value before.
+CREATE SEQUENCE operation_log_sequence;
+
+
 CREATE TABLE nomenclatural_act_logs (
     operation_id numeric PRIMARY KEY NOT NULL,
     parent_id numeric NOT NULL,
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX nomenclatural_act_logs_parent_id ON nomenclatural_act_logs (parent_id);
 CREATE INDEX nomenclatural_act_logs_entity_id ON nomenclatural_act_logs (entity_id);
 CREATE INDEX nomenclatural_act_logs_dataset_version_id ON nomenclatural_act_logs (dataset_version_id);
+CREATE INDEX nomenclatural_act_logs_log_sequence ON nomenclatural_act_logs (log_sequence);
 
 
 CREATE TABLE organism_logs (
@@ -1142,12 +1269,15 @@ CREATE TABLE organism_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX organism_logs_parent_id ON organism_logs (parent_id);
 CREATE INDEX organism_logs_entity_id ON organism_logs (entity_id);
 CREATE INDEX organism_logs_dataset_version_id ON organism_logs (dataset_version_id);
+CREATE INDEX organism_logs_log_sequence ON organism_logs (log_sequence);
 
 
 CREATE TABLE specimen_logs (
@@ -1156,12 +1286,15 @@ CREATE TABLE specimen_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX specimen_logs_parent_id ON specimen_logs (parent_id);
 CREATE INDEX specimen_logs_entity_id ON specimen_logs (entity_id);
 CREATE INDEX specimen_logs_dataset_version_id ON specimen_logs (dataset_version_id);
+CREATE INDEX specimen_logs_log_sequence ON specimen_logs (log_sequence);
 
 
 CREATE TABLE tissue_logs (
@@ -1170,12 +1303,15 @@ CREATE TABLE tissue_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX tissue_logs_parent_id ON tissue_logs (parent_id);
 CREATE INDEX tissue_logs_entity_id ON tissue_logs (entity_id);
 CREATE INDEX tissue_logs_dataset_version_id ON tissue_logs (dataset_version_id);
+CREATE INDEX tissue_logs_log_sequence ON tissue_logs (log_sequence);
 
 
 CREATE TABLE library_logs (
@@ -1184,12 +1320,15 @@ CREATE TABLE library_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX library_logs_parent_id ON library_logs (parent_id);
 CREATE INDEX library_logs_entity_id ON library_logs (entity_id);
 CREATE INDEX library_logs_dataset_version_id ON library_logs (dataset_version_id);
+CREATE INDEX library_logs_log_sequence ON library_logs (log_sequence);
 
 
 CREATE TABLE sequence_run_logs (
@@ -1198,12 +1337,15 @@ CREATE TABLE sequence_run_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX sequence_run_logs_parent_id ON sequence_run_logs (parent_id);
 CREATE INDEX sequence_run_logs_entity_id ON sequence_run_logs (entity_id);
 CREATE INDEX sequence_run_logs_dataset_version_id ON sequence_run_logs (dataset_version_id);
+CREATE INDEX sequence_run_logs_log_sequence ON sequence_run_logs (log_sequence);
 
 
 CREATE TABLE assembly_logs (
@@ -1212,12 +1354,15 @@ CREATE TABLE assembly_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX assembly_logs_parent_id ON assembly_logs (parent_id);
 CREATE INDEX assembly_logs_entity_id ON assembly_logs (entity_id);
 CREATE INDEX assembly_logs_dataset_version_id ON assembly_logs (dataset_version_id);
+CREATE INDEX assembly_logs_log_sequence ON assembly_logs (log_sequence);
 
 
 CREATE TABLE data_product_logs (
@@ -1226,12 +1371,15 @@ CREATE TABLE data_product_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX data_product_logs_parent_id ON data_product_logs (parent_id);
 CREATE INDEX data_product_logs_entity_id ON data_product_logs (entity_id);
 CREATE INDEX data_product_logs_dataset_version_id ON data_product_logs (dataset_version_id);
+CREATE INDEX data_product_logs_log_sequence ON data_product_logs (log_sequence);
 
 
 CREATE TABLE annotation_logs (
@@ -1240,12 +1388,15 @@ CREATE TABLE annotation_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX annotation_logs_parent_id ON annotation_logs (parent_id);
 CREATE INDEX annotation_logs_entity_id ON annotation_logs (entity_id);
 CREATE INDEX annotation_logs_dataset_version_id ON annotation_logs (dataset_version_id);
+CREATE INDEX annotation_logs_log_sequence ON annotation_logs (log_sequence);
 
 
 CREATE TABLE deposition_logs (
@@ -1254,12 +1405,15 @@ CREATE TABLE deposition_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX deposition_logs_parent_id ON deposition_logs (parent_id);
 CREATE INDEX deposition_logs_entity_id ON deposition_logs (entity_id);
 CREATE INDEX deposition_logs_dataset_version_id ON deposition_logs (dataset_version_id);
+CREATE INDEX deposition_logs_log_sequence ON deposition_logs (log_sequence);
 
 
 CREATE TABLE collection_event_logs (
@@ -1268,12 +1422,15 @@ CREATE TABLE collection_event_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX collection_event_logs_parent_id ON collection_event_logs (parent_id);
 CREATE INDEX collection_event_logs_entity_id ON collection_event_logs (entity_id);
 CREATE INDEX collection_event_logs_dataset_version_id ON collection_event_logs (dataset_version_id);
+CREATE INDEX collection_event_logs_log_sequence ON collection_event_logs (log_sequence);
 
 
 CREATE TABLE accession_event_logs (
@@ -1282,12 +1439,15 @@ CREATE TABLE accession_event_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX accession_event_logs_parent_id ON accession_event_logs (parent_id);
 CREATE INDEX accession_event_logs_entity_id ON accession_event_logs (entity_id);
 CREATE INDEX accession_event_logs_dataset_version_id ON accession_event_logs (dataset_version_id);
+CREATE INDEX accession_event_logs_log_sequence ON accession_event_logs (log_sequence);
 
 
 CREATE TABLE subsample_logs (
@@ -1296,12 +1456,15 @@ CREATE TABLE subsample_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX subsample_logs_parent_id ON subsample_logs (parent_id);
 CREATE INDEX subsample_logs_entity_id ON subsample_logs (entity_id);
 CREATE INDEX subsample_logs_dataset_version_id ON subsample_logs (dataset_version_id);
+CREATE INDEX subsample_logs_log_sequence ON subsample_logs (log_sequence);
 
 
 CREATE TABLE extraction_logs (
@@ -1310,12 +1473,15 @@ CREATE TABLE extraction_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX extraction_logs_parent_id ON extraction_logs (parent_id);
 CREATE INDEX extraction_logs_entity_id ON extraction_logs (entity_id);
 CREATE INDEX extraction_logs_dataset_version_id ON extraction_logs (dataset_version_id);
+CREATE INDEX extraction_logs_log_sequence ON extraction_logs (log_sequence);
 
 
 CREATE TABLE taxa_logs (
@@ -1324,12 +1490,15 @@ CREATE TABLE taxa_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX taxa_logs_parent_id ON taxa_logs (parent_id);
 CREATE INDEX taxa_logs_entity_id ON taxa_logs (entity_id);
 CREATE INDEX taxa_logs_dataset_version_id ON taxa_logs (dataset_version_id);
+CREATE INDEX taxa_logs_log_sequence ON taxa_logs (log_sequence);
 
 
 CREATE TABLE taxonomic_act_logs (
@@ -1338,12 +1507,15 @@ CREATE TABLE taxonomic_act_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX taxonomic_act_logs_parent_id ON taxonomic_act_logs (parent_id);
 CREATE INDEX taxonomic_act_logs_entity_id ON taxonomic_act_logs (entity_id);
 CREATE INDEX taxonomic_act_logs_dataset_version_id ON taxonomic_act_logs (dataset_version_id);
+CREATE INDEX taxonomic_act_logs_log_sequence ON taxonomic_act_logs (log_sequence);
 
 
 CREATE TABLE sequence_logs (
@@ -1352,12 +1524,15 @@ CREATE TABLE sequence_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX sequence_logs_parent_id ON sequence_logs (parent_id);
 CREATE INDEX sequence_logs_entity_id ON sequence_logs (entity_id);
 CREATE INDEX sequence_logs_dataset_version_id ON sequence_logs (dataset_version_id);
+CREATE INDEX sequence_logs_log_sequence ON sequence_logs (log_sequence);
 
 
 CREATE TABLE publication_logs (
@@ -1366,12 +1541,15 @@ CREATE TABLE publication_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX publication_logs_parent_id ON publication_logs (parent_id);
 CREATE INDEX publication_logs_entity_id ON publication_logs (entity_id);
 CREATE INDEX publication_logs_dataset_version_id ON publication_logs (dataset_version_id);
+CREATE INDEX publication_logs_log_sequence ON publication_logs (log_sequence);
 
 
 CREATE TABLE agent_logs (
@@ -1380,12 +1558,15 @@ CREATE TABLE agent_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX agent_logs_parent_id ON agent_logs (parent_id);
 CREATE INDEX agent_logs_entity_id ON agent_logs (entity_id);
 CREATE INDEX agent_logs_dataset_version_id ON agent_logs (dataset_version_id);
+CREATE INDEX agent_logs_log_sequence ON agent_logs (log_sequence);
 
 
 CREATE TABLE project_logs (
@@ -1394,12 +1575,60 @@ CREATE TABLE project_logs (
     entity_id varchar NOT NULL,
     dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
     action operation_action NOT NULL,
-    atom jsonb DEFAULT '{}' NOT NULL
+    atom jsonb DEFAULT '{}' NOT NULL,
+    log_sequence bigint DEFAULT nextval('operation_log_sequence') NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
 );
 
 CREATE INDEX project_logs_parent_id ON project_logs (parent_id);
 CREATE INDEX project_logs_entity_id ON project_logs (entity_id);
 CREATE INDEX project_logs_dataset_version_id ON project_logs (dataset_version_id);
+CREATE INDEX project_logs_log_sequence ON project_logs (log_sequence);
+
+
+-- Reduced entities at a specific point in an operation log. The watermark is the hybrid
+-- logical clock that the snapshot was taken at, so reducing an entity only needs the
+-- latest snapshot and the operations after the watermark.
+CREATE TABLE operation_log_snapshots (
+    log_table varchar NOT NULL,
+    entity_id varchar NOT NULL,
+    watermark numeric NOT NULL,
+    atoms jsonb DEFAULT '[]' NOT NULL,
+    created_at timestamp with time zone DEFAULT now() NOT NULL,
+    PRIMARY KEY (log_table, entity_id, watermark)
+);
+
+CREATE INDEX operation_log_snapshots_watermark ON operation_log_snapshots (log_table, watermark);
+
+
+-- Operations removed from a log table during compaction because a later operation
+-- superseded them. They are kept so that the parent_id chain can still be verified.
+CREATE TABLE operation_log_archives (
+    log_table varchar NOT NULL,
+    operation_id numeric NOT NULL,
+    parent_id numeric NOT NULL,
+    entity_id varchar NOT NULL,
+    dataset_version_id uuid REFERENCES dataset_versions ON DELETE CASCADE NOT NULL,
+    action operation_action NOT NULL,
+    atom jsonb DEFAULT '{}' NOT NULL,
+    archived_at timestamp with time zone DEFAULT now() NOT NULL,
+    PRIMARY KEY (log_table, operation_id)
+);
+
+CREATE INDEX operation_log_archives_entity_id ON operation_log_archives (log_table, entity_id);
+CREATE INDEX operation_log_archives_dataset_version_id ON operation_log_archives (dataset_version_id);
+
+
+-- Operations deleted from a log table by a compaction or a dataset version rollback. The
+-- replication feed includes these so that replicas can remove the same operations.
+CREATE TABLE operation_log_deletions (
+    log_sequence bigint PRIMARY KEY DEFAULT nextval('operation_log_sequence') NOT NULL,
+    log_table varchar NOT NULL,
+    operation_id numeric NOT NULL,
+    entity_id varchar NOT NULL,
+    reason varchar NOT NULL,
+    logged_at timestamp with time zone DEFAULT clock_timestamp() NOT NULL
+);
 
 
 ---------------------------
@@ -2038,7 +2267,12 @@ SELECT DISTINCT
     SUM(CASE WHEN assembly_events.quality = 'Complete Genome' THEN 1 ELSE 0 END) OVER entities AS complete_genomes,
     SUM(CASE WHEN assembly_events.quality = 'Chromosome' THEN 1 ELSE 0 END) OVER entities AS assembly_chromosomes,
     SUM(CASE WHEN assembly_events.quality = 'Scaffold' THEN 1 ELSE 0 END) OVER entities AS assembly_scaffolds,
-    SUM(CASE WHEN assembly_events.quality = 'Contig' THEN 1 ELSE 0 END) OVER entities AS assembly_contigs
+    SUM(CASE WHEN assembly_events.quality = 'Contig' THEN 1 ELSE 0 END) OVER entities AS assembly_contigs,
+    -- a subquery avoids counting an accession for every sequence of the specimen
+    (
+        SELECT count(*) FROM accession_events
+        WHERE accession_events.specimen_id = specimens.entity_id AND accession_events.type_status IS NOT NULL
+    ) AS types
 
 FROM specimens
 LEFT JOIN subsamples ON subsamples.specimen_id = specimens.entity_id
@@ -0,0 +1,294 @@
+//! Compare the classification of two taxonomic datasets.
+//!
+//! Taxa from the two datasets are aligned by their canonical name and the aligned pairs are
+//! checked for a different parent, rank, taxonomic status or authorship. Taxa that only appear
+//! in the first dataset are reported as missing so that gaps in the other dataset stand out too.
+//!
+//! Botanical ranks are treated as the same as their zoological equivalents, which means a
+//! `familia` in APC is the same rank as a `family` in AFD, and authorships are compared by
+//! their authors and years so that differences in punctuation aren't reported.
+
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::{TaxonomicRank, TaxonomicStatus};
+use crate::names::matching::{authority_similarity, normalise};
+
+
+/// A taxon along with the canonical name of its parent
+#[derive(Debug, Clone, Queryable)]
+pub struct TaxonNode {
+    pub id: Uuid,
+    pub scientific_name: String,
+    pub canonical_name: String,
+    pub authorship: Option<String>,
+    pub rank: TaxonomicRank,
+    pub status: TaxonomicStatus,
+    pub parent: Option<String>,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ConflictKind {
+    /// The taxon isn't in the other dataset
+    Missing,
+    Parent,
+    Rank,
+    Status,
+    Authorship,
+}
+
+
+/// A disagreement between the classification of a taxon in two datasets
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    pub canonical_name: String,
+    pub rank: TaxonomicRank,
+    pub taxon_id: Uuid,
+    pub other_taxon_id: Option<Uuid>,
+    /// The value in the first dataset
+    pub value: Option<String>,
+    /// The value in the other dataset
+    pub other_value: Option<String>,
+}
+
+
+/// Compare the taxa of a dataset with the taxa of another.
+///
+/// Conflicts are ordered by the canonical name of the taxon and then by their kind.
+pub fn compare(taxa: &[TaxonNode], other: &[TaxonNode]) -> Vec<Conflict> {
+    let mut index: HashMap<String, Vec<&TaxonNode>> = HashMap::new();
+    for node in other {
+        index.entry(name_key(&node.canonical_name)).or_default().push(node);
+    }
+
+    let mut conflicts = Vec::new();
+    for taxon in taxa {
+        let candidates = index.get(&name_key(&taxon.canonical_name));
+
+        // homonyms are disambiguated by rank before falling back to the first candidate
+        let aligned = candidates.and_then(|candidates| {
+            candidates
+                .iter()
+                .find(|candidate| equivalent_rank(&candidate.rank) == equivalent_rank(&taxon.rank))
+                .or(candidates.first())
+        });
+
+        match aligned {
+            Some(aligned) => conflicts.extend(compare_taxon(taxon, aligned)),
+            None => conflicts.push(conflict(ConflictKind::Missing, taxon, None, None, None)),
+        }
+    }
+
+    conflicts.sort_by(|a, b| a.canonical_name.cmp(&b.canonical_name).then(a.kind.cmp(&b.kind)));
+    conflicts
+}
+
+
+fn compare_taxon(taxon: &TaxonNode, other: &TaxonNode) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+
+    // root taxa are often missing a parent in one of the datasets, which isn't
+    // a disagreement in the classification so only compare known parents
+    if let (Some(parent), Some(other_parent)) = (&taxon.parent, &other.parent) {
+        if name_key(parent) != name_key(other_parent) {
+            conflicts.push(conflict(
+                ConflictKind::Parent,
+                taxon,
+                Some(other),
+                Some(parent.clone()),
+                Some(other_parent.clone()),
+            ));
+        }
+    }
+
+    if equivalent_rank(&taxon.rank) != equivalent_rank(&other.rank) {
+        conflicts.push(conflict(
+            ConflictKind::Rank,
+            taxon,
+            Some(other),
+            Some(taxon.rank.to_string()),
+            Some(other.rank.to_string()),
+        ));
+    }
+
+    if taxon.status != other.status {
+        conflicts.push(conflict(
+            ConflictKind::Status,
+            taxon,
+            Some(other),
+            Some(taxon.status.to_string()),
+            Some(other.status.to_string()),
+        ));
+    }
+
+    if let (Some(authorship), Some(other_authorship)) = (&taxon.authorship, &other.authorship) {
+        if authority_similarity(authorship, other_authorship) < 1.0 {
+            conflicts.push(conflict(
+                ConflictKind::Authorship,
+                taxon,
+                Some(other),
+                Some(authorship.clone()),
+                Some(other_authorship.clone()),
+            ));
+        }
+    }
+
+    conflicts
+}
+
+fn conflict(
+    kind: ConflictKind,
+    taxon: &TaxonNode,
+    other: Option<&TaxonNode>,
+    value: Option<String>,
+    other_value: Option<String>,
+) -> Conflict {
+    Conflict {
+        kind,
+        canonical_name: taxon.canonical_name.clone(),
+        rank: taxon.rank.clone(),
+        taxon_id: taxon.id,
+        other_taxon_id: other.map(|other| other.id),
+        value,
+        other_value,
+    }
+}
+
+fn name_key(name: &str) -> String {
+    normalise(name).to_lowercase()
+}
+
+/// The zoological rank of a botanical rank
+pub fn equivalent_rank(rank: &TaxonomicRank) -> TaxonomicRank {
+    match rank {
+        TaxonomicRank::Regnum => TaxonomicRank::Kingdom,
+        TaxonomicRank::Division => TaxonomicRank::Phylum,
+        TaxonomicRank::Subdivision => TaxonomicRank::Subphylum,
+        TaxonomicRank::Classis => TaxonomicRank::Class,
+        TaxonomicRank::Subclassis => TaxonomicRank::Subclass,
+        TaxonomicRank::Superordo => TaxonomicRank::Superorder,
+        TaxonomicRank::Ordo => TaxonomicRank::Order,
+        TaxonomicRank::Subordo => TaxonomicRank::Suborder,
+        TaxonomicRank::Familia => TaxonomicRank::Family,
+        TaxonomicRank::Subfamilia => TaxonomicRank::Subfamily,
+        TaxonomicRank::Varietas => TaxonomicRank::Variety,
+        TaxonomicRank::Subvarietas => TaxonomicRank::Subvariety,
+        rank => rank.clone(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, rank: TaxonomicRank, parent: Option<&str>, authorship: Option<&str>) -> TaxonNode {
+        TaxonNode {
+            id: Uuid::new_v4(),
+            scientific_name: match authorship {
+                Some(authorship) => format!("{name} {authorship}"),
+                None => name.to_string(),
+            },
+            canonical_name: name.to_string(),
+            authorship: authorship.map(String::from),
+            rank,
+            status: TaxonomicStatus::Accepted,
+            parent: parent.map(String::from),
+        }
+    }
+
+    fn kinds(conflicts: &[Conflict]) -> Vec<ConflictKind> {
+        conflicts.iter().map(|conflict| conflict.kind).collect()
+    }
+
+    #[test]
+    fn identical_taxa_have_no_conflicts() {
+        let taxa = vec![
+            node("Macropodidae", TaxonomicRank::Family, Some("Diprotodontia"), Some("Gray, 1821")),
+            node("Macropus", TaxonomicRank::Genus, Some("Macropodidae"), Some("Shaw, 1790")),
+        ];
+        let other = vec![
+            node("Macropus", TaxonomicRank::Genus, Some("Macropodidae"), Some("Shaw, 1790")),
+            node("Macropodidae", TaxonomicRank::Family, Some("Diprotodontia"), Some("Gray, 1821")),
+        ];
+
+        assert_eq!(compare(&taxa, &other), vec![]);
+    }
+
+    #[test]
+    fn different_parents_conflict() {
+        let taxa = vec![node("Osphranter", TaxonomicRank::Genus, Some("Macropodidae"), None)];
+        let other = vec![node("Osphranter", TaxonomicRank::Subgenus, Some("Macropus"), None)];
+
+        let conflicts = compare(&taxa, &other);
+        assert_eq!(kinds(&conflicts), vec![ConflictKind::Parent, ConflictKind::Rank]);
+        assert_eq!(conflicts[0].value, Some("Macropodidae".to_string()));
+        assert_eq!(conflicts[0].other_value, Some("Macropus".to_string()));
+        assert_eq!(conflicts[1].other_value, Some("Subgenus".to_string()));
+    }
+
+    #[test]
+    fn missing_parents_are_not_conflicts() {
+        let taxa = vec![node("Animalia", TaxonomicRank::Kingdom, None, None)];
+        let other = vec![node("Animalia", TaxonomicRank::Kingdom, Some("Eukaryota"), None)];
+        assert_eq!(compare(&taxa, &other), vec![]);
+    }
+
+    #[test]
+    fn botanical_ranks_are_equivalent() {
+        let taxa = vec![node("Myrtaceae", TaxonomicRank::Familia, Some("Myrtales"), None)];
+        let other = vec![node("Myrtaceae", TaxonomicRank::Family, Some("Myrtales"), None)];
+        assert_eq!(compare(&taxa, &other), vec![]);
+    }
+
+    #[test]
+    fn status_conflicts() {
+        let taxa = vec![node("Macropus rufus", TaxonomicRank::Species, Some("Macropus"), None)];
+        let mut synonym = node("Macropus rufus", TaxonomicRank::Species, Some("Macropus"), None);
+        synonym.status = TaxonomicStatus::Synonym;
+
+        let conflicts = compare(&taxa, &[synonym]);
+        assert_eq!(kinds(&conflicts), vec![ConflictKind::Status]);
+    }
+
+    #[test]
+    fn authorship_formatting_is_ignored() {
+        let taxa = vec![node("Macropus rufus", TaxonomicRank::Species, None, Some("(Desmarest, 1822)"))];
+        let other = vec![node("Macropus rufus", TaxonomicRank::Species, None, Some("(Desmarest 1822)"))];
+        assert_eq!(compare(&taxa, &other), vec![]);
+
+        let other = vec![node("Macropus rufus", TaxonomicRank::Species, None, Some("(Desmarest, 1820)"))];
+        assert_eq!(kinds(&compare(&taxa, &other)), vec![ConflictKind::Authorship]);
+    }
+
+    #[test]
+    fn homonyms_are_aligned_by_rank() {
+        let taxa = vec![node("Ficus", TaxonomicRank::Genus, Some("Moraceae"), None)];
+        let other = vec![
+            node("Ficus", TaxonomicRank::Family, Some("Littorinimorpha"), None),
+            node("Ficus", TaxonomicRank::Genus, Some("Moraceae"), None),
+        ];
+        assert_eq!(compare(&taxa, &other), vec![]);
+    }
+
+    #[test]
+    fn missing_taxa() {
+        let taxa = vec![
+            node("Macropus", TaxonomicRank::Genus, Some("Macropodidae"), None),
+            node("Notamacropus", TaxonomicRank::Genus, Some("Macropodidae"), None),
+        ];
+        let other = vec![node("macropus", TaxonomicRank::Genus, Some("Macropodidae"), None)];
+
+        let conflicts = compare(&taxa, &other);
+        assert_eq!(kinds(&conflicts), vec![ConflictKind::Missing]);
+        assert_eq!(conflicts[0].canonical_name, "Notamacropus");
+        assert_eq!(conflicts[0].other_taxon_id, None);
+    }
+}
@@ -13,10 +13,45 @@
 //     16bits = fractional second
 // logical = 16 bits
 
-use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, Timelike, Utc};
 
 
-#[derive(Clone, Copy, Debug, Eq, Hash)]
+/// The year that months are counted from in the physical component
+pub const EPOCH_YEAR: i32 = 2020;
+
+/// The largest value the 14 bit logical counter can hold
+pub const MAX_LOGICAL: u16 = 0b00111111_11111111;
+
+/// The largest value the 16 bit month field can hold
+const MAX_MONTHS: u16 = u16::MAX;
+
+const LOGICAL_BITS: u32 = 14;
+const LOGICAL_MASK: u64 = MAX_LOGICAL as u64;
+
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("invalid hybrid timestamp: {0}")]
+    InvalidTimestamp(String),
+}
+
+
+/// A hybrid logical clock timestamp.
+///
+/// The upper 50 bits are the physical time packed as calendar fields with millisecond
+/// precision and the lower 14 bits are a logical counter used to order events that occur
+/// within the same millisecond. Because the fields are packed from most to least significant
+/// the timestamps can be compared as plain integers.
+///
+/// Timestamps created before the month encoding was fixed counted the months as the years
+/// since the epoch plus the month of the year, so the operation ids logged with them decode to
+/// the wrong dates and there's no way to tell which of the overlapping months they were from.
+/// They can't be migrated but they don't need to be either. Their order relative to each other
+/// is unchanged and every one of them is smaller than the ids created since the fix, which
+/// count at least 72 months for anything after 2026, so the last-write-wins order of the logs
+/// is preserved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct HybridTimestamp(u64);
 
 impl HybridTimestamp {
@@ -24,16 +59,98 @@ impl HybridTimestamp {
         Self(timestamp)
     }
 
+    /// A timestamp for the current wall clock time with a logical counter of zero
+    pub fn now() -> Self {
+        Utc::now().into()
+    }
+
     pub fn with_logical(timestamp: u64, logical: u16) -> Self {
-        Self(((timestamp >> 14) << 14) | logical as u64)
+        Self((timestamp & !LOGICAL_MASK) | (logical as u64 & LOGICAL_MASK))
     }
 
+    /// Increment the logical counter.
+    ///
+    /// If the logical counter is already at its maximum we carry over into the
+    /// next millisecond of the physical component and reset the counter. The timestamp
+    /// will be slightly ahead of the wall clock but it will never go backwards.
     pub fn inc(&self) -> HybridTimestamp {
-        Self::with_logical(self.0, self.logical() + 1)
+        match self.logical() {
+            MAX_LOGICAL => self.next_millisecond(),
+            logical => Self::with_logical(self.0, logical + 1),
+        }
+    }
+
+    /// The next millisecond of the physical component with a logical counter of zero.
+    ///
+    /// The physical component is packed calendar fields so adding to the milliseconds bits
+    /// would spill into the seconds without carrying. Going through a date time carries into the
+    /// seconds, minutes, days and months properly. Fields that aren't a valid date can't be carried
+    /// and the last representable millisecond can't be carried past, so those add to the
+    /// milliseconds bits instead which is still ordered after this timestamp.
+    fn next_millisecond(&self) -> HybridTimestamp {
+        let spilled = Self(self.physical().saturating_add(1 << LOGICAL_BITS));
+
+        let carried = DateTime::<Utc>::try_from(*self)
+            .ok()
+            .and_then(|datetime| datetime.checked_add_signed(TimeDelta::milliseconds(1)))
+            .filter(|next| *next <= latest())
+            .map(HybridTimestamp::from);
+
+        match carried {
+            Some(next) if next > *self => next,
+            _ => spilled,
+        }
+    }
+
+    /// Generate a timestamp for a local or send event.
+    ///
+    /// This uses the wall clock if it is ahead of this timestamp, otherwise
+    /// it increments the logical counter.
+    pub fn tick(&self) -> HybridTimestamp {
+        self.tick_at(Self::now())
+    }
+
+    /// Generate a timestamp for a local event with the provided wall clock time.
+    pub fn tick_at(&self, now: HybridTimestamp) -> HybridTimestamp {
+        if now.physical() > self.physical() {
+            Self::with_logical(now.physical(), 0)
+        }
+        else {
+            self.inc()
+        }
+    }
+
+    /// Merge a timestamp received from another clock.
+    ///
+    /// The new timestamp is guaranteed to be greater than both this timestamp and the
+    /// remote timestamp, which means operations from different importers and machines
+    /// will retain their causal order when merged.
+    pub fn receive(&self, remote: HybridTimestamp) -> HybridTimestamp {
+        self.receive_at(remote, Self::now())
+    }
+
+    /// Merge a timestamp received from another clock with the provided wall clock time.
+    pub fn receive_at(&self, remote: HybridTimestamp, now: HybridTimestamp) -> HybridTimestamp {
+        let physical = self.physical().max(remote.physical()).max(now.physical());
+
+        let logical = match (self.physical() == physical, remote.physical() == physical) {
+            (true, true) => self.logical().max(remote.logical()),
+            (true, false) => self.logical(),
+            (false, true) => remote.logical(),
+            // the wall clock is ahead of both so there is nothing to order against
+            (false, false) => return Self::with_logical(physical, 0),
+        };
+
+        Self::with_logical(physical, logical).inc()
+    }
+
+    /// The physical component of the timestamp with the logical counter zeroed
+    pub fn physical(&self) -> u64 {
+        self.0 & !LOGICAL_MASK
     }
 
     pub fn months(&self) -> u16 {
-        ((self.0 >> 48) & 0b11111111_11111111) as u16
+        ((self.0 >> 48) & MAX_MONTHS as u64) as u16
     }
 
     pub fn days(&self) -> u8 {
@@ -53,11 +170,11 @@ impl HybridTimestamp {
     }
 
     pub fn milliseconds(&self) -> u16 {
-        ((self.0 >> 14) & 0b00000011_11111111) as u16
+        ((self.0 >> LOGICAL_BITS) & 0b00000011_11111111) as u16
     }
 
     pub fn logical(&self) -> u16 {
-        (self.0 & 0b00111111_11111111) as u16
+        (self.0 & LOGICAL_MASK) as u16
     }
 
     pub fn as_u64(&self) -> u64 {
@@ -66,60 +183,424 @@ impl HybridTimestamp {
 }
 
 
-// change this to a fallable conversion since the chrono api has deprecated to non-fallable ones
-impl From<HybridTimestamp> for DateTime<Utc> {
-    fn from(ts: HybridTimestamp) -> Self {
-        let years = 2020 + (ts.months() / 12);
-        let months = ts.months() % 12;
+impl TryFrom<HybridTimestamp> for DateTime<Utc> {
+    type Error = Error;
+
+    /// Decode the physical component of a timestamp.
+    ///
+    /// Fails if the calendar fields aren't a valid date and time, which only happens if the
+    /// timestamp wasn't encoded from a date time.
+    fn try_from(ts: HybridTimestamp) -> Result<Self, Self::Error> {
+        let years = EPOCH_YEAR + (ts.months() / 12) as i32;
+        let months = (ts.months() % 12) + 1;
 
-        NaiveDate::from_ymd_opt(years.into(), months.into(), ts.days().into())
-            .unwrap_or_default()
-            .and_hms_milli_opt(ts.hours().into(), ts.minutes().into(), ts.seconds().into(), ts.milliseconds().into())
-            .unwrap_or_default()
-            .and_utc()
+        // the milliseconds are added as a duration since timestamps created before the
+        // overflow carry was fixed can have milliseconds past 999, which rolls over into
+        // the next second
+        let datetime = NaiveDate::from_ymd_opt(years, months.into(), ts.days().into())
+            .and_then(|date| date.and_hms_opt(ts.hours().into(), ts.minutes().into(), ts.seconds().into()))
+            .ok_or(Error::InvalidTimestamp(ts.to_string()))?
+            .and_utc();
+
+        Ok(datetime + TimeDelta::milliseconds(ts.milliseconds().into()))
     }
 }
 
 impl From<DateTime<Utc>> for HybridTimestamp {
+    /// Convert the date time into a timestamp with a logical counter of zero.
+    ///
+    /// The physical component can only represent the years from the epoch year until the 16 bit
+    /// month field runs out, so date times outside of that range saturate to the first or last
+    /// millisecond that can be represented.
     fn from(source: DateTime<Utc>) -> Self {
-        let months: u64 = ((source.year() - 2020) as u64) + source.month0() as u64;
+        let source = source.clamp(earliest(), latest());
+
+        let years = (source.year() - EPOCH_YEAR) as u64;
+        let months: u64 = (years * 12) + source.month0() as u64;
+
+        // chrono represents leap seconds as milliseconds beyond 999 which would
+        // overflow into the seconds field, so we cap it to the last millisecond
+        let milliseconds = (source.nanosecond() / 1_000_000).min(999) as u64;
+
         let timestamp = (months << 48)
             | ((source.day() as u64) << 42)
             | ((source.hour() as u64) << 36)
             | ((source.minute() as u64) << 30)
             | ((source.second() as u64) << 24)
-            | (((source.nanosecond() / 1000_000) as u64) << 14);
+            | (milliseconds << 14);
 
         Self(timestamp)
     }
 }
 
+/// The first millisecond that can be represented, the start of the epoch year
+fn earliest() -> DateTime<Utc> {
+    NaiveDate::from_ymd_opt(EPOCH_YEAR, 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("the start of the epoch year is a valid date")
+        .and_utc()
+}
+
+/// The last millisecond that can be represented, the end of the last month the month field can hold
+fn latest() -> DateTime<Utc> {
+    let months = MAX_MONTHS as i32 + 1;
+    NaiveDate::from_ymd_opt(EPOCH_YEAR + months / 12, (months % 12) as u32 + 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("the month after the last month is a valid date")
+        .and_utc()
+        - TimeDelta::milliseconds(1)
+}
+
 impl std::fmt::Display for HybridTimestamp {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}", self.0)
     }
 }
 
-impl Ord for HybridTimestamp {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.0.cmp(&other.0)
+impl From<HybridTimestamp> for u64 {
+    fn from(source: HybridTimestamp) -> u64 {
+        source.0
     }
 }
 
-impl PartialOrd for HybridTimestamp {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+impl From<HybridTimestamp> for BigDecimal {
+    fn from(source: HybridTimestamp) -> BigDecimal {
+        source.0.into()
     }
 }
 
-impl PartialEq for HybridTimestamp {
-    fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+impl TryFrom<&BigDecimal> for HybridTimestamp {
+    type Error = Error;
+
+    /// Decode a timestamp stored in the operation_id or parent_id column of a log table
+    fn try_from(value: &BigDecimal) -> Result<Self, Self::Error> {
+        match value.is_integer() {
+            true => value
+                .to_u64()
+                .map(HybridTimestamp)
+                .ok_or(Error::InvalidTimestamp(value.to_string())),
+            false => Err(Error::InvalidTimestamp(value.to_string())),
+        }
     }
 }
 
-impl From<HybridTimestamp> for u64 {
-    fn from(source: HybridTimestamp) -> u64 {
-        source.0
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// A small xorshift generator so that the property tests are reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn between(&mut self, min: i64, max: i64) -> i64 {
+            min + (self.next() % (max - min) as u64) as i64
+        }
+
+        fn datetime(&mut self) -> DateTime<Utc> {
+            // 2020-01-01 to 2100-01-01
+            let millis = self.between(1_577_836_800_000, 4_102_444_800_000);
+            Utc.timestamp_millis_opt(millis).unwrap()
+        }
+
+        fn timestamp(&mut self) -> HybridTimestamp {
+            let logical = (self.next() % (MAX_LOGICAL as u64 + 1)) as u16;
+            HybridTimestamp::with_logical(HybridTimestamp::from(self.datetime()).as_u64(), logical)
+        }
+    }
+
+    fn ts(year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32, milli: u32) -> HybridTimestamp {
+        Utc.with_ymd_and_hms(year, month, day, hour, min, sec)
+            .unwrap()
+            .with_nanosecond(milli * 1_000_000)
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn test_encode_months_since_epoch() {
+        assert_eq!(ts(2020, 1, 1, 0, 0, 0, 0).months(), 0);
+        assert_eq!(ts(2020, 12, 1, 0, 0, 0, 0).months(), 11);
+        assert_eq!(ts(2021, 1, 1, 0, 0, 0, 0).months(), 12);
+        assert_eq!(ts(2024, 6, 1, 0, 0, 0, 0).months(), 53);
+    }
+
+    #[test]
+    fn test_encode_fields() {
+        let ts = ts(2024, 6, 15, 13, 45, 30, 123);
+        assert_eq!(ts.days(), 15);
+        assert_eq!(ts.hours(), 13);
+        assert_eq!(ts.minutes(), 45);
+        assert_eq!(ts.seconds(), 30);
+        assert_eq!(ts.milliseconds(), 123);
+        assert_eq!(ts.logical(), 0);
+    }
+
+    #[test]
+    fn test_later_years_are_greater() {
+        // previously the year and month were added together which made
+        // 2021-01 equal to 2020-02 and 2020-12 greater than 2021-01
+        assert!(ts(2021, 1, 1, 0, 0, 0, 0) > ts(2020, 12, 31, 23, 59, 59, 999));
+        assert!(ts(2021, 1, 1, 0, 0, 0, 0) > ts(2020, 2, 1, 0, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_saturate_before_epoch() {
+        let earliest = ts(2020, 1, 1, 0, 0, 0, 0);
+        assert_eq!(ts(2019, 12, 31, 23, 59, 59, 999), earliest);
+        assert_eq!(ts(1970, 1, 1, 0, 0, 0, 0), earliest);
+        assert_eq!(DateTime::<Utc>::try_from(earliest), Ok(Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_saturate_after_the_last_month() {
+        let last = ts(7481, 4, 30, 23, 59, 59, 999);
+        assert_eq!(last.months(), u16::MAX);
+        assert_eq!(ts(7481, 5, 1, 0, 0, 0, 0), last);
+        assert_eq!(ts(9999, 1, 1, 0, 0, 0, 0), last);
+    }
+
+    #[test]
+    fn test_decode_datetime() {
+        let dt = DateTime::<Utc>::try_from(ts(2024, 6, 15, 13, 45, 30, 123)).unwrap();
+        assert_eq!(dt, Utc.with_ymd_and_hms(2024, 6, 15, 13, 45, 30).unwrap() + TimeDelta::milliseconds(123));
+    }
+
+    #[test]
+    fn test_decode_invalid_datetime() {
+        // the day and hour fields can hold values that aren't part of a date
+        assert!(DateTime::<Utc>::try_from(HybridTimestamp::new(0)).is_err());
+        let february = ts(2024, 2, 1, 0, 0, 0, 0).as_u64();
+        assert!(DateTime::<Utc>::try_from(HybridTimestamp::new((february & !(0b111111 << 42)) | (30 << 42))).is_err());
+        assert!(DateTime::<Utc>::try_from(HybridTimestamp::new(february | (24 << 36))).is_err());
+    }
+
+    #[test]
+    fn test_decode_bigdecimal() {
+        let ts = ts(2024, 6, 15, 13, 45, 30, 123).inc();
+        let decimal: BigDecimal = ts.into();
+        assert_eq!(HybridTimestamp::try_from(&decimal), Ok(ts));
+    }
+
+    #[test]
+    fn test_decode_invalid_bigdecimal() {
+        assert!(HybridTimestamp::try_from(&BigDecimal::from(-1)).is_err());
+        assert!(HybridTimestamp::try_from(&"1.5".parse::<BigDecimal>().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_with_logical_masks_counter() {
+        let ts = HybridTimestamp::with_logical(ts(2024, 6, 15, 0, 0, 0, 1).as_u64(), u16::MAX);
+        assert_eq!(ts.milliseconds(), 1);
+        assert_eq!(ts.logical(), MAX_LOGICAL);
+    }
+
+    #[test]
+    fn test_inc_overflow_carries_into_milliseconds() {
+        let base = ts(2024, 6, 15, 0, 0, 0, 1);
+        let max = HybridTimestamp::with_logical(base.as_u64(), MAX_LOGICAL);
+        let next = max.inc();
+
+        assert!(next > max);
+        assert_eq!(next.logical(), 0);
+        assert_eq!(next.milliseconds(), 2);
+    }
+
+    #[test]
+    fn test_inc_overflow_carries_into_seconds() {
+        let base = ts(2024, 6, 15, 0, 0, 0, 999);
+        let max = HybridTimestamp::with_logical(base.as_u64(), MAX_LOGICAL);
+        let dt = DateTime::<Utc>::try_from(max.inc()).unwrap();
+
+        assert_eq!(dt, Utc.with_ymd_and_hms(2024, 6, 15, 0, 0, 1).unwrap());
+    }
+
+    #[test]
+    fn test_inc_overflow_carries_into_the_next_month() {
+        let base = ts(2024, 6, 30, 23, 59, 59, 999);
+        let max = HybridTimestamp::with_logical(base.as_u64(), MAX_LOGICAL);
+        let next = max.inc();
+
+        assert!(next > max);
+        assert_eq!(next, ts(2024, 7, 1, 0, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_inc_overflow_at_the_largest_milliseconds() {
+        // the milliseconds field can hold up to 1023 which previously spilled into the
+        // seconds bits when incremented instead of carrying through the date
+        let base = ts(2024, 6, 15, 0, 0, 59, 0).as_u64() | (1023 << LOGICAL_BITS);
+        let max = HybridTimestamp::with_logical(base, MAX_LOGICAL);
+        let next = max.inc();
+
+        assert!(next > max);
+        assert_eq!(next, ts(2024, 6, 15, 0, 1, 0, 24));
+        assert_eq!(next.seconds(), 0);
+        assert_eq!(next.minutes(), 1);
+    }
+
+    #[test]
+    fn test_inc_is_greater_at_the_overflow_boundaries() {
+        let boundaries = [
+            ts(2024, 6, 15, 0, 0, 0, 999).as_u64(),
+            ts(2024, 6, 15, 0, 0, 59, 0).as_u64() | (1023 << LOGICAL_BITS),
+            ts(2024, 2, 29, 23, 59, 59, 999).as_u64(),
+            ts(2024, 12, 31, 23, 59, 59, 999).as_u64(),
+            ts(2020, 1, 1, 0, 0, 0, 0).as_u64(),
+            // the last millisecond that can be represented can't carry into the next month
+            ts(7481, 4, 30, 23, 59, 59, 999).as_u64(),
+            // fields that aren't a valid date can't be carried through a date time
+            0,
+            ts(2024, 2, 1, 0, 0, 0, 0).as_u64() | (30 << 42),
+            ts(2024, 6, 15, 0, 0, 0, 0).as_u64() | (63 << 36),
+            // 2024-06-15 with the old month encoding
+            (9 << 48) | (15 << 42),
+        ];
+
+        for physical in boundaries {
+            let max = HybridTimestamp::with_logical(physical, MAX_LOGICAL);
+            let next = max.inc();
+            assert!(next > max, "{next} <= {max}");
+            assert_eq!(next.logical(), 0);
+        }
+    }
+
+    #[test]
+    fn test_tick_uses_wall_clock_when_ahead() {
+        let local = ts(2024, 6, 15, 0, 0, 0, 0).inc();
+        let now = ts(2024, 6, 15, 0, 0, 1, 0);
+        assert_eq!(local.tick_at(now), now);
+    }
+
+    #[test]
+    fn test_tick_increments_when_wall_clock_behind() {
+        let local = ts(2024, 6, 15, 0, 0, 1, 0);
+        let now = ts(2024, 6, 15, 0, 0, 0, 0);
+        assert_eq!(local.tick_at(now), local.inc());
+    }
+
+    #[test]
+    fn test_receive_remote_ahead() {
+        let local = ts(2024, 6, 15, 0, 0, 0, 0);
+        let remote = HybridTimestamp::with_logical(ts(2024, 6, 15, 0, 0, 5, 0).as_u64(), 7);
+        let now = ts(2024, 6, 15, 0, 0, 1, 0);
+
+        let merged = local.receive_at(remote, now);
+        assert_eq!(merged.physical(), remote.physical());
+        assert_eq!(merged.logical(), 8);
+    }
+
+    #[test]
+    fn test_receive_same_physical_time() {
+        let base = ts(2024, 6, 15, 0, 0, 0, 0).as_u64();
+        let local = HybridTimestamp::with_logical(base, 3);
+        let remote = HybridTimestamp::with_logical(base, 9);
+
+        let merged = local.receive_at(remote, HybridTimestamp::new(base));
+        assert_eq!(merged, HybridTimestamp::with_logical(base, 10));
+    }
+
+    #[test]
+    fn test_receive_wall_clock_ahead() {
+        let local = ts(2024, 6, 15, 0, 0, 0, 0).inc();
+        let remote = ts(2024, 6, 15, 0, 0, 1, 0).inc();
+        let now = ts(2024, 6, 15, 0, 0, 2, 0);
+        assert_eq!(local.receive_at(remote, now), now);
+    }
+
+    #[test]
+    fn test_receive_overflow() {
+        let base = ts(2024, 6, 15, 0, 0, 0, 0).as_u64();
+        let local = HybridTimestamp::with_logical(base, 1);
+        let remote = HybridTimestamp::with_logical(base, MAX_LOGICAL);
+
+        let merged = local.receive_at(remote, HybridTimestamp::new(base));
+        assert!(merged > remote);
+        assert_eq!(merged.logical(), 0);
+    }
+
+    #[test]
+    fn property_datetime_round_trip() {
+        let mut rng = Rng(0x2545F4914F6CDD1D);
+
+        for _ in 0..10_000 {
+            let dt = rng.datetime();
+            let ts = HybridTimestamp::from(dt);
+            assert_eq!(DateTime::<Utc>::try_from(ts), Ok(dt), "{ts}");
+            assert_eq!(HybridTimestamp::from(DateTime::<Utc>::try_from(ts).unwrap()), ts);
+        }
+    }
+
+    #[test]
+    fn property_u64_round_trip() {
+        let mut rng = Rng(0x9E3779B97F4A7C15);
+
+        for _ in 0..10_000 {
+            let ts = rng.timestamp();
+            assert_eq!(HybridTimestamp::new(ts.as_u64()), ts);
+            assert_eq!(HybridTimestamp::try_from(&BigDecimal::from(ts)), Ok(ts));
+        }
+    }
+
+    #[test]
+    fn property_encoding_preserves_order() {
+        let mut rng = Rng(0xD1B54A32D192ED03);
+
+        for _ in 0..10_000 {
+            let a = rng.datetime();
+            let b = rng.datetime();
+            assert_eq!(a.cmp(&b), HybridTimestamp::from(a).cmp(&HybridTimestamp::from(b)));
+        }
+    }
+
+    #[test]
+    fn property_tick_is_monotonic() {
+        let mut rng = Rng(0xA0761D6478BD642F);
+        let mut clock = rng.timestamp();
+
+        for _ in 0..10_000 {
+            let next = clock.tick_at(rng.timestamp());
+            assert!(next > clock, "{next} <= {clock}");
+            clock = next;
+        }
+    }
+
+    #[test]
+    fn property_receive_is_greater_than_both() {
+        let mut rng = Rng(0xE7037ED1A0B428DB);
+        let mut clock = rng.timestamp();
+
+        for _ in 0..10_000 {
+            let remote = rng.timestamp();
+            let next = clock.receive_at(remote, rng.timestamp());
+            assert!(next > clock, "{next} <= {clock}");
+            assert!(next > remote, "{next} <= {remote}");
+            clock = next;
+        }
+    }
+
+    #[test]
+    fn property_inc_is_monotonic_across_overflow() {
+        let mut rng = Rng(0x8EBC6AF09C88C6E3);
+
+        for _ in 0..1_000 {
+            let start = rng.timestamp();
+            let mut clock = HybridTimestamp::with_logical(start.as_u64(), MAX_LOGICAL - 2);
+
+            for _ in 0..5 {
+                let next = clock.inc();
+                assert!(next > clock, "{next} <= {clock}");
+                clock = next;
+            }
+        }
     }
 }
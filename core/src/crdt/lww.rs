@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use serde::Serialize;
+
 use crate::models::logs::LogOperation;
 
 // a map that uses the last-write-wins policy for each entry
@@ -51,6 +53,282 @@ where
                 .or_insert(op);
         }
 
+        // the last write for each atom is the current state of the entity. we merge
+        // rather than replace so that a map seeded from a snapshot keeps atoms that
+        // weren't changed by these operations
+        for (key, op) in inserts.iter() {
+            self.atoms.insert(key.clone(), op.atom().clone());
+        }
+
         inserts.into_values().collect()
     }
 }
+
+
+/// A reduced entity with every atom converted to a named field.
+///
+/// Because atoms are enums the variant name becomes the snake cased field name
+/// and the associated value becomes the field value. This allows any operation log
+/// to be reduced and output without needing a dedicated struct for each entity.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entity {
+    pub entity_id: String,
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl<T: Serialize> From<Map<T>> for Entity {
+    fn from(map: Map<T>) -> Self {
+        let mut fields = serde_json::Map::new();
+
+        for atom in map.atoms.into_values() {
+            // variants with a value serialize as a single entry object whereas
+            // unit variants like Empty serialize as a string and carry no data
+            if let Ok(serde_json::Value::Object(object)) = serde_json::to_value(atom) {
+                for (variant, value) in object.into_iter() {
+                    fields.insert(to_snake_case(&variant), value);
+                }
+            }
+        }
+
+        Entity {
+            entity_id: map.entity_id,
+            fields,
+        }
+    }
+}
+
+
+/// Group operations by the entity they operate on.
+///
+/// The order of the operations within each group is preserved so if the
+/// operations are in causal order then each entity group will be too.
+pub fn group_by_entity<T, A>(operations: Vec<T>) -> HashMap<String, Vec<T>>
+where
+    T: LogOperation<A>,
+{
+    let mut grouped: HashMap<String, Vec<T>> = HashMap::new();
+
+    for op in operations.into_iter() {
+        grouped.entry(op.entity_id().clone()).or_default().push(op);
+    }
+
+    grouped
+}
+
+/// Merge new operations into the existing operations and reduce them.
+///
+/// The returned operations are only the ones that determine the state of each entity
+/// which means new operations that don't change an atom are excluded. Existing operations
+/// will also be returned so the result should be inserted while ignoring conflicts.
+pub fn reduce_operations<T, A>(existing_ops: Vec<T>, new_ops: Vec<T>) -> Vec<T>
+where
+    A: ToString + Clone + PartialEq,
+    T: LogOperation<A> + Clone,
+{
+    let mut operations = existing_ops;
+    operations.extend(new_ops);
+
+    // new operations can be older than existing ones when an importer uses the
+    // timestamps of the source so we restore causal order before reducing. the sort
+    // is stable which means operations with the same id keep their input order
+    operations.sort_by(|a, b| a.id().cmp(b.id()));
+
+    let mut merged = Vec::new();
+
+    for (key, ops) in group_by_entity(operations).into_iter() {
+        let mut map = Map::new(key);
+        let reduced = map.reduce(&ops);
+        merged.extend(reduced.into_iter().cloned());
+    }
+
+    merged
+}
+
+/// Reduce all operations into the current state of each entity.
+pub fn reduce_entities<T, A>(operations: Vec<T>) -> Vec<Map<A>>
+where
+    A: ToString + Clone + PartialEq,
+    T: LogOperation<A> + Clone,
+{
+    let mut entities = Vec::new();
+
+    for (key, ops) in group_by_entity(operations).into_iter() {
+        let mut map = Map::new(key);
+        map.reduce(&ops);
+        entities.push(map);
+    }
+
+    entities
+}
+
+
+fn to_snake_case(value: &str) -> String {
+    let mut snake = String::with_capacity(value.len() + 4);
+
+    for (idx, ch) in value.char_indices() {
+        if ch.is_uppercase() {
+            if idx > 0 {
+                snake.push('_');
+            }
+            snake.extend(ch.to_lowercase());
+        }
+        else {
+            snake.push(ch);
+        }
+    }
+
+    snake
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+
+    use super::*;
+    use crate::models::logs::Action;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, strum::Display)]
+    enum Atom {
+        Empty,
+        Name(String),
+        Count(i64),
+    }
+
+    #[derive(Debug, Clone)]
+    struct Op {
+        operation_id: BigDecimal,
+        entity_id: String,
+        action: Action,
+        atom: Atom,
+    }
+
+    impl LogOperation<Atom> for Op {
+        fn id(&self) -> &BigDecimal {
+            &self.operation_id
+        }
+
+        fn entity_id(&self) -> &String {
+            &self.entity_id
+        }
+
+        fn action(&self) -> &Action {
+            &self.action
+        }
+
+        fn atom(&self) -> &Atom {
+            &self.atom
+        }
+    }
+
+    fn op(id: u64, entity_id: &str, atom: Atom) -> Op {
+        Op {
+            operation_id: BigDecimal::from(id),
+            entity_id: entity_id.to_string(),
+            action: Action::Update,
+            atom,
+        }
+    }
+
+    fn ids(ops: &[Op]) -> Vec<u64> {
+        let mut ids: Vec<u64> = ops
+            .iter()
+            .map(|op| op.operation_id.to_string().parse().unwrap())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn last_write_wins() {
+        let ops = vec![
+            op(1, "a", Atom::Name("first".into())),
+            op(2, "a", Atom::Count(1)),
+            op(3, "a", Atom::Name("second".into())),
+        ];
+
+        let mut map = Map::new("a".to_string());
+        map.reduce(&ops);
+        assert_eq!(map.atoms.get("Name"), Some(&Atom::Name("second".into())));
+        assert_eq!(map.atoms.get("Count"), Some(&Atom::Count(1)));
+    }
+
+    #[test]
+    fn reduce_operations_restores_causal_order() {
+        // the new operation is older than the existing one so it shouldn't win
+        let existing = vec![op(5, "a", Atom::Name("current".into()))];
+        let new = vec![op(2, "a", Atom::Name("stale".into()))];
+
+        let merged = reduce_operations(existing, new);
+        assert_eq!(ids(&merged), vec![5]);
+    }
+
+    #[test]
+    fn ties_keep_the_input_order() {
+        let existing = vec![op(7, "a", Atom::Count(1))];
+        let new = vec![op(7, "a", Atom::Count(2))];
+
+        let merged = reduce_operations(existing, new);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].atom, Atom::Count(2));
+    }
+
+    #[test]
+    fn unchanged_values_are_filtered() {
+        let existing = vec![op(1, "a", Atom::Count(1))];
+        let new = vec![op(2, "a", Atom::Count(1)), op(3, "a", Atom::Empty)];
+
+        // the repeated count doesn't change the entity so only the first is kept
+        let merged = reduce_operations(existing, new);
+        assert_eq!(ids(&merged), vec![1, 3]);
+    }
+
+    #[test]
+    fn values_that_change_back_are_kept() {
+        let ops = vec![
+            op(1, "a", Atom::Count(1)),
+            op(2, "a", Atom::Count(2)),
+            op(3, "a", Atom::Count(1)),
+        ];
+
+        let merged = reduce_operations(vec![], ops);
+        assert_eq!(ids(&merged), vec![3]);
+    }
+
+    #[test]
+    fn entities_are_reduced_separately() {
+        let ops = vec![
+            op(1, "a", Atom::Count(1)),
+            op(2, "b", Atom::Count(1)),
+            op(3, "a", Atom::Count(2)),
+        ];
+
+        let mut entities = reduce_entities(ops);
+        entities.sort_by(|a, b| a.entity_id.cmp(&b.entity_id));
+        assert_eq!(entities[0].atoms.get("Count"), Some(&Atom::Count(2)));
+        assert_eq!(entities[1].atoms.get("Count"), Some(&Atom::Count(1)));
+    }
+
+    #[test]
+    fn reduce_merges_into_existing_atoms() {
+        // a map seeded from a snapshot keeps the atoms that later operations don't change
+        let mut map = Map::new("a".to_string());
+        map.atoms.insert("Name".to_string(), Atom::Name("snapshot".into()));
+        map.atoms.insert("Count".to_string(), Atom::Count(1));
+
+        map.reduce(&vec![op(5, "a", Atom::Count(2))]);
+        assert_eq!(map.atoms.get("Name"), Some(&Atom::Name("snapshot".into())));
+        assert_eq!(map.atoms.get("Count"), Some(&Atom::Count(2)));
+    }
+
+    #[test]
+    fn entity_fields_are_snake_cased() {
+        let mut map = Map::new("a".to_string());
+        map.reduce(&vec![op(1, "a", Atom::Name("Aus bus".into())), op(2, "a", Atom::Empty)]);
+
+        let entity = Entity::from(map);
+        assert_eq!(entity.fields.get("name"), Some(&serde_json::json!("Aus bus")));
+        assert_eq!(entity.fields.len(), 1);
+        assert_eq!(to_snake_case("ScientificName"), "scientific_name");
+    }
+}
@@ -3,7 +3,6 @@ pub mod hlc;
 pub mod lww;
 
 use bigdecimal::BigDecimal;
-use chrono::Utc;
 pub use dataframe::{DataFrame, DataFrameOperation};
 
 use self::hlc::HybridTimestamp;
@@ -14,8 +13,7 @@ pub struct Version(HybridTimestamp);
 
 impl Version {
     pub fn new() -> Version {
-        let ts: HybridTimestamp = Utc::now().into();
-        Version(ts)
+        Version(HybridTimestamp::now())
     }
 
     /// Get the next frame version.
@@ -24,18 +22,37 @@ impl Version {
     /// than the current version it will return it. However, if the current
     /// clock is ahead we keep incrementing it.
     pub fn next(&self) -> Version {
-        let ts: HybridTimestamp = Utc::now().into();
-        if ts > self.0 {
-            Version(ts)
-        }
-        else {
-            Version(self.0.inc())
-        }
+        Version(self.0.tick())
+    }
+
+    /// Merge a version from another clock.
+    ///
+    /// When importing operations that were versioned by a different importer or
+    /// machine this should be called with each remote version so that any new versions
+    /// generated afterwards are ordered after them.
+    pub fn receive(&self, remote: Version) -> Version {
+        Version(self.0.receive(remote.0))
     }
 
     pub fn inc(&self) -> Version {
         Version(self.0.inc())
     }
+
+    pub fn timestamp(&self) -> HybridTimestamp {
+        self.0
+    }
+}
+
+impl Default for Version {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<HybridTimestamp> for Version {
+    fn from(value: HybridTimestamp) -> Self {
+        Version(value)
+    }
 }
 
 impl From<Version> for BigDecimal {
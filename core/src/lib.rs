@@ -1,5 +1,7 @@
+pub mod comparison;
 pub mod crdt;
 pub mod models;
+pub mod names;
 pub mod schema;
 pub mod schema_gnl;
 pub mod search;
@@ -0,0 +1,63 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use crate::schema;
+
+
+/// Why an operation was removed from its log table
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
+#[strum(serialize_all = "snake_case")]
+pub enum DeletionReason {
+    /// The operation was superseded and moved into the archive table
+    Compaction,
+    /// The dataset version the operation was imported under was removed
+    Rollback,
+}
+
+
+/// An operation that was deleted from a log table.
+///
+/// Deletions share the insertion sequence of the log tables so that replicas following
+/// the sequence see them in the same order as the operations they remove.
+#[derive(Queryable, Selectable, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = schema::operation_log_deletions)]
+pub struct OperationLogDeletion {
+    pub log_sequence: i64,
+    pub log_table: String,
+    pub operation_id: BigDecimal,
+    pub entity_id: String,
+    pub reason: String,
+    pub logged_at: DateTime<Utc>,
+}
+
+
+/// Record the `(operation_id, entity_id)` of operations deleted from a log table.
+pub fn record_deletions(
+    conn: &mut PgConnection,
+    table_name: &str,
+    deletion_reason: DeletionReason,
+    deleted: Vec<(BigDecimal, String)>,
+) -> QueryResult<usize> {
+    use schema::operation_log_deletions::dsl::*;
+
+    let reason_name = deletion_reason.to_string();
+    let mut total = 0;
+
+    for chunk in deleted.chunks(1000) {
+        let rows: Vec<_> = chunk
+            .iter()
+            .map(|(op_id, entity)| {
+                (log_table.eq(table_name), operation_id.eq(op_id), entity_id.eq(entity), reason.eq(&reason_name))
+            })
+            .collect();
+
+        total += diesel::insert_into(operation_log_deletions)
+            .values(rows)
+            .execute(conn)?;
+    }
+
+    Ok(total)
+}
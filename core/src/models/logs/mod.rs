@@ -1,23 +1,30 @@
 pub mod agents;
 pub mod data_products;
+pub mod deletions;
 pub mod extractions;
 pub mod projects;
 pub mod sequences;
+pub mod snapshots;
 pub mod specimens;
 pub mod subsamples;
 
 pub use agents::*;
 use bigdecimal::BigDecimal;
 pub use data_products::*;
+pub use deletions::*;
+use diesel::{PgConnection, QueryResult};
 pub use extractions::*;
 pub use projects::*;
 pub use sequences::*;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 pub use specimens::*;
 use strum::Display;
 pub use subsamples::*;
+use uuid::Uuid;
 
 use super::{Dataset, DatasetVersion};
+use crate::crdt::DataFrameOperation;
 use crate::schema;
 
 
@@ -36,6 +43,66 @@ pub trait LogOperation<T> {
     fn atom(&self) -> &T;
 }
 
+/// The names of every variant in an atom enum.
+///
+/// This is implemented by the `Atom` derive and lets importers map a column or field
+/// name to the atom it should produce.
+pub trait AtomVariants {
+    fn variant_names() -> &'static [&'static str];
+}
+
+/// Plumbing to load and store operations in their log table.
+///
+/// This is implemented by the `OperationLog` derive using the diesel `table_name`
+/// attribute on the operation struct, which allows the importers and reducers to work
+/// with any of the log tables generically.
+pub trait OperationLogTable: LogOperation<Self::Atom> + From<DataFrameOperation<Self::Atom>> + Clone + Sized {
+    type Atom: AtomVariants + Serialize + DeserializeOwned + ToString + Clone + PartialEq + Default;
+
+    /// The name of the log table in the database
+    fn table_name() -> &'static str;
+
+    fn parent_id(&self) -> &BigDecimal;
+    fn dataset_version_id(&self) -> &Uuid;
+
+    /// Load all operations in the log table ordered by the operation id
+    fn load_all(conn: &mut PgConnection) -> QueryResult<Vec<Self>>;
+
+    /// Load all operations for a specific entity ordered by the operation id
+    fn load_entity(conn: &mut PgConnection, entity_id: &str) -> QueryResult<Vec<Self>>;
+
+    /// Load all operations for the entities ordered by the operation id
+    fn load_entities(conn: &mut PgConnection, entity_ids: &[String]) -> QueryResult<Vec<Self>>;
+
+    /// Get a page of the entities with operations up to and including the watermark, ordered by the
+    /// entity id and starting after the entity id of the previous page
+    fn entities_until(
+        conn: &mut PgConnection,
+        watermark: &BigDecimal,
+        after: &str,
+        limit: i64,
+    ) -> QueryResult<Vec<String>>;
+
+    /// Load all operations after the watermark ordered by the operation id
+    fn load_after(conn: &mut PgConnection, watermark: &BigDecimal) -> QueryResult<Vec<Self>>;
+
+    /// Remove the operations from the log table and record them as deleted
+    fn delete_all(conn: &mut PgConnection, operation_ids: &[BigDecimal], reason: DeletionReason) -> QueryResult<usize>;
+
+    /// Remove every operation imported under the dataset version and record them as deleted
+    fn delete_dataset_version(
+        conn: &mut PgConnection,
+        dataset_version_id: &Uuid,
+        reason: DeletionReason,
+    ) -> QueryResult<usize>;
+
+    /// Get the entities that already have operations in the log table
+    fn existing_entities(conn: &mut PgConnection, entity_ids: &[String]) -> QueryResult<Vec<String>>;
+
+    /// Insert the operations into the log table, skipping any that already exist
+    fn insert_all(conn: &mut PgConnection, operations: &[Self]) -> QueryResult<usize>;
+}
+
 pub trait LogOperationDataset {
     fn dataset_version(&self) -> &DatasetVersion;
     fn dataset(&self) -> &Dataset;
@@ -0,0 +1,451 @@
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{Action, DeletionReason, OperationLogTable};
+use crate::crdt::hlc::HybridTimestamp;
+use crate::crdt::lww::{self, Map};
+use crate::schema;
+
+
+/// The reduced state of an entity at a point in the operation log.
+///
+/// Snapshots are taken when a log is compacted and contain every atom of the entity
+/// as determined by reducing all operations up to and including the watermark.
+#[derive(Queryable, Selectable, Insertable, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = schema::operation_log_snapshots)]
+pub struct OperationLogSnapshot {
+    pub log_table: String,
+    pub entity_id: String,
+    pub watermark: BigDecimal,
+    pub atoms: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An operation that was superseded by a later operation and moved out of its log table.
+#[derive(Queryable, Selectable, Insertable, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = schema::operation_log_archives)]
+pub struct ArchivedOperation {
+    pub log_table: String,
+    pub operation_id: BigDecimal,
+    pub parent_id: BigDecimal,
+    pub entity_id: String,
+    pub dataset_version_id: Uuid,
+    pub action: Action,
+    pub atom: serde_json::Value,
+    pub archived_at: DateTime<Utc>,
+}
+
+impl ArchivedOperation {
+    pub fn archive<T: OperationLogTable>(op: &T) -> Result<Self, serde_json::Error> {
+        Ok(ArchivedOperation {
+            log_table: T::table_name().to_string(),
+            operation_id: op.id().clone(),
+            parent_id: op.parent_id().clone(),
+            entity_id: op.entity_id().clone(),
+            dataset_version_id: *op.dataset_version_id(),
+            action: op.action().clone(),
+            atom: serde_json::to_value(op.atom())?,
+            archived_at: Utc::now(),
+        })
+    }
+}
+
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Compaction {
+    pub entities: usize,
+    pub snapshots: usize,
+    pub archived: usize,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ChainVerification {
+    pub operations: usize,
+    pub archived: usize,
+    /// Operations with a parent that can't be found in either the log or the archive
+    pub dangling: Vec<BigDecimal>,
+}
+
+impl ChainVerification {
+    pub fn is_valid(&self) -> bool {
+        self.dangling.is_empty()
+    }
+}
+
+
+/// The number of entities compacted before their archived operations are deleted
+const ENTITY_BATCH_SIZE: usize = 10_000;
+
+
+/// The snapshots and archived operations for a batch of compacted entities
+#[derive(Debug, Default)]
+pub struct CompactedEntities {
+    pub entities: usize,
+    pub snapshots: Vec<OperationLogSnapshot>,
+    pub archives: Vec<ArchivedOperation>,
+}
+
+
+/// Compact a log table up to and including the watermark.
+///
+/// Every entity with operations at or before the watermark is reduced and the resulting
+/// state is saved as a snapshot. Operations that no longer determine the state of an entity
+/// are moved into the archive table so that the log only contains the winning operations
+/// while the full history, and thus every parent id, remains available for verification.
+///
+/// Because the winning operations stay in the log table a compacted log reduces to the same
+/// state as an uncompacted one, the snapshots simply allow reducers to skip the older operations.
+pub fn compact<T: OperationLogTable>(conn: &mut PgConnection, watermark: HybridTimestamp) -> QueryResult<Compaction> {
+    let watermark: BigDecimal = watermark.into();
+
+    conn.transaction(|conn| {
+        let mut compaction = Compaction::default();
+        let mut after = String::new();
+
+        // entities are compacted a page at a time so that only the operations of a page are
+        // held in memory and the superseded operations are deleted once per page
+        loop {
+            let entity_ids = T::entities_until(conn, &watermark, &after, ENTITY_BATCH_SIZE as i64)?;
+            let Some(last) = entity_ids.last() else {
+                break;
+            };
+            after = last.clone();
+
+            let operations = T::load_entities(conn, &entity_ids)?;
+            let grouped: Vec<(String, Vec<T>)> = lww::group_by_entity(operations).into_iter().collect();
+
+            let compacted = compact_entities(&grouped, &watermark).map_err(serialization_error)?;
+            compaction.entities += compacted.entities;
+
+            for chunk in compacted.archives.chunks(1000) {
+                compaction.archived += diesel::insert_into(schema::operation_log_archives::table)
+                    .values(chunk)
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+            }
+
+            let ids: Vec<BigDecimal> = compacted.archives.iter().map(|op| op.operation_id.clone()).collect();
+            T::delete_all(conn, &ids, DeletionReason::Compaction)?;
+
+            for chunk in compacted.snapshots.chunks(1000) {
+                compaction.snapshots += diesel::insert_into(schema::operation_log_snapshots::table)
+                    .values(chunk)
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+            }
+        }
+
+        Ok(compaction)
+    })
+}
+
+/// Snapshot the entities and find the operations that no longer determine their state.
+///
+/// Only operations up to and including the watermark are compacted, any later operations
+/// are left alone for a future compaction. The operations of each entity must be in causal order.
+pub fn compact_entities<T: OperationLogTable>(
+    entities: &[(String, Vec<T>)],
+    watermark: &BigDecimal,
+) -> Result<CompactedEntities, serde_json::Error> {
+    let mut compacted = CompactedEntities::default();
+
+    for (entity_id, ops) in entities {
+        let ops: Vec<T> = ops.iter().filter(|op| op.id() <= watermark).cloned().collect();
+        if ops.is_empty() {
+            continue;
+        }
+
+        let mut map = Map::new(entity_id.clone());
+        let winners: HashSet<&BigDecimal> = map.reduce(&ops).into_iter().map(|op| op.id()).collect();
+
+        for op in ops.iter().filter(|op| !winners.contains(op.id())) {
+            compacted.archives.push(ArchivedOperation::archive(op)?);
+        }
+
+        let atoms: Vec<&T::Atom> = map.atoms.values().collect();
+        compacted.snapshots.push(OperationLogSnapshot {
+            log_table: T::table_name().to_string(),
+            entity_id: entity_id.clone(),
+            watermark: watermark.clone(),
+            atoms: serde_json::to_value(atoms)?,
+            created_at: Utc::now(),
+        });
+
+        compacted.entities += 1;
+    }
+
+    Ok(compacted)
+}
+
+/// Get the most recent snapshot for every entity in a log table.
+pub fn latest_snapshots<T: OperationLogTable>(conn: &mut PgConnection) -> QueryResult<Vec<OperationLogSnapshot>> {
+    use schema::operation_log_snapshots::dsl::*;
+
+    operation_log_snapshots
+        .filter(log_table.eq(T::table_name()))
+        .distinct_on(entity_id)
+        .order((entity_id, watermark.desc()))
+        .select(OperationLogSnapshot::as_select())
+        .load(conn)
+}
+
+/// Reduce every entity in a log table starting from the latest snapshots.
+///
+/// Each entity is seeded with the atoms from its latest snapshot and only the operations
+/// after the snapshot watermark are applied. Logs that have never been compacted are
+/// reduced from the first operation.
+pub fn reduce_from_snapshots<T: OperationLogTable>(conn: &mut PgConnection) -> QueryResult<Vec<Map<T::Atom>>> {
+    let snapshots = latest_snapshots::<T>(conn)?;
+
+    // a compaction snapshots every entity with operations before the watermark so
+    // the lowest watermark is the earliest operation we still need to look at
+    let operations = match snapshots.iter().map(|snapshot| &snapshot.watermark).min() {
+        Some(floor) => T::load_after(conn, floor)?,
+        None => T::load_all(conn)?,
+    };
+
+    reduce_snapshots(snapshots, operations).map_err(deserialization_error)
+}
+
+/// Seed each entity with its snapshot and reduce the operations after the snapshot watermark.
+pub fn reduce_snapshots<T: OperationLogTable>(
+    snapshots: Vec<OperationLogSnapshot>,
+    operations: Vec<T>,
+) -> Result<Vec<Map<T::Atom>>, serde_json::Error> {
+    let mut entities: HashMap<String, (Option<BigDecimal>, Map<T::Atom>)> = HashMap::new();
+
+    for snapshot in snapshots {
+        let atoms: Vec<T::Atom> = serde_json::from_value(snapshot.atoms)?;
+
+        let mut map = Map::new(snapshot.entity_id.clone());
+        for atom in atoms {
+            map.atoms.insert(atom.to_string(), atom);
+        }
+
+        entities.insert(snapshot.entity_id, (Some(snapshot.watermark), map));
+    }
+
+    for (entity_id, ops) in lww::group_by_entity(operations) {
+        let (entity_watermark, map) = entities
+            .entry(entity_id.clone())
+            .or_insert_with(|| (None, Map::new(entity_id)));
+
+        let ops: Vec<T> = match entity_watermark {
+            Some(entity_watermark) => ops.into_iter().filter(|op| *op.id() > *entity_watermark).collect(),
+            None => ops,
+        };
+
+        map.reduce(&ops);
+    }
+
+    Ok(entities.into_values().map(|(_, map)| map).collect())
+}
+
+/// Verify that the parent of every operation in a log table can still be found.
+///
+/// Compaction moves operations into the archive so a parent can live in either the
+/// log table or the archive. Operations that are their own parent start a chain.
+pub fn verify_chain<T: OperationLogTable>(conn: &mut PgConnection) -> QueryResult<ChainVerification> {
+    use schema::operation_log_archives::dsl::*;
+
+    let operations = T::load_all(conn)?;
+    let archived = operation_log_archives
+        .filter(log_table.eq(T::table_name()))
+        .select((operation_id, parent_id))
+        .load::<(BigDecimal, BigDecimal)>(conn)?;
+
+    let chain: Vec<(BigDecimal, BigDecimal)> = operations
+        .iter()
+        .map(|op| (op.id().clone(), op.parent_id().clone()))
+        .collect();
+
+    Ok(ChainVerification {
+        operations: operations.len(),
+        archived: archived.len(),
+        dangling: dangling_parents(&chain, &archived),
+    })
+}
+
+/// Find the operations with a parent that is neither in the log nor the archive.
+///
+/// Both lists are pairs of an operation id and its parent id.
+pub fn dangling_parents(
+    operations: &[(BigDecimal, BigDecimal)],
+    archived: &[(BigDecimal, BigDecimal)],
+) -> Vec<BigDecimal> {
+    let mut ids: HashSet<&BigDecimal> = operations.iter().map(|(id, _)| id).collect();
+    ids.extend(archived.iter().map(|(id, _)| id));
+
+    let mut dangling: Vec<BigDecimal> = operations
+        .iter()
+        .chain(archived.iter())
+        .filter(|(id, parent)| id != parent && !ids.contains(parent))
+        .map(|(id, _)| id.clone())
+        .collect();
+    dangling.sort();
+    dangling
+}
+
+
+fn serialization_error(err: serde_json::Error) -> diesel::result::Error {
+    diesel::result::Error::SerializationError(Box::new(err))
+}
+
+fn deserialization_error(err: serde_json::Error) -> diesel::result::Error {
+    diesel::result::Error::DeserializationError(Box::new(err))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::logs::{AgentAtom, AgentOperation};
+
+    fn op(id: u64, parent_id: u64, entity_id: &str, atom: AgentAtom) -> AgentOperation {
+        AgentOperation {
+            operation_id: BigDecimal::from(id),
+            parent_id: BigDecimal::from(parent_id),
+            entity_id: entity_id.to_string(),
+            dataset_version_id: Uuid::nil(),
+            action: Action::Update,
+            atom,
+        }
+    }
+
+    fn name(value: &str) -> AgentAtom {
+        AgentAtom::FullName(value.to_string())
+    }
+
+    fn operations() -> Vec<AgentOperation> {
+        vec![
+            op(1, 1, "a", name("Smith")),
+            op(2, 1, "a", AgentAtom::Orcid("0000-0001".into())),
+            op(3, 2, "b", name("Jones")),
+            op(4, 2, "a", name("J. Smith")),
+            op(5, 3, "b", name("Jones")),
+            op(6, 4, "a", name("John Smith")),
+            op(7, 5, "c", name("Brown")),
+        ]
+    }
+
+    fn group(operations: Vec<AgentOperation>) -> Vec<(String, Vec<AgentOperation>)> {
+        let mut grouped: Vec<(String, Vec<AgentOperation>)> = lww::group_by_entity(operations).into_iter().collect();
+        grouped.sort_by(|a, b| a.0.cmp(&b.0));
+        grouped
+    }
+
+    fn states(entities: Vec<Map<AgentAtom>>) -> Vec<(String, Vec<(String, AgentAtom)>)> {
+        let mut states: Vec<(String, Vec<(String, AgentAtom)>)> = entities
+            .into_iter()
+            .map(|map| {
+                let mut atoms: Vec<(String, AgentAtom)> = map.atoms.into_iter().collect();
+                atoms.sort_by(|a, b| a.0.cmp(&b.0));
+                (map.entity_id, atoms)
+            })
+            .collect();
+        states.sort_by(|a, b| a.0.cmp(&b.0));
+        states
+    }
+
+    /// Compact the operations and return what would remain in the log table
+    fn compact_log(
+        operations: Vec<AgentOperation>,
+        watermark: u64,
+    ) -> (Vec<OperationLogSnapshot>, Vec<ArchivedOperation>, Vec<AgentOperation>) {
+        let compacted = compact_entities(&group(operations.clone()), &BigDecimal::from(watermark)).unwrap();
+        let archived: HashSet<&BigDecimal> = compacted.archives.iter().map(|op| &op.operation_id).collect();
+        let remaining = operations
+            .iter()
+            .filter(|op| !archived.contains(&op.operation_id))
+            .cloned()
+            .collect();
+        (compacted.snapshots, compacted.archives, remaining)
+    }
+
+    #[test]
+    fn snapshot_reduce_matches_full_reduce() {
+        let full = states(lww::reduce_entities(operations()));
+
+        for watermark in 0..=8 {
+            let (snapshots, _, remaining) = compact_log(operations(), watermark);
+            let floor = BigDecimal::from(watermark);
+
+            // the reducer only loads operations after the lowest snapshot watermark
+            let remaining = match snapshots.is_empty() {
+                true => remaining,
+                false => remaining.into_iter().filter(|op| op.operation_id > floor).collect(),
+            };
+
+            let reduced = reduce_snapshots(snapshots, remaining).unwrap();
+            assert_eq!(states(reduced), full, "watermark {watermark}");
+        }
+    }
+
+    #[test]
+    fn operations_at_the_watermark_are_compacted() {
+        // operation 4 supersedes operation 1 and is exactly at the watermark
+        let (snapshots, archives, _) = compact_log(operations(), 4);
+        let archived: Vec<String> = archives.iter().map(|op| op.operation_id.to_string()).collect();
+
+        assert_eq!(archived, vec!["1"]);
+        assert_eq!(snapshots.len(), 2);
+        assert!(
+            snapshots
+                .iter()
+                .all(|snapshot| snapshot.watermark == BigDecimal::from(4))
+        );
+    }
+
+    #[test]
+    fn operations_after_the_watermark_are_not_compacted() {
+        // operation 6 supersedes operation 4 but is after the watermark so neither is archived
+        let (snapshots, archives, remaining) = compact_log(operations(), 5);
+        let archived: Vec<String> = archives.iter().map(|op| op.operation_id.to_string()).collect();
+
+        // the repeated name on b doesn't change the entity so it's superseded as well
+        assert_eq!(archived, vec!["1", "5"]);
+        assert!(remaining.iter().any(|op| op.operation_id == BigDecimal::from(6)));
+        assert!(!snapshots.iter().any(|snapshot| snapshot.entity_id == "c"));
+    }
+
+    #[test]
+    fn compacting_before_the_first_operation_does_nothing() {
+        let (snapshots, archives, remaining) = compact_log(operations(), 0);
+        assert!(snapshots.is_empty());
+        assert!(archives.is_empty());
+        assert_eq!(remaining.len(), operations().len());
+    }
+
+    #[test]
+    fn compacted_chain_is_valid() {
+        let (_, archives, remaining) = compact_log(operations(), 8);
+        let chain: Vec<(BigDecimal, BigDecimal)> = remaining
+            .iter()
+            .map(|op| (op.operation_id.clone(), op.parent_id.clone()))
+            .collect();
+        let archived: Vec<(BigDecimal, BigDecimal)> = archives
+            .iter()
+            .map(|op| (op.operation_id.clone(), op.parent_id.clone()))
+            .collect();
+
+        assert_eq!(dangling_parents(&chain, &archived), Vec::<BigDecimal>::new());
+    }
+
+    #[test]
+    fn broken_chain_is_dangling() {
+        // deleting the superseded operations without archiving them breaks the chain
+        let (_, _, remaining) = compact_log(operations(), 8);
+        let chain: Vec<(BigDecimal, BigDecimal)> = remaining
+            .iter()
+            .map(|op| (op.operation_id.clone(), op.parent_id.clone()))
+            .collect();
+
+        let dangling = dangling_parents(&chain, &[]);
+        assert_eq!(dangling, vec![BigDecimal::from(2), BigDecimal::from(6), BigDecimal::from(7)]);
+    }
+}
@@ -135,6 +135,23 @@ pub struct DatasetVersion {
     pub imported_at: DateTime<Utc>,
 }
 
+/// A taxonomy dataset registered as a backbone for classifying names
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = schema::taxonomic_backbones)]
+pub struct TaxonomicBackbone {
+    pub dataset_id: Uuid,
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The backbone used by default for the datasets of a source
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = schema::source_backbones)]
+pub struct SourceBackbone {
+    pub source_id: Uuid,
+    pub backbone_id: Uuid,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, diesel_derive_enum::DbEnum)]
 #[ExistingTypePath = "schema::sql_types::TaxonomicStatus"]
 pub enum TaxonomicStatus {
@@ -1187,6 +1204,14 @@ pub struct TraceData {
     pub trace_id: Option<String>,
     pub trace_name: Option<String>,
     pub trace_link: Option<String>,
+
+    pub a_analyzed: Option<Vec<Option<i16>>>,
+    pub c_analyzed: Option<Vec<Option<i16>>>,
+    pub g_analyzed: Option<Vec<Option<i16>>>,
+    pub t_analyzed: Option<Vec<Option<i16>>>,
+    pub base_calls: Option<String>,
+    pub quality_scores: Option<Vec<Option<i16>>>,
+    pub peak_locations: Option<Vec<Option<i16>>>,
 }
 
 #[derive(Clone, Queryable, Insertable, Debug, Serialize, Deserialize)]
@@ -1230,7 +1255,7 @@ pub enum PublicationType {
     Url,
 }
 
-#[derive(Queryable, Selectable, Insertable, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Queryable, Selectable, Insertable, Debug, Default, Serialize, Deserialize)]
 #[diesel(table_name = schema::publications)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Publication {
@@ -1252,6 +1277,35 @@ pub struct Publication {
 
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+
+    pub journal: Option<String>,
+    pub volume: Option<String>,
+    pub issue: Option<String>,
+    pub pages: Option<String>,
+}
+
+
+#[derive(Queryable, Selectable, Insertable, AsChangeset, Debug, Clone, Default, Serialize, Deserialize)]
+#[diesel(table_name = schema::treatments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Treatment {
+    pub entity_id: String,
+    pub dataset_version_id: Uuid,
+    pub publication_entity_id: String,
+    pub treatment_uri: String,
+    pub lsid: Option<String>,
+    pub scientific_name: Option<String>,
+    pub canonical_name: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug, Clone, Default, Serialize, Deserialize)]
+#[diesel(table_name = schema::treatment_sections)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TreatmentSection {
+    pub treatment_entity_id: String,
+    pub position: i32,
+    pub section_type: String,
+    pub text: String,
 }
 
 
@@ -288,6 +288,10 @@ pub enum PublicationAtom {
     SourceUrl(String),
     Type(PublicationType),
     Citation(String),
+    Journal(String),
+    Volume(String),
+    Issue(String),
+    Pages(String),
     RecordCreatedAt(DateTime<Utc>),
     RecordUpdatedAt(DateTime<Utc>),
 }
@@ -304,52 +308,3 @@ pub struct PublicationOperation {
     pub action: Action,
     pub atom: PublicationAtom,
 }
-
-
-#[derive(Atom, Debug, Default, Clone, Serialize, Deserialize, AsExpression, FromSqlRow, PartialEq, Display)]
-#[diesel(sql_type = diesel::sql_types::Jsonb)]
-pub enum SpecimenAtom {
-    #[default]
-    Empty,
-
-    /// The globally unique specimen id.
-    SpecimenId(String),
-
-    /// Used to link the collection to a name.
-    ScientificName(String),
-
-    /// When the specimen registration happened. Strictly YYYY-MM-DD
-    EventDate(String),
-    /// What time the specimen registration happened. Strictly HH:MM:SS
-    EventTime(String),
-
-    /// The name of the institution that owns the specimen.
-    InstitutionName(String),
-    /// The short code of the institution.
-    InstitutionCode(String),
-    /// The ID for the specific specimen. Typically the voucher registration number.
-    CollectionRepositoryId(String),
-    /// The code for the specific collection repository in the institution.
-    CollectionRepositoryCode(String),
-
-    TypeStatus(String),
-
-    Preparation(String),
-
-    OtherCatalogNumbers(String),
-
-    Disposition(String),
-}
-
-#[derive(OperationLog, Queryable, Selectable, Insertable, Associations, Debug, Serialize, Deserialize, Clone)]
-#[diesel(belongs_to(DatasetVersion))]
-#[diesel(table_name = schema::specimen_logs)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
-pub struct SpecimenOperation {
-    pub operation_id: BigDecimal,
-    pub parent_id: BigDecimal,
-    pub entity_id: String,
-    pub dataset_version_id: Uuid,
-    pub action: Action,
-    pub atom: SpecimenAtom,
-}
@@ -69,6 +69,28 @@ pub struct SequenceRun {
 }
 
 
+/// A Sanger chromatogram with the analyzed channels in ACGT order
+#[derive(Clone, Queryable, Selectable, Insertable, Debug, Serialize, Deserialize)]
+#[diesel(table_name = schema::sequence_traces)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SequenceTrace {
+    pub trace_id: String,
+    pub process_id: String,
+    pub sequence_run_id: Option<String>,
+    pub trace_name: String,
+    pub trace_link: String,
+
+    pub a_analyzed: Option<Vec<Option<i16>>>,
+    pub c_analyzed: Option<Vec<Option<i16>>>,
+    pub g_analyzed: Option<Vec<Option<i16>>>,
+    pub t_analyzed: Option<Vec<Option<i16>>>,
+
+    pub base_calls: Option<String>,
+    pub quality_scores: Option<Vec<Option<i16>>>,
+    pub peak_locations: Option<Vec<Option<i16>>>,
+}
+
+
 #[derive(Clone, Queryable, Selectable, Insertable, Debug, Serialize, Deserialize)]
 #[diesel(table_name = schema::assemblies)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
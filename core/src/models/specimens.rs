@@ -77,7 +77,7 @@ pub struct Tissue {
 }
 
 
-#[derive(Clone, Queryable, Selectable, Insertable, Debug, Serialize, Deserialize)]
+#[derive(Clone, Queryable, Selectable, Insertable, Debug, Default, Serialize, Deserialize)]
 #[diesel(table_name = schema::collection_events)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct CollectionEvent {
@@ -123,6 +123,84 @@ pub struct CollectionEvent {
     pub strain: Option<String>,
     pub isolate: Option<String>,
     pub field_notes: Option<String>,
+
+    /// Set when the latitude and longitude have been generalised by a sensitive data rule
+    pub location_generalisation: Option<LocationGeneralisation>,
+}
+
+
+/// How precisely the location of a sensitive collection event can be published.
+///
+/// The variants are ordered from least to most restrictive so that the most restrictive
+/// generalisation can be picked when multiple rules apply to the same collection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, diesel_derive_enum::DbEnum)]
+#[ExistingTypePath = "schema::sql_types::LocationGeneralisation"]
+pub enum LocationGeneralisation {
+    /// Round the coordinates to one decimal place, roughly 11km at the equator
+    TenthDegree,
+    /// Round the coordinates to a whole degree, roughly 111km at the equator
+    OneDegree,
+    /// Don't publish the coordinates at all
+    Withhold,
+}
+
+impl LocationGeneralisation {
+    /// Get the coordinates that can be published, if any
+    pub fn apply(&self, latitude: f64, longitude: f64) -> Option<(f64, f64)> {
+        Some((self.coordinate(latitude)?, self.coordinate(longitude)?))
+    }
+
+    /// Get a single latitude or longitude that can be published, if any.
+    /// Used when the coordinates aren't available as a pair, such as the atoms of an operation log
+    pub fn coordinate(&self, value: f64) -> Option<f64> {
+        match self {
+            LocationGeneralisation::TenthDegree => Some(round(value, 10.0)),
+            LocationGeneralisation::OneDegree => Some(value.round()),
+            LocationGeneralisation::Withhold => None,
+        }
+    }
+
+    /// Generalise a pair of optional coordinates.
+    ///
+    /// A lone latitude or longitude doesn't locate anything on its own but there's no
+    /// reason to publish it for a sensitive collection either so it is also withheld.
+    pub fn generalise(&self, latitude: Option<f64>, longitude: Option<f64>) -> (Option<f64>, Option<f64>) {
+        match (latitude, longitude) {
+            (Some(latitude), Some(longitude)) => match self.apply(latitude, longitude) {
+                Some((latitude, longitude)) => (Some(latitude), Some(longitude)),
+                None => (None, None),
+            },
+            _ => (None, None),
+        }
+    }
+}
+
+fn round(value: f64, scale: f64) -> f64 {
+    (value * scale).round() / scale
+}
+
+
+/// A rule requiring the locations of a species or everything in a dataset to be generalised
+#[derive(Clone, Queryable, Selectable, Insertable, Debug, Serialize, Deserialize)]
+#[diesel(table_name = schema::sensitive_data_rules)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SensitiveDataRule {
+    pub id: Uuid,
+    pub name_id: Option<Uuid>,
+    pub dataset_id: Option<Uuid>,
+    pub generalisation: LocationGeneralisation,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The precise coordinates of a generalised collection event
+#[derive(Clone, Queryable, Selectable, Insertable, Debug, Serialize, Deserialize)]
+#[diesel(table_name = schema::sensitive_locations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SensitiveLocation {
+    pub entity_id: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
 }
 
 #[derive(Clone, Queryable, Selectable, Insertable, Debug, Serialize, Deserialize)]
@@ -169,4 +247,63 @@ pub struct SpecimenStats {
     pub assembly_chromosomes: i64,
     pub assembly_scaffolds: i64,
     pub assembly_contigs: i64,
+    /// The number of accessions with a type status
+    pub types: i64,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generalises_to_a_tenth_of_a_degree() {
+        let coords = LocationGeneralisation::TenthDegree.apply(-33.8688, 151.2093);
+        assert_eq!(coords, Some((-33.9, 151.2)));
+    }
+
+    #[test]
+    fn generalises_to_a_degree() {
+        let coords = LocationGeneralisation::OneDegree.apply(-33.8688, 151.2093);
+        assert_eq!(coords, Some((-34.0, 151.0)));
+    }
+
+    #[test]
+    fn withholds_coordinates() {
+        assert_eq!(LocationGeneralisation::Withhold.apply(-33.8688, 151.2093), None);
+    }
+
+    #[test]
+    fn generalises_single_coordinates() {
+        assert_eq!(LocationGeneralisation::TenthDegree.coordinate(151.2093), Some(151.2));
+        assert_eq!(LocationGeneralisation::OneDegree.coordinate(-33.8688), Some(-34.0));
+        assert_eq!(LocationGeneralisation::Withhold.coordinate(-33.8688), None);
+    }
+
+    #[test]
+    fn generalises_complete_coordinates() {
+        let coords = LocationGeneralisation::OneDegree.generalise(Some(-33.8688), Some(151.2093));
+        assert_eq!(coords, (Some(-34.0), Some(151.0)));
+
+        let coords = LocationGeneralisation::Withhold.generalise(Some(-33.8688), Some(151.2093));
+        assert_eq!(coords, (None, None));
+    }
+
+    #[test]
+    fn withholds_incomplete_coordinates() {
+        let coords = LocationGeneralisation::TenthDegree.generalise(Some(-33.8688), None);
+        assert_eq!(coords, (None, None));
+    }
+
+    #[test]
+    fn orders_by_restriction() {
+        let strictest = [
+            LocationGeneralisation::OneDegree,
+            LocationGeneralisation::Withhold,
+            LocationGeneralisation::TenthDegree,
+        ]
+        .into_iter()
+        .max();
+        assert_eq!(strictest, Some(LocationGeneralisation::Withhold));
+    }
 }
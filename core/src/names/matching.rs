@@ -0,0 +1,622 @@
+//! Match names against the names in the database.
+//!
+//! Names are matched in stages, each with a lower confidence than the one before it:
+//!
+//! 1. exact, the scientific name is identical
+//! 2. canonical, the names are the same without authorship
+//! 3. stemmed, the epithets only differ in their Latin gender endings like `-us`, `-a` and `-um`
+//! 4. fuzzy, the names are within a small edit distance of each other
+//!
+//! When the query has an authorship it is compared with the authorship of the candidates
+//! so that homonyms and variations in the authority are ranked accordingly.
+//!
+//! Loading the candidates and following synonyms needs a database connection, so this
+//! module provides the queries and leaves running them to the sync and async callers.
+
+use std::collections::{HashMap, HashSet};
+
+use diesel::expression::functions::define_sql_function;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_builder::{BoxedSqlQuery, SqlQuery};
+use diesel::sql_types::{Array, BigInt, Integer, Nullable, Text, Uuid as SqlUuid, Varchar};
+use uuid::Uuid;
+
+use super::{parse, parse_authority, ParsedName};
+use crate::models::{Name, ACCEPTED_NAMES};
+use crate::schema;
+
+
+/// Fuzzy matches with a lower similarity than this are discarded
+pub const FUZZY_THRESHOLD: f64 = 0.8;
+
+const CANONICAL_CONFIDENCE: f64 = 0.95;
+const STEMMED_CONFIDENCE: f64 = 0.85;
+const FUZZY_CONFIDENCE: f64 = 0.8;
+
+/// How much a disagreeing authorship can lower the confidence of a match
+const AUTHORITY_WEIGHT: f64 = 0.1;
+
+/// Latin endings removed when stemming an epithet, longest first
+const ENDINGS: &[&str] = &["ii", "ae", "us", "um", "is", "a", "e", "i"];
+
+
+define_sql_function!(fn split_part(string: Varchar, delimiter: Text, field: Integer) -> Text);
+define_sql_function!(fn lower(text: Text) -> Text);
+define_sql_function!(fn left(text: Text, n: Integer) -> Text);
+
+
+/// How a name was matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum MatchType {
+    Exact,
+    Canonical,
+    Stemmed,
+    Fuzzy,
+}
+
+
+/// A candidate name matched to a query
+#[derive(Debug, Clone)]
+pub struct NameMatch {
+    pub name: Name,
+    pub match_type: MatchType,
+    /// How likely the candidate is the queried name, from 0 to 1
+    pub confidence: f64,
+    /// The accepted name when the matched name is a synonym
+    pub accepted: Option<Name>,
+}
+
+
+/// The parts of a name used to compare it with other names.
+///
+/// Everything is lowercase and the subgenus is left out since it is often omitted.
+#[derive(Debug, Clone)]
+struct NameKey {
+    genus: String,
+    epithets: Vec<String>,
+    stems: Vec<String>,
+    canonical: String,
+}
+
+impl NameKey {
+    fn new(name: &str, parsed: Option<&ParsedName>) -> NameKey {
+        let (genus, epithets): (String, Vec<String>) = match parsed {
+            Some(parsed) => {
+                let epithets = [&parsed.specific_epithet, &parsed.infraspecific_epithet];
+                let epithets = epithets.into_iter().flatten().map(|epithet| epithet.to_lowercase()).collect();
+                (parsed.genus.to_lowercase(), epithets)
+            }
+            // names that can't be parsed are compared word for word
+            None => {
+                let mut words = name.split_whitespace().map(|word| word.to_lowercase());
+                (words.next().unwrap_or_default(), words.collect())
+            }
+        };
+
+        let stems = epithets.iter().map(|epithet| stem(epithet)).collect();
+        let mut canonical = genus.clone();
+        for epithet in &epithets {
+            canonical.push(' ');
+            canonical.push_str(epithet);
+        }
+
+        NameKey {
+            genus,
+            epithets,
+            stems,
+            canonical,
+        }
+    }
+}
+
+
+/// A name to match
+#[derive(Debug, Clone)]
+pub struct NameQuery {
+    pub verbatim: String,
+    pub normalised: String,
+    pub parsed: Option<ParsedName>,
+    /// The parsed name formatted the same way as the names in the database
+    formatted: Option<String>,
+    key: NameKey,
+}
+
+impl NameQuery {
+    pub fn new(name: &str) -> NameQuery {
+        let normalised = normalise(name);
+        let parsed = parse(&normalised);
+        let key = NameKey::new(&normalised, parsed.as_ref());
+        let formatted = parsed.as_ref().map(|parsed| parsed.scientific_name());
+
+        NameQuery {
+            verbatim: name.to_string(),
+            normalised,
+            parsed,
+            formatted,
+            key,
+        }
+    }
+}
+
+
+struct Candidate {
+    name: Name,
+    key: NameKey,
+    authority: Option<String>,
+}
+
+/// Matches queries against a set of candidate names loaded from the database
+pub struct Matcher {
+    candidates: Vec<Candidate>,
+    by_genus: HashMap<String, Vec<usize>>,
+    by_epithet: HashMap<String, Vec<usize>>,
+}
+
+impl Matcher {
+    pub fn new(names: Vec<Name>) -> Matcher {
+        let mut candidates = Vec::with_capacity(names.len());
+        let mut by_genus: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_epithet: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (idx, name) in names.into_iter().enumerate() {
+            // the scientific name keeps the subgenus in parentheses so it parses more reliably
+            let parsed = parse(&name.scientific_name).or_else(|| parse(&name.canonical_name));
+            let key = NameKey::new(&name.canonical_name, parsed.as_ref());
+            let authority = name.authorship.clone().or_else(|| parsed.and_then(|parsed| parsed.authority()));
+
+            by_genus.entry(key.genus.clone()).or_default().push(idx);
+            if let Some(epithet) = key.epithets.first() {
+                by_epithet.entry(epithet.clone()).or_default().push(idx);
+            }
+
+            candidates.push(Candidate { name, key, authority });
+        }
+
+        Matcher {
+            candidates,
+            by_genus,
+            by_epithet,
+        }
+    }
+
+    /// Match the query against every candidate that shares its genus or first epithet.
+    ///
+    /// The matches are ordered by confidence with the most likely match first.
+    pub fn match_name(&self, query: &NameQuery) -> Vec<NameMatch> {
+        let mut indices: HashSet<usize> = HashSet::new();
+        if let Some(genus) = self.by_genus.get(&query.key.genus) {
+            indices.extend(genus);
+        }
+        if let Some(epithet) = query.key.epithets.first().and_then(|epithet| self.by_epithet.get(epithet)) {
+            indices.extend(epithet);
+        }
+
+        let mut matches: Vec<NameMatch> = indices
+            .into_iter()
+            .filter_map(|idx| {
+                let candidate = &self.candidates[idx];
+                let (match_type, confidence) = score(query, candidate)?;
+                Some(NameMatch {
+                    name: candidate.name.clone(),
+                    match_type,
+                    confidence,
+                    accepted: None,
+                })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then_with(|| a.name.scientific_name.cmp(&b.name.scientific_name))
+        });
+        matches
+    }
+}
+
+
+/// The most likely match, or nothing when the top candidates are equally likely
+pub fn best_match(matches: &[NameMatch]) -> Option<&NameMatch> {
+    match matches {
+        [first, second, ..] if (first.confidence - second.confidence).abs() < f64::EPSILON => None,
+        [first, ..] => Some(first),
+        [] => None,
+    }
+}
+
+/// Set the accepted name on every match of a name that is a synonym
+pub fn follow_synonyms(matches: &mut [NameMatch], accepted: &HashMap<Uuid, Name>) {
+    for name_match in matches.iter_mut() {
+        name_match.accepted = accepted.get(&name_match.name.id).cloned();
+    }
+}
+
+
+/// Collapse whitespace and strip the quotes names are sometimes wrapped in
+pub fn normalise(name: &str) -> String {
+    let name = name.trim().trim_matches(|ch| ch == '"' || ch == '\'');
+    name.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Remove the gender ending of a Latin epithet.
+///
+/// Masculine `-er` endings drop the `e` so that `niger`, `nigra` and `nigrum` all stem to `nigr`.
+pub fn stem(epithet: &str) -> String {
+    let epithet = epithet.to_lowercase();
+
+    let stemmed = if let Some(base) = epithet.strip_suffix("ensis").or_else(|| epithet.strip_suffix("ense")) {
+        format!("{base}ens")
+    }
+    else if let Some(base) = epithet.strip_suffix("er") {
+        format!("{base}r")
+    }
+    else {
+        let base = ENDINGS.iter().find_map(|ending| epithet.strip_suffix(ending));
+        base.unwrap_or(&epithet).to_string()
+    };
+
+    // very short epithets are too ambiguous once stemmed
+    match stemmed.chars().count() >= 3 {
+        true => stemmed,
+        false => epithet,
+    }
+}
+
+/// The similarity of two strings from 0 to 1 based on their Damerau-Levenshtein distance
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    // optimal string alignment distance with the two previous rows kept around for transpositions
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+
+fn score(query: &NameQuery, candidate: &Candidate) -> Option<(MatchType, f64)> {
+    let scientific_name = Some(&candidate.name.scientific_name);
+    if candidate.name.scientific_name == query.normalised || scientific_name == query.formatted.as_ref() {
+        return Some((MatchType::Exact, 1.0));
+    }
+
+    let same_genus = candidate.key.genus == query.key.genus;
+    let same_stems = !query.key.stems.is_empty() && candidate.key.stems == query.key.stems;
+
+    let (match_type, confidence) = if candidate.key.canonical == query.key.canonical {
+        (MatchType::Canonical, CANONICAL_CONFIDENCE)
+    }
+    else if same_genus && same_stems {
+        (MatchType::Stemmed, STEMMED_CONFIDENCE)
+    }
+    else {
+        let similarity = similarity(&candidate.key.canonical, &query.key.canonical);
+        if similarity < FUZZY_THRESHOLD {
+            return None;
+        }
+        (MatchType::Fuzzy, FUZZY_CONFIDENCE * similarity)
+    };
+
+    let query_authority = query.parsed.as_ref().and_then(|parsed| parsed.authority());
+    let confidence = match (query_authority, &candidate.authority) {
+        (Some(query_authority), Some(authority)) => {
+            let agreement = authority_similarity(&query_authority, authority);
+            confidence - AUTHORITY_WEIGHT * (1.0 - agreement)
+        }
+        _ => confidence,
+    };
+
+    Some((match_type, confidence))
+}
+
+/// Compare two authorities by their authors and years.
+///
+/// Punctuation and spacing is ignored and years that disagree halve the similarity.
+pub fn authority_similarity(a: &str, b: &str) -> f64 {
+    let (a_authors, a_years) = authority_parts(a);
+    let (b_authors, b_years) = authority_parts(b);
+
+    let similarity = similarity(&a_authors, &b_authors);
+    match !a_years.is_empty() && !b_years.is_empty() && a_years != b_years {
+        true => similarity / 2.0,
+        false => similarity,
+    }
+}
+
+fn authority_parts(authority: &str) -> (String, Vec<String>) {
+    let (authorship, basionym) = parse_authority(authority);
+    let authorships = [basionym, authorship];
+    let authorships = authorships.iter().flatten();
+
+    let authors = authorships
+        .clone()
+        .flat_map(|authorship| authorship.authors.chars())
+        .filter(|ch| ch.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let years = authorships.filter_map(|authorship| authorship.year.clone()).collect();
+
+    (authors, years)
+}
+
+
+/// Load every name that could match one of the queries.
+///
+/// Candidates share the genus or first epithet of a query, which also covers misspelt genera
+/// and names moved to another genus, and are then compared with [`Matcher::match_name`].
+///
+/// Common genera and epithets like `australis` would pull in a large part of the names table
+/// for a big batch, so candidates are also bounded by initials. A candidate in the same genus
+/// must share the initial of the first epithet, after the subgenus if it has one, and a
+/// candidate with the same epithet must share the initial of the genus. Canonical and stemmed
+/// matches always do, which means only fuzzy matches misspelling an initial are missed.
+pub fn candidates_query(queries: &[NameQuery]) -> schema::names::BoxedQuery<'static, Pg> {
+    use schema::names;
+
+    let scientific_names: Vec<String> = queries.iter().map(|query| query.normalised.clone()).collect();
+    let (genus_keys, epithet_keys) = candidate_keys(queries);
+
+    let genus = lower(split_part(names::canonical_name, " ", 1));
+    let genus_initial = lower(left(names::canonical_name, 1));
+    let epithet = split_part(names::canonical_name, " ", 2);
+    let epithet_initial = lower(left(split_part(names::canonical_name, " ", 2), 1));
+    let subgenus_epithet_initial = lower(left(split_part(names::canonical_name, " ", 3), 1));
+
+    names::table
+        .filter(names::scientific_name.eq_any(scientific_names))
+        .or_filter(genus.concat(" ").concat(epithet_initial).eq_any(genus_keys.clone()))
+        .or_filter(genus.concat(" ").concat(subgenus_epithet_initial).eq_any(genus_keys))
+        .or_filter(epithet.concat(" ").concat(genus_initial).eq_any(epithet_keys))
+        .into_boxed()
+}
+
+/// The genus and first epithet initial, and the first epithet and genus initial, of every query
+fn candidate_keys(queries: &[NameQuery]) -> (Vec<String>, Vec<String>) {
+    let initial = |text: &str| text.chars().next().map(String::from).unwrap_or_default();
+
+    let genus_keys = queries
+        .iter()
+        .map(|query| {
+            let epithet = query.key.epithets.first().map(|epithet| initial(epithet)).unwrap_or_default();
+            format!("{} {epithet}", query.key.genus)
+        })
+        .collect();
+
+    let epithet_keys = queries
+        .iter()
+        .filter_map(|query| {
+            let epithet = query.key.epithets.first()?;
+            Some(format!("{epithet} {}", initial(&query.key.genus)))
+        })
+        .collect();
+
+    (genus_keys, epithet_keys)
+}
+
+
+/// The accepted name of a synonym
+#[derive(Debug, QueryableByName)]
+pub struct AcceptedName {
+    #[diesel(sql_type = SqlUuid)]
+    pub synonym_id: Uuid,
+    #[diesel(sql_type = SqlUuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = Varchar)]
+    pub scientific_name: String,
+    #[diesel(sql_type = Varchar)]
+    pub canonical_name: String,
+    #[diesel(sql_type = Nullable<Varchar>)]
+    pub authorship: Option<String>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub entity_id: Option<i64>,
+}
+
+impl From<AcceptedName> for (Uuid, Name) {
+    fn from(value: AcceptedName) -> Self {
+        let name = Name {
+            id: value.id,
+            scientific_name: value.scientific_name,
+            canonical_name: value.canonical_name,
+            authorship: value.authorship,
+            entity_id: value.entity_id,
+        };
+        (value.synonym_id, name)
+    }
+}
+
+/// Get the accepted names of the names that are synonyms.
+///
+/// A name is a synonym when it belongs to a taxon that isn't accepted and a taxonomic act
+/// points that taxon to an accepted one. The most recent act wins when there are several.
+pub fn accepted_names_query(name_ids: Vec<Uuid>) -> BoxedSqlQuery<'static, Pg, SqlQuery> {
+    diesel::sql_query(
+        "SELECT DISTINCT ON (synonym_names.name_id)
+             synonym_names.name_id AS synonym_id,
+             names.id, names.scientific_name, names.canonical_name, names.authorship, names.entity_id
+         FROM taxon_names synonym_names
+         JOIN taxa synonyms ON synonyms.id = synonym_names.taxon_id
+         JOIN taxonomic_acts acts ON acts.taxon_id = synonyms.id
+         JOIN taxa accepted ON accepted.id = acts.accepted_taxon_id
+         JOIN taxon_names accepted_names ON accepted_names.taxon_id = accepted.id
+         JOIN names ON names.id = accepted_names.name_id
+         WHERE synonym_names.name_id = ANY($1)
+         AND synonyms.status <> ALL($2)
+         AND accepted.status = ANY($2)
+         ORDER BY synonym_names.name_id, acts.updated_at DESC",
+    )
+    .into_boxed()
+    .bind::<Array<SqlUuid>, _>(name_ids)
+    .bind::<Array<schema::sql_types::TaxonomicStatus>, _>(ACCEPTED_NAMES.to_vec())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(scientific_name: &str, canonical_name: &str, authorship: Option<&str>) -> Name {
+        Name {
+            id: Uuid::new_v4(),
+            scientific_name: scientific_name.to_string(),
+            canonical_name: canonical_name.to_string(),
+            authorship: authorship.map(|authorship| authorship.to_string()),
+            entity_id: None,
+        }
+    }
+
+    fn matcher() -> Matcher {
+        Matcher::new(vec![
+            name("Eucalyptus globulus Labill.", "Eucalyptus globulus", Some("Labill.")),
+            name("Eucalyptus regnans F.Muell.", "Eucalyptus regnans", Some("F.Muell.")),
+            name("Acacia dealbata Link", "Acacia dealbata", Some("Link")),
+            name("Ozimops kitcheneri McKenzie, Reardon & Adams, 2014", "Ozimops kitcheneri", None),
+            name("Stigmodera (Castiarina) chamelauci Barker, 1987", "Stigmodera Castiarina chamelauci", None),
+            name("Aus bus Smith, 1850", "Aus bus", Some("Smith, 1850")),
+            name("Aus bus Jones, 1901", "Aus bus", Some("Jones, 1901")),
+        ])
+    }
+
+    fn best(query: &str) -> Option<(String, MatchType)> {
+        let matches = matcher().match_name(&NameQuery::new(query));
+        best_match(&matches).map(|best| (best.name.scientific_name.clone(), best.match_type))
+    }
+
+    #[test]
+    fn it_bounds_candidates_by_initials() {
+        let queries = [
+            NameQuery::new("Eucalyptus globulus Labill."),
+            NameQuery::new("Stigmodera (Castiarina) chamelauci Barker, 1987"),
+            NameQuery::new("Eucalyptus"),
+        ];
+        let (genus_keys, epithet_keys) = candidate_keys(&queries);
+
+        assert_eq!(genus_keys, vec!["eucalyptus g", "stigmodera c", "eucalyptus "]);
+        assert_eq!(epithet_keys, vec!["globulus e", "chamelauci s"]);
+    }
+
+    #[test]
+    fn it_normalises_names() {
+        assert_eq!(normalise("  Eucalyptus   globulus\tLabill. "), "Eucalyptus globulus Labill.");
+        assert_eq!(normalise("\"Eucalyptus globulus\""), "Eucalyptus globulus");
+    }
+
+    #[test]
+    fn it_stems_gender_endings() {
+        assert_eq!(stem("alpinus"), stem("alpina"));
+        assert_eq!(stem("alpinus"), stem("alpinum"));
+        assert_eq!(stem("niger"), stem("nigra"));
+        assert_eq!(stem("niger"), stem("nigrum"));
+        assert_eq!(stem("gracilis"), stem("gracile"));
+        assert_eq!(stem("victoriensis"), stem("victoriense"));
+        assert_eq!(stem("smithii"), stem("smithi"));
+        assert_ne!(stem("alpinus"), stem("albus"));
+        assert_eq!(stem("ea"), "ea");
+    }
+
+    #[test]
+    fn it_measures_similarity() {
+        assert_eq!(similarity("globulus", "globulus"), 1.0);
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("abc", ""), 0.0);
+        // a transposition is a single edit
+        assert_eq!(similarity("globulus", "golbulus"), 1.0 - 1.0 / 8.0);
+        assert_eq!(similarity("globulus", "globulis"), 1.0 - 1.0 / 8.0);
+        assert!(similarity("globulus", "regnans") < FUZZY_THRESHOLD);
+    }
+
+    #[test]
+    fn it_matches_exact_names() {
+        let expected = Some(("Eucalyptus globulus Labill.".into(), MatchType::Exact));
+        assert_eq!(best("Eucalyptus globulus Labill."), expected);
+        assert_eq!(best(" Eucalyptus  globulus Labill."), expected);
+    }
+
+    #[test]
+    fn it_matches_canonical_names() {
+        let expected = Some(("Eucalyptus globulus Labill.".into(), MatchType::Canonical));
+        assert_eq!(best("Eucalyptus globulus"), expected);
+        assert_eq!(best("Eucalyptus globulus Labill"), expected);
+        assert_eq!(best("eucalyptus globulus"), expected);
+
+        let expected = Some(("Stigmodera (Castiarina) chamelauci Barker, 1987".into(), MatchType::Canonical));
+        assert_eq!(best("Stigmodera chamelauci"), expected);
+    }
+
+    #[test]
+    fn it_matches_stemmed_epithets() {
+        assert_eq!(best("Eucalyptus globula"), Some(("Eucalyptus globulus Labill.".into(), MatchType::Stemmed)));
+        assert_eq!(best("Acacia dealbatus"), Some(("Acacia dealbata Link".into(), MatchType::Stemmed)));
+    }
+
+    #[test]
+    fn it_matches_misspelt_names() {
+        assert_eq!(best("Eucalyptus globulos"), Some(("Eucalyptus globulus Labill.".into(), MatchType::Fuzzy)));
+        assert_eq!(best("Eucalytpus regnans"), Some(("Eucalyptus regnans F.Muell.".into(), MatchType::Fuzzy)));
+
+        let expected = Some(("Ozimops kitcheneri McKenzie, Reardon & Adams, 2014".into(), MatchType::Fuzzy));
+        assert_eq!(best("Ozimops kitchneri"), expected);
+        assert_eq!(best("Eucalyptus banksii"), None);
+        assert_eq!(best("Banksia serrata"), None);
+    }
+
+    #[test]
+    fn it_ranks_matches_by_confidence() {
+        let matches = matcher().match_name(&NameQuery::new("Eucalyptus globulus"));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].confidence, CANONICAL_CONFIDENCE);
+
+        let exact = matcher().match_name(&NameQuery::new("Eucalyptus globulus Labill."));
+        let stemmed = matcher().match_name(&NameQuery::new("Eucalyptus globula"));
+        let fuzzy = matcher().match_name(&NameQuery::new("Eucalyptus globulos"));
+        assert!(exact[0].confidence > matches[0].confidence);
+        assert!(matches[0].confidence > stemmed[0].confidence);
+        assert!(stemmed[0].confidence > fuzzy[0].confidence);
+    }
+
+    #[test]
+    fn it_uses_the_authority_to_pick_between_homonyms() {
+        assert_eq!(best("Aus bus"), None);
+        assert_eq!(best("Aus bus Smith"), Some(("Aus bus Smith, 1850".into(), MatchType::Canonical)));
+        assert_eq!(best("Aus bus Jones 1901"), Some(("Aus bus Jones, 1901".into(), MatchType::Exact)));
+        assert_eq!(best("Aus bus (Jones, 1901)"), Some(("Aus bus Jones, 1901".into(), MatchType::Canonical)));
+
+        let matches = matcher().match_name(&NameQuery::new("Aus bus Jones, 1850"));
+        assert_eq!(matches.len(), 2);
+        assert!(matches[0].confidence < CANONICAL_CONFIDENCE);
+    }
+
+    #[test]
+    fn it_follows_synonyms() {
+        let mut matches = matcher().match_name(&NameQuery::new("Acacia dealbata"));
+        let accepted = name("Racosperma dealbatum (Link) Pedley", "Racosperma dealbatum", Some("(Link) Pedley"));
+
+        let mut synonyms = HashMap::new();
+        synonyms.insert(matches[0].name.id, accepted.clone());
+        follow_synonyms(&mut matches, &synonyms);
+
+        assert_eq!(matches[0].accepted.as_ref().map(|name| name.id), Some(accepted.id));
+    }
+}
@@ -0,0 +1,794 @@
+//! Parse scientific names into their components.
+//!
+//! The parser works on whitespace separated tokens rather than a regular expression so that
+//! it can look ahead for rank markers and tell the lowercase prefixes of author names apart
+//! from epithets. It understands both botanical and zoological names, for example:
+//!
+//! - `Abies alba subsp. apennina Brullo, Scelsi & Spamp.`
+//! - `Mentha × piperita L.`
+//! - `Phascolarctos cinereus cinereus (Goldfuss, 1817)`
+//! - `Stigmodera (Castiarina) chamelauci Barker, 1987`
+//! - `Eucalyptus cf. globulus`
+//! - `Centaurea jacea (L.) Mill.`
+//!
+//! Undetermined names like `Acacia sp.` and hybrid formulae like `Quercus alba × Quercus rubra`
+//! don't refer to a single name and are not parsed.
+
+pub mod matching;
+
+use std::fmt;
+
+
+/// Lowercase words that can follow an epithet without being an epithet themselves.
+///
+/// Most of these are prefixes of author names such as `van de Poll` or `de Bruin` and
+/// the rest are parts of the authorship like `ex` and `sensu`.
+const NON_EPITHETS: &[&str] = &[
+    "van", "von", "de", "del", "della", "der", "den", "des", "di", "da", "das", "dos", "du", "le", "la", "ter", "ten",
+    "zur", "ex", "in", "et", "non", "nec", "sensu", "auct", "emend",
+];
+
+
+/// The marker placed before an infraspecific epithet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankMarker {
+    Subspecies,
+    Variety,
+    Subvariety,
+    Form,
+    Subform,
+}
+
+impl RankMarker {
+    /// The standard abbreviation used in names
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            RankMarker::Subspecies => "subsp.",
+            RankMarker::Variety => "var.",
+            RankMarker::Subvariety => "subvar.",
+            RankMarker::Form => "f.",
+            RankMarker::Subform => "subf.",
+        }
+    }
+
+    /// The name of the rank the marker denotes
+    pub fn rank(&self) -> &'static str {
+        match self {
+            RankMarker::Subspecies => "subspecies",
+            RankMarker::Variety => "variety",
+            RankMarker::Subvariety => "subvariety",
+            RankMarker::Form => "form",
+            RankMarker::Subform => "subform",
+        }
+    }
+
+    /// Parse a marker token, returning whether it is a hybrid (notho) rank as well
+    fn parse(token: &str) -> Option<(RankMarker, bool)> {
+        let (token, hybrid) = match token.strip_prefix("notho") {
+            Some(token) => (token, true),
+            None => (token, false),
+        };
+
+        let marker = match token {
+            "subsp." | "subsp" | "ssp." | "ssp" => RankMarker::Subspecies,
+            "var." | "var" => RankMarker::Variety,
+            "subvar." | "subvar" => RankMarker::Subvariety,
+            "f." | "fo." | "forma" => RankMarker::Form,
+            "subf." | "subfo." => RankMarker::Subform,
+            _ => return None,
+        };
+        Some((marker, hybrid))
+    }
+}
+
+
+/// Which part of the name is marked as a hybrid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hybrid {
+    /// A nothogenus such as `× Agrohordeum macounii`
+    Genus,
+    /// A nothospecies such as `Mentha × piperita`
+    Species,
+    /// A nothotaxon below species such as `Mentha × piperita nothosubsp. citrata`
+    Infraspecific,
+}
+
+
+/// An open nomenclature qualifier on an identification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Qualifier {
+    /// The specimen should be compared with (cf.) the species
+    Confer,
+    /// The specimen has an affinity with (aff.) but is distinct from the species
+    Affinis,
+}
+
+impl Qualifier {
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            Qualifier::Confer => "cf.",
+            Qualifier::Affinis => "aff.",
+        }
+    }
+
+    fn parse(token: &str) -> Option<Qualifier> {
+        match token {
+            "cf." | "cf" => Some(Qualifier::Confer),
+            "aff." | "aff" => Some(Qualifier::Affinis),
+            _ => None,
+        }
+    }
+}
+
+
+/// The authors of a name and the year it was published when known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authorship {
+    pub authors: String,
+    pub year: Option<String>,
+}
+
+impl fmt::Display for Authorship {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.authors.is_empty(), &self.year) {
+            (false, Some(year)) => write!(f, "{}, {year}", self.authors),
+            (true, Some(year)) => write!(f, "{year}"),
+            (_, None) => write!(f, "{}", self.authors),
+        }
+    }
+}
+
+
+/// The components of a scientific name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedName {
+    /// The genus, or the uninomial when the name is above the rank of genus
+    pub genus: String,
+    pub subgenus: Option<String>,
+    pub specific_epithet: Option<String>,
+    pub infraspecific_epithet: Option<String>,
+    /// The marker of an infraspecific rank. Zoological trinomials don't have one
+    pub rank_marker: Option<RankMarker>,
+    pub hybrid: Option<Hybrid>,
+    pub qualifier: Option<Qualifier>,
+    /// The authorship of the name in its current combination
+    pub authorship: Option<Authorship>,
+    /// The parenthesised authorship of the original combination
+    pub basionym_authorship: Option<Authorship>,
+}
+
+impl ParsedName {
+    fn new(genus: &str) -> ParsedName {
+        ParsedName {
+            genus: genus.to_string(),
+            subgenus: None,
+            specific_epithet: None,
+            infraspecific_epithet: None,
+            rank_marker: None,
+            hybrid: None,
+            qualifier: None,
+            authorship: None,
+            basionym_authorship: None,
+        }
+    }
+
+    /// The name without authorship, markers or qualifiers
+    pub fn canonical_name(&self) -> String {
+        let parts = [
+            Some(&self.genus),
+            self.subgenus.as_ref(),
+            self.specific_epithet.as_ref(),
+            self.infraspecific_epithet.as_ref(),
+        ];
+        parts.into_iter().flatten().cloned().collect::<Vec<String>>().join(" ")
+    }
+
+    /// The name without authorship but with the hybrid and rank markers
+    pub fn canonical_name_with_marker(&self) -> String {
+        let mut parts = Vec::new();
+
+        if self.hybrid == Some(Hybrid::Genus) {
+            parts.push("×".to_string());
+        }
+        parts.push(self.genus.clone());
+
+        if let Some(subgenus) = &self.subgenus {
+            parts.push(format!("({subgenus})"));
+        }
+        if let Some(epithet) = &self.specific_epithet {
+            if self.hybrid == Some(Hybrid::Species) {
+                parts.push("×".to_string());
+            }
+            parts.push(epithet.clone());
+        }
+        if let Some(epithet) = &self.infraspecific_epithet {
+            match (self.rank_marker, self.hybrid) {
+                (Some(marker), Some(Hybrid::Infraspecific)) => parts.push(format!("notho{}", marker.abbreviation())),
+                (Some(marker), _) => parts.push(marker.abbreviation().to_string()),
+                (None, _) => {}
+            }
+            parts.push(epithet.clone());
+        }
+
+        parts.join(" ")
+    }
+
+    /// The full authorship with the basionym authorship in parentheses
+    pub fn authority(&self) -> Option<String> {
+        match (&self.basionym_authorship, &self.authorship) {
+            (Some(basionym), Some(authorship)) => Some(format!("({basionym}) {authorship}")),
+            (Some(basionym), None) => Some(format!("({basionym})")),
+            (None, Some(authorship)) => Some(authorship.to_string()),
+            (None, None) => None,
+        }
+    }
+
+    /// The canonical name with markers followed by the authority
+    pub fn scientific_name(&self) -> String {
+        match self.authority() {
+            Some(authority) => format!("{} {authority}", self.canonical_name_with_marker()),
+            None => self.canonical_name_with_marker(),
+        }
+    }
+}
+
+
+/// Parse a scientific name into its components.
+///
+/// Returns `None` when the name doesn't start with a uninomial, is an undetermined
+/// name like `Acacia sp.`, or is a hybrid formula made up of multiple names.
+pub fn parse(name: &str) -> Option<ParsedName> {
+    let tokens = tokenize(name);
+    let mut pos = 0;
+
+    // the genus with an optional nothogenus sign that may be attached to it
+    let mut hybrid = None;
+    let mut genus = tokens.first()?.1;
+    if is_hybrid_sign(genus) {
+        hybrid = Some(Hybrid::Genus);
+        pos += 1;
+        genus = tokens.get(pos)?.1;
+    }
+    else if let Some(stripped) = genus.strip_prefix('×') {
+        hybrid = Some(Hybrid::Genus);
+        genus = stripped;
+    }
+
+    if !is_uninomial(genus) {
+        return None;
+    }
+    pos += 1;
+
+    let mut parsed = ParsedName::new(genus);
+    parsed.hybrid = hybrid;
+
+    if let Some(subgenus) = tokens.get(pos).and_then(|(_, token)| parse_subgenus(token)) {
+        parsed.subgenus = Some(subgenus.to_string());
+        pos += 1;
+    }
+
+    if let Some(qualifier) = tokens.get(pos).and_then(|(_, token)| Qualifier::parse(token)) {
+        parsed.qualifier = Some(qualifier);
+        pos += 1;
+    }
+
+    // the specific epithet with an optional nothospecies sign that may be attached to it
+    let mut epithet = tokens.get(pos).map(|(_, token)| *token);
+    if epithet.is_some_and(is_hybrid_sign) && tokens.get(pos + 1).is_some_and(|(_, token)| is_epithet(token)) {
+        parsed.hybrid = Some(Hybrid::Species);
+        pos += 1;
+        epithet = tokens.get(pos).map(|(_, token)| *token);
+    }
+    else if let Some(stripped) = epithet.and_then(|token| token.strip_prefix('×')) {
+        if is_epithet(stripped) {
+            parsed.hybrid = Some(Hybrid::Species);
+            epithet = Some(stripped);
+        }
+    }
+
+    if epithet.is_some_and(is_undetermined) {
+        return None;
+    }
+
+    if let Some(epithet) = epithet.filter(|token| is_epithet(token)) {
+        parsed.specific_epithet = Some(epithet.to_string());
+        pos += 1;
+
+        // a hybrid formula such as `Quercus alba × Quercus rubra`
+        let next = tokens.get(pos).map(|(_, token)| *token);
+        if next.is_some_and(is_hybrid_sign) || next.is_some_and(|token| token.starts_with('×')) {
+            return None;
+        }
+
+        // botanical infraspecific names have a rank marker that can come after the
+        // authorship of the species, as in `Acacia dealbata Link subsp. subalpina Tindale`.
+        // the last marker is used for names with multiple infraspecific ranks
+        let marker = (pos..tokens.len()).rev().find_map(|idx| {
+            let (marker, notho) = RankMarker::parse(tokens[idx].1)?;
+            let epithet = tokens.get(idx + 1).map(|(_, token)| *token).filter(|token| is_epithet(token))?;
+            Some((idx, marker, notho, epithet))
+        });
+
+        match marker {
+            Some((idx, marker, notho, epithet)) => {
+                parsed.rank_marker = Some(marker);
+                parsed.infraspecific_epithet = Some(epithet.to_string());
+                if notho {
+                    parsed.hybrid = Some(Hybrid::Infraspecific);
+                }
+                pos = idx + 2;
+            }
+            // zoological trinomials have the subspecies epithet directly after the species
+            None => {
+                if let Some((_, epithet)) = tokens.get(pos).filter(|(_, token)| is_epithet(token)) {
+                    parsed.infraspecific_epithet = Some(epithet.to_string());
+                    pos += 1;
+                }
+            }
+        }
+    }
+
+    if let Some((offset, _)) = tokens.get(pos) {
+        let (authorship, basionym_authorship) = parse_authority(&name[*offset..]);
+        parsed.authorship = authorship;
+        parsed.basionym_authorship = basionym_authorship;
+    }
+
+    Some(parsed)
+}
+
+
+/// Parse an authority into the combination authorship and the parenthesised basionym authorship
+pub fn parse_authority(authority: &str) -> (Option<Authorship>, Option<Authorship>) {
+    let authority = authority.trim();
+
+    if let Some(inner) = authority.strip_prefix('(') {
+        let mut depth = 1;
+        let close = inner.char_indices().find_map(|(idx, ch)| {
+            match ch {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(idx)
+        });
+
+        if let Some(close) = close {
+            let basionym = parse_authorship(&inner[..close]);
+            let combination = parse_authorship(&inner[close + 1..]);
+            return (combination, basionym);
+        }
+    }
+
+    (parse_authorship(authority), None)
+}
+
+/// Parse the authors and year out of a single authorship such as `Sharp, 1882` or `L.`
+pub fn parse_authorship(authorship: &str) -> Option<Authorship> {
+    let authorship = authorship.trim().trim_end_matches(',').trim();
+    if authorship.is_empty() {
+        return None;
+    }
+
+    let (authors, last) = match authorship.rsplit_once(char::is_whitespace) {
+        Some((authors, last)) => (authors, last),
+        None => ("", authorship),
+    };
+
+    match parse_year(last) {
+        Some(year) => Some(Authorship {
+            authors: authors.trim().trim_end_matches(',').trim().to_string(),
+            year: Some(year.to_string()),
+        }),
+        None => Some(Authorship {
+            authors: authorship.to_string(),
+            year: None,
+        }),
+    }
+}
+
+
+/// Split the name on whitespace keeping the byte offset of each token
+fn tokenize(name: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+
+    for (idx, ch) in name.char_indices() {
+        match (ch.is_whitespace(), start) {
+            (true, Some(begin)) => {
+                tokens.push((begin, &name[begin..idx]));
+                start = None;
+            }
+            (false, None) => start = Some(idx),
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        tokens.push((begin, &name[begin..]));
+    }
+
+    tokens
+}
+
+/// A year of publication like `1882`, `1882a` or `[1882]`
+fn parse_year(token: &str) -> Option<&str> {
+    let year = token.trim_start_matches(['[', '(']).trim_end_matches([']', ')', ',', '.']);
+    let digits = year.trim_end_matches(|ch: char| ch.is_ascii_lowercase());
+
+    let valid = digits.len() == 4
+        && digits.chars().all(|ch| ch.is_ascii_digit())
+        && (digits.starts_with('1') || digits.starts_with('2'))
+        && year.len() - digits.len() <= 1;
+
+    valid.then_some(year)
+}
+
+fn is_hybrid_sign(token: &str) -> bool {
+    matches!(token, "×" | "x" | "X")
+}
+
+fn is_undetermined(token: &str) -> bool {
+    matches!(token, "sp." | "sp" | "spp." | "spp" | "indet." | "indet")
+}
+
+fn is_uninomial(token: &str) -> bool {
+    let mut chars = token.chars();
+    match chars.next() {
+        Some(first) if first.is_uppercase() => {
+            let rest = chars.as_str();
+            !rest.is_empty() && rest.chars().all(|ch| ch.is_lowercase() || ch == '-')
+        }
+        _ => false,
+    }
+}
+
+fn is_epithet(token: &str) -> bool {
+    let mut chars = token.chars();
+    match chars.next() {
+        Some(first) if first.is_lowercase() => {
+            let rest = chars.as_str();
+            !rest.is_empty()
+                && rest.chars().all(|ch| ch.is_lowercase() || ch == '-')
+                && !NON_EPITHETS.contains(&token)
+        }
+        _ => false,
+    }
+}
+
+fn parse_subgenus(token: &str) -> Option<&str> {
+    let subgenus = token.strip_prefix('(')?.strip_suffix(')')?;
+    is_uninomial(subgenus).then_some(subgenus)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorship(authors: &str, year: Option<&str>) -> Option<Authorship> {
+        Some(Authorship {
+            authors: authors.to_string(),
+            year: year.map(|year| year.to_string()),
+        })
+    }
+
+    #[test]
+    fn it_parses_uninomials() {
+        let name = parse("Acacia").unwrap();
+        assert_eq!(name.genus, "Acacia");
+        assert_eq!(name.specific_epithet, None);
+        assert_eq!(name.authorship, None);
+
+        let name = parse("Acacia Mill.").unwrap();
+        assert_eq!(name.genus, "Acacia");
+        assert_eq!(name.authorship, authorship("Mill.", None));
+
+        let name = parse("Fabaceae Lindl.").unwrap();
+        assert_eq!(name.genus, "Fabaceae");
+        assert_eq!(name.authorship, authorship("Lindl.", None));
+
+        let name = parse("Diprotodontia Owen, 1866").unwrap();
+        assert_eq!(name.genus, "Diprotodontia");
+        assert_eq!(name.authorship, authorship("Owen", Some("1866")));
+    }
+
+    #[test]
+    fn it_parses_binomials() {
+        let name = parse("Sternopriscus oscillator Sharp, 1882").unwrap();
+        assert_eq!(name.genus, "Sternopriscus");
+        assert_eq!(name.subgenus, None);
+        assert_eq!(name.specific_epithet, Some("oscillator".to_string()));
+        assert_eq!(name.infraspecific_epithet, None);
+        assert_eq!(name.authorship, authorship("Sharp", Some("1882")));
+        assert_eq!(name.basionym_authorship, None);
+        assert_eq!(name.canonical_name(), "Sternopriscus oscillator");
+
+        let name = parse("Eucalyptus globulus Labill.").unwrap();
+        assert_eq!(name.specific_epithet, Some("globulus".to_string()));
+        assert_eq!(name.authorship, authorship("Labill.", None));
+
+        let name = parse("Homo sapiens").unwrap();
+        assert_eq!(name.canonical_name(), "Homo sapiens");
+        assert_eq!(name.authority(), None);
+    }
+
+    #[test]
+    fn it_parses_unicode_names() {
+        let name = parse("Notothenia larseni Lönnberg, 1905").unwrap();
+        assert_eq!(name.specific_epithet, Some("larseni".to_string()));
+        assert_eq!(name.authorship, authorship("Lönnberg", Some("1905")));
+
+        let name = parse("Carabus pöppigi Müller, 1850").unwrap();
+        assert_eq!(name.specific_epithet, Some("pöppigi".to_string()));
+        assert_eq!(name.authorship, authorship("Müller", Some("1850")));
+    }
+
+    #[test]
+    fn it_parses_hyphenated_epithets() {
+        let name = parse("Symphyotrichum novae-angliae (L.) G.L.Nesom").unwrap();
+        assert_eq!(name.specific_epithet, Some("novae-angliae".to_string()));
+        assert_eq!(name.basionym_authorship, authorship("L.", None));
+        assert_eq!(name.authorship, authorship("G.L.Nesom", None));
+    }
+
+    #[test]
+    fn it_parses_subgenera() {
+        let name = parse("Stigmodera (Castiarina) chamelauci Barker, 1987").unwrap();
+        assert_eq!(name.genus, "Stigmodera");
+        assert_eq!(name.subgenus, Some("Castiarina".to_string()));
+        assert_eq!(name.specific_epithet, Some("chamelauci".to_string()));
+        assert_eq!(name.authorship, authorship("Barker", Some("1987")));
+        assert_eq!(name.canonical_name(), "Stigmodera Castiarina chamelauci");
+        assert_eq!(name.canonical_name_with_marker(), "Stigmodera (Castiarina) chamelauci");
+
+        let name = parse("Clivina (Clivina) gemina gemina Baehr, 2017").unwrap();
+        assert_eq!(name.subgenus, Some("Clivina".to_string()));
+        assert_eq!(name.specific_epithet, Some("gemina".to_string()));
+        assert_eq!(name.infraspecific_epithet, Some("gemina".to_string()));
+        assert_eq!(name.authorship, authorship("Baehr", Some("2017")));
+    }
+
+    #[test]
+    fn it_parses_multiple_authors() {
+        let name = parse("Rhombus grandisquama Temminck & Schlegel, 1846").unwrap();
+        assert_eq!(name.authorship, authorship("Temminck & Schlegel", Some("1846")));
+
+        let name = parse("Ozimops kitcheneri McKenzie, Reardon & Adams, 2014").unwrap();
+        assert_eq!(name.authorship, authorship("McKenzie, Reardon & Adams", Some("2014")));
+
+        let name = parse("Abies alba subsp. apennina Brullo, Scelsi & Spamp.").unwrap();
+        assert_eq!(name.authorship, authorship("Brullo, Scelsi & Spamp.", None));
+
+        let name = parse("Eucalyptus regnans F.Muell. ex Benth.").unwrap();
+        assert_eq!(name.authorship, authorship("F.Muell. ex Benth.", None));
+
+        let name = parse("Grevillea robusta A.Cunn. ex R.Br.").unwrap();
+        assert_eq!(name.specific_epithet, Some("robusta".to_string()));
+        assert_eq!(name.infraspecific_epithet, None);
+        assert_eq!(name.authorship, authorship("A.Cunn. ex R.Br.", None));
+    }
+
+    #[test]
+    fn it_parses_lowercase_author_prefixes() {
+        let cases = [
+            ("Astraeus pygmaeus van de Poll, 1886", "van de Poll"),
+            ("Pseudorhiza aurosa von Lendenfeld, 1882", "von Lendenfeld"),
+            ("Metapenaeopsis mannarensis de Bruin, 1965", "de Bruin"),
+            ("Pterygotrigla robertsi del Cerro & Lloris, 1997", "del Cerro & Lloris"),
+            ("Dendrogaster ludwigi le Roi, 1905", "le Roi"),
+            ("Conus textile du Bois, 1900", "du Bois"),
+        ];
+
+        for (scientific_name, authors) in cases {
+            let name = parse(scientific_name).unwrap();
+            assert_eq!(name.infraspecific_epithet, None, "{scientific_name}");
+            assert_eq!(name.authorship.unwrap().authors, authors, "{scientific_name}");
+        }
+    }
+
+    #[test]
+    fn it_parses_zoological_trinomials() {
+        let name = parse("Glottis nebularius georgi Mathews, 1915").unwrap();
+        assert_eq!(name.specific_epithet, Some("nebularius".to_string()));
+        assert_eq!(name.infraspecific_epithet, Some("georgi".to_string()));
+        assert_eq!(name.rank_marker, None);
+        assert_eq!(name.authorship, authorship("Mathews", Some("1915")));
+        assert_eq!(name.canonical_name(), "Glottis nebularius georgi");
+    }
+
+    #[test]
+    fn it_parses_parenthesised_zoological_authorship() {
+        let name = parse("Phascolarctos cinereus cinereus (Goldfuss, 1817)").unwrap();
+        assert_eq!(name.specific_epithet, Some("cinereus".to_string()));
+        assert_eq!(name.infraspecific_epithet, Some("cinereus".to_string()));
+        assert_eq!(name.authorship, None);
+        assert_eq!(name.basionym_authorship, authorship("Goldfuss", Some("1817")));
+        assert_eq!(name.authority(), Some("(Goldfuss, 1817)".to_string()));
+
+        let name = parse("Macropus giganteus (Shaw 1790)").unwrap();
+        assert_eq!(name.basionym_authorship, authorship("Shaw", Some("1790")));
+        assert_eq!(name.authority(), Some("(Shaw, 1790)".to_string()));
+
+        let name = parse("Osphranter rufus (Desmarest, 1822)").unwrap();
+        assert_eq!(name.scientific_name(), "Osphranter rufus (Desmarest, 1822)");
+    }
+
+    #[test]
+    fn it_parses_botanical_combination_authorship() {
+        let name = parse("Centaurea jacea (L.) Mill.").unwrap();
+        assert_eq!(name.basionym_authorship, authorship("L.", None));
+        assert_eq!(name.authorship, authorship("Mill.", None));
+        assert_eq!(name.authority(), Some("(L.) Mill.".to_string()));
+
+        let name = parse("Corymbia citriodora (Hook.) K.D.Hill & L.A.S.Johnson").unwrap();
+        assert_eq!(name.basionym_authorship, authorship("Hook.", None));
+        assert_eq!(name.authorship, authorship("K.D.Hill & L.A.S.Johnson", None));
+
+        let name = parse("Melaleuca quinquenervia (Cav.) S.T.Blake, 1958").unwrap();
+        assert_eq!(name.basionym_authorship, authorship("Cav.", None));
+        assert_eq!(name.authorship, authorship("S.T.Blake", Some("1958")));
+
+        let name = parse("Pteridium esculentum (G.Forst.) Cockayne").unwrap();
+        assert_eq!(name.scientific_name(), "Pteridium esculentum (G.Forst.) Cockayne");
+    }
+
+    #[test]
+    fn it_parses_infraspecific_rank_markers() {
+        let cases = [
+            ("Abies alba subsp. apennina Brullo", RankMarker::Subspecies, "apennina"),
+            ("Poa annua ssp. annua", RankMarker::Subspecies, "annua"),
+            ("Acacia cyclops var. minor Benth.", RankMarker::Variety, "minor"),
+            ("Acacia cyclops var minor", RankMarker::Variety, "minor"),
+            ("Rosa canina subvar. glabra", RankMarker::Subvariety, "glabra"),
+            ("Quercus robur f. fastigiata (Lam.) O.Schwarz", RankMarker::Form, "fastigiata"),
+            ("Quercus robur forma fastigiata", RankMarker::Form, "fastigiata"),
+            ("Quercus robur fo. fastigiata", RankMarker::Form, "fastigiata"),
+            ("Rosa canina subf. alba", RankMarker::Subform, "alba"),
+        ];
+
+        for (scientific_name, marker, epithet) in cases {
+            let name = parse(scientific_name).unwrap();
+            assert_eq!(name.rank_marker, Some(marker), "{scientific_name}");
+            assert_eq!(name.infraspecific_epithet, Some(epithet.to_string()), "{scientific_name}");
+        }
+
+        let name = parse("Quercus robur f. fastigiata (Lam.) O.Schwarz").unwrap();
+        assert_eq!(name.canonical_name(), "Quercus robur fastigiata");
+        assert_eq!(name.canonical_name_with_marker(), "Quercus robur f. fastigiata");
+        assert_eq!(name.basionym_authorship, authorship("Lam.", None));
+        assert_eq!(name.authorship, authorship("O.Schwarz", None));
+    }
+
+    #[test]
+    fn it_parses_infraspecific_names_with_species_authorship() {
+        let name = parse("Acacia dealbata Link subsp. subalpina Tindale & Kodela").unwrap();
+        assert_eq!(name.specific_epithet, Some("dealbata".to_string()));
+        assert_eq!(name.rank_marker, Some(RankMarker::Subspecies));
+        assert_eq!(name.infraspecific_epithet, Some("subalpina".to_string()));
+        assert_eq!(name.authorship, authorship("Tindale & Kodela", None));
+
+        // autonyms don't have an authorship of their own
+        let name = parse("Poa annua L. var. annua").unwrap();
+        assert_eq!(name.infraspecific_epithet, Some("annua".to_string()));
+        assert_eq!(name.authorship, None);
+    }
+
+    #[test]
+    fn it_uses_the_last_rank_marker() {
+        let name = parse("Rosa canina var. dumalis f. alba").unwrap();
+        assert_eq!(name.rank_marker, Some(RankMarker::Form));
+        assert_eq!(name.infraspecific_epithet, Some("alba".to_string()));
+    }
+
+    #[test]
+    fn it_ignores_markers_in_authorship() {
+        let name = parse("Dicksonia antarctica Labill. f.").unwrap();
+        assert_eq!(name.rank_marker, None);
+        assert_eq!(name.infraspecific_epithet, None);
+        assert_eq!(name.authorship, authorship("Labill. f.", None));
+    }
+
+    #[test]
+    fn it_parses_hybrids() {
+        let name = parse("Mentha × piperita L.").unwrap();
+        assert_eq!(name.hybrid, Some(Hybrid::Species));
+        assert_eq!(name.specific_epithet, Some("piperita".to_string()));
+        assert_eq!(name.authorship, authorship("L.", None));
+        assert_eq!(name.canonical_name(), "Mentha piperita");
+        assert_eq!(name.canonical_name_with_marker(), "Mentha × piperita");
+
+        let name = parse("Mentha ×piperita").unwrap();
+        assert_eq!(name.hybrid, Some(Hybrid::Species));
+        assert_eq!(name.specific_epithet, Some("piperita".to_string()));
+
+        let name = parse("Mentha x piperita L.").unwrap();
+        assert_eq!(name.hybrid, Some(Hybrid::Species));
+        assert_eq!(name.specific_epithet, Some("piperita".to_string()));
+
+        let name = parse("× Agrohordeum macounii (Vasey) Lepage").unwrap();
+        assert_eq!(name.hybrid, Some(Hybrid::Genus));
+        assert_eq!(name.genus, "Agrohordeum");
+        assert_eq!(name.specific_epithet, Some("macounii".to_string()));
+        assert_eq!(name.basionym_authorship, authorship("Vasey", None));
+        assert_eq!(name.canonical_name_with_marker(), "× Agrohordeum macounii");
+
+        let name = parse("×Agrohordeum macounii").unwrap();
+        assert_eq!(name.hybrid, Some(Hybrid::Genus));
+        assert_eq!(name.genus, "Agrohordeum");
+
+        let name = parse("Mentha × piperita nothosubsp. citrata (Ehrh.) Briq.").unwrap();
+        assert_eq!(name.hybrid, Some(Hybrid::Infraspecific));
+        assert_eq!(name.rank_marker, Some(RankMarker::Subspecies));
+        assert_eq!(name.infraspecific_epithet, Some("citrata".to_string()));
+        assert_eq!(name.canonical_name_with_marker(), "Mentha piperita nothosubsp. citrata");
+    }
+
+    #[test]
+    fn it_rejects_hybrid_formulae() {
+        assert_eq!(parse("Quercus alba × Quercus rubra"), None);
+        assert_eq!(parse("Salix alba x Salix fragilis"), None);
+    }
+
+    #[test]
+    fn it_parses_qualifiers() {
+        let name = parse("Eucalyptus cf. globulus").unwrap();
+        assert_eq!(name.qualifier, Some(Qualifier::Confer));
+        assert_eq!(name.specific_epithet, Some("globulus".to_string()));
+        assert_eq!(name.canonical_name(), "Eucalyptus globulus");
+
+        let name = parse("Eucalyptus aff. globulus Labill.").unwrap();
+        assert_eq!(name.qualifier, Some(Qualifier::Affinis));
+        assert_eq!(name.authorship, authorship("Labill.", None));
+
+        let name = parse("Litoria cf aurea").unwrap();
+        assert_eq!(name.qualifier, Some(Qualifier::Confer));
+        assert_eq!(name.specific_epithet, Some("aurea".to_string()));
+    }
+
+    #[test]
+    fn it_rejects_undetermined_names() {
+        assert_eq!(parse("Acacia sp."), None);
+        assert_eq!(parse("Acacia spp."), None);
+        assert_eq!(parse("Acacia sp. Bungle Range (A.A.Mitchell 123)"), None);
+    }
+
+    #[test]
+    fn it_rejects_names_without_a_uninomial() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("   "), None);
+        assert_eq!(parse("acacia dealbata"), None);
+        assert_eq!(parse("ACACIA DEALBATA"), None);
+        assert_eq!(parse("A. dealbata"), None);
+    }
+
+    #[test]
+    fn it_parses_years() {
+        let name = parse("Aus bus Smith, 1850a").unwrap();
+        assert_eq!(name.authorship, authorship("Smith", Some("1850a")));
+
+        let name = parse("Aus bus Smith, [1850]").unwrap();
+        assert_eq!(name.authorship, authorship("Smith", Some("1850")));
+
+        let name = parse("Aus bus Smith 1850").unwrap();
+        assert_eq!(name.authorship, authorship("Smith", Some("1850")));
+
+        let name = parse("Aus bus Smith, 850").unwrap();
+        assert_eq!(name.authorship, authorship("Smith, 850", None));
+    }
+
+    #[test]
+    fn it_normalises_whitespace() {
+        let name = parse("  Sternopriscus   oscillator\tSharp, 1882 ").unwrap();
+        assert_eq!(name.canonical_name(), "Sternopriscus oscillator");
+        assert_eq!(name.authorship, authorship("Sharp", Some("1882")));
+    }
+
+    #[test]
+    fn it_parses_authorities() {
+        assert_eq!(parse_authority("Sharp, 1882"), (authorship("Sharp", Some("1882")), None));
+        assert_eq!(parse_authority("(Goldfuss, 1817)"), (None, authorship("Goldfuss", Some("1817"))));
+        assert_eq!(parse_authority("(L.) Mill."), (authorship("Mill.", None), authorship("L.", None)));
+        assert_eq!(parse_authority(""), (None, None));
+        assert_eq!(parse_authority("(unbalanced"), (authorship("(unbalanced", None), None));
+    }
+}
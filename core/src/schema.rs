@@ -25,6 +25,10 @@ pub mod sql_types {
     #[diesel(postgres_type(name = "job_status"))]
     pub struct JobStatus;
 
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "location_generalisation"))]
+    pub struct LocationGeneralisation;
+
     #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "nomenclatural_act_type"))]
     pub struct NomenclaturalActType;
@@ -69,6 +73,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -125,6 +131,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -164,6 +172,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -269,6 +279,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -283,10 +295,15 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::LocationGeneralisation;
+
     collection_events (entity_id) {
         specimen_id -> Varchar,
         event_date -> Nullable<Date>,
@@ -326,6 +343,7 @@ diesel::table! {
         environment_local_scale -> Nullable<Varchar>,
         environment_medium -> Nullable<Varchar>,
         material_sample_id -> Nullable<Varchar>,
+        location_generalisation -> Nullable<LocationGeneralisation>,
     }
 }
 
@@ -340,6 +358,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -439,6 +459,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -492,6 +514,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -612,6 +636,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -656,6 +682,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -676,6 +704,43 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::OperationAction;
+
+    operation_log_archives (log_table, operation_id) {
+        log_table -> Varchar,
+        operation_id -> Numeric,
+        parent_id -> Numeric,
+        entity_id -> Varchar,
+        dataset_version_id -> Uuid,
+        action -> OperationAction,
+        atom -> Jsonb,
+        archived_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    operation_log_deletions (log_sequence) {
+        log_sequence -> Int8,
+        log_table -> Varchar,
+        operation_id -> Numeric,
+        entity_id -> Varchar,
+        reason -> Varchar,
+        logged_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    operation_log_snapshots (log_table, entity_id, watermark) {
+        log_table -> Varchar,
+        entity_id -> Varchar,
+        watermark -> Numeric,
+        atoms -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::OperationAction;
@@ -687,6 +752,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -738,6 +805,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -780,6 +849,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -804,6 +875,10 @@ diesel::table! {
         record_updated_at -> Nullable<Timestamptz>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        journal -> Nullable<Varchar>,
+        volume -> Nullable<Varchar>,
+        issue -> Nullable<Varchar>,
+        pages -> Nullable<Varchar>,
     }
 }
 
@@ -820,6 +895,28 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::LocationGeneralisation;
+
+    sensitive_data_rules (id) {
+        id -> Uuid,
+        name_id -> Nullable<Uuid>,
+        dataset_id -> Nullable<Uuid>,
+        generalisation -> LocationGeneralisation,
+        reason -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    sensitive_locations (entity_id) {
+        entity_id -> Varchar,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::OperationAction;
@@ -831,6 +928,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -845,6 +944,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -872,6 +973,23 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    sequence_traces (trace_id) {
+        trace_id -> Varchar,
+        process_id -> Varchar,
+        sequence_run_id -> Nullable<Varchar>,
+        trace_name -> Varchar,
+        trace_link -> Varchar,
+        a_analyzed -> Nullable<Array<Nullable<Int2>>>,
+        c_analyzed -> Nullable<Array<Nullable<Int2>>>,
+        g_analyzed -> Nullable<Array<Nullable<Int2>>>,
+        t_analyzed -> Nullable<Array<Nullable<Int2>>>,
+        base_calls -> Nullable<Varchar>,
+        quality_scores -> Nullable<Array<Nullable<Int2>>>,
+        peak_locations -> Nullable<Array<Nullable<Int2>>>,
+    }
+}
+
 diesel::table! {
     sequences (id) {
         id -> Uuid,
@@ -927,6 +1045,13 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    source_backbones (source_id) {
+        source_id -> Uuid,
+        backbone_id -> Uuid,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::DataReuseStatus;
@@ -959,6 +1084,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -982,6 +1109,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -1053,6 +1182,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -1087,6 +1218,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -1104,6 +1237,35 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    taxonomic_backbones (dataset_id) {
+        dataset_id -> Uuid,
+        is_default -> Bool,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    treatment_sections (treatment_entity_id, position) {
+        treatment_entity_id -> Varchar,
+        position -> Int4,
+        section_type -> Varchar,
+        text -> Text,
+    }
+}
+
+diesel::table! {
+    treatments (entity_id) {
+        entity_id -> Varchar,
+        dataset_version_id -> Uuid,
+        publication_entity_id -> Varchar,
+        treatment_uri -> Varchar,
+        lsid -> Nullable<Varchar>,
+        scientific_name -> Nullable<Varchar>,
+        canonical_name -> Nullable<Varchar>,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::OperationAction;
@@ -1115,6 +1277,8 @@ diesel::table! {
         dataset_version_id -> Uuid,
         action -> OperationAction,
         atom -> Jsonb,
+        log_sequence -> Int8,
+        logged_at -> Timestamptz,
     }
 }
 
@@ -1197,6 +1361,7 @@ diesel::joinable!(name_attributes -> datasets (dataset_id));
 diesel::joinable!(name_attributes -> names (name_id));
 diesel::joinable!(nomenclatural_act_logs -> dataset_versions (dataset_version_id));
 diesel::joinable!(nomenclatural_acts -> publications (publication_id));
+diesel::joinable!(operation_log_archives -> dataset_versions (dataset_version_id));
 diesel::joinable!(organism_logs -> dataset_versions (dataset_version_id));
 diesel::joinable!(organisms -> agents (identified_by));
 diesel::joinable!(organisms -> names (name_id));
@@ -1206,15 +1371,20 @@ diesel::joinable!(project_members -> projects (project_entity_id));
 diesel::joinable!(publication_logs -> dataset_versions (dataset_version_id));
 diesel::joinable!(regions -> datasets (dataset_id));
 diesel::joinable!(regions -> names (name_id));
+diesel::joinable!(sensitive_data_rules -> datasets (dataset_id));
+diesel::joinable!(sensitive_data_rules -> names (name_id));
 diesel::joinable!(sequence_logs -> dataset_versions (dataset_version_id));
 diesel::joinable!(sequence_run_logs -> dataset_versions (dataset_version_id));
 diesel::joinable!(sequence_runs -> libraries (library_id));
+diesel::joinable!(sequence_traces -> sequence_runs (sequence_run_id));
 diesel::joinable!(sequences -> datasets (dataset_id));
 diesel::joinable!(sequences -> dna_extracts (dna_extract_id));
 diesel::joinable!(sequences -> names (name_id));
 diesel::joinable!(sequencing_events -> datasets (dataset_id));
 diesel::joinable!(sequencing_events -> sequences (sequence_id));
 diesel::joinable!(sequencing_run_events -> sequencing_events (sequencing_event_id));
+diesel::joinable!(source_backbones -> sources (source_id));
+diesel::joinable!(source_backbones -> taxonomic_backbones (backbone_id));
 diesel::joinable!(specimen_logs -> dataset_versions (dataset_version_id));
 diesel::joinable!(specimens -> names (name_id));
 diesel::joinable!(specimens -> organisms (organism_id));
@@ -1226,7 +1396,10 @@ diesel::joinable!(taxon_names -> names (name_id));
 diesel::joinable!(taxon_names -> taxa (taxon_id));
 diesel::joinable!(taxon_photos -> taxa (taxon_id));
 diesel::joinable!(taxonomic_act_logs -> dataset_versions (dataset_version_id));
+diesel::joinable!(taxonomic_backbones -> datasets (dataset_id));
 diesel::joinable!(tissue_logs -> dataset_versions (dataset_version_id));
+diesel::joinable!(treatment_sections -> treatments (treatment_entity_id));
+diesel::joinable!(treatments -> dataset_versions (dataset_version_id));
 diesel::joinable!(vernacular_names -> datasets (dataset_id));
 diesel::joinable!(vernacular_names -> names (name_id));
 
@@ -1264,6 +1437,9 @@ diesel::allow_tables_to_appear_in_same_query!(
     names,
     nomenclatural_act_logs,
     nomenclatural_acts,
+    operation_log_archives,
+    operation_log_deletions,
+    operation_log_snapshots,
     organism_logs,
     organisms,
     project_logs,
@@ -1272,12 +1448,16 @@ diesel::allow_tables_to_appear_in_same_query!(
     publication_logs,
     publications,
     regions,
+    sensitive_data_rules,
+    sensitive_locations,
     sequence_logs,
     sequence_run_logs,
     sequence_runs,
+    sequence_traces,
     sequences,
     sequencing_events,
     sequencing_run_events,
+    source_backbones,
     sources,
     specimen_logs,
     specimens,
@@ -1289,8 +1469,11 @@ diesel::allow_tables_to_appear_in_same_query!(
     taxon_photos,
     taxonomic_act_logs,
     taxonomic_acts,
+    taxonomic_backbones,
     tissue_logs,
     tissues,
+    treatment_sections,
+    treatments,
     users,
     vernacular_names,
 );
@@ -85,6 +85,7 @@ diesel::table! {
         assembly_chromosomes -> BigInt,
         assembly_scaffolds -> BigInt,
         assembly_contigs -> BigInt,
+        types -> BigInt,
     }
 }
 
@@ -308,6 +309,7 @@ use super::schema::{
     specimens,
     taxa,
     taxon_names,
+    taxonomic_backbones,
 };
 
 diesel::joinable!(species -> taxa (id));
@@ -344,6 +346,7 @@ diesel::allow_tables_to_appear_in_same_query!(species, taxon_names);
 diesel::allow_tables_to_appear_in_same_query!(species, assembly_events);
 diesel::allow_tables_to_appear_in_same_query!(species, deposition_events);
 diesel::allow_tables_to_appear_in_same_query!(species, sequences);
+diesel::allow_tables_to_appear_in_same_query!(species, taxonomic_backbones);
 diesel::allow_tables_to_appear_in_same_query!(specimen_stats, specimens);
 diesel::allow_tables_to_appear_in_same_query!(specimen_stats, accession_events);
 diesel::allow_tables_to_appear_in_same_query!(specimen_stats, collection_events);
@@ -0,0 +1,97 @@
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use super::{Error, PgPool, schema};
+
+
+/// A taxonomy dataset registered as a backbone
+#[derive(Clone, Debug, Queryable)]
+pub struct Backbone {
+    pub dataset_id: Uuid,
+    pub global_id: String,
+    pub name: String,
+    pub is_default: bool,
+}
+
+
+#[derive(Clone)]
+pub struct BackboneProvider {
+    pub pool: PgPool,
+}
+
+impl BackboneProvider {
+    pub async fn all(&self) -> Result<Vec<Backbone>, Error> {
+        use schema::{datasets, taxonomic_backbones as backbones};
+        let mut conn = self.pool.get().await?;
+
+        let records = backbones::table
+            .inner_join(datasets::table)
+            .select((datasets::id, datasets::global_id, datasets::name, backbones::is_default))
+            .order_by(datasets::name)
+            .load::<Backbone>(&mut conn)
+            .await?;
+
+        Ok(records)
+    }
+
+    /// Find a registered backbone by the global id of its dataset
+    pub async fn find(&self, global_id: &str) -> Result<Backbone, Error> {
+        use schema::{datasets, taxonomic_backbones as backbones};
+        let mut conn = self.pool.get().await?;
+
+        let backbone = backbones::table
+            .inner_join(datasets::table)
+            .select((datasets::id, datasets::global_id, datasets::name, backbones::is_default))
+            .filter(datasets::global_id.eq(global_id))
+            .get_result::<Backbone>(&mut conn)
+            .await
+            .optional()?;
+
+        backbone.ok_or_else(|| Error::NotFound(global_id.to_string()))
+    }
+
+    /// Get the backbone used when neither the request nor the source specify one
+    pub async fn default(&self) -> Result<Backbone, Error> {
+        use schema::{datasets, taxonomic_backbones as backbones};
+        let mut conn = self.pool.get().await?;
+
+        let backbone = backbones::table
+            .inner_join(datasets::table)
+            .select((datasets::id, datasets::global_id, datasets::name, backbones::is_default))
+            .filter(backbones::is_default.eq(true))
+            .get_result::<Backbone>(&mut conn)
+            .await
+            .optional()?;
+
+        backbone.ok_or_else(|| Error::NotFound("default taxonomic backbone".to_string()))
+    }
+
+    /// Get the backbone a source uses by default, falling back to the default backbone
+    pub async fn for_source(&self, source_id: &Uuid) -> Result<Backbone, Error> {
+        use schema::{datasets, source_backbones, taxonomic_backbones as backbones};
+        let mut conn = self.pool.get().await?;
+
+        let backbone = source_backbones::table
+            .inner_join(backbones::table.inner_join(datasets::table))
+            .select((datasets::id, datasets::global_id, datasets::name, backbones::is_default))
+            .filter(source_backbones::source_id.eq(source_id))
+            .get_result::<Backbone>(&mut conn)
+            .await
+            .optional()?;
+
+        match backbone {
+            Some(backbone) => Ok(backbone),
+            None => self.default().await,
+        }
+    }
+
+    /// Get the requested backbone, or the default for the source or everything when not requested
+    pub async fn resolve(&self, global_id: Option<&str>, source_id: Option<&Uuid>) -> Result<Backbone, Error> {
+        match (global_id, source_id) {
+            (Some(global_id), _) => self.find(global_id).await,
+            (None, Some(source_id)) => self.for_source(source_id).await,
+            (None, None) => self.default().await,
+        }
+    }
+}
@@ -6,7 +6,7 @@ use super::extensions::Paginate;
 use super::models::{Dataset, Taxon};
 use super::{PageResult, PgPool, schema};
 use crate::database::Error;
-use crate::database::sources::ALA_DATASET_ID;
+use crate::database::backbones::Backbone;
 
 
 #[derive(Clone)]
@@ -47,7 +47,7 @@ impl DatasetProvider {
         Ok(dataset?)
     }
 
-    pub async fn species(&self, dataset: &Dataset, page: i64) -> PageResult<Taxon> {
+    pub async fn species(&self, dataset: &Dataset, page: i64, backbone: &Backbone) -> PageResult<Taxon> {
         use schema::{datasets, name_attributes, names, taxa, taxon_names};
         let mut conn = self.pool.get().await?;
 
@@ -57,7 +57,7 @@ impl DatasetProvider {
             .inner_join(taxa::table.on(taxa::id.eq(taxon_names::taxon_id)))
             .inner_join(datasets::table.on(datasets::id.eq(taxa::dataset_id)))
             .filter(name_attributes::dataset_id.eq(dataset.id))
-            .filter(datasets::global_id.eq(ALA_DATASET_ID))
+            .filter(datasets::id.eq(backbone.dataset_id))
             .select(taxa::all_columns)
             .order_by(taxa::scientific_name)
             .paginate(page)
@@ -9,6 +9,7 @@ use diesel_async::RunQueryDsl;
 
 use super::{Sort, SortOrder};
 use crate::database::Error;
+use crate::database::extensions::lower_opt;
 
 
 type FilterableQuerySource = LeftJoinQuerySource<
@@ -25,6 +26,8 @@ pub enum Filter {
     Country(Vec<String>),
     Data(Vec<HasData>),
     CollectedBetween { after: NaiveDate, before: NaiveDate },
+    /// Case insensitive type statuses such as holotype or paratype
+    TypeStatus(Vec<String>),
 }
 
 pub enum HasData {
@@ -60,6 +63,11 @@ pub fn with_any_country(names: &Vec<String>) -> _ {
     collection_events::country.eq_any(names)
 }
 
+pub fn with_any_type_status(statuses: &[String]) -> FilterExpression<'static> {
+    let statuses: Vec<String> = statuses.iter().map(|status| status.to_lowercase()).collect();
+    Box::new(lower_opt(accession_events::type_status).eq_any(statuses).nullable())
+}
+
 #[diesel::dsl::auto_type(no_type_alias)]
 pub fn with_collection_date_between<'a>(after: &'a NaiveDate, before: &'a NaiveDate) -> _ {
     collection_events::event_date.between(after, before)
@@ -80,6 +88,7 @@ pub fn with_filter<'a>(filter: &'a Filter) -> FilterExpression<'a> {
         Filter::Institution(values) => Box::new(with_any_institution(values).nullable()),
         Filter::Country(values) => Box::new(with_any_country(values).nullable()),
         Filter::CollectedBetween { after, before } => Box::new(with_collection_date_between(after, before).nullable()),
+        Filter::TypeStatus(values) => with_any_type_status(values),
         Filter::Data(values) => {
             let mut predicates = None;
 
@@ -132,6 +141,7 @@ impl<'a, T> DynamicFilters<'a> for T {}
 pub struct Options {
     pub institutions: Vec<String>,
     pub countries: Vec<String>,
+    pub type_statuses: Vec<String>,
 }
 
 impl Options {
@@ -152,9 +162,18 @@ impl Options {
             .load::<String>(conn)
             .await?;
 
+        let type_statuses = with_filter_tables()
+            .group_by(accession_events::type_status)
+            .select(accession_events::type_status.assume_not_null())
+            .filter(accession_events::type_status.is_not_null())
+            .dynamic_filters(filters)
+            .load::<String>(conn)
+            .await?;
+
         Ok(Options {
             institutions,
             countries,
+            type_statuses,
         })
     }
 }
@@ -3,6 +3,7 @@ pub mod extensions;
 pub mod agents;
 pub mod annotations;
 pub mod assemblies;
+pub mod backbones;
 pub mod collections;
 pub mod datasets;
 pub mod depositions;
@@ -16,6 +17,8 @@ pub mod overview;
 pub mod provenance;
 pub mod publications;
 pub mod registrations;
+pub mod replication;
+pub mod sensitive;
 pub mod sequences;
 pub mod sources;
 pub mod species;
@@ -24,6 +27,7 @@ pub mod stats;
 pub mod subsamples;
 pub mod taxa;
 pub mod tissues;
+pub mod treatments;
 
 
 pub use arga_core::{get_database_url, models, schema, schema_gnl};
@@ -45,6 +49,9 @@ pub enum Error {
     #[error("the record '{0}' could not found")]
     NotFound(String),
 
+    #[error("the filter '{0}' is not supported here")]
+    UnsupportedFilter(String),
+
     #[error(transparent)]
     Connection(#[from] diesel::result::Error),
 
@@ -99,11 +106,15 @@ pub struct Database {
     pub agents: agents::AgentProvider,
     pub tissues: tissues::TissueProvider,
     pub registrations: registrations::RegistrationProvider,
+    pub replication: replication::ReplicationProvider,
     pub collections: collections::CollectionProvider,
     pub assemblies: assemblies::AssemblyProvider,
     pub libraries: libraries::LibraryProvider,
     pub annotations: annotations::AnnotationProvider,
     pub depositions: depositions::DepositionProvider,
+    pub treatments: treatments::TreatmentProvider,
+    pub sensitive: sensitive::SensitiveDataProvider,
+    pub backbones: backbones::BackboneProvider,
 }
 
 impl Database {
@@ -132,11 +143,15 @@ impl Database {
             agents: agents::AgentProvider { pool: pool.clone() },
             tissues: tissues::TissueProvider { pool: pool.clone() },
             registrations: registrations::RegistrationProvider { pool: pool.clone() },
+            replication: replication::ReplicationProvider { pool: pool.clone() },
             collections: collections::CollectionProvider { pool: pool.clone() },
             assemblies: assemblies::AssemblyProvider { pool: pool.clone() },
             libraries: libraries::LibraryProvider { pool: pool.clone() },
             annotations: annotations::AnnotationProvider { pool: pool.clone() },
             depositions: depositions::DepositionProvider { pool: pool.clone() },
+            treatments: treatments::TreatmentProvider { pool: pool.clone() },
+            sensitive: sensitive::SensitiveDataProvider { pool: pool.clone() },
+            backbones: backbones::BackboneProvider { pool: pool.clone() },
             pool,
         })
     }
@@ -1,13 +1,43 @@
-use arga_core::models::{Name, Taxon};
+use std::collections::HashMap;
+
+use arga_core::models::{AccessionEvent, CollectionEvent, Name, Publication, Taxon, Treatment};
+use arga_core::names::matching::{self, AcceptedName, Matcher, NameMatch, NameQuery};
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use diesel_async::RunQueryDsl;
 use uuid::Uuid;
 
 use super::PgPool;
+use super::taxa::{NomenclaturalAct, TypeSpecimen};
 use crate::database::extensions::lower;
-use crate::database::schema;
+use crate::database::{schema, schema_gnl};
 use crate::http::Error;
 
+/// A taxonomic act along with the name of the dataset it comes from
+#[derive(Debug, Queryable)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SourcedTaxonomicAct {
+    pub entity_id: String,
+    pub taxon: Taxon,
+    pub accepted_taxon: Option<Taxon>,
+    pub source_url: Option<String>,
+    pub data_created_at: Option<DateTime<Utc>>,
+    pub data_updated_at: Option<DateTime<Utc>>,
+    pub dataset_name: String,
+}
+
+/// Everything that has happened to a name, from its description through to how
+/// the taxonomic sources treat it today
+pub struct NameHistory {
+    /// Acts published on the name or that the name was acted on by
+    pub nomenclatural_acts: Vec<NomenclaturalAct>,
+    /// Acts on the taxa of the name along with the publication they were cited from, if known
+    pub taxonomic_acts: Vec<(SourcedTaxonomicAct, Option<Publication>)>,
+    pub treatments: Vec<(Treatment, Option<Publication>)>,
+    pub type_specimens: Vec<TypeSpecimen>,
+}
+
+
 #[derive(Clone)]
 pub struct NameProvider {
     pub pool: PgPool,
@@ -79,4 +109,199 @@ impl NameProvider {
 
         Ok(taxa)
     }
+
+    /// Match names in bulk, following the matches that are synonyms to their accepted names
+    pub async fn match_names(&self, names: &[String]) -> Result<Vec<(NameQuery, Vec<NameMatch>)>, Error> {
+        let mut conn = self.pool.get().await?;
+
+        let queries: Vec<NameQuery> = names.iter().map(|name| NameQuery::new(name)).collect();
+        let candidates = matching::candidates_query(&queries).load::<Name>(&mut conn).await?;
+        let matcher = Matcher::new(candidates);
+
+        let mut results: Vec<(NameQuery, Vec<NameMatch>)> = queries
+            .into_iter()
+            .map(|query| {
+                let matches = matcher.match_name(&query);
+                (query, matches)
+            })
+            .collect();
+
+        let name_ids = results
+            .iter()
+            .flat_map(|(_, matches)| matches.iter().map(|name_match| name_match.name.id))
+            .collect();
+
+        let accepted = self.accepted_names(name_ids).await?;
+        for (_, matches) in results.iter_mut() {
+            matching::follow_synonyms(matches, &accepted);
+        }
+
+        Ok(results)
+    }
+
+    /// Get the acts, treatments and type specimens of a name for building its timeline
+    pub async fn history(&self, name_id: &Uuid) -> Result<NameHistory, Error> {
+        use schema::{
+            accession_events,
+            collection_events,
+            datasets,
+            names,
+            nomenclatural_acts as nomenclatural,
+            publications,
+            specimens,
+            taxa,
+            taxon_names,
+            taxonomic_acts as taxonomic,
+            treatments,
+        };
+        let mut conn = self.pool.get().await?;
+
+        let acted_on = diesel::alias!(names as acted_on);
+
+        let nomenclatural_acts = nomenclatural::table
+            .inner_join(publications::table)
+            .inner_join(names::table.on(names::id.eq(nomenclatural::name_id)))
+            .inner_join(acted_on.on(acted_on.field(names::id).eq(nomenclatural::acted_on_id)))
+            .filter(nomenclatural::name_id.eq(name_id))
+            .or_filter(nomenclatural::acted_on_id.eq(name_id))
+            .select((
+                nomenclatural::entity_id,
+                nomenclatural::act,
+                nomenclatural::source_url,
+                Publication::as_select(),
+                Name::as_select(),
+                acted_on.fields(<Name as Selectable<diesel::pg::Pg>>::construct_selection()),
+            ))
+            .load::<NomenclaturalAct>(&mut conn)
+            .await?;
+
+        // a name can be used by a taxon in every taxonomic source, so we get the acts of
+        // all of them including the ones where another taxon is a synonym of it
+        let taxon_ids = taxon_names::table
+            .filter(taxon_names::name_id.eq(name_id))
+            .select(taxon_names::taxon_id)
+            .load::<Uuid>(&mut conn)
+            .await?;
+
+        let accepted = diesel::alias!(taxa as accepted_taxon);
+
+        let taxonomic_acts = taxonomic::table
+            .inner_join(taxa::table.on(taxa::id.eq(taxonomic::taxon_id)))
+            .left_join(accepted.on(taxonomic::accepted_taxon_id.eq(accepted.field(taxa::id).nullable())))
+            .inner_join(datasets::table.on(datasets::id.eq(taxa::dataset_id)))
+            .filter(taxonomic::taxon_id.eq_any(&taxon_ids))
+            .or_filter(taxonomic::accepted_taxon_id.eq_any(&taxon_ids))
+            .select((
+                taxonomic::entity_id,
+                Taxon::as_select(),
+                accepted
+                    .fields(<Taxon as Selectable<diesel::pg::Pg>>::construct_selection())
+                    .nullable(),
+                taxonomic::source_url,
+                taxonomic::data_created_at,
+                taxonomic::data_updated_at,
+                datasets::name,
+            ))
+            .load::<SourcedTaxonomicAct>(&mut conn)
+            .await?;
+
+        // taxonomic acts aren't linked to a publication directly so we find the one the
+        // taxon is cited from, which is where the act was made
+        let citations: Vec<&str> = taxonomic_acts
+            .iter()
+            .filter_map(|act| act.taxon.citation.as_deref())
+            .collect();
+
+        let cited = publications::table
+            .filter(publications::citation.eq_any(&citations))
+            .select(Publication::as_select())
+            .load::<Publication>(&mut conn)
+            .await?;
+
+        let mut cited_publications: HashMap<String, Publication> = HashMap::new();
+        for publication in cited {
+            if let Some(citation) = publication.citation.clone() {
+                cited_publications.entry(citation).or_insert(publication);
+            }
+        }
+
+        let taxonomic_acts = taxonomic_acts
+            .into_iter()
+            .map(|act| {
+                let publication = act.taxon.citation.as_ref().and_then(|citation| cited_publications.get(citation));
+                let publication = publication.cloned();
+                (act, publication)
+            })
+            .collect();
+
+        let treatments = treatments::table
+            .inner_join(names::table.on(treatments::canonical_name.eq(names::canonical_name.nullable())))
+            .left_join(publications::table.on(publications::entity_id.eq(treatments::publication_entity_id)))
+            .filter(names::id.eq(name_id))
+            .select((Treatment::as_select(), Option::<Publication>::as_select()))
+            .load::<(Treatment, Option<Publication>)>(&mut conn)
+            .await?;
+
+        let type_specimens = specimens::table
+            .inner_join(accession_events::table)
+            .inner_join(collection_events::table)
+            .inner_join(names::table.on(names::id.eq(accession_events::name_id)))
+            .select((AccessionEvent::as_select(), CollectionEvent::as_select(), Name::as_select()))
+            .filter(accession_events::name_id.eq(name_id))
+            .filter(accession_events::type_status.is_not_null())
+            .load::<TypeSpecimen>(&mut conn)
+            .await?;
+
+        Ok(NameHistory {
+            nomenclatural_acts,
+            taxonomic_acts,
+            treatments,
+            type_specimens,
+        })
+    }
+
+    pub async fn find_by_name_ids(&self, name_ids: &[Uuid]) -> Result<Vec<Name>, Error> {
+        use schema::names;
+        let mut conn = self.pool.get().await?;
+
+        let records = names::table
+            .filter(names::id.eq_any(name_ids))
+            .order_by(names::scientific_name)
+            .load::<Name>(&mut conn)
+            .await?;
+
+        Ok(records)
+    }
+
+    /// The accepted names of the names that are synonyms, keyed by the synonym name id
+    pub async fn accepted_names(&self, name_ids: Vec<Uuid>) -> Result<HashMap<Uuid, Name>, Error> {
+        let mut conn = self.pool.get().await?;
+
+        let accepted = matching::accepted_names_query(name_ids)
+            .load::<AcceptedName>(&mut conn)
+            .await?
+            .into_iter()
+            .map(|accepted| accepted.into())
+            .collect();
+
+        Ok(accepted)
+    }
+
+    /// The classification of the names from their taxa in the default backbone
+    pub async fn classifications(&self, name_ids: &[Uuid]) -> Result<HashMap<Uuid, serde_json::Value>, Error> {
+        use schema::{taxon_names, taxonomic_backbones};
+        use schema_gnl::species;
+        let mut conn = self.pool.get().await?;
+
+        let records = taxon_names::table
+            .inner_join(species::table)
+            .inner_join(taxonomic_backbones::table.on(species::dataset_id.eq(taxonomic_backbones::dataset_id)))
+            .filter(taxonomic_backbones::is_default.eq(true))
+            .filter(taxon_names::name_id.eq_any(name_ids))
+            .select((taxon_names::name_id, species::classification))
+            .load::<(Uuid, serde_json::Value)>(&mut conn)
+            .await?;
+
+        Ok(records.into_iter().collect())
+    }
 }
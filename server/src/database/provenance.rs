@@ -1,9 +1,109 @@
 use arga_core::models::logs::SpecimenOperation;
-use arga_core::models::{Dataset, DatasetVersion, NomenclaturalActOperation, TaxonOperation};
+use arga_core::models::{
+    AccessionEventOperation,
+    AgentOperation,
+    AnnotationOperation,
+    AssemblyOperation,
+    CollectionEventOperation,
+    DataProductOperation,
+    Dataset,
+    DatasetVersion,
+    DepositionOperation,
+    ExtractionOperation,
+    LibraryOperation,
+    NomenclaturalActOperation,
+    OrganismOperation,
+    ProjectOperation,
+    SequenceRunOperation,
+    SubsampleOperation,
+    TaxonOperation,
+    TissueOperation,
+};
+use bigdecimal::BigDecimal;
 use diesel::prelude::*;
 use diesel_async::RunQueryDsl;
+use uuid::Uuid;
 
-use super::{schema, Error, PgPool};
+use super::extensions::{Page, Paginate};
+use super::{Error, PageResult, PgPool, schema};
+
+
+/// An operation along with the dataset it was imported from
+pub type DatasetOperation<T> = (T, DatasetVersion, Dataset);
+
+/// Limits the operations returned for an entity
+#[derive(Debug, Clone, Default)]
+pub struct OperationFilter {
+    /// Only include operations imported from this dataset
+    pub dataset_id: Option<Uuid>,
+    /// Only include operations with an operation id at or after this hybrid timestamp
+    pub from: Option<BigDecimal>,
+    /// Only include operations with an operation id at or before this hybrid timestamp
+    pub until: Option<BigDecimal>,
+}
+
+
+/// Generate a paginated provider method for the history of an entity in a log table.
+///
+/// Every log table has the same shape so the only difference between the queries is
+/// the table and the operation struct it is loaded into.
+macro_rules! find_logs {
+    ($name:ident, $table:ident, $operation:ty) => {
+        pub async fn $name(
+            &self,
+            entity_id: &str,
+            filter: &OperationFilter,
+            page: i64,
+            page_size: i64,
+        ) -> PageResult<DatasetOperation<$operation>> {
+            use schema::{dataset_versions, datasets, $table};
+            let mut conn = self.pool.get().await?;
+
+            // a flat struct since the paginated count can't be loaded alongside a nested tuple
+            #[derive(Queryable, Selectable)]
+            #[diesel(table_name = $table)]
+            #[diesel(check_for_backend(diesel::pg::Pg))]
+            struct Row {
+                #[diesel(embed)]
+                operation: $operation,
+                #[diesel(embed)]
+                version: DatasetVersion,
+                #[diesel(embed)]
+                dataset: Dataset,
+            }
+
+            let mut query = $table::table
+                .inner_join(dataset_versions::table.on(dataset_versions::id.eq($table::dataset_version_id)))
+                .inner_join(datasets::table.on(datasets::id.eq(dataset_versions::dataset_id)))
+                .filter($table::entity_id.eq(entity_id))
+                .select(Row::as_select())
+                .into_boxed();
+
+            if let Some(dataset_id) = filter.dataset_id {
+                query = query.filter(datasets::id.eq(dataset_id));
+            }
+            if let Some(from) = &filter.from {
+                query = query.filter($table::operation_id.ge(from.clone()));
+            }
+            if let Some(until) = &filter.until {
+                query = query.filter($table::operation_id.le(until.clone()));
+            }
+
+            let rows = query
+                .order($table::operation_id.asc())
+                .paginate(page)
+                .per_page(page_size)
+                .load::<(Row, i64)>(&mut conn)
+                .await?;
+
+            let page: Page<Row> = rows.into();
+            Ok(Page {
+                records: page.records.into_iter().map(|row| (row.operation, row.version, row.dataset)).collect(),
+                total: page.total,
+            })
+        }
+    };
+}
 
 #[derive(Clone)]
 pub struct ProvenanceProvider {
@@ -17,6 +117,7 @@ impl ProvenanceProvider {
 
         let operations = nomenclatural_act_logs::table
             .filter(nomenclatural_act_logs::entity_id.eq(entity_id))
+            .select(NomenclaturalActOperation::as_select())
             .load::<NomenclaturalActOperation>(&mut conn)
             .await?;
 
@@ -76,4 +177,19 @@ impl ProvenanceProvider {
 
         Ok(operations)
     }
+
+    find_logs!(organism_logs, organism_logs, OrganismOperation);
+    find_logs!(collection_event_logs, collection_event_logs, CollectionEventOperation);
+    find_logs!(accession_event_logs, accession_event_logs, AccessionEventOperation);
+    find_logs!(tissue_logs, tissue_logs, TissueOperation);
+    find_logs!(subsample_logs, subsample_logs, SubsampleOperation);
+    find_logs!(extraction_logs, extraction_logs, ExtractionOperation);
+    find_logs!(library_logs, library_logs, LibraryOperation);
+    find_logs!(sequence_run_logs, sequence_run_logs, SequenceRunOperation);
+    find_logs!(assembly_logs, assembly_logs, AssemblyOperation);
+    find_logs!(annotation_logs, annotation_logs, AnnotationOperation);
+    find_logs!(deposition_logs, deposition_logs, DepositionOperation);
+    find_logs!(agent_logs, agent_logs, AgentOperation);
+    find_logs!(project_logs, project_logs, ProjectOperation);
+    find_logs!(data_product_logs, data_product_logs, DataProductOperation);
 }
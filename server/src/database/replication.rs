@@ -0,0 +1,116 @@
+use bigdecimal::BigDecimal;
+use diesel::QueryableByName;
+use diesel::sql_types::{BigInt, Jsonb, Nullable, Numeric, Text, Uuid as SqlUuid};
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::{Error, PgPool};
+
+
+/// Every operation log table that gets replicated
+pub const LOG_TABLES: [&str; 20] = [
+    "accession_event_logs",
+    "agent_logs",
+    "annotation_logs",
+    "assembly_logs",
+    "collection_event_logs",
+    "data_product_logs",
+    "deposition_logs",
+    "extraction_logs",
+    "library_logs",
+    "nomenclatural_act_logs",
+    "organism_logs",
+    "project_logs",
+    "publication_logs",
+    "sequence_logs",
+    "sequence_run_logs",
+    "specimen_logs",
+    "subsample_logs",
+    "taxa_logs",
+    "taxonomic_act_logs",
+    "tissue_logs",
+];
+
+
+/// An operation inserted into or deleted from any of the log tables.
+///
+/// The operation and parent ids are serialized as strings since hybrid timestamps
+/// are larger than the integers that can be safely represented in JSON. Deletions have
+/// the `Delete` action and a reason, but no parent, dataset version or atom.
+#[derive(Debug, Clone, Serialize, QueryableByName)]
+pub struct ReplicatedOperation {
+    #[diesel(sql_type = BigInt)]
+    pub log_sequence: i64,
+    #[diesel(sql_type = Text)]
+    pub log: String,
+    #[diesel(sql_type = Numeric)]
+    pub operation_id: BigDecimal,
+    #[diesel(sql_type = Nullable<Numeric>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<BigDecimal>,
+    #[diesel(sql_type = Text)]
+    pub entity_id: String,
+    #[diesel(sql_type = Nullable<SqlUuid>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dataset_version_id: Option<Uuid>,
+    #[diesel(sql_type = Text)]
+    pub action: String,
+    #[diesel(sql_type = Nullable<Jsonb>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub atom: Option<serde_json::Value>,
+    #[diesel(sql_type = Nullable<Text>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+
+#[derive(Clone)]
+pub struct ReplicationProvider {
+    pub pool: PgPool,
+}
+
+impl ReplicationProvider {
+    /// Get the operations and deletions across all log tables that come after the sequence.
+    ///
+    /// Sequence values are taken when a row is inserted but a transaction can commit after
+    /// another one that took a later value, so a consumer paging on the sequence alone would
+    /// skip the rows of long running imports. To avoid the gap only rows logged before the
+    /// oldest transaction that is still writing started are returned. Those rows can't have
+    /// a sequence value higher than anything that transaction will commit.
+    pub async fn operations_after(&self, log_sequence: i64, limit: i64) -> Result<Vec<ReplicatedOperation>, Error> {
+        let mut conn = self.pool.get().await?;
+
+        // the table names are constants so it's safe to build the union from them. each
+        // branch is filtered by the sequence so that postgres can use the sequence index
+        // of every log table and merge the results rather than scanning them
+        let mut branches = LOG_TABLES
+            .iter()
+            .map(|table| {
+                format!(
+                    "SELECT log_sequence, '{table}' AS log, operation_id, parent_id, entity_id, dataset_version_id, action::text AS action, atom, NULL AS reason, logged_at FROM {table} WHERE log_sequence > $1"
+                )
+            })
+            .collect::<Vec<String>>();
+
+        branches.push(
+            "SELECT log_sequence, log_table AS log, operation_id, NULL, entity_id, NULL, 'Delete', NULL, reason, logged_at FROM operation_log_deletions WHERE log_sequence > $1".to_string(),
+        );
+
+        let union = branches.join(" UNION ALL ");
+        let horizon =
+            "SELECT coalesce(min(xact_start), clock_timestamp()) FROM pg_stat_activity WHERE backend_xid IS NOT NULL";
+
+        let sql = format!(
+            "SELECT log_sequence, log, operation_id, parent_id, entity_id, dataset_version_id, action, atom, reason FROM ({union}) AS operations WHERE logged_at < ({horizon}) ORDER BY log_sequence LIMIT $2"
+        );
+
+        let operations = diesel::sql_query(sql)
+            .bind::<BigInt, _>(log_sequence)
+            .bind::<BigInt, _>(limit)
+            .load::<ReplicatedOperation>(&mut conn)
+            .await?;
+
+        Ok(operations)
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+
+use arga_core::models::{CollectionEvent, LocationGeneralisation, SensitiveLocation};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use super::{Error, PgPool, schema};
+
+
+#[derive(Clone)]
+pub struct SensitiveDataProvider {
+    pub pool: PgPool,
+}
+
+impl SensitiveDataProvider {
+    /// Get the species, dataset and species in dataset rules that can apply to records of the names.
+    ///
+    /// Collection events are generalised when the rules are applied but a rule added since
+    /// then won't be reflected in the loaded data, so public resolvers use these as well.
+    pub async fn generalisations(&self, name_ids: &[Uuid]) -> Result<Generalisations, Error> {
+        use schema::sensitive_data_rules::dsl::*;
+        let mut conn = self.pool.get().await?;
+
+        let rules = sensitive_data_rules
+            .filter(name_id.eq_any(name_ids).or(name_id.is_null()))
+            .select((name_id, dataset_id, generalisation))
+            .load::<(Option<Uuid>, Option<Uuid>, LocationGeneralisation)>(&mut conn)
+            .await?;
+
+        let mut generalisations = Generalisations::default();
+        for (name, dataset, rule) in rules {
+            generalisations.insert(name, dataset, rule);
+        }
+
+        Ok(generalisations)
+    }
+
+    /// Generalise the collection events with the rules of their names and the datasets they came from
+    pub async fn generalise(&self, mut events: Vec<CollectionEvent>) -> Result<Vec<CollectionEvent>, Error> {
+        let name_ids: Vec<Uuid> = events.iter().map(|event| event.name_id).collect();
+        let entity_ids: Vec<&str> = events.iter().map(|event| event.entity_id.as_str()).collect();
+        let generalisations = self.generalisations(&name_ids).await?;
+        let datasets = self.collection_event_datasets(&entity_ids).await?;
+
+        for event in events.iter_mut() {
+            let event_datasets = datasets.get(&event.entity_id).map(Vec::as_slice).unwrap_or_default();
+            generalisations.apply(event, event_datasets);
+        }
+        Ok(events)
+    }
+
+    /// Get the generalisation that applies to a collection event for anyone other than administrators.
+    ///
+    /// This is the stricter of the generalisation the event was loaded with and the current rules
+    /// for its name and datasets. Events that haven't been loaded yet can't be matched to a rule so
+    /// their coordinates are withheld entirely.
+    pub async fn collection_event_generalisation(
+        &self,
+        entity_id: &str,
+    ) -> Result<Option<LocationGeneralisation>, Error> {
+        let mut rules = self.collection_event_generalisations(&[entity_id]).await?;
+        Ok(rules.remove(entity_id).flatten())
+    }
+
+    /// Get the generalisation that applies to each of the collection events for anyone other than
+    /// administrators, keyed by the entity id. See [`Self::collection_event_generalisation`]
+    pub async fn collection_event_generalisations(
+        &self,
+        entity_ids: &[&str],
+    ) -> Result<HashMap<String, Option<LocationGeneralisation>>, Error> {
+        use schema::collection_events;
+        let mut conn = self.pool.get().await?;
+
+        let events = collection_events::table
+            .filter(collection_events::entity_id.eq_any(entity_ids))
+            .select((
+                collection_events::entity_id,
+                collection_events::name_id,
+                collection_events::location_generalisation,
+            ))
+            .load::<(String, Uuid, Option<LocationGeneralisation>)>(&mut conn)
+            .await?;
+
+        let name_ids: Vec<Uuid> = events.iter().map(|(_, name_id, _)| *name_id).collect();
+        let generalisations = self.generalisations(&name_ids).await?;
+        let datasets = self.collection_event_datasets(entity_ids).await?;
+
+        let mut rules = withhold_all(entity_ids);
+        for (entity_id, name_id, loaded) in events {
+            let event_datasets = datasets.get(&entity_id).map(Vec::as_slice).unwrap_or_default();
+            let rule = generalisations.get(&name_id, event_datasets);
+            rules.insert(entity_id, rule.max(loaded));
+        }
+        Ok(rules)
+    }
+
+    /// Get the generalisation that applies to an organism for anyone other than administrators.
+    ///
+    /// Organisms carry the coordinates of their collection so this is the strictest of the current
+    /// rules for the organism and the generalisations its collection events were loaded with.
+    /// Organisms that haven't been loaded yet have their coordinates withheld entirely.
+    pub async fn organism_generalisation(&self, entity_id: &str) -> Result<Option<LocationGeneralisation>, Error> {
+        let mut rules = self.organism_generalisations(&[entity_id]).await?;
+        Ok(rules.remove(entity_id).flatten())
+    }
+
+    /// Get the generalisation that applies to each of the organisms for anyone other than
+    /// administrators, keyed by the entity id. See [`Self::organism_generalisation`]
+    pub async fn organism_generalisations(
+        &self,
+        entity_ids: &[&str],
+    ) -> Result<HashMap<String, Option<LocationGeneralisation>>, Error> {
+        use schema::{collection_events, dataset_versions, organism_logs, organisms};
+        let mut conn = self.pool.get().await?;
+
+        let organisms = organisms::table
+            .filter(organisms::entity_id.eq_any(entity_ids))
+            .select((organisms::entity_id, organisms::name_id))
+            .load::<(String, Uuid)>(&mut conn)
+            .await?;
+
+        let dataset_rows = organism_logs::table
+            .inner_join(dataset_versions::table)
+            .filter(organism_logs::entity_id.eq_any(entity_ids))
+            .select((organism_logs::entity_id, dataset_versions::dataset_id))
+            .distinct()
+            .load::<(String, Uuid)>(&mut conn)
+            .await?;
+
+        let loaded_rows = collection_events::table
+            .filter(collection_events::organism_id.eq_any(entity_ids))
+            .filter(collection_events::location_generalisation.is_not_null())
+            .select((collection_events::organism_id, collection_events::location_generalisation))
+            .load::<(String, Option<LocationGeneralisation>)>(&mut conn)
+            .await?;
+
+        let mut datasets: HashMap<String, Vec<Uuid>> = HashMap::new();
+        for (entity_id, dataset_id) in dataset_rows {
+            datasets.entry(entity_id).or_default().push(dataset_id);
+        }
+
+        let mut loaded: HashMap<String, LocationGeneralisation> = HashMap::new();
+        for (organism_id, generalisation) in loaded_rows {
+            if let Some(generalisation) = generalisation {
+                insert_strictest(&mut loaded, organism_id, generalisation);
+            }
+        }
+
+        let name_ids: Vec<Uuid> = organisms.iter().map(|(_, name_id)| *name_id).collect();
+        let generalisations = self.generalisations(&name_ids).await?;
+
+        let mut rules = withhold_all(entity_ids);
+        for (entity_id, name_id) in organisms {
+            let organism_datasets = datasets.get(&entity_id).map(Vec::as_slice).unwrap_or_default();
+            let rule = generalisations.get(&name_id, organism_datasets);
+            rules.insert(entity_id.clone(), rule.max(loaded.get(&entity_id).copied()));
+        }
+        Ok(rules)
+    }
+
+    /// Get the generalisation that applies to each of the specimens for anyone other than
+    /// administrators, keyed by the specimen entity id. Specimens are located by their collection
+    /// event so this is the generalisation of the event. See [`Self::collection_event_generalisation`]
+    pub async fn specimen_generalisations(
+        &self,
+        entity_ids: &[&str],
+    ) -> Result<HashMap<String, Option<LocationGeneralisation>>, Error> {
+        use schema::collection_events;
+        let mut conn = self.pool.get().await?;
+
+        let events = collection_events::table
+            .filter(collection_events::specimen_id.eq_any(entity_ids))
+            .select((collection_events::specimen_id, collection_events::entity_id))
+            .load::<(String, String)>(&mut conn)
+            .await?;
+
+        let event_ids: Vec<&str> = events.iter().map(|(_, event_id)| event_id.as_str()).collect();
+        let event_rules = self.collection_event_generalisations(&event_ids).await?;
+
+        // a specimen with several collection events gets the strictest of them
+        let mut rules: HashMap<String, Option<LocationGeneralisation>> = HashMap::new();
+        for (specimen_id, event_id) in events {
+            let rule = event_rules.get(&event_id).copied().flatten();
+            rules
+                .entry(specimen_id)
+                .and_modify(|existing| *existing = (*existing).max(rule))
+                .or_insert(rule);
+        }
+
+        for (entity_id, withheld) in withhold_all(entity_ids) {
+            rules.entry(entity_id).or_insert(withheld);
+        }
+        Ok(rules)
+    }
+
+    /// Get the datasets that contributed to each of the collection events
+    async fn collection_event_datasets(&self, entity_ids: &[&str]) -> Result<HashMap<String, Vec<Uuid>>, Error> {
+        use schema::{collection_event_logs, dataset_versions};
+        let mut conn = self.pool.get().await?;
+
+        let rows = collection_event_logs::table
+            .inner_join(dataset_versions::table)
+            .filter(collection_event_logs::entity_id.eq_any(entity_ids))
+            .select((collection_event_logs::entity_id, dataset_versions::dataset_id))
+            .distinct()
+            .load::<(String, Uuid)>(&mut conn)
+            .await?;
+
+        let mut datasets: HashMap<String, Vec<Uuid>> = HashMap::new();
+        for (entity_id, dataset_id) in rows {
+            datasets.entry(entity_id).or_default().push(dataset_id);
+        }
+        Ok(datasets)
+    }
+
+    /// Get the precise location of a generalised collection event.
+    /// This must only ever be returned to administrators
+    pub async fn precise_location(&self, entity_id: &str) -> Result<Option<SensitiveLocation>, Error> {
+        use schema::sensitive_locations;
+        let mut conn = self.pool.get().await?;
+
+        let location = sensitive_locations::table
+            .filter(sensitive_locations::entity_id.eq(entity_id))
+            .select(SensitiveLocation::as_select())
+            .get_result::<SensitiveLocation>(&mut conn)
+            .await
+            .optional()?;
+
+        Ok(location)
+    }
+}
+
+
+/// The strictest rules keyed by the name, dataset, or name and dataset they match
+#[derive(Debug, Clone, Default)]
+pub struct Generalisations {
+    names: HashMap<Uuid, LocationGeneralisation>,
+    datasets: HashMap<Uuid, LocationGeneralisation>,
+    names_in_datasets: HashMap<(Uuid, Uuid), LocationGeneralisation>,
+}
+
+impl Generalisations {
+    /// Add a rule. A rule with both a name and a dataset only matches records of that name in that dataset
+    pub fn insert(&mut self, name_id: Option<Uuid>, dataset_id: Option<Uuid>, rule: LocationGeneralisation) {
+        match (name_id, dataset_id) {
+            (Some(name_id), Some(dataset_id)) => insert_strictest(&mut self.names_in_datasets, (name_id, dataset_id), rule),
+            (Some(name_id), None) => insert_strictest(&mut self.names, name_id, rule),
+            (None, Some(dataset_id)) => insert_strictest(&mut self.datasets, dataset_id, rule),
+            (None, None) => {}
+        }
+    }
+
+    /// The strictest rule for a record of the name from any of the datasets
+    pub fn get(&self, name_id: &Uuid, datasets: &[Uuid]) -> Option<LocationGeneralisation> {
+        let by_name = self.names.get(name_id);
+        let by_dataset = datasets.iter().filter_map(|dataset_id| self.datasets.get(dataset_id));
+        let by_both = datasets
+            .iter()
+            .filter_map(|dataset_id| self.names_in_datasets.get(&(*name_id, *dataset_id)));
+        by_name.into_iter().chain(by_dataset).chain(by_both).max().copied()
+    }
+
+    /// Generalise a collection event if a rule is stricter than the one it was loaded with
+    pub fn apply(&self, event: &mut CollectionEvent, datasets: &[Uuid]) {
+        let stricter = self
+            .get(&event.name_id, datasets)
+            .filter(|rule| Some(*rule) > event.location_generalisation);
+        if let Some(rule) = stricter {
+            (event.latitude, event.longitude) = rule.generalise(event.latitude, event.longitude);
+            event.location_generalisation = Some(rule);
+        }
+    }
+}
+
+fn insert_strictest<K: std::hash::Hash + Eq>(
+    rules: &mut HashMap<K, LocationGeneralisation>,
+    key: K,
+    rule: LocationGeneralisation,
+) {
+    rules
+        .entry(key)
+        .and_modify(|existing| *existing = (*existing).max(rule))
+        .or_insert(rule);
+}
+
+/// Withhold the coordinates of every entity until it is found to have been loaded
+fn withhold_all(entity_ids: &[&str]) -> HashMap<String, Option<LocationGeneralisation>> {
+    entity_ids
+        .iter()
+        .map(|entity_id| (entity_id.to_string(), Some(LocationGeneralisation::Withhold)))
+        .collect()
+}
+
+
+/// Generalise a pair of coordinates in place if there is a rule
+pub fn generalise(rule: Option<LocationGeneralisation>, latitude: &mut Option<f64>, longitude: &mut Option<f64>) {
+    if let Some(rule) = rule {
+        (*latitude, *longitude) = rule.generalise(*latitude, *longitude);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(name_id: Uuid, loaded: Option<LocationGeneralisation>) -> CollectionEvent {
+        CollectionEvent {
+            name_id,
+            latitude: Some(-33.8688),
+            longitude: Some(151.2093),
+            location_generalisation: loaded,
+            ..Default::default()
+        }
+    }
+
+    fn rules(rules: &[(Option<Uuid>, Option<Uuid>, LocationGeneralisation)]) -> Generalisations {
+        let mut generalisations = Generalisations::default();
+        for (name_id, dataset_id, rule) in rules {
+            generalisations.insert(*name_id, *dataset_id, *rule);
+        }
+        generalisations
+    }
+
+    #[test]
+    fn applies_the_rule_for_the_name() {
+        let name_id = Uuid::new_v4();
+        let mut event = event(name_id, None);
+        rules(&[(Some(name_id), None, LocationGeneralisation::OneDegree)]).apply(&mut event, &[]);
+
+        assert_eq!((event.latitude, event.longitude), (Some(-34.0), Some(151.0)));
+        assert_eq!(event.location_generalisation, Some(LocationGeneralisation::OneDegree));
+    }
+
+    #[test]
+    fn ignores_rules_for_other_names() {
+        let mut event = event(Uuid::new_v4(), None);
+        rules(&[(Some(Uuid::new_v4()), None, LocationGeneralisation::Withhold)]).apply(&mut event, &[]);
+
+        assert_eq!((event.latitude, event.longitude), (Some(-33.8688), Some(151.2093)));
+        assert_eq!(event.location_generalisation, None);
+    }
+
+    #[test]
+    fn applies_the_rule_for_the_dataset() {
+        let dataset_id = Uuid::new_v4();
+        let mut event = event(Uuid::new_v4(), None);
+        rules(&[(None, Some(dataset_id), LocationGeneralisation::Withhold)]).apply(&mut event, &[dataset_id]);
+
+        assert_eq!((event.latitude, event.longitude), (None, None));
+        assert_eq!(event.location_generalisation, Some(LocationGeneralisation::Withhold));
+    }
+
+    #[test]
+    fn matches_rules_with_a_name_and_dataset_on_both() {
+        let name_id = Uuid::new_v4();
+        let dataset_id = Uuid::new_v4();
+        let generalisations = rules(&[(Some(name_id), Some(dataset_id), LocationGeneralisation::Withhold)]);
+
+        assert_eq!(generalisations.get(&name_id, &[dataset_id]), Some(LocationGeneralisation::Withhold));
+        assert_eq!(generalisations.get(&name_id, &[Uuid::new_v4()]), None);
+        assert_eq!(generalisations.get(&name_id, &[]), None);
+        assert_eq!(generalisations.get(&Uuid::new_v4(), &[dataset_id]), None);
+    }
+
+    #[test]
+    fn keeps_a_stricter_loaded_generalisation() {
+        let name_id = Uuid::new_v4();
+        let mut event = event(name_id, Some(LocationGeneralisation::Withhold));
+        event.latitude = None;
+        event.longitude = None;
+        rules(&[(Some(name_id), None, LocationGeneralisation::TenthDegree)]).apply(&mut event, &[]);
+
+        assert_eq!((event.latitude, event.longitude), (None, None));
+        assert_eq!(event.location_generalisation, Some(LocationGeneralisation::Withhold));
+    }
+
+    #[test]
+    fn applies_a_rule_stricter_than_the_loaded_one() {
+        let name_id = Uuid::new_v4();
+        let mut event = event(name_id, Some(LocationGeneralisation::TenthDegree));
+        rules(&[(Some(name_id), None, LocationGeneralisation::Withhold)]).apply(&mut event, &[]);
+
+        assert_eq!((event.latitude, event.longitude), (None, None));
+        assert_eq!(event.location_generalisation, Some(LocationGeneralisation::Withhold));
+    }
+
+    #[test]
+    fn picks_the_strictest_matching_rule() {
+        let name_id = Uuid::new_v4();
+        let dataset_id = Uuid::new_v4();
+        let generalisations = rules(&[
+            (Some(name_id), None, LocationGeneralisation::TenthDegree),
+            (None, Some(dataset_id), LocationGeneralisation::OneDegree),
+            (Some(name_id), Some(Uuid::new_v4()), LocationGeneralisation::Withhold),
+        ]);
+
+        assert_eq!(generalisations.get(&name_id, &[dataset_id]), Some(LocationGeneralisation::OneDegree));
+        assert_eq!(generalisations.get(&name_id, &[]), Some(LocationGeneralisation::TenthDegree));
+        assert_eq!(Generalisations::default().get(&name_id, &[dataset_id]), None);
+    }
+}
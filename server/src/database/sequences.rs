@@ -129,18 +129,29 @@ impl SequenceProvider {
         Ok(events)
     }
 
+    /// Get the trace of a sequencing run along with the chromatogram if it has been imported
     pub async fn trace_data(&self, sequence_run_event_id: &Uuid) -> Result<TraceData, Error> {
-        use schema::{deposition_events, sequencing_events, sequencing_run_events};
+        use schema::{deposition_events, sequence_traces, sequencing_events, sequencing_run_events};
         let mut conn = self.pool.get().await?;
 
         let trace = sequencing_run_events::table
             .inner_join(sequencing_events::table)
             .inner_join(deposition_events::table.on(deposition_events::sequence_id.eq(sequencing_events::sequence_id)))
+            .left_join(
+                sequence_traces::table.on(sequence_traces::trace_id.nullable().eq(sequencing_run_events::trace_id)),
+            )
             .select((
                 deposition_events::accession,
                 sequencing_run_events::trace_id,
                 sequencing_run_events::trace_name,
                 sequencing_run_events::trace_link,
+                sequence_traces::a_analyzed.nullable(),
+                sequence_traces::c_analyzed.nullable(),
+                sequence_traces::g_analyzed.nullable(),
+                sequence_traces::t_analyzed.nullable(),
+                sequence_traces::base_calls.nullable(),
+                sequence_traces::quality_scores.nullable(),
+                sequence_traces::peak_locations.nullable(),
             ))
             .filter(sequencing_run_events::id.eq(sequence_run_event_id))
             .get_result::<TraceData>(&mut conn)
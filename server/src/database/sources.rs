@@ -10,13 +10,12 @@ use super::extensions::date_utils::DateParser;
 use super::models::{Dataset, Source};
 use super::{PageResult, PgPool, schema, schema_gnl};
 use crate::database::Error;
+use crate::database::backbones::Backbone;
 use crate::database::extensions::filters::{Filter, with_filters};
 use crate::database::extensions::species_filters::{SortDirection, SpeciesSort, with_sorting};
 use crate::database::extensions::sum_if;
 use crate::database::taxa::RankSummary;
 
-pub const ALA_DATASET_ID: &str = "ARGA:TL:0001013";
-
 #[derive(Clone, Debug)]
 pub enum SummarySort {
     TotalGenomic,
@@ -55,7 +54,12 @@ impl SourceProvider {
     }
 
     // Helper method to get source species IDs with consistent filtering
-    async fn get_source_species_ids(&self, source: &Source, filters: &Option<Vec<Filter>>) -> Result<Vec<Uuid>, Error> {
+    async fn get_source_species_ids(
+        &self,
+        source: &Source,
+        filters: &Option<Vec<Filter>>,
+        backbone: &Backbone,
+    ) -> Result<Vec<Uuid>, Error> {
         use schema::{datasets, name_attributes as attrs, taxon_names};
         use schema_gnl::species;
 
@@ -76,7 +80,7 @@ impl SourceProvider {
             .select(species::id)
             .distinct()
             .filter(datasets::source_id.eq(source.id))
-            .filter(taxa_datasets.field(datasets::global_id).eq(ALA_DATASET_ID))
+            .filter(taxa_datasets.field(datasets::id).eq(backbone.dataset_id))
             .load::<Uuid>(&mut conn)
             .await?;
 
@@ -88,13 +92,14 @@ impl SourceProvider {
         &self,
         source: &Source,
         filters: &Option<Vec<Filter>>,
+        backbone: &Backbone,
         sort: SummarySort,
         limit: i64,
     ) -> Result<Vec<super::taxa::Summary>, Error> {
         use schema::taxa;
         use schema_gnl::taxa_tree_stats as stats;
 
-        let source_species = self.get_source_species_ids(source, filters).await?;
+        let source_species = self.get_source_species_ids(source, filters, backbone).await?;
 
         if source_species.is_empty() {
             return Ok(vec![]);
@@ -218,6 +223,7 @@ impl SourceProvider {
         page_size: i64,
         sort: SpeciesSort,
         direction: SortDirection,
+        backbone: &Backbone,
     ) -> PageResult<Species> {
         use schema::{datasets, name_attributes as attrs, taxon_names};
         use schema_gnl::species;
@@ -239,7 +245,7 @@ impl SourceProvider {
             .select(species::all_columns)
             .distinct()
             .filter(datasets::source_id.eq(source.id))
-            .filter(taxa_datasets.field(datasets::global_id).eq(ALA_DATASET_ID))
+            .filter(taxa_datasets.field(datasets::id).eq(backbone.dataset_id))
             .paginate(page)
             .per_page(page_size)
             .load::<(Species, i64)>(&mut conn)
@@ -253,8 +259,9 @@ impl SourceProvider {
         &self,
         source: &Source,
         filters: &Option<Vec<Filter>>,
+        backbone: &Backbone,
     ) -> Result<Vec<super::taxa::Summary>, Error> {
-        self.get_species_summary(source, filters, SummarySort::TotalGenomic, 10)
+        self.get_species_summary(source, filters, backbone, SummarySort::TotalGenomic, 10)
             .await
     }
 
@@ -263,8 +270,9 @@ impl SourceProvider {
         &self,
         source: &Source,
         filters: &Option<Vec<Filter>>,
+        backbone: &Backbone,
     ) -> Result<Vec<super::taxa::Summary>, Error> {
-        self.get_species_summary(source, filters, SummarySort::Genomes, 10)
+        self.get_species_summary(source, filters, backbone, SummarySort::Genomes, 10)
             .await
     }
 
@@ -273,8 +281,9 @@ impl SourceProvider {
         &self,
         source: &Source,
         filters: &Option<Vec<Filter>>,
+        backbone: &Backbone,
     ) -> Result<Vec<super::taxa::Summary>, Error> {
-        self.get_species_summary(source, filters, SummarySort::Loci, 10).await
+        self.get_species_summary(source, filters, backbone, SummarySort::Loci, 10).await
     }
 
     // Summary statistics for a specific rank for species in this source
@@ -282,11 +291,12 @@ impl SourceProvider {
         &self,
         source: &Source,
         filters: &Option<Vec<Filter>>,
+        backbone: &Backbone,
     ) -> Result<super::taxa::RankSummary, Error> {
         use schema::taxa;
         use schema_gnl::taxa_tree_stats as stats;
 
-        let source_species = self.get_source_species_ids(source, filters).await?;
+        let source_species = self.get_source_species_ids(source, filters, backbone).await?;
 
         let total_count = source_species.len() as i64;
 
@@ -330,6 +340,7 @@ impl SourceProvider {
         &self,
         source: &Source,
         filters: &Option<Vec<Filter>>,
+        backbone: &Backbone,
     ) -> Result<Vec<GenomeRelease>, Error> {
         use schema::{datasets, deposition_events, name_attributes as attrs, names, sequences, taxon_names};
         use schema_gnl::species;
@@ -351,7 +362,7 @@ impl SourceProvider {
             .select(attrs::name_id)
             .distinct()
             .filter(datasets::source_id.eq(source.id))
-            .filter(taxa_datasets.field(datasets::global_id).eq(ALA_DATASET_ID))
+            .filter(taxa_datasets.field(datasets::id).eq(backbone.dataset_id))
             .load::<Uuid>(&mut conn)
             .await?;
 
@@ -393,6 +404,7 @@ impl SourceProvider {
         &self,
         source: &Source,
         filters: &Option<Vec<Filter>>,
+        backbone: &Backbone,
     ) -> Result<Vec<KingdomPhylumCount>, Error> {
         use std::collections::HashMap;
 
@@ -416,7 +428,7 @@ impl SourceProvider {
             .select(species::classification)
             .distinct()
             .filter(datasets::source_id.eq(source.id))
-            .filter(taxa_datasets.field(datasets::global_id).eq(ALA_DATASET_ID))
+            .filter(taxa_datasets.field(datasets::id).eq(backbone.dataset_id))
             .load::<serde_json::Value>(&mut conn)
             .await?;
 
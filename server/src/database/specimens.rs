@@ -6,6 +6,7 @@ use super::{Error, PgPool, schema};
 use crate::database::models::{
     AccessionEvent,
     CollectionEvent,
+    Name,
     Organism,
     Specimen,
     SpecimenStats,
@@ -141,6 +142,27 @@ impl SpecimenProvider {
         Ok(accessions)
     }
 
+    /// Get the names that a specimen is a type of along with the type status.
+    ///
+    /// The name comes from the accession rather than the specimen since a specimen
+    /// can be the type of a name that is now a synonym of the specimen name.
+    pub async fn typified_names(&self, specimen_id: &str) -> Result<Vec<(String, Name)>, Error> {
+        use schema::{accession_events, names};
+        let mut conn = self.pool.get().await?;
+
+        let names = accession_events::table
+            .inner_join(names::table.on(names::id.eq(accession_events::name_id)))
+            .filter(accession_events::specimen_id.eq(specimen_id))
+            .filter(accession_events::type_status.is_not_null())
+            .select((accession_events::type_status.assume_not_null(), Name::as_select()))
+            .distinct()
+            .order_by(names::scientific_name)
+            .load::<(String, Name)>(&mut conn)
+            .await?;
+
+        Ok(names)
+    }
+
     /// Get tissues that have been sampled from a specific specimen.
     ///
     /// This does not get the tissue with the provided specimen_id, rather
@@ -16,7 +16,7 @@ use crate::database::extensions::classification_filters::with_classification;
 use crate::database::extensions::filters::{with_classification as with_species_classification, with_filters};
 use crate::database::extensions::filters_new::stats;
 use crate::database::extensions::sum_if;
-use crate::database::sources::ALA_DATASET_ID;
+use crate::database::backbones::Backbone;
 
 
 #[derive(Clone, Debug, SimpleObject, Serialize, Deserialize)]
@@ -157,6 +157,7 @@ impl StatsProvider {
         &self,
         taxon: Classification,
         ranks: &Vec<TaxonomicRank>,
+        backbone: &Backbone,
     ) -> Result<Vec<TaxonomicRankStat>, Error> {
         use diesel::dsl::{count_star, sql};
         use diesel::sql_types::Float;
@@ -171,7 +172,7 @@ impl StatsProvider {
             .filter(with_classification(&taxon))
             .into_boxed()
             .inner_join(datasets::table.on(taxa::dataset_id.eq(datasets::id)))
-            .filter(datasets::global_id.eq(ALA_DATASET_ID))
+            .filter(datasets::id.eq(backbone.dataset_id))
             .first::<uuid::Uuid>(&mut conn)
             .await?;
 
@@ -206,7 +207,11 @@ impl StatsProvider {
         Ok(stats)
     }
 
-    pub async fn complete_genomes_by_year(&self, taxon: Classification) -> Result<Vec<(i32, i64)>, Error> {
+    pub async fn complete_genomes_by_year(
+        &self,
+        taxon: Classification,
+        backbone: &Backbone,
+    ) -> Result<Vec<(i32, i64)>, Error> {
         use diesel::dsl::{count_star, sql};
         use diesel::sql_types::Integer;
         use schema::{datasets, taxon_names};
@@ -227,7 +232,7 @@ impl StatsProvider {
             .inner_join(datasets::table.on(datasets::id.eq(species::dataset_id)))
             .filter(taxon_names::taxon_id.eq_any(filtered_species))
             .filter(sequence_milestones::representation.eq("Full"))
-            .filter(datasets::global_id.eq(ALA_DATASET_ID))
+            .filter(datasets::id.eq(backbone.dataset_id))
             .select((
                 sql::<Integer>("date_part('year', to_date(deposition_date, 'YYYY/MM/DD'))::integer"),
                 count_star(),
@@ -244,6 +249,7 @@ impl StatsProvider {
         &self,
         name: &str,
         filters: &Option<Vec<Filter>>,
+        backbone: &Backbone,
     ) -> Result<Vec<(i32, i64)>, Error> {
         use diesel::dsl::{count_star, sql};
         use diesel::sql_types::Integer;
@@ -275,7 +281,7 @@ impl StatsProvider {
             .select(species::id)
             .distinct()
             .filter(datasets::source_id.eq(source_id))
-            .filter(taxa_datasets.field(datasets::global_id).eq(ALA_DATASET_ID))
+            .filter(taxa_datasets.field(datasets::id).eq(backbone.dataset_id))
             .load::<uuid::Uuid>(&mut conn)
             .await?;
 
@@ -310,6 +316,7 @@ impl StatsProvider {
         &self,
         taxon: Classification,
         include_ranks: Vec<TaxonomicRank>,
+        backbone: &Backbone,
     ) -> Result<Vec<TaxonStatNode>, Error> {
         use schema::{datasets, taxa};
         use schema_gnl::{taxa_tree, taxa_tree_stats};
@@ -321,7 +328,7 @@ impl StatsProvider {
             .filter(with_classification(&taxon))
             .into_boxed()
             .inner_join(datasets::table.on(taxa::dataset_id.eq(datasets::id)))
-            .filter(datasets::global_id.eq(ALA_DATASET_ID))
+            .filter(datasets::id.eq(backbone.dataset_id))
             .order(datasets::global_id.asc())
             .first::<uuid::Uuid>(&mut conn)
             .await?;
@@ -12,6 +12,7 @@ use arga_core::models::{
     TaxonWithDataset,
     TaxonomicRank,
 };
+use arga_core::comparison::TaxonNode;
 use bigdecimal::{BigDecimal, Zero};
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
@@ -21,14 +22,14 @@ use uuid::Uuid;
 
 use super::extensions::species_filters::{SortDirection, SpeciesSort};
 use super::extensions::taxa_filters::TaxaFilter;
-use super::extensions::{Paginate, sum_if};
+use super::extensions::{Paginate, lower, sum_if};
 use super::models::Species;
 use super::{Error, PageResult, PgPool, schema, schema_gnl};
 use crate::database::extensions::classification_filters::{
     Classification as ClassificationFilter,
     with_classification,
 };
-use crate::database::extensions::filters::{Filter, with_filters};
+use crate::database::extensions::filters::{DataType, Filter, FilterKind, with_filters};
 use crate::database::extensions::species_filters::{
     with_accepted_classification,
     with_classification as with_species_classification,
@@ -88,6 +89,68 @@ pub struct SpeciesSummary {
     pub total_genomic: i64,
 }
 
+/// The accumulated stats of a node in the taxa tree
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = schema_gnl::taxa_tree_stats)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TreeStats {
+    pub children: i64,
+    pub descendants: i64,
+    pub species: Option<i64>,
+    pub loci: Option<BigDecimal>,
+    pub genomes: Option<BigDecimal>,
+    pub specimens: Option<BigDecimal>,
+    pub other: Option<BigDecimal>,
+    pub total_genomic: Option<BigDecimal>,
+}
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = schema::taxa)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TaxonWithStats {
+    #[diesel(embed)]
+    pub taxon: Taxon,
+    #[diesel(embed)]
+    pub stats: TreeStats,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TreeSort {
+    ScientificName,
+    Children,
+    Descendants,
+    Species,
+    Genomes,
+    Loci,
+    Specimens,
+    TotalGenomic,
+}
+
+/// How to narrow down and order the nodes in the tree below a taxon
+pub struct TreeOptions {
+    pub rank: Option<TaxonomicRank>,
+    pub filters: Vec<Filter>,
+    pub sort: TreeSort,
+    pub direction: SortDirection,
+}
+
+/// The nodes to get from the tree below a taxon
+enum TreeNodes {
+    Children,
+    Descendants,
+}
+
+/// The name of a filter kind as used in an error for filters that can't be applied
+fn filter_name(kind: &FilterKind) -> &'static str {
+    match kind {
+        FilterKind::Classification(_) => "classification",
+        FilterKind::VernacularGroup(_) => "vernacular group",
+        FilterKind::HasData(_) => "has data",
+        FilterKind::Dataset(_) => "dataset",
+        FilterKind::Attribute(_) => "attribute",
+    }
+}
+
 #[derive(Debug, Queryable)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct RankSummary {
@@ -329,6 +392,179 @@ impl TaxaProvider {
         Ok(nodes)
     }
 
+    /// The direct children of a taxon along with the stats accumulated from their descendants
+    pub async fn children(
+        &self,
+        taxon_id: &Uuid,
+        options: &TreeOptions,
+        page: i64,
+        per_page: i64,
+    ) -> PageResult<TaxonWithStats> {
+        self.tree_nodes(taxon_id, TreeNodes::Children, options, page, per_page).await
+    }
+
+    /// All taxa below a taxon along with the stats accumulated from their descendants
+    pub async fn descendants(
+        &self,
+        taxon_id: &Uuid,
+        options: &TreeOptions,
+        page: i64,
+        per_page: i64,
+    ) -> PageResult<TaxonWithStats> {
+        self.tree_nodes(taxon_id, TreeNodes::Descendants, options, page, per_page).await
+    }
+
+    async fn tree_nodes(
+        &self,
+        taxon_id: &Uuid,
+        nodes: TreeNodes,
+        options: &TreeOptions,
+        page: i64,
+        per_page: i64,
+    ) -> PageResult<TaxonWithStats> {
+        use schema::taxa;
+        use schema_gnl::taxa_tree_stats as stats;
+
+        let mut conn = self.pool.get().await?;
+
+        // the taxa_tree_stats view has a row for every node below a taxon that accumulates the
+        // stats of the node's own descendants. by only using the rows of the tree rooted at the
+        // supplied taxon we get the stats of every descendant without having to traverse the tree
+        let mut query = taxa::table
+            .inner_join(stats::table.on(stats::id.eq(taxa::id)))
+            .filter(stats::taxon_id.eq(taxon_id))
+            .filter(stats::id.ne(taxon_id))
+            .select(TaxonWithStats::as_select())
+            .into_boxed();
+
+        if let TreeNodes::Children = nodes {
+            query = query.filter(taxa::parent_id.eq(taxon_id));
+        }
+
+        if let Some(rank) = &options.rank {
+            query = query.filter(taxa::rank.eq(rank));
+        }
+
+        // only data and dataset filters can be applied to the tree since every other filter is
+        // specific to species. the stats are accumulated so a node has data if any of its
+        // descendants do
+        let zero = BigDecimal::zero();
+        for filter in &options.filters {
+            query = match filter {
+                Filter::Include(FilterKind::Dataset(dataset_id)) => query.filter(taxa::dataset_id.eq(*dataset_id)),
+                Filter::Exclude(FilterKind::Dataset(dataset_id)) => query.filter(taxa::dataset_id.ne(*dataset_id)),
+                Filter::Include(FilterKind::HasData(data_type)) => match data_type {
+                    DataType::Genome => query.filter(stats::genomes.gt(zero.clone())),
+                    DataType::Locus => query.filter(stats::loci.gt(zero.clone())),
+                    DataType::Specimen => query.filter(stats::specimens.gt(zero.clone())),
+                    DataType::Other => query.filter(stats::other.gt(zero.clone())),
+                },
+                Filter::Exclude(FilterKind::HasData(data_type)) => match data_type {
+                    DataType::Genome => query.filter(stats::genomes.is_null().or(stats::genomes.le(zero.clone()))),
+                    DataType::Locus => query.filter(stats::loci.is_null().or(stats::loci.le(zero.clone()))),
+                    DataType::Specimen => {
+                        query.filter(stats::specimens.is_null().or(stats::specimens.le(zero.clone())))
+                    }
+                    DataType::Other => query.filter(stats::other.is_null().or(stats::other.le(zero.clone()))),
+                },
+                Filter::Include(kind) | Filter::Exclude(kind) => {
+                    return Err(Error::UnsupportedFilter(filter_name(kind).to_string()));
+                }
+            };
+        }
+
+        query = match (options.sort, &options.direction) {
+            (TreeSort::ScientificName, SortDirection::Asc) => query.order(taxa::scientific_name.asc()),
+            (TreeSort::ScientificName, SortDirection::Desc) => query.order(taxa::scientific_name.desc()),
+            (TreeSort::Children, SortDirection::Asc) => query.order(stats::children.asc()),
+            (TreeSort::Children, SortDirection::Desc) => query.order(stats::children.desc()),
+            (TreeSort::Descendants, SortDirection::Asc) => query.order(stats::descendants.asc()),
+            (TreeSort::Descendants, SortDirection::Desc) => query.order(stats::descendants.desc()),
+            (TreeSort::Species, SortDirection::Asc) => query.order(stats::species.asc().nulls_first()),
+            (TreeSort::Species, SortDirection::Desc) => query.order(stats::species.desc().nulls_last()),
+            (TreeSort::Genomes, SortDirection::Asc) => query.order(stats::genomes.asc().nulls_first()),
+            (TreeSort::Genomes, SortDirection::Desc) => query.order(stats::genomes.desc().nulls_last()),
+            (TreeSort::Loci, SortDirection::Asc) => query.order(stats::loci.asc().nulls_first()),
+            (TreeSort::Loci, SortDirection::Desc) => query.order(stats::loci.desc().nulls_last()),
+            (TreeSort::Specimens, SortDirection::Asc) => query.order(stats::specimens.asc().nulls_first()),
+            (TreeSort::Specimens, SortDirection::Desc) => query.order(stats::specimens.desc().nulls_last()),
+            (TreeSort::TotalGenomic, SortDirection::Asc) => query.order(stats::total_genomic.asc().nulls_first()),
+            (TreeSort::TotalGenomic, SortDirection::Desc) => query.order(stats::total_genomic.desc().nulls_last()),
+        };
+
+        // keep the pages stable when nodes have the same stats
+        let records = query
+            .then_order_by(taxa::scientific_name.asc())
+            .paginate(page)
+            .per_page(per_page)
+            .load::<(TaxonWithStats, i64)>(&mut conn)
+            .await?;
+
+        Ok(records.into())
+    }
+
+    /// The taxon and every taxon below it with the name of their parent for comparing classifications
+    pub async fn classification_nodes(&self, taxon_id: &Uuid) -> Result<Vec<TaxonNode>, Error> {
+        use schema::taxa;
+        use schema_gnl::taxa_tree_stats as stats;
+        let mut conn = self.pool.get().await?;
+
+        let parent = diesel::alias!(taxa as parent);
+
+        let nodes = taxa::table
+            .inner_join(stats::table.on(stats::id.eq(taxa::id)))
+            .left_join(parent.on(taxa::parent_id.eq(parent.field(taxa::id).nullable())))
+            .filter(stats::taxon_id.eq(taxon_id))
+            .select((
+                taxa::id,
+                taxa::scientific_name,
+                taxa::canonical_name,
+                taxa::authorship,
+                taxa::rank,
+                taxa::status,
+                parent.field(taxa::canonical_name).nullable(),
+            ))
+            .load::<TaxonNode>(&mut conn)
+            .await?;
+
+        Ok(nodes)
+    }
+
+    /// Every taxon in a dataset with one of the canonical names, with the name of their parent.
+    ///
+    /// The taxa are matched by name anywhere in the dataset rather than below an equivalent taxon
+    /// because a taxon that the dataset places under a different parent is exactly the kind of
+    /// disagreement a comparison is meant to find.
+    pub async fn classification_nodes_by_name(
+        &self,
+        dataset_id: &Uuid,
+        canonical_names: &[String],
+    ) -> Result<Vec<TaxonNode>, Error> {
+        use schema::taxa;
+        let mut conn = self.pool.get().await?;
+
+        let parent = diesel::alias!(taxa as parent);
+        let names: Vec<String> = canonical_names.iter().map(|name| name.to_lowercase()).collect();
+
+        let nodes = taxa::table
+            .left_join(parent.on(taxa::parent_id.eq(parent.field(taxa::id).nullable())))
+            .filter(taxa::dataset_id.eq(dataset_id))
+            .filter(lower(taxa::canonical_name).eq_any(names))
+            .select((
+                taxa::id,
+                taxa::scientific_name,
+                taxa::canonical_name,
+                taxa::authorship,
+                taxa::rank,
+                taxa::status,
+                parent.field(taxa::canonical_name).nullable(),
+            ))
+            .load::<TaxonNode>(&mut conn)
+            .await?;
+
+        Ok(nodes)
+    }
+
     /// Summary statistics for a specific rank below the specified taxon
     pub async fn rank_summary(&self, taxon_id: &Uuid, rank: &TaxonomicRank) -> Result<RankSummary, Error> {
         use diesel::dsl::count_star;
@@ -0,0 +1,77 @@
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Text};
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use super::extensions::Paginate;
+use super::{Error, PageResult, PgPool, schema};
+use crate::database::models::{Treatment, TreatmentSection};
+
+
+#[derive(Clone)]
+pub struct TreatmentProvider {
+    pub pool: PgPool,
+}
+
+impl TreatmentProvider {
+    /// Get all treatments of a name. Treatments are linked by the canonical name
+    /// of the taxon in the nomenclature section of the treatment
+    pub async fn for_name(&self, name_id: &Uuid) -> Result<Vec<Treatment>, Error> {
+        use schema::{names, treatments};
+        let mut conn = self.pool.get().await?;
+
+        let records = treatments::table
+            .inner_join(names::table.on(treatments::canonical_name.eq(names::canonical_name.nullable())))
+            .filter(names::id.eq(name_id))
+            .select(Treatment::as_select())
+            .order_by(treatments::treatment_uri)
+            .load::<Treatment>(&mut conn)
+            .await?;
+
+        Ok(records)
+    }
+
+    /// Get the text sections of a treatment in the order they appear
+    pub async fn sections(&self, treatment_entity_id: &str) -> Result<Vec<TreatmentSection>, Error> {
+        use schema::treatment_sections;
+        let mut conn = self.pool.get().await?;
+
+        let records = treatment_sections::table
+            .filter(treatment_sections::treatment_entity_id.eq(treatment_entity_id))
+            .select(TreatmentSection::as_select())
+            .order_by(treatment_sections::position)
+            .load::<TreatmentSection>(&mut conn)
+            .await?;
+
+        Ok(records)
+    }
+
+    /// Find treatments with any section matching the query.
+    ///
+    /// The query supports the web search syntax of postgres, such as quoted phrases
+    /// and excluding words with a minus, and uses the english full text index on the sections.
+    pub async fn search(&self, query: &str, page: i64, page_size: i64) -> PageResult<Treatment> {
+        use schema::{treatment_sections, treatments};
+        let mut conn = self.pool.get().await?;
+
+        let matching = treatment_sections::table
+            .filter(
+                sql::<Bool>("to_tsvector('english', text) @@ websearch_to_tsquery('english', ")
+                    .bind::<Text, _>(query.to_string())
+                    .sql(")"),
+            )
+            .select(treatment_sections::treatment_entity_id);
+
+        let page = treatments::table
+            .filter(treatments::entity_id.eq_any(matching))
+            .select(Treatment::as_select())
+            .order_by((treatments::scientific_name, treatments::treatment_uri))
+            .paginate(page)
+            .per_page(page_size)
+            .load::<(Treatment, i64)>(&mut conn)
+            .await?;
+
+        Ok(page.into())
+    }
+}
@@ -8,9 +8,10 @@ mod taxa;
 
 use axum::routing::{get, post};
 use axum::{Json, Router};
-use axum_login::AuthManagerLayerBuilder;
 use axum_login::tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+use axum_login::{AuthManagerLayer, AuthManagerLayerBuilder};
 use tower_sessions::cookie::Key;
+use tower_sessions::service::SignedCookie;
 use tracing::instrument;
 
 use crate::database::models;
@@ -18,8 +19,11 @@ use crate::http::auth::{AuthSession, Credentials, DatabaseUserStore};
 use crate::http::{Context, Error};
 
 
-/// The REST gateway for the admin backend for basic CRUD operations
-pub(crate) fn router(context: Context) -> Router<Context> {
+/// The auth session layer.
+///
+/// This is applied to every route rather than just the admin backend so that the GraphQL
+/// resolvers can check whether the request was made by a logged in administrator.
+pub(crate) fn auth_layer(context: &Context) -> AuthManagerLayer<DatabaseUserStore, MemoryStore, SignedCookie> {
     // for cookie signing
     let key = Key::generate();
 
@@ -32,9 +36,14 @@ pub(crate) fn router(context: Context) -> Router<Context> {
         .with_signed(key);
     // .with_same_site(tower_sessions::cookie::SameSite::Lax);
 
-    let auth_backend = DatabaseUserStore::new(context.database.pool);
-    let auth_layer = AuthManagerLayerBuilder::new(auth_backend, session_layer).build();
+    let auth_backend = DatabaseUserStore::new(context.database.pool.clone());
+    AuthManagerLayerBuilder::new(auth_backend, session_layer).build()
+}
+
 
+/// The REST gateway for the admin backend for basic CRUD operations.
+/// Expects the auth layer to be applied by the root router
+pub(crate) fn router() -> Router<Context> {
     // unlike tower services the layers in the axum router is applied outside in like an onion.
     // so the layer and routes at the bottom will be applied before the ones at the top, which means
     // that protected routes have to be added ABOVE the login_required! route layer
@@ -45,7 +54,6 @@ pub(crate) fn router(context: Context) -> Router<Context> {
         .route("/me", get(logged_in_user))
         .route("/logout", get(logout_handler))
         .route("/login", post(login_handler))
-        .layer(auth_layer)
 }
 
 
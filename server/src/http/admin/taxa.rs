@@ -7,7 +7,7 @@ use diesel_async::RunQueryDsl;
 use serde::{Deserialize, Serialize};
 
 use crate::database::models::{TaxonomicRank, TaxonomicStatus};
-use crate::database::sources::ALA_DATASET_ID;
+use crate::database::backbones::Backbone;
 use crate::database::{Database, schema};
 use crate::http::Context;
 use crate::http::error::InternalError;
@@ -26,11 +26,24 @@ struct TaxonName {
 pub struct ParentTaxon {
     /// The scientific name of the parent taxon to retrieve descendants of
     parent: String,
+    /// The global id of the taxonomic backbone. Defaults to the default backbone
+    backbone: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BackboneParams {
+    /// The global id of the taxonomic backbone. Defaults to the default backbone
+    backbone: Option<String>,
 }
 
 
-async fn classes(State(database): State<Database>) -> Result<Json<Vec<TaxonName>>, InternalError> {
+async fn classes(
+    Query(params): Query<BackboneParams>,
+    State(database): State<Database>,
+) -> Result<Json<Vec<TaxonName>>, InternalError> {
     use schema::{datasets, taxa};
+
+    let backbone = database.backbones.resolve(params.backbone.as_deref(), None).await?;
     let mut conn = database.pool.get().await?;
 
     let records = taxa::table
@@ -38,7 +51,7 @@ async fn classes(State(database): State<Database>) -> Result<Json<Vec<TaxonName>
         .select(TaxonName::as_select())
         .filter(taxa::rank.eq(TaxonomicRank::Class))
         .filter(taxa::status.eq(TaxonomicStatus::Accepted))
-        .filter(datasets::global_id.eq(ALA_DATASET_ID))
+        .filter(datasets::id.eq(backbone.dataset_id))
         .order_by(taxa::scientific_name)
         .load::<TaxonName>(&mut conn)
         .await?;
@@ -50,7 +63,8 @@ async fn families(
     Query(params): Query<ParentTaxon>,
     State(database): State<Database>,
 ) -> Result<Json<Vec<TaxonName>>, InternalError> {
-    let records = get_descendants(&database, params.parent, TaxonomicRank::Family).await?;
+    let backbone = database.backbones.resolve(params.backbone.as_deref(), None).await?;
+    let records = get_descendants(&database, params.parent, TaxonomicRank::Family, &backbone).await?;
     Ok(Json(records))
 }
 
@@ -58,7 +72,8 @@ async fn genera(
     Query(params): Query<ParentTaxon>,
     State(database): State<Database>,
 ) -> Result<Json<Vec<TaxonName>>, InternalError> {
-    let records = get_descendants(&database, params.parent, TaxonomicRank::Genus).await?;
+    let backbone = database.backbones.resolve(params.backbone.as_deref(), None).await?;
+    let records = get_descendants(&database, params.parent, TaxonomicRank::Genus, &backbone).await?;
     Ok(Json(records))
 }
 
@@ -67,7 +82,8 @@ async fn species(
     Query(params): Query<ParentTaxon>,
     State(database): State<Database>,
 ) -> Result<Json<Vec<TaxonName>>, InternalError> {
-    let records = get_descendants(&database, params.parent, TaxonomicRank::Species).await?;
+    let backbone = database.backbones.resolve(params.backbone.as_deref(), None).await?;
+    let records = get_descendants(&database, params.parent, TaxonomicRank::Species, &backbone).await?;
     Ok(Json(records))
 }
 
@@ -85,6 +101,7 @@ async fn get_descendants(
     database: &Database,
     parent: String,
     rank: TaxonomicRank,
+    backbone: &Backbone,
 ) -> Result<Vec<TaxonName>, InternalError> {
     use schema::{datasets, taxa};
     use schema_gnl::taxa_dag;
@@ -102,7 +119,7 @@ async fn get_descendants(
         .filter(root.field(taxa::scientific_name).eq(parent))
         .filter(taxa::rank.eq(rank))
         .filter(taxa::status.eq(TaxonomicStatus::Accepted))
-        .filter(datasets::global_id.eq(ALA_DATASET_ID))
+        .filter(datasets::id.eq(backbone.dataset_id))
         .order_by(taxa::scientific_name)
         .load::<TaxonName>(&mut conn)
         .await?;
@@ -1,4 +1,7 @@
 use async_trait::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header;
+use axum::http::request::Parts;
 use axum_login::{AuthUser, AuthnBackend, UserId};
 use diesel::prelude::*;
 use diesel_async::pooled_connection::bb8::Pool;
@@ -8,6 +11,7 @@ use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::database::{models, schema};
+use crate::http::Context;
 
 
 pub type AuthSession = axum_login::AuthSession<DatabaseUserStore>;
@@ -27,6 +31,67 @@ pub enum Role {
 }
 
 
+/// The user making a request.
+///
+/// The GraphQL resolvers don't have access to the auth session so this is added
+/// to the request data instead, allowing restricted fields to check the user role.
+#[derive(Debug, Clone, Default)]
+pub struct Viewer {
+    pub role: Option<Role>,
+}
+
+impl Viewer {
+    pub fn is_admin(&self) -> bool {
+        self.role == Some(Role::Admin)
+    }
+}
+
+impl From<&AuthSession> for Viewer {
+    fn from(session: &AuthSession) -> Self {
+        Viewer {
+            role: session.user.as_ref().map(|user| user.user_role.clone()),
+        }
+    }
+}
+
+
+/// A partner authenticated with a replication access token.
+///
+/// Mirrors consume the replication feed non-interactively so they can't use the cookie
+/// based admin session. Instead they send one of the configured tokens as a bearer token
+/// in the `Authorization` header.
+#[derive(Debug, Clone)]
+pub struct ReplicationConsumer;
+
+impl FromRequestParts<Context> for ReplicationConsumer {
+    type Rejection = super::Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Context) -> Result<Self, Self::Rejection> {
+        let unauthorized = || super::Error::Unauthorized("replication".to_string());
+
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(unauthorized)?;
+
+        let tokens = &state.config.replication_tokens;
+        match tokens.iter().any(|known| tokens_match(known.expose_secret(), token)) {
+            true => Ok(ReplicationConsumer),
+            false => Err(unauthorized()),
+        }
+    }
+}
+
+/// Compare two tokens in constant time so that the response time doesn't leak how
+/// much of a token was correct
+fn tokens_match(known: &str, token: &str) -> bool {
+    let (known, token) = (known.as_bytes(), token.as_bytes());
+    known.len() == token.len() && known.iter().zip(token).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+
 #[derive(Deserialize)]
 pub struct Credentials {
     pub email: String,
@@ -166,3 +231,21 @@ impl From<axum_login::Error<DatabaseUserStore>> for super::Error {
         Self::Authentication
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_identical_tokens() {
+        assert!(tokens_match("4f1c2a9e", "4f1c2a9e"));
+    }
+
+    #[test]
+    fn rejects_different_tokens() {
+        assert!(!tokens_match("4f1c2a9e", "4f1c2a9f"));
+        assert!(!tokens_match("4f1c2a9e", "4f1c2a9"));
+        assert!(!tokens_match("4f1c2a9e", ""));
+    }
+}
@@ -7,6 +7,9 @@ pub enum Error {
     #[error("missing the '{0}' parameter in the request")]
     MissingParam(String),
 
+    #[error("invalid value for the '{0}' parameter: {1}")]
+    InvalidParam(String, String),
+
     #[error("the resource '{0}' could not found")]
     NotFound(String),
 
@@ -19,6 +22,12 @@ pub enum Error {
     #[error("an authentication error occurred")]
     Authentication,
 
+    #[error("the resource '{0}' is only available to administrators")]
+    Forbidden(String),
+
+    #[error("the resource '{0}' requires a valid access token")]
+    Unauthorized(String),
+
     #[error(transparent)]
     Internal(#[from] anyhow::Error),
 
@@ -56,6 +65,9 @@ impl From<crate::database::Error> for Error {
         // log the resource attempting to load
         match err {
             crate::database::Error::NotFound(resource) => Error::NotFound(resource),
+            crate::database::Error::UnsupportedFilter(filter) => {
+                Error::InvalidParam("filters".to_string(), format!("the filter '{filter}' is not supported here"))
+            }
             err => Error::Database(err),
         }
     }
@@ -66,8 +78,11 @@ impl Error {
     fn status_code(&self) -> StatusCode {
         match self {
             Error::MissingParam(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidParam(_, _) => StatusCode::BAD_REQUEST,
             Error::GraphQLRequest(_) => StatusCode::BAD_REQUEST,
             Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::Forbidden(_) => StatusCode::FORBIDDEN,
+            Error::Unauthorized(_) => StatusCode::UNAUTHORIZED,
             Error::InvalidData(_, _, _) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::GraphQL(_) => StatusCode::INTERNAL_SERVER_ERROR,
 
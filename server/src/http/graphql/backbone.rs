@@ -0,0 +1,29 @@
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::database::backbones;
+
+
+/// A taxonomy dataset that can be used to classify names.
+///
+/// Queries that aggregate up a classification accept the global id of a backbone
+/// and otherwise use the default backbone of the source or the overall default.
+#[derive(Clone, Debug, SimpleObject, Serialize, Deserialize)]
+pub struct TaxonomicBackbone {
+    pub dataset_id: Uuid,
+    pub global_id: String,
+    pub name: String,
+    pub is_default: bool,
+}
+
+impl From<backbones::Backbone> for TaxonomicBackbone {
+    fn from(value: backbones::Backbone) -> Self {
+        Self {
+            dataset_id: value.dataset_id,
+            global_id: value.global_id,
+            name: value.name,
+            is_default: value.is_default,
+        }
+    }
+}
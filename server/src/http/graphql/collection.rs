@@ -2,6 +2,7 @@ use async_graphql::*;
 
 use super::common::{CollectionDetails, Publication};
 use crate::database::{Database, models};
+use crate::http::auth::Viewer;
 use crate::http::{Context as State, Error};
 
 
@@ -19,10 +20,8 @@ impl Collection {
             CollectionBy::Id(id) => db.collections.find_by_id(&id).await?,
         };
 
-        match collection {
-            None => Ok(None),
-            Some(collection) => Ok(Some(Self::from_record(collection))),
-        }
+        let collections = db.sensitive.generalise(collection.into_iter().collect()).await?;
+        Ok(collections.into_iter().next().map(Self::from_record))
     }
 
     pub fn from_record(collection: models::CollectionEvent) -> Collection {
@@ -43,4 +42,26 @@ impl CollectionQuery {
         let state = ctx.data::<State>()?;
         Ok(None)
     }
+
+    /// The precise coordinates of a generalised collection. Only available to administrators
+    async fn precise_location(&self, ctx: &Context<'_>) -> Result<Option<PreciseLocation>, Error> {
+        let viewer = ctx.data_opt::<Viewer>();
+        if !viewer.is_some_and(|viewer| viewer.is_admin()) {
+            return Err(Error::Forbidden(String::from("preciseLocation")));
+        }
+
+        let state = ctx.data::<State>()?;
+        let location = state.database.sensitive.precise_location(&self.collection.entity_id).await?;
+        Ok(location.map(|location| PreciseLocation {
+            latitude: location.latitude,
+            longitude: location.longitude,
+        }))
+    }
+}
+
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct PreciseLocation {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
 }
@@ -42,8 +42,11 @@ pub use specimens::{
 pub use subsamples::SubsampleDetails;
 pub use taxonomy::{NameDetails, Taxonomy};
 
+use self::operation_logs::EntityOperation;
 use super::markers::SpeciesMarker;
 use super::species::{GenomicComponent, SpecimenOptions, SpecimenSummary, WholeGenome};
+use super::taxon::TaxonTreeItem;
+use super::treatment::Treatment;
 
 
 #[derive(SimpleObject)]
@@ -52,6 +55,9 @@ use super::species::{GenomicComponent, SpecimenOptions, SpecimenSummary, WholeGe
 #[graphql(concrete(name = "SpeciesMarkerPage", params(SpeciesMarker)))]
 #[graphql(concrete(name = "GenomicComponentPage", params(GenomicComponent)))]
 #[graphql(concrete(name = "AssemblyPage", params(AssemblyDetails)))]
+#[graphql(concrete(name = "EntityOperationPage", params(EntityOperation)))]
+#[graphql(concrete(name = "TreatmentPage", params(Treatment)))]
+#[graphql(concrete(name = "TaxonTreeItemPage", params(TaxonTreeItem)))]
 pub struct Page<T: OutputType> {
     pub records: Vec<T>,
     pub total: i64,
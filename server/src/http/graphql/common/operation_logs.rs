@@ -1,11 +1,14 @@
-use arga_core::crdt::hlc::HybridTimestamp;
+use arga_core::crdt::hlc::{HybridTimestamp, MAX_LOGICAL};
+use arga_core::models::logs::OperationLogTable;
+use arga_core::models::LocationGeneralisation;
 use async_graphql::*;
-use bigdecimal::ToPrimitive;
+use bigdecimal::{BigDecimal, ToPrimitive};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use super::datasets::{DatasetDetails, DatasetVersion};
 use super::taxonomy::{NomenclaturalActType, TaxonomicRank, TaxonomicStatus};
+use crate::database::provenance::{self, DatasetOperation};
 use crate::database::{models, Database};
 use crate::http::Error;
 
@@ -241,25 +244,17 @@ impl TryFrom<models::NomenclaturalActOperation> for NomenclaturalActOperation {
     type Error = Error;
 
     fn try_from(value: models::NomenclaturalActOperation) -> Result<Self, Self::Error> {
-        let ts = HybridTimestamp::new(value.operation_id.to_u64().unwrap());
+        let (operation_id, parent_id) = operation_ids(&value.operation_id, &value.parent_id)?;
 
         Ok(Self {
-            operation_id: value.operation_id.to_u64().ok_or(Error::InvalidData(
-                "operation_id".to_string(),
-                "Operation".to_string(),
-                value.operation_id.to_string(),
-            ))?,
-            parent_id: value.parent_id.to_u64().ok_or(Error::InvalidData(
-                "parent_id".to_string(),
-                "Operation".to_string(),
-                value.operation_id.to_string(),
-            ))?,
+            operation_id,
+            parent_id,
             entity_id: value.entity_id,
             action: value.action.into(),
             atom: value.atom.into(),
             dataset: DatasetDetails::default(),
             dataset_version: DatasetVersion::default(),
-            logged_at: ts.into(),
+            logged_at: logged_at(operation_id)?,
         })
     }
 }
@@ -323,25 +318,17 @@ impl TryFrom<models::logs::SpecimenOperation> for SpecimenOperation {
     type Error = Error;
 
     fn try_from(value: models::logs::SpecimenOperation) -> Result<Self, Self::Error> {
-        let ts = HybridTimestamp::new(value.operation_id.to_u64().unwrap());
+        let (operation_id, parent_id) = operation_ids(&value.operation_id, &value.parent_id)?;
 
         Ok(Self {
-            operation_id: value.operation_id.to_u64().ok_or(Error::InvalidData(
-                "operation_id".to_string(),
-                "Operation".to_string(),
-                value.operation_id.to_string(),
-            ))?,
-            parent_id: value.parent_id.to_u64().ok_or(Error::InvalidData(
-                "parent_id".to_string(),
-                "Operation".to_string(),
-                value.operation_id.to_string(),
-            ))?,
+            operation_id,
+            parent_id,
             entity_id: value.entity_id.to_string(),
             action: value.action.into(),
             atom: value.atom.into(),
             dataset: DatasetDetails::default(),
             dataset_version: DatasetVersion::default(),
-            logged_at: ts.into(),
+            logged_at: logged_at(operation_id)?,
         })
     }
 }
@@ -404,25 +391,17 @@ impl TryFrom<models::TaxonOperation> for TaxonOperation {
     type Error = Error;
 
     fn try_from(value: models::TaxonOperation) -> Result<Self, Self::Error> {
-        let ts = HybridTimestamp::new(value.operation_id.to_u64().unwrap());
+        let (operation_id, parent_id) = operation_ids(&value.operation_id, &value.parent_id)?;
 
         Ok(Self {
-            operation_id: value.operation_id.to_u64().ok_or(Error::InvalidData(
-                "operation_id".to_string(),
-                "Operation".to_string(),
-                value.operation_id.to_string(),
-            ))?,
-            parent_id: value.parent_id.to_u64().ok_or(Error::InvalidData(
-                "parent_id".to_string(),
-                "Operation".to_string(),
-                value.operation_id.to_string(),
-            ))?,
+            operation_id,
+            parent_id,
             entity_id: value.entity_id,
             action: value.action.into(),
             atom: value.atom.into(),
             dataset: DatasetDetails::default(),
             dataset_version: DatasetVersion::default(),
-            logged_at: ts.into(),
+            logged_at: logged_at(operation_id)?,
         })
     }
 }
@@ -458,3 +437,149 @@ impl From<models::TaxonAtom> for TaxonAtom {
         }
     }
 }
+
+
+/// Limit the operations in an entity history
+#[derive(InputObject, Debug, Default)]
+pub struct OperationFilter {
+    /// Only include operations imported from the dataset with this id
+    pub dataset_id: Option<uuid::Uuid>,
+    /// Only include operations logged at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Only include operations logged at or before this time
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl From<OperationFilter> for provenance::OperationFilter {
+    fn from(value: OperationFilter) -> Self {
+        // operation ids are hybrid timestamps so a time range can be filtered
+        // by converting the bounds into the lowest and highest ids for that time
+        let from = value.from.map(|from| BigDecimal::from(HybridTimestamp::from(from)));
+        let until = value.to.map(|to| {
+            let timestamp = HybridTimestamp::from(to);
+            BigDecimal::from(HybridTimestamp::with_logical(timestamp.as_u64(), MAX_LOGICAL))
+        });
+
+        provenance::OperationFilter {
+            dataset_id: value.dataset_id,
+            from,
+            until,
+        }
+    }
+}
+
+
+/// An atom from any operation log.
+///
+/// The type is the atom variant in the same screaming snake case used by the
+/// typed atom enums and the value is the JSON representation of the atom data.
+#[derive(SimpleObject)]
+pub struct EntityAtom {
+    pub r#type: String,
+    pub value: Json<serde_json::Value>,
+}
+
+impl EntityAtom {
+    pub fn new<A: Serialize>(atom: &A) -> Result<EntityAtom, Error> {
+        let value = serde_json::to_value(atom).map_err(|err| Error::Internal(err.into()))?;
+
+        // atoms are externally tagged enums so unit variants serialize as a string and
+        // every other variant as an object with a single entry keyed by the variant name
+        let (variant, value) = match value {
+            serde_json::Value::String(variant) => (variant, serde_json::Value::Null),
+            serde_json::Value::Object(object) => match object.into_iter().next() {
+                Some((variant, value)) => (variant, value),
+                None => ("Empty".to_string(), serde_json::Value::Null),
+            },
+            value => {
+                return Err(Error::InvalidData(
+                    "atom".to_string(),
+                    "Operation".to_string(),
+                    value.to_string(),
+                ));
+            }
+        };
+
+        Ok(EntityAtom {
+            r#type: to_screaming_snake_case(&variant),
+            value: Json(value),
+        })
+    }
+
+    /// Generalise the value of a latitude or longitude atom with a sensitive data rule
+    pub fn generalise(&mut self, rule: LocationGeneralisation) {
+        if self.r#type != "LATITUDE" && self.r#type != "LONGITUDE" {
+            return;
+        }
+
+        let value = self.value.0.as_f64().and_then(|value| rule.coordinate(value));
+        self.value = Json(value.map(serde_json::Value::from).unwrap_or_default());
+    }
+}
+
+
+/// An operation from any log table.
+///
+/// Entities that don't have a dedicated operation type with typed atoms use this
+/// to expose their history, which is every entity other than taxa, specimens and nomenclatural acts.
+#[derive(SimpleObject)]
+pub struct EntityOperation {
+    pub operation_id: u64,
+    pub parent_id: u64,
+    pub entity_id: String,
+    pub dataset_version: DatasetVersion,
+    pub dataset: DatasetDetails,
+    pub action: Action,
+    pub atom: EntityAtom,
+    pub logged_at: DateTime<Utc>,
+}
+
+impl<T: OperationLogTable> TryFrom<DatasetOperation<T>> for EntityOperation {
+    type Error = Error;
+
+    fn try_from((value, version, dataset): DatasetOperation<T>) -> Result<Self, Self::Error> {
+        let (operation_id, parent_id) = operation_ids(value.id(), value.parent_id())?;
+
+        Ok(Self {
+            operation_id,
+            parent_id,
+            entity_id: value.entity_id().clone(),
+            dataset_version: version.into(),
+            dataset: dataset.into(),
+            action: value.action().clone().into(),
+            atom: EntityAtom::new(value.atom())?,
+            logged_at: logged_at(operation_id)?,
+        })
+    }
+}
+
+
+/// Convert the operation and parent ids of a log into the integers exposed in the schema
+fn operation_ids(operation_id: &BigDecimal, parent_id: &BigDecimal) -> Result<(u64, u64), Error> {
+    let invalid = |field: &str, value: &BigDecimal| {
+        Error::InvalidData(field.to_string(), "Operation".to_string(), value.to_string())
+    };
+
+    let operation_id = operation_id.to_u64().ok_or_else(|| invalid("operation_id", operation_id))?;
+    let parent_id = parent_id.to_u64().ok_or_else(|| invalid("parent_id", parent_id))?;
+    Ok((operation_id, parent_id))
+}
+
+/// The time an operation was logged, decoded from the hybrid timestamp of its id
+fn logged_at(operation_id: u64) -> Result<DateTime<Utc>, Error> {
+    DateTime::<Utc>::try_from(HybridTimestamp::new(operation_id))
+        .map_err(|_| Error::InvalidData("operation_id".to_string(), "Operation".to_string(), operation_id.to_string()))
+}
+
+fn to_screaming_snake_case(value: &str) -> String {
+    let mut snake = String::with_capacity(value.len() + 4);
+
+    for (idx, ch) in value.char_indices() {
+        if ch.is_uppercase() && idx > 0 {
+            snake.push('_');
+        }
+        snake.extend(ch.to_uppercase());
+    }
+
+    snake
+}
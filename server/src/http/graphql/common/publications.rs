@@ -28,6 +28,10 @@ pub struct Publication {
     pub source_urls: Option<Vec<String>>,
     pub publication_type: Option<PublicationType>,
     pub citation: Option<String>,
+    pub journal: Option<String>,
+    pub volume: Option<String>,
+    pub issue: Option<String>,
+    pub pages: Option<String>,
 }
 
 impl From<models::Publication> for Publication {
@@ -44,6 +48,10 @@ impl From<models::Publication> for Publication {
             source_urls: value.source_urls.map(|i| i.into_iter().filter_map(|v| v).collect()),
             publication_type: value.publication_type.map(|t| t.into()),
             citation: value.citation,
+            journal: value.journal,
+            volume: value.volume,
+            issue: value.issue,
+            pages: value.pages,
         }
     }
 }
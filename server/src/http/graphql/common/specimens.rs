@@ -1,9 +1,20 @@
 use async_graphql::*;
 use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
 
 use crate::database::models;
 
 
+/// How the coordinates of a sensitive collection have been generalised
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Enum, Serialize, Deserialize)]
+#[graphql(remote = "models::LocationGeneralisation")]
+pub enum LocationGeneralisation {
+    TenthDegree,
+    OneDegree,
+    Withhold,
+}
+
+
 #[derive(Clone, Debug, SimpleObject)]
 pub struct OrganismDetails {
     pub entity_id: String,
@@ -113,6 +124,7 @@ pub struct CollectionDetails {
     pub strain: Option<String>,
     pub isolate: Option<String>,
     pub field_notes: Option<String>,
+    pub location_generalisation: Option<LocationGeneralisation>,
 }
 
 impl From<models::CollectionEvent> for CollectionDetails {
@@ -154,6 +166,7 @@ impl From<models::CollectionEvent> for CollectionDetails {
             strain: value.strain,
             isolate: value.isolate,
             field_notes: value.field_notes,
+            location_generalisation: value.location_generalisation.map(|g| g.into()),
         }
     }
 }
@@ -202,6 +215,7 @@ pub struct CollectionEvent {
     pub strain: Option<String>,
     pub isolate: Option<String>,
     pub field_notes: Option<String>,
+    pub location_generalisation: Option<LocationGeneralisation>,
 }
 
 impl From<models::CollectionEvent> for CollectionEvent {
@@ -244,6 +258,7 @@ impl From<models::CollectionEvent> for CollectionEvent {
             strain: value.strain,
             isolate: value.isolate,
             field_notes: value.field_notes,
+            location_generalisation: value.location_generalisation.map(|g| g.into()),
         }
     }
 }
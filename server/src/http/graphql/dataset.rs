@@ -37,11 +37,21 @@ pub struct DatasetQuery {
 #[Object]
 impl DatasetQuery {
     #[instrument(skip(self, ctx))]
-    async fn species(&self, ctx: &Context<'_>, page: i64) -> Result<Page<SpeciesCard>, Error> {
+    async fn species(
+        &self,
+        ctx: &Context<'_>,
+        page: i64,
+        backbone: Option<String>,
+    ) -> Result<Page<SpeciesCard>, Error> {
         let state = ctx.data::<State>()?;
         let helper = SpeciesHelper::new(&state.database);
 
-        let page = state.database.datasets.species(&self.dataset, page).await?;
+        let backbone = state
+            .database
+            .backbones
+            .resolve(backbone.as_deref(), Some(&self.dataset.source_id))
+            .await?;
+        let page = state.database.datasets.species(&self.dataset, page, &backbone).await?;
         let cards = helper.cards(page.records).await?;
 
         Ok(Page {
@@ -3,6 +3,7 @@ use tracing::instrument;
 use uuid::Uuid;
 
 use crate::database::models;
+use crate::database::sensitive::generalise;
 use crate::http::{Context as State, Error};
 
 
@@ -48,7 +49,19 @@ impl Markers {
     async fn species(&self, ctx: &Context<'_>, canonical_name: String) -> Result<Vec<SpeciesMarker>, Error> {
         let state = ctx.data::<State>()?;
         let markers = state.database.markers.species(&canonical_name).await?;
-        let markers = markers.into_iter().map(|m| m.into()).collect();
+
+        let name_ids: Vec<Uuid> = markers.iter().map(|marker| marker.name_id).collect();
+        let generalisations = state.database.sensitive.generalisations(&name_ids).await?;
+
+        let markers = markers
+            .into_iter()
+            .map(|marker| {
+                let rule = generalisations.get(&marker.name_id, &[marker.dataset_id]);
+                let mut marker = SpeciesMarker::from(marker);
+                generalise(rule, &mut marker.latitude, &mut marker.longitude);
+                marker
+            })
+            .collect();
         Ok(markers)
     }
 }
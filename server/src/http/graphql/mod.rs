@@ -3,6 +3,7 @@ pub mod helpers;
 
 pub mod annotation;
 pub mod assembly;
+pub mod backbone;
 pub mod collection;
 pub mod data_product;
 pub mod dataset;
@@ -28,6 +29,7 @@ pub mod subsample;
 pub mod taxa;
 pub mod taxon;
 pub mod tissue;
+pub mod treatment;
 
 use assembly::Assembly;
 use async_graphql::extensions::Tracing;
@@ -38,6 +40,7 @@ use axum::response::IntoResponse;
 use axum::routing::get;
 use axum::{Extension, Router};
 
+use self::backbone::TaxonomicBackbone;
 use self::common::FilterItem;
 use self::dataset::Dataset;
 use self::dna_extract::DnaExtract;
@@ -45,6 +48,7 @@ use self::extensions::ErrorLogging;
 use self::maps::Maps;
 use self::marker::Marker;
 use self::markers::Markers;
+use self::names::Names;
 use self::organism::Organism;
 use self::overview::Overview;
 use self::provenance::Provenance;
@@ -58,6 +62,7 @@ use self::subsample::Subsample;
 use self::taxa::Taxa;
 use self::taxon::Taxon;
 use super::error::Error;
+use crate::http::auth::{AuthSession, Viewer};
 use crate::http::Context as State;
 
 pub type ArgaSchema = Schema<Query, EmptyMutation, EmptySubscription>;
@@ -77,13 +82,30 @@ impl Query {
         Search {}
     }
 
-    async fn species(&self, ctx: &Context<'_>, canonical_name: String) -> Result<Species, Error> {
+    async fn species(
+        &self,
+        ctx: &Context<'_>,
+        canonical_name: String,
+        backbone: Option<String>,
+    ) -> Result<Species, Error> {
+        let state = ctx.data::<State>()?;
+        let backbone = state.database.backbones.resolve(backbone.as_deref(), None).await?;
+        Species::new(&state.database, canonical_name, &backbone).await
+    }
+
+    async fn stats(&self, backbone: Option<String>) -> Statistics {
+        Statistics { backbone }
+    }
+
+    /// The taxonomic backbones that can be used to classify names
+    async fn backbones(&self, ctx: &Context<'_>) -> Result<Vec<TaxonomicBackbone>, Error> {
         let state = ctx.data::<State>()?;
-        Species::new(&state.database, canonical_name).await
+        let backbones = state.database.backbones.all().await?;
+        Ok(backbones.into_iter().map(|backbone| backbone.into()).collect())
     }
 
-    async fn stats(&self) -> Statistics {
-        Statistics {}
+    async fn names(&self) -> Names {
+        Names {}
     }
 
     async fn maps(&self, tolerance: Option<f32>) -> Maps {
@@ -100,9 +122,10 @@ impl Query {
         ctx: &Context<'_>,
         by: source::SourceBy,
         filters: Option<Vec<FilterItem>>,
+        backbone: Option<String>,
     ) -> Result<Source, Error> {
         let state = ctx.data::<State>()?;
-        Source::new(&state.database, &by, filters.unwrap_or_default()).await
+        Source::new(&state.database, &by, filters.unwrap_or_default(), backbone).await
     }
 
     async fn dataset(&self, ctx: &Context<'_>, by: dataset::DatasetBy) -> Result<Dataset, Error> {
@@ -182,8 +205,10 @@ pub(crate) fn schema(state: State) -> ArgaSchema {
 }
 
 /// Handles graphql requests.
-async fn graphql_handler(schema: Extension<ArgaSchema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+/// The logged in user is passed along so that resolvers can restrict sensitive fields
+async fn graphql_handler(schema: Extension<ArgaSchema>, session: AuthSession, req: GraphQLRequest) -> GraphQLResponse {
+    let viewer = Viewer::from(&session);
+    schema.execute(req.into_inner().data(viewer)).await.into()
 }
 
 /// Adds the built-in graphql IDE when visiting with a browser.
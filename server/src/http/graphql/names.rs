@@ -1,8 +1,14 @@
 use arga_core::models;
+use arga_core::names::matching::{self, MatchType, NameMatch};
 use async_graphql::*;
+use chrono::{Datelike, NaiveDate};
 use uuid::Uuid;
 
-use super::common::taxonomy::{NameDetails, TaxonDetails};
+use super::common::Publication;
+use super::common::taxonomy::{NameDetails, NomenclaturalActType, TaxonDetails};
+use super::treatment::Treatment;
+use crate::database::names::SourcedTaxonomicAct;
+use crate::database::taxa;
 use crate::http::{Context as State, Error};
 
 
@@ -29,4 +35,249 @@ impl NameQuery {
         let taxa = taxa.into_iter().map(|t| t.into()).collect();
         Ok(taxa)
     }
+
+    /// The treatments of the name in the literature, linking to the original descriptions
+    async fn treatments(&self, ctx: &Context<'_>) -> Result<Vec<Treatment>, Error> {
+        let state = ctx.data::<State>()?;
+        let treatments = state.database.treatments.for_name(&self.name_id).await?;
+        Ok(treatments.into_iter().map(Treatment::new).collect())
+    }
+
+    /// The history of the name in chronological order.
+    ///
+    /// Merges the nomenclatural acts, how each taxonomic source treats the name, the treatments
+    /// of the name and its type designations. Events without a date are at the end.
+    async fn timeline(&self, ctx: &Context<'_>) -> Result<Vec<TimelineEvent>, Error> {
+        let state = ctx.data::<State>()?;
+        let history = state.database.names.history(&self.name_id).await?;
+
+        let mut events: Vec<TimelineEvent> = Vec::new();
+        events.extend(history.nomenclatural_acts.into_iter().map(TimelineEvent::from));
+        events.extend(history.taxonomic_acts.into_iter().map(TimelineEvent::from));
+        events.extend(history.treatments.into_iter().map(TimelineEvent::from));
+        events.extend(history.type_specimens.into_iter().map(TimelineEvent::from));
+
+        events.sort_by_key(|event| {
+            (
+                event.year.is_none(),
+                event.year,
+                event.date.is_none(),
+                event.date,
+                event.event_type,
+            )
+        });
+        Ok(events)
+    }
+}
+
+
+#[derive(Enum, Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord)]
+pub enum TimelineEventType {
+    /// A nomenclatural act published on the name or one it was based on
+    NomenclaturalAct,
+    /// A taxonomic source accepting the name or treating it as a synonym
+    TaxonomicAct,
+    /// A treatment of the name in the literature
+    Treatment,
+    /// A type specimen of the name
+    TypeDesignation,
+}
+
+/// An event in the history of a name
+#[derive(SimpleObject)]
+pub struct TimelineEvent {
+    pub event_type: TimelineEventType,
+    pub year: Option<i32>,
+    pub date: Option<NaiveDate>,
+    /// The name or taxon the event happened to
+    pub scientific_name: String,
+    /// The name acted on for nomenclatural acts, or the accepted name for synonyms
+    pub related_name: Option<String>,
+    pub act: Option<NomenclaturalActType>,
+    pub type_status: Option<String>,
+    /// The dataset or institution the event was recorded by
+    pub source: Option<String>,
+    pub source_url: Option<String>,
+    pub citation: Option<String>,
+    pub publication: Option<Publication>,
+}
+
+impl TimelineEvent {
+    fn new(event_type: TimelineEventType, scientific_name: String) -> TimelineEvent {
+        TimelineEvent {
+            event_type,
+            year: None,
+            date: None,
+            scientific_name,
+            related_name: None,
+            act: None,
+            type_status: None,
+            source: None,
+            source_url: None,
+            citation: None,
+            publication: None,
+        }
+    }
+}
+
+impl From<taxa::NomenclaturalAct> for TimelineEvent {
+    fn from(value: taxa::NomenclaturalAct) -> Self {
+        let mut event = TimelineEvent::new(TimelineEventType::NomenclaturalAct, value.name.scientific_name);
+        event.year = value.publication.published_year;
+        event.date = value.publication.published_date.map(|date| date.date_naive());
+        event.act = Some(value.act.into());
+        event.source_url = Some(value.source_url);
+        event.citation = value.publication.citation.clone();
+        event.publication = Some(value.publication.into());
+
+        if value.acted_on.id != value.name.id {
+            event.related_name = Some(value.acted_on.scientific_name);
+        }
+        event
+    }
+}
+
+impl From<(SourcedTaxonomicAct, Option<models::Publication>)> for TimelineEvent {
+    fn from((value, publication): (SourcedTaxonomicAct, Option<models::Publication>)) -> Self {
+        let mut event = TimelineEvent::new(TimelineEventType::TaxonomicAct, value.taxon.scientific_name);
+        event.source = Some(value.dataset_name);
+        event.source_url = value.source_url;
+        event.citation = value.taxon.citation;
+        event.related_name = value
+            .accepted_taxon
+            .filter(|accepted| accepted.id != value.taxon.id)
+            .map(|accepted| accepted.scientific_name);
+
+        // the data created date is when the source recorded the act rather than when it
+        // was made, so the act is only dated when we know the publication it was made in
+        if let Some(publication) = publication {
+            event.year = publication.published_year;
+            event.date = publication.published_date.map(|date| date.date_naive());
+            event.publication = Some(publication.into());
+        }
+        event
+    }
+}
+
+impl From<(models::Treatment, Option<models::Publication>)> for TimelineEvent {
+    fn from((treatment, publication): (models::Treatment, Option<models::Publication>)) -> Self {
+        let name = treatment.scientific_name.or(treatment.canonical_name).unwrap_or_default();
+        let mut event = TimelineEvent::new(TimelineEventType::Treatment, name);
+        event.source_url = Some(treatment.treatment_uri);
+
+        if let Some(publication) = publication {
+            event.year = publication.published_year;
+            event.date = publication.published_date.map(|date| date.date_naive());
+            event.citation = publication.citation.clone();
+            event.publication = Some(publication.into());
+        }
+        event
+    }
+}
+
+impl From<taxa::TypeSpecimen> for TimelineEvent {
+    fn from(value: taxa::TypeSpecimen) -> Self {
+        let mut event = TimelineEvent::new(TimelineEventType::TypeDesignation, value.name.scientific_name);
+
+        // the designation itself isn't recorded so we use when the type was accessioned or collected
+        let date = value.accession.event_date.or(value.collection.event_date);
+        event.year = date.map(|date| date.year());
+        event.date = date;
+        event.type_status = value.accession.type_status;
+        event.source = value.accession.institution_name.or(value.accession.institution_code);
+        event
+    }
+}
+
+
+/// The most names that can be matched in a single request
+const MAX_MATCH_BATCH: usize = 1000;
+
+
+/// Queries that work across many names
+pub struct Names;
+
+#[Object]
+impl Names {
+    /// Match a batch of names to the names ARGA knows about.
+    ///
+    /// Names are matched exactly, then by their canonical name, then by stemming the gender
+    /// endings of the epithets and finally by their spelling. Matches that are synonyms
+    /// include the accepted name they point to.
+    #[graphql(name = "match")]
+    async fn match_names(&self, ctx: &Context<'_>, names: Vec<String>) -> Result<Vec<NameMatchResult>, Error> {
+        if names.len() > MAX_MATCH_BATCH {
+            let message = format!("At most {MAX_MATCH_BATCH} names can be matched at once");
+            return Err(async_graphql::Error::new(message).into());
+        }
+
+        let state = ctx.data::<State>()?;
+        let results = state.database.names.match_names(&names).await?;
+
+        let results = results
+            .into_iter()
+            .map(|(query, matches)| NameMatchResult {
+                matched: matching::best_match(&matches).cloned().map(|name_match| name_match.into()),
+                candidates: matches.into_iter().map(|name_match| name_match.into()).collect(),
+                input: query.verbatim,
+            })
+            .collect();
+
+        Ok(results)
+    }
+}
+
+
+#[derive(Enum, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NameMatchType {
+    /// The scientific name is identical
+    Exact,
+    /// The name is the same without the authorship
+    Canonical,
+    /// The epithets only differ in their gender endings
+    Stemmed,
+    /// The name is spelt slightly differently
+    Fuzzy,
+}
+
+impl From<MatchType> for NameMatchType {
+    fn from(value: MatchType) -> Self {
+        match value {
+            MatchType::Exact => NameMatchType::Exact,
+            MatchType::Canonical => NameMatchType::Canonical,
+            MatchType::Stemmed => NameMatchType::Stemmed,
+            MatchType::Fuzzy => NameMatchType::Fuzzy,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct NameCandidate {
+    pub name: NameDetails,
+    pub match_type: NameMatchType,
+    /// How likely the candidate is the requested name, from 0 to 1
+    pub confidence: f64,
+    /// The accepted name when the candidate is a synonym
+    pub accepted: Option<NameDetails>,
+}
+
+impl From<NameMatch> for NameCandidate {
+    fn from(value: NameMatch) -> Self {
+        Self {
+            name: value.name.into(),
+            match_type: value.match_type.into(),
+            confidence: value.confidence,
+            accepted: value.accepted.map(|name| name.into()),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct NameMatchResult {
+    /// The name as it was requested
+    pub input: String,
+    /// The most likely candidate. Empty when nothing matched or the best candidates are equally likely
+    pub matched: Option<NameCandidate>,
+    /// Every candidate ordered by confidence
+    pub candidates: Vec<NameCandidate>,
 }
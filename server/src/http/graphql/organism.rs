@@ -47,6 +47,7 @@ impl OrganismQuery {
         let state = ctx.data::<State>()?;
         let entity_id = &self.organism.entity_id;
         let collections = state.database.organisms.collections(entity_id).await?;
+        let collections = state.database.sensitive.generalise(collections).await?;
         Ok(collections.into_iter().map(|r| Collection::from_record(r)).collect())
     }
 
@@ -1,29 +1,158 @@
+use arga_core::models::logs::OperationLogTable;
+use arga_core::models::LocationGeneralisation;
 use async_graphql::*;
 
-use super::common::operation_logs::{NomenclaturalActOperation, OperationBy, SpecimenOperation, TaxonOperation};
+use super::common::operation_logs::{
+    EntityOperation,
+    NomenclaturalActOperation,
+    OperationBy,
+    OperationFilter,
+    SpecimenOperation,
+    TaxonOperation,
+};
+use super::common::Page;
+use crate::database::provenance::DatasetOperation;
+use crate::database::{self, PageResult};
+use crate::http::auth::Viewer;
 use crate::http::{Context as State, Error};
 
 
 pub struct Provenance;
 
-#[Object]
-impl Provenance {
-    pub async fn specimen(&self, ctx: &Context<'_>, by: OperationBy) -> Result<Vec<SpecimenOperation>, Error> {
-        let state = ctx.data::<State>()?;
-        SpecimenOperation::new(&state.database, by).await
-    }
+/// Generate the provenance resolvers for the entities exposed as an `EntityOperation`.
+///
+/// Every one of these resolvers pages through the history of an entity with the
+/// provider method of the same name, so only the field and method names differ.
+macro_rules! provenance_resolvers {
+    ($($(#[$doc:meta])* $field:ident => $logs:ident),* $(,)?) => {
+        #[Object]
+        impl Provenance {
+            pub async fn specimen(&self, ctx: &Context<'_>, by: OperationBy) -> Result<Vec<SpecimenOperation>, Error> {
+                let state = ctx.data::<State>()?;
+                SpecimenOperation::new(&state.database, by).await
+            }
+
+            pub async fn taxon(&self, ctx: &Context<'_>, by: OperationBy) -> Result<Vec<TaxonOperation>, Error> {
+                let state = ctx.data::<State>()?;
+                TaxonOperation::new(&state.database, by).await
+            }
+
+            pub async fn nomenclatural_act(
+                &self,
+                ctx: &Context<'_>,
+                by: OperationBy,
+            ) -> Result<Vec<NomenclaturalActOperation>, Error> {
+                let state = ctx.data::<State>()?;
+                NomenclaturalActOperation::new(&state.database, by).await
+            }
+
+            /// The history of a collection event. The coordinates of sensitive collections are
+            /// generalised in the same way as the collection itself unless the viewer is an administrator
+            pub async fn collection_event(
+                &self,
+                ctx: &Context<'_>,
+                by: OperationBy,
+                filter: Option<OperationFilter>,
+                page: i64,
+                page_size: i64,
+            ) -> Result<Page<EntityOperation>, Error> {
+                let state = ctx.data::<State>()?;
+                let (entity_id, filter) = query_args(by, filter);
+                let page = state.database.provenance.collection_event_logs(&entity_id, &filter, page, page_size).await;
+                let mut page = into_page(page)?;
+
+                if !is_admin(ctx) {
+                    let rule = state.database.sensitive.collection_event_generalisation(&entity_id).await?;
+                    generalise(&mut page, rule);
+                }
+                Ok(page)
+            }
+
+            /// The history of an organism. Organisms carry the coordinates of their collection so
+            /// they are generalised like the collection unless the viewer is an administrator
+            pub async fn organism(
+                &self,
+                ctx: &Context<'_>,
+                by: OperationBy,
+                filter: Option<OperationFilter>,
+                page: i64,
+                page_size: i64,
+            ) -> Result<Page<EntityOperation>, Error> {
+                let state = ctx.data::<State>()?;
+                let (entity_id, filter) = query_args(by, filter);
+                let page = state.database.provenance.organism_logs(&entity_id, &filter, page, page_size).await;
+                let mut page = into_page(page)?;
+
+                if !is_admin(ctx) {
+                    let rule = state.database.sensitive.organism_generalisation(&entity_id).await?;
+                    generalise(&mut page, rule);
+                }
+                Ok(page)
+            }
+
+            $(
+                $(#[$doc])*
+                pub async fn $field(
+                    &self,
+                    ctx: &Context<'_>,
+                    by: OperationBy,
+                    filter: Option<OperationFilter>,
+                    page: i64,
+                    page_size: i64,
+                ) -> Result<Page<EntityOperation>, Error> {
+                    let state = ctx.data::<State>()?;
+                    let (entity_id, filter) = query_args(by, filter);
+                    let page = state.database.provenance.$logs(&entity_id, &filter, page, page_size).await;
+                    into_page(page)
+                }
+            )*
+        }
+    };
+}
 
-    pub async fn taxon(&self, ctx: &Context<'_>, by: OperationBy) -> Result<Vec<TaxonOperation>, Error> {
-        let state = ctx.data::<State>()?;
-        TaxonOperation::new(&state.database, by).await
+provenance_resolvers! {
+    accession_event => accession_event_logs,
+    tissue => tissue_logs,
+    subsample => subsample_logs,
+    extraction => extraction_logs,
+    library => library_logs,
+    sequence_run => sequence_run_logs,
+    assembly => assembly_logs,
+    annotation => annotation_logs,
+    deposition => deposition_logs,
+    agent => agent_logs,
+    project => project_logs,
+    data_product => data_product_logs,
+}
+
+
+fn query_args(by: OperationBy, filter: Option<OperationFilter>) -> (String, database::provenance::OperationFilter) {
+    let OperationBy::EntityId(entity_id) = by;
+    (entity_id, filter.unwrap_or_default().into())
+}
+
+fn is_admin(ctx: &Context<'_>) -> bool {
+    ctx.data_opt::<Viewer>().is_some_and(|viewer| viewer.is_admin())
+}
+
+/// Generalise the coordinate atoms of a page of operations with a sensitive data rule
+fn generalise(page: &mut Page<EntityOperation>, rule: Option<LocationGeneralisation>) {
+    if let Some(rule) = rule {
+        for record in page.records.iter_mut() {
+            record.atom.generalise(rule);
+        }
     }
+}
 
-    pub async fn nomenclatural_act(
-        &self,
-        ctx: &Context<'_>,
-        by: OperationBy,
-    ) -> Result<Vec<NomenclaturalActOperation>, Error> {
-        let state = ctx.data::<State>()?;
-        NomenclaturalActOperation::new(&state.database, by).await
+fn into_page<T: OperationLogTable>(page: PageResult<DatasetOperation<T>>) -> Result<Page<EntityOperation>, Error> {
+    let page = page?;
+    let mut records = Vec::with_capacity(page.records.len());
+    for record in page.records {
+        records.push(EntityOperation::try_from(record)?);
     }
+
+    Ok(Page {
+        records,
+        total: page.total,
+    })
 }
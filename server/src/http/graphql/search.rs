@@ -5,6 +5,8 @@ use async_graphql::*;
 use serde::Deserialize;
 use uuid::Uuid;
 
+use super::common::Page;
+use super::treatment::Treatment;
 use crate::http::{Context as State, Error};
 
 
@@ -20,6 +22,23 @@ pub struct Search {}
 
 #[Object]
 impl Search {
+    /// Search the text of taxonomic treatments
+    async fn treatments(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        page: i64,
+        per_page: i64,
+    ) -> Result<Page<Treatment>, Error> {
+        let state = ctx.data::<State>()?;
+        let page = state.database.treatments.search(&query, page, per_page).await?;
+
+        Ok(Page {
+            records: page.records.into_iter().map(Treatment::new).collect(),
+            total: page.total,
+        })
+    }
+
     async fn full_text(
         &self,
         ctx: &Context<'_>,
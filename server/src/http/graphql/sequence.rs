@@ -209,6 +209,17 @@ pub struct TraceData {
     pub trace_id: Option<String>,
     pub trace_name: Option<String>,
     pub trace_link: Option<String>,
+
+    /// The analyzed chromatogram channels. Empty if the trace file hasn't been imported
+    pub a_analyzed: Option<Vec<Option<i16>>>,
+    pub c_analyzed: Option<Vec<Option<i16>>>,
+    pub g_analyzed: Option<Vec<Option<i16>>>,
+    pub t_analyzed: Option<Vec<Option<i16>>>,
+    pub base_calls: Option<String>,
+    /// The phred quality score of each base call
+    pub quality_scores: Option<Vec<Option<i16>>>,
+    /// The scan number in the channels of the peak for each base call
+    pub peak_locations: Option<Vec<Option<i16>>>,
 }
 
 
@@ -227,6 +238,13 @@ impl SequencingRunEventQuery {
             trace_id: trace.trace_id,
             trace_name: trace.trace_name,
             trace_link: trace.trace_link,
+            a_analyzed: trace.a_analyzed,
+            c_analyzed: trace.c_analyzed,
+            g_analyzed: trace.g_analyzed,
+            t_analyzed: trace.t_analyzed,
+            base_calls: trace.base_calls,
+            quality_scores: trace.quality_scores,
+            peak_locations: trace.peak_locations,
         })
     }
 }
@@ -4,10 +4,12 @@ use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::backbone::TaxonomicBackbone;
 use super::common::species::{SortDirection, SpeciesSort};
 use super::common::{DatasetDetails, FilterItem, Page, SpeciesCard, convert_filters};
 use super::helpers::{self, SpeciesHelper, csv};
 use super::taxon::{DataBreakdown, RankSummary};
+use crate::database::backbones::Backbone;
 use crate::database::extensions::filters::Filter;
 use crate::database::extensions::species_filters::{self};
 use crate::database::{Database, sources};
@@ -58,7 +60,12 @@ impl From<sources::KingdomPhylumCount> for KingdomPhylumCount {
 pub struct Source(SourceDetails, SourceQuery);
 
 impl Source {
-    pub async fn new(db: &Database, by: &SourceBy, filters: Vec<FilterItem>) -> Result<Source, Error> {
+    pub async fn new(
+        db: &Database,
+        by: &SourceBy,
+        filters: Vec<FilterItem>,
+        backbone: Option<String>,
+    ) -> Result<Source, Error> {
         let source = match by {
             SourceBy::Id(id) => db.sources.find_by_id(id).await?,
             SourceBy::Name(name) => db.sources.find_by_name(name).await?,
@@ -67,6 +74,7 @@ impl Source {
         let query = SourceQuery {
             source,
             filters: convert_filters(filters)?,
+            backbone,
         };
         Ok(Source(details, query))
     }
@@ -80,6 +88,7 @@ impl Source {
                 let query = SourceQuery {
                     source: record,
                     filters: vec![],
+                    backbone: None,
                 };
                 Source(details, query)
             })
@@ -91,10 +100,29 @@ impl Source {
 pub struct SourceQuery {
     source: models::Source,
     filters: Vec<Filter>,
+    backbone: Option<String>,
+}
+
+impl SourceQuery {
+    /// Use the requested backbone or the default backbone of the source
+    async fn resolve_backbone(&self, state: &State) -> Result<Backbone, Error> {
+        let backbone = state
+            .database
+            .backbones
+            .resolve(self.backbone.as_deref(), Some(&self.source.id))
+            .await?;
+        Ok(backbone)
+    }
 }
 
 #[Object]
 impl SourceQuery {
+    /// The taxonomic backbone used to classify the species of the source
+    async fn backbone(&self, ctx: &Context<'_>) -> Result<TaxonomicBackbone, Error> {
+        let state = ctx.data::<State>()?;
+        Ok(self.resolve_backbone(state).await?.into())
+    }
+
     async fn datasets(&self, ctx: &Context<'_>) -> Result<Vec<DatasetDetails>, Error> {
         let state = ctx.data::<State>()?;
         let records = state.database.sources.datasets(&self.source).await?;
@@ -111,6 +139,7 @@ impl SourceQuery {
         sort_direction: Option<SortDirection>,
     ) -> Result<Page<SpeciesCard>, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let helper = SpeciesHelper::new(&state.database);
 
         let page = state
@@ -129,6 +158,7 @@ impl SourceQuery {
                     Some(dir) => dir.into(),
                     _ => species_filters::SortDirection::Asc,
                 },
+                &backbone,
             )
             .await?;
 
@@ -142,6 +172,7 @@ impl SourceQuery {
 
     async fn species_csv(&self, ctx: &Context<'_>) -> Result<String, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
 
         let page = state
             .database
@@ -153,6 +184,7 @@ impl SourceQuery {
                 1000000, // some arbitrary number of records that hopefully is enough for all of them (1 million)
                 species_filters::SpeciesSort::ScientificName,
                 species_filters::SortDirection::Asc,
+                &backbone,
             )
             .await?;
 
@@ -163,11 +195,12 @@ impl SourceQuery {
 
     async fn species_genomic_data_summary(&self, ctx: &Context<'_>) -> Result<Vec<DataBreakdown>, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
         let summaries = state
             .database
             .sources
-            .species_genomic_data_summary(&self.source, &filters_option)
+            .species_genomic_data_summary(&self.source, &filters_option, &backbone)
             .await?;
         let summaries: Vec<DataBreakdown> = summaries.into_iter().map(|r| r.into()).collect();
         Ok(summaries)
@@ -175,11 +208,12 @@ impl SourceQuery {
 
     async fn species_genomic_data_summary_csv(&self, ctx: &Context<'_>) -> Result<String, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
         let summaries = state
             .database
             .sources
-            .species_genomic_data_summary(&self.source, &filters_option)
+            .species_genomic_data_summary(&self.source, &filters_option, &backbone)
             .await?;
         let summaries: Vec<DataBreakdown> = summaries.into_iter().map(|r| r.into()).collect();
         let csv = helpers::csv::generic(summaries).await?;
@@ -188,11 +222,12 @@ impl SourceQuery {
 
     async fn species_genomes_summary(&self, ctx: &Context<'_>) -> Result<Vec<DataBreakdown>, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
         let summaries = state
             .database
             .sources
-            .species_genomes_summary(&self.source, &filters_option)
+            .species_genomes_summary(&self.source, &filters_option, &backbone)
             .await?;
         let summaries: Vec<DataBreakdown> = summaries.into_iter().map(|r| r.into()).collect();
         Ok(summaries)
@@ -200,11 +235,12 @@ impl SourceQuery {
 
     async fn species_genomes_summary_csv(&self, ctx: &Context<'_>) -> Result<String, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
         let summaries = state
             .database
             .sources
-            .species_genomes_summary(&self.source, &filters_option)
+            .species_genomes_summary(&self.source, &filters_option, &backbone)
             .await?;
         let summaries: Vec<DataBreakdown> = summaries.into_iter().map(|r| r.into()).collect();
         let csv = helpers::csv::generic(summaries).await?;
@@ -213,11 +249,12 @@ impl SourceQuery {
 
     async fn species_loci_summary(&self, ctx: &Context<'_>) -> Result<Vec<DataBreakdown>, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
         let summaries = state
             .database
             .sources
-            .species_loci_summary(&self.source, &filters_option)
+            .species_loci_summary(&self.source, &filters_option, &backbone)
             .await?;
         let summaries: Vec<DataBreakdown> = summaries.into_iter().map(|r| r.into()).collect();
         Ok(summaries)
@@ -225,11 +262,12 @@ impl SourceQuery {
 
     async fn species_loci_summary_csv(&self, ctx: &Context<'_>) -> Result<String, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
         let summaries = state
             .database
             .sources
-            .species_loci_summary(&self.source, &filters_option)
+            .species_loci_summary(&self.source, &filters_option, &backbone)
             .await?;
         let summaries: Vec<DataBreakdown> = summaries.into_iter().map(|r| r.into()).collect();
         let csv = helpers::csv::generic(summaries).await?;
@@ -238,11 +276,12 @@ impl SourceQuery {
 
     async fn latest_genome_releases(&self, ctx: &Context<'_>) -> Result<Vec<GenomeRelease>, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
         let summaries = state
             .database
             .sources
-            .latest_genome_releases(&self.source, &filters_option)
+            .latest_genome_releases(&self.source, &filters_option, &backbone)
             .await?;
         let summaries: Vec<GenomeRelease> = summaries.into_iter().map(|r| r.into()).collect();
         Ok(summaries)
@@ -250,11 +289,12 @@ impl SourceQuery {
 
     async fn latest_genome_releases_csv(&self, ctx: &Context<'_>) -> Result<String, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
         let summaries = state
             .database
             .sources
-            .latest_genome_releases(&self.source, &filters_option)
+            .latest_genome_releases(&self.source, &filters_option, &backbone)
             .await?;
         let summaries: Vec<GenomeRelease> = summaries.into_iter().map(|r| r.into()).collect();
         let csv = helpers::csv::generic(summaries).await?;
@@ -263,15 +303,17 @@ impl SourceQuery {
 
     async fn summary(&self, ctx: &Context<'_>) -> Result<RankSummary, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
-        let summary = state.database.sources.summary(&self.source, &filters_option).await?;
+        let summary = state.database.sources.summary(&self.source, &filters_option, &backbone).await?;
         Ok(summary.into())
     }
 
     async fn summary_csv(&self, ctx: &Context<'_>) -> Result<String, async_graphql::Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
-        let summary = state.database.sources.summary(&self.source, &filters_option).await?;
+        let summary = state.database.sources.summary(&self.source, &filters_option, &backbone).await?;
         let out: Vec<RankSummary> = vec![summary.into()];
         let csv = csv::generic(out).await?;
         Ok(csv)
@@ -279,11 +321,12 @@ impl SourceQuery {
 
     async fn taxonomic_diversity(&self, ctx: &Context<'_>) -> Result<Vec<KingdomPhylumCount>, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
         let diversity = state
             .database
             .sources
-            .taxonomic_diversity(&self.source, &filters_option)
+            .taxonomic_diversity(&self.source, &filters_option, &backbone)
             .await?;
         let diversity: Vec<KingdomPhylumCount> = diversity.into_iter().map(|r| r.into()).collect();
         Ok(diversity)
@@ -291,11 +334,12 @@ impl SourceQuery {
 
     async fn taxonomic_diversity_csv(&self, ctx: &Context<'_>) -> Result<String, Error> {
         let state = ctx.data::<State>()?;
+        let backbone = self.resolve_backbone(state).await?;
         let filters_option = (!self.filters.is_empty()).then(|| self.filters.clone());
         let diversity = state
             .database
             .sources
-            .taxonomic_diversity(&self.source, &filters_option)
+            .taxonomic_diversity(&self.source, &filters_option, &backbone)
             .await?;
         let diversity: Vec<KingdomPhylumCount> = diversity.into_iter().map(|r| r.into()).collect();
         let csv = helpers::csv::generic(diversity).await?;
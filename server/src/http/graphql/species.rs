@@ -21,7 +21,8 @@ use super::common::{
 use super::markers::SpeciesMarker;
 use crate::database::extensions::filters_new::{self, Sort};
 use crate::database::models::{Name as ArgaName, Name};
-use crate::database::sources::ALA_DATASET_ID;
+use crate::database::sensitive::{Generalisations, generalise};
+use crate::database::backbones::Backbone;
 use crate::database::{Database, schema, species};
 use crate::http::graphql::common::FilteredPage;
 use crate::http::{Context as State, Error};
@@ -35,7 +36,7 @@ pub struct Species {
 #[Object]
 impl Species {
     #[graphql(skip)]
-    pub async fn new(db: &Database, canonical_name: String) -> Result<Species, Error> {
+    pub async fn new(db: &Database, canonical_name: String, backbone: &Backbone) -> Result<Species, Error> {
         use schema::{datasets, names, taxa, taxon_names};
         let mut conn = db.pool.get().await?;
 
@@ -47,7 +48,7 @@ impl Species {
             .await?;
 
         // get names that are identified as being the same species
-        // via a taxonomic system
+        // via the taxonomic backbone
         let linked_taxa_query = taxon_names::table
             .select(taxon_names::taxon_id)
             .filter(taxon_names::name_id.eq_any(&name_ids))
@@ -57,7 +58,7 @@ impl Species {
             .inner_join(taxa::table.on(taxa::id.eq(taxon_names::taxon_id)))
             .inner_join(datasets::table.on(datasets::id.eq(taxa::dataset_id)))
             .filter(taxon_names::taxon_id.eq_any(linked_taxa_query))
-            .filter(datasets::global_id.eq(ALA_DATASET_ID))
+            .filter(datasets::id.eq(backbone.dataset_id))
             .select(taxon_names::name_id)
             .load::<Uuid>(&mut conn)
             .await?;
@@ -146,7 +147,20 @@ impl Species {
             .species
             .specimens(&self.names, filters, sorting.into(), page, page_size)
             .await?;
-        let specimens = page.records.into_iter().map(|r| r.into()).collect();
+
+        let entity_ids: Vec<&str> = page.records.iter().map(|record| record.entity_id.as_str()).collect();
+        let rules = state.database.sensitive.specimen_generalisations(&entity_ids).await?;
+
+        let specimens = page
+            .records
+            .into_iter()
+            .map(|record| {
+                let rule = rules.get(&record.entity_id).copied().flatten();
+                let mut specimen = SpecimenSummary::from(record);
+                generalise(rule, &mut specimen.latitude, &mut specimen.longitude);
+                specimen
+            })
+            .collect();
         Ok(FilteredPage {
             records: specimens,
             total: page.total,
@@ -169,7 +183,18 @@ impl Species {
             .species
             .whole_genomes(&self.names, &filters, page, page_size)
             .await?;
-        let sequences = page.records.into_iter().map(|r| r.into()).collect();
+
+        let generalisations = self.generalisations(state).await?;
+        let sequences = page
+            .records
+            .into_iter()
+            .map(|record| {
+                let rule = generalisations.get(&record.name_id, &[record.dataset_id]);
+                let mut genome = WholeGenome::from(record);
+                generalise(rule, &mut genome.latitude, &mut genome.longitude);
+                genome
+            })
+            .collect();
         Ok(Page {
             records: sequences,
             total: page.total,
@@ -179,7 +204,18 @@ impl Species {
     async fn markers(&self, ctx: &Context<'_>, page: i64, page_size: i64) -> Result<Page<SpeciesMarker>, Error> {
         let state = ctx.data::<State>()?;
         let page = state.database.species.loci(&self.names, page, page_size).await?;
-        let markers = page.records.into_iter().map(|m| m.into()).collect();
+
+        let generalisations = self.generalisations(state).await?;
+        let markers = page
+            .records
+            .into_iter()
+            .map(|record| {
+                let rule = generalisations.get(&record.name_id, &[record.dataset_id]);
+                let mut marker = SpeciesMarker::from(record);
+                generalise(rule, &mut marker.latitude, &mut marker.longitude);
+                marker
+            })
+            .collect();
         Ok(Page {
             records: markers,
             total: page.total,
@@ -198,7 +234,18 @@ impl Species {
             .species
             .genomic_components(&self.names, page, page_size)
             .await?;
-        let components = page.records.into_iter().map(|m| m.into()).collect();
+
+        let generalisations = self.generalisations(state).await?;
+        let components = page
+            .records
+            .into_iter()
+            .map(|record| {
+                let rule = generalisations.get(&record.name_id, &[record.dataset_id]);
+                let mut component = GenomicComponent::from(record);
+                generalise(rule, &mut component.latitude, &mut component.longitude);
+                component
+            })
+            .collect();
         Ok(Page {
             records: components,
             total: page.total,
@@ -208,7 +255,14 @@ impl Species {
     async fn reference_genome(&self, ctx: &Context<'_>) -> Result<Option<WholeGenome>, Error> {
         let state = ctx.data::<State>()?;
         let genome = state.database.species.reference_genome(&self.names).await?;
-        let genome = genome.map(|g| g.into());
+
+        let generalisations = self.generalisations(state).await?;
+        let genome = genome.map(|record| {
+            let rule = generalisations.get(&record.name_id, &[record.dataset_id]);
+            let mut genome = WholeGenome::from(record);
+            generalise(rule, &mut genome.latitude, &mut genome.longitude);
+            genome
+        });
         Ok(genome)
     }
 
@@ -250,6 +304,17 @@ impl Species {
 }
 
 
+impl Species {
+    /// The sensitive data rules of the species names. Records that belong to the species are
+    /// generalised with the rules for their name and dataset in case a rule was added since
+    /// they were loaded
+    async fn generalisations(&self, state: &State) -> Result<Generalisations, Error> {
+        let name_ids: Vec<Uuid> = self.names.iter().map(|name| name.id).collect();
+        Ok(state.database.sensitive.generalisations(&name_ids).await?)
+    }
+}
+
+
 struct SpeciesOverview {
     names: Vec<Name>,
 }
@@ -354,7 +419,21 @@ impl SpeciesMapping {
         let state = ctx.data::<State>()?;
         let name_ids: Vec<Uuid> = self.names.iter().map(|name| name.id.clone()).collect();
         let map_markers = state.database.species.specimens_map_markers(&name_ids).await?;
-        Ok(map_markers.into_iter().map(|m| m.into()).collect())
+
+        // withheld locations can't be placed on a map at all so they are dropped
+        let entity_ids: Vec<&str> = map_markers.iter().map(|marker| marker.entity_id.as_str()).collect();
+        let rules = state.database.sensitive.specimen_generalisations(&entity_ids).await?;
+
+        let map_markers = map_markers
+            .into_iter()
+            .filter_map(|mut marker| {
+                if let Some(rule) = rules.get(&marker.entity_id).copied().flatten() {
+                    (marker.latitude, marker.longitude) = rule.apply(marker.latitude, marker.longitude)?;
+                }
+                Some(marker.into())
+            })
+            .collect();
+        Ok(map_markers)
     }
 }
 
@@ -727,6 +806,7 @@ pub enum SpecimenFilterItem {
     Country(Vec<String>),
     Data(Vec<HasData>),
     CollectedBetween(DateRange),
+    TypeStatus(Vec<String>),
 }
 
 #[derive(Enum, Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -752,6 +832,7 @@ impl From<SpecimenFilterItem> for crate::database::extensions::filters_new::spec
                 after: range.after,
                 before: range.before,
             },
+            TypeStatus(value) => Filter::TypeStatus(value),
         }
     }
 }
@@ -798,6 +879,7 @@ impl From<SpecimenSorting> for Sort<filters_new::specimens::sorting::Sortable> {
 pub struct SpecimenOptions {
     institutions: Vec<String>,
     countries: Vec<String>,
+    type_statuses: Vec<String>,
 }
 
 impl From<filters_new::specimens::Options> for SpecimenOptions {
@@ -805,6 +887,7 @@ impl From<filters_new::specimens::Options> for SpecimenOptions {
         Self {
             institutions: value.institutions,
             countries: value.countries,
+            type_statuses: value.type_statuses,
         }
     }
 }
@@ -1,7 +1,9 @@
 use async_graphql::*;
 use tracing::instrument;
+use uuid::Uuid;
 
 use super::common::specimens::TissueDetails;
+use super::common::taxonomy::NameDetails;
 use super::common::{AccessionEvent, CollectionEvent, OrganismDetails};
 use crate::database::{Database, models};
 use crate::http::{Context as State, Error};
@@ -65,6 +67,7 @@ impl SpecimenQuery {
         let state = ctx.data::<State>()?;
         let specimen_id = &self.specimen.entity_id;
         let collections = state.database.specimens.collection_events(specimen_id).await?;
+        let collections = state.database.sensitive.generalise(collections).await?;
         Ok(collections.into_iter().map(|r| r.into()).collect())
     }
 
@@ -89,11 +92,20 @@ impl SpecimenQuery {
         Ok(stats.into())
     }
 
+    /// The names the specimen is a type of along with its type status
+    async fn typifies(&self, ctx: &Context<'_>) -> Result<Vec<TypifiedName>, Error> {
+        let state = ctx.data::<State>()?;
+        let specimen_id = &self.specimen.entity_id;
+        let names = state.database.specimens.typified_names(specimen_id).await?;
+        Ok(names.into_iter().map(|r| r.into()).collect())
+    }
+
     #[instrument(skip(self, ctx))]
     async fn events(&self, ctx: &Context<'_>) -> Result<SpecimenEvents, Error> {
         let state = ctx.data::<State>()?;
         let specimen_id = &self.specimen.entity_id;
         let collections = state.database.specimens.collection_events(specimen_id).await?;
+        let collections = state.database.sensitive.generalise(collections).await?;
         let accessions = state.database.specimens.accession_events(specimen_id).await?;
 
         Ok(SpecimenEvents {
@@ -130,6 +142,24 @@ pub struct SpecimenEvents {
 }
 
 
+#[derive(SimpleObject)]
+pub struct TypifiedName {
+    pub name_id: Uuid,
+    pub name: NameDetails,
+    pub type_status: String,
+}
+
+impl From<(String, models::Name)> for TypifiedName {
+    fn from((type_status, name): (String, models::Name)) -> Self {
+        Self {
+            name_id: name.id,
+            name: name.into(),
+            type_status,
+        }
+    }
+}
+
+
 #[derive(SimpleObject)]
 pub struct SpecimenStats {
     pub entity_id: String,
@@ -143,6 +173,8 @@ pub struct SpecimenStats {
     pub assembly_chromosomes: i64,
     pub assembly_scaffolds: i64,
     pub assembly_contigs: i64,
+    /// The number of type designations of the specimen
+    pub types: i64,
 }
 
 impl From<models::SpecimenStats> for SpecimenStats {
@@ -159,6 +191,7 @@ impl From<models::SpecimenStats> for SpecimenStats {
             assembly_chromosomes: value.assembly_chromosomes,
             assembly_scaffolds: value.assembly_scaffolds,
             assembly_contigs: value.assembly_contigs,
+            types: value.types,
         }
     }
 }
@@ -3,15 +3,28 @@ use async_graphql::*;
 use bigdecimal::ToPrimitive;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
+use uuid::Uuid;
 
 use super::common::taxonomy::TaxonomicRank;
 use super::common::{FilterItem, convert_filters};
 use super::helpers::csv;
+use crate::database::backbones::Backbone;
 use crate::database::stats::{BreakdownItem, TaxonStatNode, TaxonomicRankStat};
 use crate::http::Context as State;
 
 
-pub struct Statistics;
+pub struct Statistics {
+    /// The global id of the taxonomic backbone to aggregate with
+    pub backbone: Option<String>,
+}
+
+impl Statistics {
+    /// Use the requested backbone or the default backbone of the source
+    async fn resolve_backbone(&self, state: &State, source_id: Option<&Uuid>) -> Result<Backbone> {
+        let backbone = state.database.backbones.resolve(self.backbone.as_deref(), source_id).await?;
+        Ok(backbone)
+    }
+}
 
 #[Object]
 impl Statistics {
@@ -55,7 +68,8 @@ impl Statistics {
         let classification = taxon_rank.to_classification(taxon_canonical_name);
         let include_ranks = include_ranks.into_iter().map(|i| i.into()).collect();
 
-        let tree = state.database.stats.taxon_tree(classification, include_ranks).await?;
+        let backbone = self.resolve_backbone(state, None).await?;
+        let tree = state.database.stats.taxon_tree(classification, include_ranks, &backbone).await?;
 
         let mut stats: Vec<TaxonTreeNodeStatistics> = tree.into_iter().map(|i| i.into()).collect();
         stats.sort();
@@ -86,7 +100,8 @@ impl Statistics {
         let classification = taxon_rank.to_classification(taxon_canonical_name);
         let ranks: Vec<models::TaxonomicRank> = ranks.into_iter().map(|r| r.into()).collect();
 
-        let stats = state.database.stats.taxonomic_ranks(classification, &ranks).await?;
+        let backbone = self.resolve_backbone(state, None).await?;
+        let stats = state.database.stats.taxonomic_ranks(classification, &ranks, &backbone).await?;
         Ok(stats.into_iter().map(|s| s.into()).collect())
     }
 
@@ -101,7 +116,8 @@ impl Statistics {
         let classification = taxon_rank.to_classification(taxon_canonical_name);
         let ranks: Vec<models::TaxonomicRank> = ranks.into_iter().map(|r| r.into()).collect();
 
-        let stats = state.database.stats.taxonomic_ranks(classification, &ranks).await?;
+        let backbone = self.resolve_backbone(state, None).await?;
+        let stats = state.database.stats.taxonomic_ranks(classification, &ranks, &backbone).await?;
         let stats_mapped: Vec<TaxonomicRankStatistic> = stats.into_iter().map(|s| s.into()).collect();
 
         csv::generic(stats_mapped).await
@@ -115,7 +131,8 @@ impl Statistics {
     ) -> Result<Vec<CompleteGenomesByYearStatistic>> {
         let state = ctx.data::<State>()?;
         let classification = taxon_rank.to_classification(taxon_canonical_name);
-        let stats = state.database.stats.complete_genomes_by_year(classification).await?;
+        let backbone = self.resolve_backbone(state, None).await?;
+        let stats = state.database.stats.complete_genomes_by_year(classification, &backbone).await?;
         let stats = stats
             .into_iter()
             .map(|(year, total)| CompleteGenomesByYearStatistic { year, total })
@@ -131,7 +148,8 @@ impl Statistics {
     ) -> Result<String> {
         let state = ctx.data::<State>()?;
         let classification = taxon_rank.to_classification(taxon_canonical_name);
-        let stats = state.database.stats.complete_genomes_by_year(classification).await?;
+        let backbone = self.resolve_backbone(state, None).await?;
+        let stats = state.database.stats.complete_genomes_by_year(classification, &backbone).await?;
         let stats = stats
             .into_iter()
             .map(|(year, total)| CompleteGenomesByYearStatistic { year, total })
@@ -150,10 +168,13 @@ impl Statistics {
         let filters = convert_filters(filters.unwrap_or_default())?;
         let filters_option = (!filters.is_empty()).then(|| filters);
 
+        let source = state.database.sources.find_by_name(&name).await?;
+        let backbone = self.resolve_backbone(state, Some(&source.id)).await?;
+
         let stats = state
             .database
             .stats
-            .complete_genomes_by_year_for_source(&name, &filters_option)
+            .complete_genomes_by_year_for_source(&name, &filters_option, &backbone)
             .await?;
 
         let stats: Vec<CompleteGenomesByYearStatistic> = stats
@@ -174,10 +195,13 @@ impl Statistics {
         let filters = convert_filters(filters.unwrap_or_default())?;
         let filters_option = (!filters.is_empty()).then(|| filters);
 
+        let source = state.database.sources.find_by_name(&name).await?;
+        let backbone = self.resolve_backbone(state, Some(&source.id)).await?;
+
         let stats = state
             .database
             .stats
-            .complete_genomes_by_year_for_source(&name, &filters_option)
+            .complete_genomes_by_year_for_source(&name, &filters_option, &backbone)
             .await?;
 
         let stats: Vec<CompleteGenomesByYearStatistic> = stats
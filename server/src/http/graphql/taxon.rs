@@ -1,4 +1,4 @@
-use arga_core::models;
+use arga_core::{comparison, models};
 use async_graphql::*;
 use bigdecimal::ToPrimitive;
 use chrono::{DateTime, Utc};
@@ -175,6 +175,91 @@ impl TaxonQuery {
         Ok(hierarchy)
     }
 
+    /// The taxa directly below this taxon, each with the stats accumulated from their descendants.
+    /// Nodes can be expanded a page at a time by querying the children of a child.
+    /// Only data and dataset filters apply, any other filter is rejected
+    #[allow(clippy::too_many_arguments)]
+    async fn children(
+        &self,
+        ctx: &Context<'_>,
+        page: i64,
+        page_size: i64,
+        rank: Option<TaxonomicRank>,
+        filters: Option<Vec<FilterItem>>,
+        sort: Option<TaxonTreeSort>,
+        sort_direction: Option<SortDirection>,
+    ) -> Result<Page<TaxonTreeItem>, Error> {
+        let state = ctx.data::<State>()?;
+        let options = tree_options(rank, filters, sort, sort_direction)?;
+        let page = state
+            .database
+            .taxa
+            .children(&self.taxon.id, &options, page, page_size)
+            .await?;
+
+        Ok(Page {
+            records: page.records.into_iter().map(TaxonTreeItem::from).collect(),
+            total: page.total,
+        })
+    }
+
+    /// All taxa below this taxon at any depth, each with the stats accumulated from their descendants.
+    /// Only data and dataset filters apply since the stats of a node include the data of every
+    /// descendant, any other filter is rejected
+    #[allow(clippy::too_many_arguments)]
+    async fn descendants(
+        &self,
+        ctx: &Context<'_>,
+        page: i64,
+        page_size: i64,
+        rank: Option<TaxonomicRank>,
+        filters: Option<Vec<FilterItem>>,
+        sort: Option<TaxonTreeSort>,
+        sort_direction: Option<SortDirection>,
+    ) -> Result<Page<TaxonTreeItem>, Error> {
+        let state = ctx.data::<State>()?;
+        let options = tree_options(rank, filters, sort, sort_direction)?;
+        let page = state
+            .database
+            .taxa
+            .descendants(&self.taxon.id, &options, page, page_size)
+            .await?;
+
+        Ok(Page {
+            records: page.records.into_iter().map(TaxonTreeItem::from).collect(),
+            total: page.total,
+        })
+    }
+
+    /// Where the classification of this taxon and the taxa below it disagrees with the same
+    /// taxa in another dataset. Taxa are aligned by name with taxa anywhere in the other dataset and
+    /// compared by their parent, rank, status and authorship. Taxa that aren't in the other dataset
+    /// are reported as missing
+    async fn classification_conflicts(
+        &self,
+        ctx: &Context<'_>,
+        other_dataset: Uuid,
+        kinds: Option<Vec<ConflictKind>>,
+    ) -> Result<Vec<ClassificationConflict>, Error> {
+        let state = ctx.data::<State>()?;
+        let taxa = &state.database.taxa;
+
+        let nodes = taxa.classification_nodes(&self.taxon.id).await?;
+        let names: Vec<String> = nodes.iter().map(|node| node.canonical_name.clone()).collect();
+        let other_nodes = taxa.classification_nodes_by_name(&other_dataset, &names).await?;
+
+        let kinds: Option<Vec<comparison::ConflictKind>> =
+            kinds.map(|kinds| kinds.into_iter().map(|kind| kind.into()).collect());
+
+        let conflicts = comparison::compare(&nodes, &other_nodes)
+            .into_iter()
+            .filter(|conflict| kinds.as_ref().is_none_or(|kinds| kinds.contains(&conflict.kind)))
+            .map(ClassificationConflict::from)
+            .collect();
+
+        Ok(conflicts)
+    }
+
     async fn summary(&self, ctx: &Context<'_>, rank: TaxonomicRank) -> Result<RankSummary, Error> {
         let state = ctx.data::<State>()?;
         let summary = state.database.taxa.rank_summary(&self.taxon.id, &rank.into()).await?;
@@ -386,6 +471,112 @@ impl From<models::TaxonTreeNode> for TaxonNode {
     }
 }
 
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Enum)]
+#[graphql(remote = "taxa::TreeSort")]
+pub enum TaxonTreeSort {
+    ScientificName,
+    Children,
+    Descendants,
+    Species,
+    Genomes,
+    Loci,
+    Specimens,
+    TotalGenomic,
+}
+
+fn tree_options(
+    rank: Option<TaxonomicRank>,
+    filters: Option<Vec<FilterItem>>,
+    sort: Option<TaxonTreeSort>,
+    sort_direction: Option<SortDirection>,
+) -> Result<taxa::TreeOptions, Error> {
+    Ok(taxa::TreeOptions {
+        rank: rank.map(|rank| rank.into()),
+        filters: convert_filters(filters.unwrap_or_default())?,
+        sort: sort.unwrap_or(TaxonTreeSort::ScientificName).into(),
+        direction: sort_direction.unwrap_or(SortDirection::Asc).into(),
+    })
+}
+
+
+/// A node in the taxonomic tree below a taxon
+#[derive(SimpleObject)]
+pub struct TaxonTreeItem {
+    /// The taxon id, used to get the children of this node
+    pub id: Uuid,
+    pub taxon: TaxonDetails,
+    /// Total amount of taxa directly below this node
+    pub children: i64,
+    /// Total amount of taxa below this node at any depth
+    pub descendants: i64,
+    /// Total amount of species below this node
+    pub species: i64,
+    pub loci: i64,
+    pub genomes: i64,
+    pub specimens: i64,
+    pub other: i64,
+    pub total_genomic: i64,
+}
+
+impl From<taxa::TaxonWithStats> for TaxonTreeItem {
+    fn from(value: taxa::TaxonWithStats) -> Self {
+        let stats = value.stats;
+        Self {
+            id: value.taxon.id,
+            taxon: value.taxon.into(),
+            children: stats.children,
+            descendants: stats.descendants,
+            species: stats.species.unwrap_or(0),
+            loci: stats.loci.map(|v| v.to_i64().unwrap_or(0)).unwrap_or(0),
+            genomes: stats.genomes.map(|v| v.to_i64().unwrap_or(0)).unwrap_or(0),
+            specimens: stats.specimens.map(|v| v.to_i64().unwrap_or(0)).unwrap_or(0),
+            other: stats.other.map(|v| v.to_i64().unwrap_or(0)).unwrap_or(0),
+            total_genomic: stats.total_genomic.map(|v| v.to_i64().unwrap_or(0)).unwrap_or(0),
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Enum)]
+#[graphql(remote = "comparison::ConflictKind")]
+pub enum ConflictKind {
+    /// The taxon isn't in the other dataset
+    Missing,
+    Parent,
+    Rank,
+    Status,
+    Authorship,
+}
+
+/// A disagreement between the classification of a taxon in two datasets
+#[derive(SimpleObject)]
+pub struct ClassificationConflict {
+    pub kind: ConflictKind,
+    pub canonical_name: String,
+    pub rank: TaxonomicRank,
+    pub taxon_id: Uuid,
+    pub other_taxon_id: Option<Uuid>,
+    /// The value in the dataset of the taxon
+    pub value: Option<String>,
+    /// The value in the other dataset
+    pub other_value: Option<String>,
+}
+
+impl From<comparison::Conflict> for ClassificationConflict {
+    fn from(value: comparison::Conflict) -> Self {
+        Self {
+            kind: value.kind.into(),
+            canonical_name: value.canonical_name,
+            rank: value.rank.into(),
+            taxon_id: value.taxon_id,
+            other_taxon_id: value.other_taxon_id,
+            value: value.value,
+            other_value: value.other_value,
+        }
+    }
+}
+
+
 #[derive(SimpleObject, Serialize)]
 pub struct RankSummary {
     /// Total amount of taxa in the rank
@@ -0,0 +1,86 @@
+use async_graphql::*;
+
+use super::common::Publication;
+use crate::database::{self, models};
+use crate::http::{Context as State, Error};
+
+
+/// A taxonomic treatment from the literature describing a taxon
+#[derive(MergedObject)]
+pub struct Treatment(TreatmentDetails, TreatmentQuery);
+
+impl Treatment {
+    pub fn new(treatment: models::Treatment) -> Treatment {
+        let query = TreatmentQuery {
+            entity_id: treatment.entity_id.clone(),
+            publication_entity_id: treatment.publication_entity_id.clone(),
+        };
+        Treatment(treatment.into(), query)
+    }
+}
+
+
+#[derive(SimpleObject)]
+pub struct TreatmentDetails {
+    pub entity_id: String,
+    /// The link to the original treatment
+    pub treatment_uri: String,
+    pub lsid: Option<String>,
+    pub scientific_name: Option<String>,
+    pub canonical_name: Option<String>,
+}
+
+impl From<models::Treatment> for TreatmentDetails {
+    fn from(value: models::Treatment) -> Self {
+        Self {
+            entity_id: value.entity_id,
+            treatment_uri: value.treatment_uri,
+            lsid: value.lsid,
+            scientific_name: value.scientific_name,
+            canonical_name: value.canonical_name,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct TreatmentSection {
+    pub position: i32,
+    /// The kind of section such as description, diagnosis or etymology
+    pub section_type: String,
+    pub text: String,
+}
+
+impl From<models::TreatmentSection> for TreatmentSection {
+    fn from(value: models::TreatmentSection) -> Self {
+        Self {
+            position: value.position,
+            section_type: value.section_type,
+            text: value.text,
+        }
+    }
+}
+
+
+struct TreatmentQuery {
+    entity_id: String,
+    publication_entity_id: String,
+}
+
+#[Object]
+impl TreatmentQuery {
+    async fn sections(&self, ctx: &Context<'_>) -> Result<Vec<TreatmentSection>, Error> {
+        let state = ctx.data::<State>()?;
+        let sections = state.database.treatments.sections(&self.entity_id).await?;
+        Ok(sections.into_iter().map(|section| section.into()).collect())
+    }
+
+    /// The publication the treatment was extracted from
+    async fn publication(&self, ctx: &Context<'_>) -> Result<Option<Publication>, Error> {
+        let state = ctx.data::<State>()?;
+        match state.database.publications.find_by_id(&self.publication_entity_id).await {
+            Ok(publication) => Ok(Some(publication.into())),
+            Err(database::Error::NotFound(_)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
@@ -6,6 +6,7 @@ use arga_core::search::SearchIndex;
 use axum::Router;
 use axum::extract::FromRef;
 use axum::http::{HeaderValue, Uri, header};
+use secrecy::SecretString;
 use tower_http::cors::CorsLayer;
 use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::sensitive_headers::{SetSensitiveRequestHeadersLayer, SetSensitiveResponseHeadersLayer};
@@ -20,6 +21,8 @@ pub mod error;
 pub mod graphql;
 pub mod health;
 pub mod proxy;
+pub mod reconciliation;
+pub mod replication;
 
 pub use error::Error;
 
@@ -37,6 +40,10 @@ pub struct Config {
     pub frontend_host: String,
 
     pub admin_proxy: Option<Uri>,
+
+    /// The access tokens given to partners mirroring the operation logs
+    /// through the replication feed. The feed is disabled when there are none
+    pub replication_tokens: Vec<SecretString>,
 }
 
 
@@ -127,8 +134,11 @@ fn router(context: Context) -> Result<Router, Error> {
     let router = Router::new()
         .merge(health::router())
         .merge(graphql::router(context.clone()))
-        .nest("/api/admin", admin::router(context.clone()))
+        .merge(replication::router())
+        .merge(reconciliation::router())
+        .nest("/api/admin", admin::router())
         .nest("/admin", proxy::admin_web_router(context.clone()))
+        .layer(admin::auth_layer(&context))
         .layer(service)
         .with_state(context);
 
@@ -0,0 +1,614 @@
+use std::collections::{BTreeMap, HashMap};
+
+use arga_core::models::Name;
+use arga_core::names::matching::NameMatch;
+use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Form, Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::error::Error;
+use crate::database::Database;
+use crate::http::Context;
+
+
+/// The most queries that can be reconciled in one request
+const MAX_QUERIES: usize = 1000;
+const DEFAULT_LIMIT: usize = 5;
+
+/// Candidates scoring at least this much are considered a match when they
+/// aren't tied and don't conflict with the classification in the query
+const MATCH_SCORE: f64 = 95.0;
+
+/// The score of a candidate is multiplied by this for every rank in the query
+/// that conflicts with the classification of the candidate
+const CONFLICT_PENALTY: f64 = 0.5;
+
+const NAME_TYPE: &str = "name";
+const ACCEPTED_NAME: &str = "accepted_name";
+const AUTHORSHIP: &str = "authorship";
+
+/// The ranks that can be used to disambiguate a query and pulled in when extending.
+/// The botanical ranks are used when a taxon doesn't have the zoological ones
+const RANKS: [(&str, &str, &[&str]); 6] = [
+    ("kingdom", "Kingdom", &["kingdom", "regnum"]),
+    ("phylum", "Phylum", &["phylum", "division"]),
+    ("class", "Class", &["class", "classis"]),
+    ("order", "Order", &["order", "ordo"]),
+    ("family", "Family", &["family", "familia"]),
+    ("genus", "Genus", &["genus"]),
+];
+
+
+/// The parameters of the service endpoint. Reconciliation clients send either
+/// a batch of queries or an extend request as a JSON encoded string, and without
+/// either the service manifest is returned
+#[derive(Debug, Default, Deserialize)]
+struct ServiceParams {
+    queries: Option<String>,
+    extend: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreviewParams {
+    id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProposeParams {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    limit: Option<usize>,
+}
+
+
+#[derive(Debug, Deserialize)]
+struct ReconciliationQuery {
+    query: String,
+    limit: Option<usize>,
+    #[serde(default)]
+    properties: Vec<PropertyValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PropertyValue {
+    pid: String,
+    v: Value,
+}
+
+impl ReconciliationQuery {
+    /// The values of a property in the query. Clients can send a single value or a list
+    fn property(&self, pid: &str) -> Vec<String> {
+        self.properties
+            .iter()
+            .filter(|prop| prop.pid == pid)
+            .flat_map(|prop| match &prop.v {
+                Value::Array(values) => values.iter().filter_map(property_string).collect::<Vec<String>>(),
+                value => property_string(value).into_iter().collect(),
+            })
+            .collect()
+    }
+
+    /// The name to match, including the authorship when it's in a separate column
+    fn name(&self) -> String {
+        let mut name = self.query.trim().to_string();
+        for authorship in self.property(AUTHORSHIP) {
+            name.push(' ');
+            name.push_str(authorship.trim());
+        }
+        name
+    }
+}
+
+fn property_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        // reconciled values from another column come through as an object
+        Value::Object(object) => object.get("name").and_then(|name| name.as_str()).map(String::from),
+        _ => None,
+    }
+}
+
+
+#[derive(Debug, Serialize)]
+struct EntityType {
+    id: &'static str,
+    name: &'static str,
+}
+
+const ENTITY_TYPE: EntityType = EntityType {
+    id: NAME_TYPE,
+    name: "Scientific name",
+};
+
+
+#[derive(Debug, Serialize)]
+struct Candidate {
+    id: Uuid,
+    name: String,
+    score: f64,
+    #[serde(rename = "match")]
+    matched: bool,
+    #[serde(rename = "type")]
+    kind: [EntityType; 1],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip)]
+    conflicts: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct QueryResult {
+    result: Vec<Candidate>,
+}
+
+
+#[derive(Debug, Deserialize)]
+struct ExtendRequest {
+    ids: Vec<Uuid>,
+    properties: Vec<ExtendProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtendProperty {
+    id: String,
+}
+
+
+pub(crate) fn router() -> Router<Context> {
+    Router::new()
+        .route("/api/reconcile", get(service_get).post(service_post))
+        .route("/api/reconcile/names/{id}", get(name))
+        .route("/api/reconcile/preview", get(preview))
+        .route("/api/reconcile/properties", get(propose_properties))
+}
+
+
+async fn service_get(
+    headers: HeaderMap,
+    Query(params): Query<ServiceParams>,
+    State(database): State<Database>,
+) -> Result<Response, Error> {
+    service(headers, params, database).await
+}
+
+async fn service_post(
+    headers: HeaderMap,
+    State(database): State<Database>,
+    Form(params): Form<ServiceParams>,
+) -> Result<Response, Error> {
+    service(headers, params, database).await
+}
+
+
+/// The Reconciliation Service API.
+///
+/// Implements version 0.2 of the API over the names in ARGA so that clients like OpenRefine
+/// can reconcile a column of scientific names. Queries are matched in a single batch with
+/// the same matcher used by `names.match` and scored by the confidence of the match, which is
+/// lowered when the classification sent as properties conflicts with the default backbone.
+async fn service(headers: HeaderMap, params: ServiceParams, database: Database) -> Result<Response, Error> {
+    if let Some(queries) = params.queries {
+        let queries = parse_queries(&queries)?;
+        return Ok(Json(reconcile(queries, &database).await?).into_response());
+    }
+
+    if let Some(extend) = params.extend {
+        let request: ExtendRequest =
+            serde_json::from_str(&extend).map_err(|err| Error::InvalidParam("extend".to_string(), err.to_string()))?;
+        return Ok(Json(extend_names(request, &database).await?).into_response());
+    }
+
+    Ok(Json(manifest(&base_url(&headers))).into_response())
+}
+
+fn parse_queries(queries: &str) -> Result<BTreeMap<String, ReconciliationQuery>, Error> {
+    let queries: BTreeMap<String, ReconciliationQuery> =
+        serde_json::from_str(queries).map_err(|err| Error::InvalidParam("queries".to_string(), err.to_string()))?;
+
+    if queries.len() > MAX_QUERIES {
+        let message = format!("at most {MAX_QUERIES} queries can be reconciled at once");
+        return Err(Error::InvalidParam("queries".to_string(), message));
+    }
+    Ok(queries)
+}
+
+
+/// The URL the service is being accessed from so that the manifest can point
+/// clients to the preview and extend endpoints behind a proxy
+fn base_url(headers: &HeaderMap) -> String {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let scheme = header("x-forwarded-proto").unwrap_or("http");
+    let host = header("x-forwarded-host").or_else(|| header("host")).unwrap_or("localhost");
+    format!("{scheme}://{host}")
+}
+
+fn manifest(base_url: &str) -> Value {
+    json!({
+        "versions": ["0.2"],
+        "name": "ARGA scientific names",
+        "identifierSpace": format!("{base_url}/api/reconcile/names/"),
+        "view": {
+            "url": format!("{base_url}/api/reconcile/names/{{{{id}}}}"),
+        },
+        "schemaSpace": "http://rs.tdwg.org/dwc/terms/",
+        "defaultTypes": [ENTITY_TYPE],
+        "preview": {
+            "url": format!("{base_url}/api/reconcile/preview?id={{{{id}}}}"),
+            "width": 400,
+            "height": 200,
+        },
+        "extend": {
+            "propose_properties": {
+                "service_url": base_url,
+                "service_path": "/api/reconcile/properties",
+            },
+            "property_settings": [],
+        },
+    })
+}
+
+
+async fn reconcile(
+    queries: BTreeMap<String, ReconciliationQuery>,
+    database: &Database,
+) -> Result<BTreeMap<String, QueryResult>, Error> {
+    let names: Vec<String> = queries.values().map(|query| query.name()).collect();
+    let results = database.names.match_names(&names).await?;
+
+    // candidates are classified by their accepted name so that synonyms can be disambiguated too
+    let name_ids: Vec<Uuid> = results
+        .iter()
+        .flat_map(|(_, matches)| matches.iter().map(|name_match| accepted(name_match).id))
+        .collect();
+    let classifications = database.names.classifications(&name_ids).await?;
+
+    let mut response = BTreeMap::new();
+    for ((key, query), (_, matches)) in queries.into_iter().zip(results) {
+        response.insert(key, query_result(&query, &matches, &classifications));
+    }
+
+    Ok(response)
+}
+
+/// Score and rank the candidates of a query
+fn query_result(query: &ReconciliationQuery, matches: &[NameMatch], classifications: &HashMap<Uuid, Value>) -> QueryResult {
+    let mut candidates: Vec<Candidate> = matches
+        .iter()
+        .map(|name_match| score(query, name_match, classifications.get(&accepted(name_match).id)))
+        .collect();
+
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates.truncate(query.limit.unwrap_or(DEFAULT_LIMIT));
+
+    // only flag a match when it's unambiguous so that clients can auto-match safely
+    let runner_up = candidates.get(1).map(|candidate| candidate.score);
+    if let Some(best) = candidates.first_mut() {
+        best.matched = best.score >= MATCH_SCORE && !best.conflicts && runner_up.is_none_or(|s| s < best.score);
+    }
+
+    QueryResult { result: candidates }
+}
+
+fn accepted(name_match: &NameMatch) -> &Name {
+    name_match.accepted.as_ref().unwrap_or(&name_match.name)
+}
+
+fn score(query: &ReconciliationQuery, name_match: &NameMatch, classification: Option<&Value>) -> Candidate {
+    let mut score = name_match.confidence * 100.0;
+    let mut conflicts = false;
+
+    for (rank, _, _) in RANKS {
+        let expected = query.property(rank);
+        if expected.is_empty() {
+            continue;
+        }
+
+        let value = classification.and_then(|classification| rank_value(classification, rank));
+        if let Some(value) = value {
+            if !expected.iter().any(|expected| expected.trim().eq_ignore_ascii_case(&value)) {
+                score *= CONFLICT_PENALTY;
+                conflicts = true;
+            }
+        }
+    }
+
+    Candidate {
+        id: name_match.name.id,
+        name: name_match.name.scientific_name.clone(),
+        score: (score * 100.0).round() / 100.0,
+        matched: false,
+        kind: [ENTITY_TYPE],
+        description: name_match
+            .accepted
+            .as_ref()
+            .map(|accepted| format!("Synonym of {}", accepted.scientific_name)),
+        conflicts,
+    }
+}
+
+fn rank_value(classification: &Value, rank: &str) -> Option<String> {
+    let (_, _, keys) = RANKS.iter().find(|(id, _, _)| *id == rank)?;
+    keys.iter()
+        .find_map(|key| classification.get(key).and_then(|name| name.as_str()))
+        .map(String::from)
+}
+
+
+async fn propose_properties(Query(params): Query<ProposeParams>) -> Result<Json<Value>, Error> {
+    let kind = params.kind.unwrap_or_else(|| NAME_TYPE.to_string());
+    if kind != NAME_TYPE {
+        return Err(Error::NotFound(kind));
+    }
+
+    let mut properties = vec![json!({ "id": ACCEPTED_NAME, "name": "Accepted name" })];
+    properties.extend(RANKS.iter().map(|(id, name, _)| json!({ "id": id, "name": name })));
+    properties.truncate(params.limit.unwrap_or(properties.len()));
+
+    Ok(Json(json!({ "type": kind, "properties": properties })))
+}
+
+
+/// Pull in the accepted name and the classification in the default backbone of reconciled names
+async fn extend_names(request: ExtendRequest, database: &Database) -> Result<Value, Error> {
+    let accepted = database.names.accepted_names(request.ids.clone()).await?;
+
+    let accepted_ids: Vec<Uuid> = request
+        .ids
+        .iter()
+        .map(|id| accepted.get(id).map(|name| name.id).unwrap_or(*id))
+        .collect();
+    let classifications = database.names.classifications(&accepted_ids).await?;
+
+    let accepted_names: HashMap<Uuid, Name> = database
+        .names
+        .find_by_name_ids(&accepted_ids)
+        .await?
+        .into_iter()
+        .map(|name| (name.id, name))
+        .collect();
+
+    extend_response(&request, &accepted_ids, &accepted_names, &classifications)
+}
+
+/// Build the extend response from the accepted name id of every requested id
+fn extend_response(
+    request: &ExtendRequest,
+    accepted_ids: &[Uuid],
+    accepted_names: &HashMap<Uuid, Name>,
+    classifications: &HashMap<Uuid, Value>,
+) -> Result<Value, Error> {
+    let mut meta = Vec::new();
+    for prop in &request.properties {
+        match RANKS.iter().find(|(id, _, _)| *id == prop.id) {
+            Some((id, name, _)) => meta.push(json!({ "id": id, "name": name })),
+            None if prop.id == ACCEPTED_NAME => {
+                meta.push(json!({ "id": ACCEPTED_NAME, "name": "Accepted name", "type": ENTITY_TYPE }))
+            }
+            None => return Err(Error::NotFound(prop.id.clone())),
+        }
+    }
+
+    let mut rows = serde_json::Map::new();
+    for (id, accepted_id) in request.ids.iter().zip(accepted_ids.iter()) {
+        let mut row = serde_json::Map::new();
+
+        for prop in &request.properties {
+            let values = match prop.id.as_str() {
+                ACCEPTED_NAME => accepted_names
+                    .get(accepted_id)
+                    .map(|name| json!({ "id": name.id, "name": name.scientific_name }))
+                    .into_iter()
+                    .collect(),
+                rank => classifications
+                    .get(accepted_id)
+                    .and_then(|classification| rank_value(classification, rank))
+                    .map(|value| json!({ "str": value }))
+                    .into_iter()
+                    .collect(),
+            };
+            row.insert(prop.id.clone(), Value::Array(values));
+        }
+
+        rows.insert(id.to_string(), Value::Object(row));
+    }
+
+    Ok(json!({ "meta": meta, "rows": rows }))
+}
+
+
+/// The name that an identifier in the service's identifier space refers to
+async fn name(Path(id): Path<Uuid>, State(database): State<Database>) -> Result<Json<Name>, Error> {
+    Ok(Json(database.names.find_by_name_id(&id).await?))
+}
+
+
+/// A small HTML summary of a name shown by clients when hovering over a candidate
+async fn preview(Query(params): Query<PreviewParams>, State(database): State<Database>) -> Result<Html<String>, Error> {
+    let name = database.names.find_by_name_id(&params.id).await?;
+    let accepted = database.names.accepted_names(vec![name.id]).await?.remove(&name.id);
+
+    let classified_id = accepted.as_ref().map(|accepted| accepted.id).unwrap_or(name.id);
+    let classification = database.names.classifications(&[classified_id]).await?.remove(&classified_id);
+
+    let mut html = format!(
+        "<div style=\"font-family: sans-serif; font-size: 14px\"><p><strong><em>{}</em></strong> {}</p>",
+        escape(&name.canonical_name),
+        escape(name.authorship.as_deref().unwrap_or_default()),
+    );
+
+    if let Some(accepted) = accepted {
+        html.push_str(&format!("<p>Synonym of <em>{}</em></p>", escape(&accepted.scientific_name)));
+    }
+
+    if let Some(classification) = classification {
+        let ranks: Vec<String> = RANKS
+            .iter()
+            .filter_map(|(rank, _, _)| rank_value(&classification, rank))
+            .map(|value| escape(&value))
+            .collect();
+        html.push_str(&format!("<p>{}</p>", ranks.join(" &rsaquo; ")));
+    }
+
+    html.push_str("</div>");
+    Ok(Html(html))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use arga_core::names::matching::MatchType;
+
+    use super::*;
+
+    fn name(scientific_name: &str) -> Name {
+        Name {
+            id: Uuid::new_v4(),
+            scientific_name: scientific_name.to_string(),
+            canonical_name: scientific_name.to_string(),
+            authorship: None,
+            entity_id: None,
+        }
+    }
+
+    fn name_match(name: Name, confidence: f64, accepted: Option<Name>) -> NameMatch {
+        NameMatch {
+            name,
+            match_type: MatchType::Exact,
+            confidence,
+            accepted,
+        }
+    }
+
+    fn query(json: Value) -> ReconciliationQuery {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn manifest_points_to_resolvable_urls() {
+        let manifest = manifest("https://api.arga.org.au");
+
+        assert_eq!(manifest["versions"], json!(["0.2"]));
+        assert_eq!(manifest["identifierSpace"], "https://api.arga.org.au/api/reconcile/names/");
+        assert_eq!(manifest["view"]["url"], "https://api.arga.org.au/api/reconcile/names/{{id}}");
+        assert_eq!(manifest["preview"]["url"], "https://api.arga.org.au/api/reconcile/preview?id={{id}}");
+        assert_eq!(manifest["defaultTypes"], json!([{ "id": "name", "name": "Scientific name" }]));
+        assert_eq!(manifest["extend"]["propose_properties"]["service_path"], "/api/reconcile/properties");
+    }
+
+    #[test]
+    fn rejects_malformed_and_oversized_queries() {
+        assert!(matches!(parse_queries("{not json"), Err(Error::InvalidParam(param, _)) if param == "queries"));
+
+        let queries: serde_json::Map<String, Value> = (0..=MAX_QUERIES)
+            .map(|idx| (format!("q{idx}"), json!({ "query": "Acacia dealbata" })))
+            .collect();
+        let queries = Value::Object(queries).to_string();
+        assert!(matches!(parse_queries(&queries), Err(Error::InvalidParam(param, _)) if param == "queries"));
+
+        let queries = parse_queries(r#"{"q0": {"query": "Acacia dealbata", "limit": 2}}"#).unwrap();
+        assert_eq!(queries["q0"].query, "Acacia dealbata");
+        assert_eq!(queries["q0"].limit, Some(2));
+    }
+
+    #[test]
+    fn matches_an_unambiguous_candidate() {
+        let matches = vec![
+            name_match(name("Acacia dealbata"), 1.0, None),
+            name_match(name("Acacia decurrens"), 0.8, None),
+        ];
+        let result = query_result(&query(json!({ "query": "Acacia dealbata" })), &matches, &HashMap::new());
+        let result = serde_json::to_value(result).unwrap();
+
+        assert_eq!(result["result"][0]["name"], "Acacia dealbata");
+        assert_eq!(result["result"][0]["score"], 100.0);
+        assert_eq!(result["result"][0]["match"], true);
+        assert_eq!(result["result"][0]["type"], json!([{ "id": "name", "name": "Scientific name" }]));
+        assert_eq!(result["result"][1]["name"], "Acacia decurrens");
+        assert_eq!(result["result"][1]["match"], false);
+    }
+
+    #[test]
+    fn penalises_conflicting_classifications() {
+        let plant = name("Morus alba");
+        let bird = name("Morus bassanus");
+        let classifications = HashMap::from([
+            (plant.id, json!({ "kingdom": "Plantae" })),
+            (bird.id, json!({ "kingdom": "Animalia" })),
+        ]);
+        let matches = vec![name_match(bird, 1.0, None), name_match(plant, 0.9, None)];
+
+        let query = query(json!({
+            "query": "Morus",
+            "properties": [{ "pid": "kingdom", "v": "Plantae" }],
+        }));
+        let result = serde_json::to_value(query_result(&query, &matches, &classifications)).unwrap();
+
+        assert_eq!(result["result"][0]["name"], "Morus alba");
+        assert_eq!(result["result"][0]["score"], 90.0);
+        assert_eq!(result["result"][0]["match"], false);
+        assert_eq!(result["result"][1]["score"], 50.0);
+    }
+
+    #[test]
+    fn describes_synonyms_and_limits_candidates() {
+        let accepted = name("Acacia dealbata");
+        let matches = vec![
+            name_match(name("Racosperma dealbatum"), 1.0, Some(accepted)),
+            name_match(name("Acacia decurrens"), 0.8, None),
+        ];
+        let query = query(json!({ "query": "Racosperma dealbatum", "limit": 1 }));
+        let result = serde_json::to_value(query_result(&query, &matches, &HashMap::new())).unwrap();
+
+        assert_eq!(result["result"].as_array().unwrap().len(), 1);
+        assert_eq!(result["result"][0]["description"], "Synonym of Acacia dealbata");
+        assert_eq!(result["result"][0]["match"], true);
+    }
+
+    #[test]
+    fn extends_names_with_accepted_names_and_classification() {
+        let synonym = name("Racosperma dealbatum");
+        let accepted = name("Acacia dealbata");
+        let request: ExtendRequest = serde_json::from_value(json!({
+            "ids": [synonym.id],
+            "properties": [{ "id": "accepted_name" }, { "id": "family" }, { "id": "genus" }],
+        }))
+        .unwrap();
+
+        let accepted_names = HashMap::from([(accepted.id, accepted.clone())]);
+        let classifications = HashMap::from([(accepted.id, json!({ "familia": "Fabaceae" }))]);
+        let response = extend_response(&request, &[accepted.id], &accepted_names, &classifications).unwrap();
+
+        assert_eq!(response["meta"][0]["id"], "accepted_name");
+        assert_eq!(response["meta"][1], json!({ "id": "family", "name": "Family" }));
+
+        let row = &response["rows"][synonym.id.to_string()];
+        assert_eq!(row["accepted_name"], json!([{ "id": accepted.id, "name": "Acacia dealbata" }]));
+        assert_eq!(row["family"], json!([{ "str": "Fabaceae" }]));
+        assert_eq!(row["genus"], json!([]));
+    }
+
+    #[test]
+    fn rejects_unknown_extend_properties() {
+        let request: ExtendRequest = serde_json::from_value(json!({
+            "ids": [Uuid::new_v4()],
+            "properties": [{ "id": "colour" }],
+        }))
+        .unwrap();
+
+        let response = extend_response(&request, &[Uuid::new_v4()], &HashMap::new(), &HashMap::new());
+        assert!(matches!(response, Err(Error::NotFound(property)) if property == "colour"));
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use arga_core::models::LocationGeneralisation;
+use axum::Router;
+use axum::extract::{Query, State};
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use serde::Deserialize;
+
+use super::error::Error;
+use crate::database::Database;
+use crate::database::replication::ReplicatedOperation;
+use crate::http::Context;
+use crate::http::auth::ReplicationConsumer;
+
+
+const DEFAULT_LIMIT: i64 = 10_000;
+const MAX_LIMIT: i64 = 100_000;
+
+
+#[derive(Debug, Deserialize)]
+struct FeedParams {
+    /// The log sequence of the last line received. Omit to start from the beginning
+    after: Option<String>,
+    /// The maximum amount of operations to return
+    limit: Option<i64>,
+}
+
+
+pub(crate) fn router() -> Router<Context> {
+    Router::new().route("/api/replication/operations", get(operations))
+}
+
+
+/// The change feed of every operation log.
+///
+/// Operations are returned in JSON Lines in the order they were inserted into the logs. To
+/// continue the feed pass the `log_sequence` of the last line as the `after` parameter. An
+/// empty response means the consumer is up to date. Only partners with a replication access
+/// token can read the feed.
+///
+/// The latitude and longitude atoms of collection events and organisms are generalised with
+/// the same sensitive data rules used for the public. A withheld coordinate is sent without
+/// its atom so that consumers still see every operation and can keep paging.
+///
+/// The insertion order is not the causal order, an importer can add operations with hybrid
+/// timestamps older than the ones already replicated. Consumers must reduce the operations
+/// of an entity by their `operation_id` with the same last-write-wins semantics used when
+/// reducing the logs rather than applying them as they arrive.
+///
+/// Lines with the `Delete` action remove an operation from the consumer's copy of the log.
+/// They are emitted when a dataset version is rolled back and when a log is compacted. A
+/// compaction only removes superseded operations so the reduced state doesn't change.
+async fn operations(
+    _consumer: ReplicationConsumer,
+    Query(params): Query<FeedParams>,
+    State(database): State<Database>,
+) -> Result<impl IntoResponse, Error> {
+    let after = match params.after {
+        Some(after) => after
+            .parse::<i64>()
+            .map_err(|err| Error::InvalidParam("after".to_string(), err.to_string()))?,
+        None => 0,
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let mut operations = database.replication.operations_after(after, limit).await?;
+
+    let event_ids = coordinate_entities(&operations, "collection_event_logs");
+    let organism_ids = coordinate_entities(&operations, "organism_logs");
+    let rules = Rules {
+        collection_events: database.sensitive.collection_event_generalisations(&event_ids).await?,
+        organisms: database.sensitive.organism_generalisations(&organism_ids).await?,
+    };
+    for operation in operations.iter_mut() {
+        rules.generalise(operation);
+    }
+
+    let mut body = String::new();
+    for operation in operations {
+        let line = serde_json::to_string(&operation).map_err(|err| Error::Internal(err.into()))?;
+        body.push_str(&line);
+        body.push('\n');
+    }
+
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], body))
+}
+
+
+/// The entities of a log with a latitude or longitude atom in the operations
+fn coordinate_entities<'a>(operations: &'a [ReplicatedOperation], log: &str) -> Vec<&'a str> {
+    let mut entity_ids: Vec<&str> = operations
+        .iter()
+        .filter(|operation| operation.log == log && coordinate(operation).is_some())
+        .map(|operation| operation.entity_id.as_str())
+        .collect();
+
+    entity_ids.sort();
+    entity_ids.dedup();
+    entity_ids
+}
+
+/// The variant and value of a latitude or longitude atom. Atoms are externally tagged
+/// so these are an object with the variant name as the only key
+fn coordinate(operation: &ReplicatedOperation) -> Option<(&str, Option<f64>)> {
+    let (variant, value) = operation.atom.as_ref()?.as_object()?.iter().next()?;
+    match variant.as_str() {
+        "Latitude" | "Longitude" => Some((variant.as_str(), value.as_f64())),
+        _ => None,
+    }
+}
+
+
+/// The sensitive data rules of the collection events and organisms in a page of the feed
+struct Rules {
+    collection_events: HashMap<String, Option<LocationGeneralisation>>,
+    organisms: HashMap<String, Option<LocationGeneralisation>>,
+}
+
+impl Rules {
+    /// Generalise a latitude or longitude atom with the rule for its entity. Coordinates of an
+    /// entity without a known rule are withheld since the entity can't be matched to the rules
+    fn generalise(&self, operation: &mut ReplicatedOperation) {
+        let rules = match operation.log.as_str() {
+            "collection_event_logs" => &self.collection_events,
+            "organism_logs" => &self.organisms,
+            _ => return,
+        };
+        let Some((variant, value)) = coordinate(operation) else {
+            return;
+        };
+
+        let rule = match rules.get(&operation.entity_id) {
+            Some(rule) => *rule,
+            None => Some(LocationGeneralisation::Withhold),
+        };
+        let Some(rule) = rule else {
+            return;
+        };
+
+        operation.atom = match value.and_then(|value| rule.coordinate(value)) {
+            Some(value) => Some(serde_json::json!({ variant: value })),
+            None => None,
+        };
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+
+    use super::*;
+
+    fn operation(log: &str, entity_id: &str, atom: serde_json::Value) -> ReplicatedOperation {
+        ReplicatedOperation {
+            log_sequence: 1,
+            log: log.to_string(),
+            operation_id: BigDecimal::from(1),
+            parent_id: Some(BigDecimal::from(1)),
+            entity_id: entity_id.to_string(),
+            dataset_version_id: None,
+            action: "Update".to_string(),
+            atom: Some(atom),
+            reason: None,
+        }
+    }
+
+    fn rules(
+        collection_events: &[(&str, Option<LocationGeneralisation>)],
+        organisms: &[(&str, Option<LocationGeneralisation>)],
+    ) -> Rules {
+        let into_map = |rules: &[(&str, Option<LocationGeneralisation>)]| {
+            rules.iter().map(|(entity_id, rule)| (entity_id.to_string(), *rule)).collect()
+        };
+        Rules {
+            collection_events: into_map(collection_events),
+            organisms: into_map(organisms),
+        }
+    }
+
+    #[test]
+    fn generalises_sensitive_coordinates() {
+        let rules = rules(&[("event", Some(LocationGeneralisation::OneDegree))], &[]);
+        let mut latitude = operation("collection_event_logs", "event", serde_json::json!({ "Latitude": -33.8688 }));
+        let mut longitude = operation("collection_event_logs", "event", serde_json::json!({ "Longitude": 151.2093 }));
+        rules.generalise(&mut latitude);
+        rules.generalise(&mut longitude);
+
+        assert_eq!(latitude.atom, Some(serde_json::json!({ "Latitude": -34.0 })));
+        assert_eq!(longitude.atom, Some(serde_json::json!({ "Longitude": 151.0 })));
+    }
+
+    #[test]
+    fn drops_withheld_coordinates() {
+        let rules = rules(&[], &[("organism", Some(LocationGeneralisation::Withhold))]);
+        let mut latitude = operation("organism_logs", "organism", serde_json::json!({ "Latitude": -33.8688 }));
+        rules.generalise(&mut latitude);
+
+        assert_eq!(latitude.atom, None);
+    }
+
+    #[test]
+    fn withholds_coordinates_of_unknown_entities() {
+        let rules = rules(&[], &[]);
+        let mut latitude = operation("collection_event_logs", "event", serde_json::json!({ "Latitude": -33.8688 }));
+        rules.generalise(&mut latitude);
+
+        assert_eq!(latitude.atom, None);
+    }
+
+    #[test]
+    fn keeps_coordinates_without_a_rule() {
+        let rules = rules(&[("event", None)], &[]);
+        let mut latitude = operation("collection_event_logs", "event", serde_json::json!({ "Latitude": -33.8688 }));
+        rules.generalise(&mut latitude);
+
+        assert_eq!(latitude.atom, Some(serde_json::json!({ "Latitude": -33.8688 })));
+    }
+
+    #[test]
+    fn ignores_other_atoms_and_logs() {
+        let rules = rules(&[], &[]);
+        let mut locality = operation("collection_event_logs", "event", serde_json::json!({ "Locality": "Sydney" }));
+        let mut specimen = operation("specimen_logs", "specimen", serde_json::json!({ "Latitude": -33.8688 }));
+        rules.generalise(&mut locality);
+        rules.generalise(&mut specimen);
+
+        assert_eq!(locality.atom, Some(serde_json::json!({ "Locality": "Sydney" })));
+        assert_eq!(specimen.atom, Some(serde_json::json!({ "Latitude": -33.8688 })));
+    }
+
+    #[test]
+    fn finds_the_entities_with_coordinates() {
+        let operations = [
+            operation("collection_event_logs", "b", serde_json::json!({ "Latitude": 1.0 })),
+            operation("collection_event_logs", "a", serde_json::json!({ "Longitude": 1.0 })),
+            operation("collection_event_logs", "b", serde_json::json!({ "Longitude": 1.0 })),
+            operation("collection_event_logs", "c", serde_json::json!({ "Locality": "Sydney" })),
+            operation("organism_logs", "d", serde_json::json!({ "Latitude": 1.0 })),
+        ];
+
+        assert_eq!(coordinate_entities(&operations, "collection_event_logs"), vec!["a", "b"]);
+        assert_eq!(coordinate_entities(&operations, "organism_logs"), vec!["d"]);
+    }
+}
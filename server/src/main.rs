@@ -2,6 +2,7 @@ use arga_backend::database::Database;
 use arga_backend::http;
 use diesel::connection::set_default_instrumentation;
 use dotenvy::dotenv;
+use secrecy::SecretString;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::{EnvFilter, Registry};
 
@@ -42,6 +43,15 @@ async fn serve() {
     let admin_proxy = std::env::var("ADMIN_PROXY").ok();
     let admin_proxy = admin_proxy.map(|proxy| proxy.parse::<axum::http::Uri>().expect("Invalid admin proxy"));
 
+    // comma separated access tokens for partners consuming the replication feed
+    let replication_tokens = std::env::var("REPLICATION_TOKENS").unwrap_or_default();
+    let replication_tokens = replication_tokens
+        .split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(SecretString::from)
+        .collect();
+
     // show database logging if enabled
     if std::env::var("LOG_DATABASE").is_ok() {
         set_default_instrumentation(Database::simple_logger).expect("Failed to setup database instrumentation");
@@ -59,6 +69,7 @@ async fn serve() {
         bind_address,
         frontend_host,
         admin_proxy,
+        replication_tokens,
     };
 
     http::serve(config, database).await.expect("Failed to start server");
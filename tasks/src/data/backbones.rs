@@ -0,0 +1,152 @@
+use arga_core::models::{SourceBackbone, TaxonomicBackbone};
+use arga_core::schema;
+use chrono::Utc;
+use diesel::*;
+use tracing::info;
+use uuid::Uuid;
+
+use super::{Error, ParseError};
+use crate::data::oplogger::{find_database_id, get_pool};
+
+
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Register a taxonomy dataset as a backbone
+    Add {
+        /// The global id of the taxonomy dataset
+        dataset: String,
+
+        /// Use the backbone when a request or source doesn't specify one
+        #[arg(long)]
+        default: bool,
+    },
+
+    /// Remove a backbone from the registry
+    Remove {
+        /// The global id of the taxonomy dataset
+        dataset: String,
+    },
+
+    /// Use a backbone by default when querying the datasets of a source
+    Assign {
+        /// The name of the source
+        source: String,
+
+        /// The global id of the registered taxonomy dataset
+        dataset: String,
+    },
+
+    /// List the registered backbones
+    List,
+}
+
+pub fn process_command(command: &Command) {
+    match command {
+        Command::Add { dataset, default } => add(dataset, *default).unwrap(),
+        Command::Remove { dataset } => remove(dataset).unwrap(),
+        Command::Assign { source, dataset } => assign(source, dataset).unwrap(),
+        Command::List => list().unwrap(),
+    }
+}
+
+
+fn add(dataset: &str, default: bool) -> Result<(), Error> {
+    use schema::taxonomic_backbones::dsl::*;
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+    let id = find_database_id(dataset)?;
+
+    conn.transaction(|conn| {
+        // only one backbone can be the default
+        if default {
+            diesel::update(taxonomic_backbones.filter(is_default.eq(true)))
+                .set(is_default.eq(false))
+                .execute(conn)?;
+        }
+
+        diesel::insert_into(taxonomic_backbones)
+            .values(TaxonomicBackbone {
+                dataset_id: id,
+                is_default: default,
+                created_at: Utc::now(),
+            })
+            .on_conflict(dataset_id)
+            .do_update()
+            .set(is_default.eq(default))
+            .execute(conn)?;
+
+        Ok::<(), Error>(())
+    })?;
+
+    info!(dataset, default, "Taxonomic backbone registered");
+    Ok(())
+}
+
+fn remove(dataset: &str) -> Result<(), Error> {
+    use schema::taxonomic_backbones::dsl::*;
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+    let id = find_database_id(dataset)?;
+
+    let removed = diesel::delete(taxonomic_backbones.filter(dataset_id.eq(id))).execute(&mut conn)?;
+    if removed == 0 {
+        return Err(ParseError::NotFound(dataset.to_string()).into());
+    }
+
+    info!(dataset, "Taxonomic backbone removed");
+    Ok(())
+}
+
+fn assign(source: &str, dataset: &str) -> Result<(), Error> {
+    use schema::{source_backbones, sources, taxonomic_backbones};
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    let source_id = sources::table
+        .filter(sources::name.eq(source))
+        .select(sources::id)
+        .get_result::<Uuid>(&mut conn)
+        .optional()?
+        .ok_or_else(|| ParseError::NotFound(source.to_string()))?;
+
+    let backbone_id = taxonomic_backbones::table
+        .filter(taxonomic_backbones::dataset_id.eq(find_database_id(dataset)?))
+        .select(taxonomic_backbones::dataset_id)
+        .get_result::<Uuid>(&mut conn)
+        .optional()?
+        .ok_or_else(|| ParseError::NotFound(dataset.to_string()))?;
+
+    diesel::insert_into(source_backbones::table)
+        .values(SourceBackbone { source_id, backbone_id })
+        .on_conflict(source_backbones::source_id)
+        .do_update()
+        .set(source_backbones::backbone_id.eq(backbone_id))
+        .execute(&mut conn)?;
+
+    info!(source, dataset, "Source backbone assigned");
+    Ok(())
+}
+
+fn list() -> Result<(), Error> {
+    use schema::{datasets, taxonomic_backbones};
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    let backbones = taxonomic_backbones::table
+        .inner_join(datasets::table)
+        .select((datasets::global_id, datasets::name, taxonomic_backbones::is_default))
+        .order_by(datasets::name)
+        .load::<(String, String, bool)>(&mut conn)?;
+
+    for (global_id, name, default) in backbones {
+        match default {
+            true => println!("{global_id}\t{name}\t(default)"),
+            false => println!("{global_id}\t{name}"),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,302 @@
+//! A minimal reader for ABIF (Applied Biosystems) trace files.
+//!
+//! Only the parts of the format needed to extract the analyzed trace channels and the
+//! base calls are supported. An ABIF file starts with the `ABIF` magic and a version number followed
+//! by a directory entry pointing to the list of all tagged data items in the file.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("not an ABIF file")]
+    InvalidMagic,
+
+    #[error("entry {0} points outside of the file")]
+    OutOfBounds(String),
+}
+
+
+/// A tagged data item in the directory.
+///
+/// Entries are identified by a four character name and a number, for example
+/// `DATA9` is the analyzed data for the first channel.
+#[derive(Debug, Clone)]
+struct Entry {
+    element_size: usize,
+    num_elements: usize,
+    data: Vec<u8>,
+}
+
+/// A parsed ABIF file
+#[derive(Debug, Clone, Default)]
+pub struct Abif {
+    entries: HashMap<(String, i32), Entry>,
+}
+
+impl Abif {
+    pub fn read<R: Read>(mut reader: R) -> Result<Abif, Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Abif::parse(&bytes)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Abif, Error> {
+        if bytes.get(0..4) != Some(b"ABIF".as_slice()) {
+            return Err(Error::InvalidMagic);
+        }
+
+        // the root directory entry is stored right after the magic and version
+        let root = bytes.get(6..34).ok_or_else(|| Error::OutOfBounds("root".to_string()))?;
+        let num_elements = to_usize(be_i32(&root[12..16]), "root")?;
+        let offset = to_usize(be_i32(&root[20..24]), "root")?;
+
+        let mut entries = HashMap::new();
+
+        for idx in 0..num_elements {
+            let entry = idx
+                .checked_mul(28)
+                .and_then(|pos| offset.checked_add(pos))
+                .and_then(|start| bytes.get(start..start.checked_add(28)?))
+                .ok_or_else(|| Error::OutOfBounds("directory".to_string()))?;
+
+            let name = String::from_utf8_lossy(&entry[0..4]).to_string();
+            let number = be_i32(&entry[4..8]);
+            let tag = format!("{name}{number}");
+
+            let element_size = to_usize(be_i16(&entry[10..12]).into(), &tag)?;
+            let num_elements = to_usize(be_i32(&entry[12..16]), &tag)?;
+            let data_size = to_usize(be_i32(&entry[16..20]), &tag)?;
+
+            // data of four bytes or less is stored in the offset field itself
+            let data = if data_size <= 4 {
+                entry[20..20 + data_size].to_vec()
+            }
+            else {
+                let data_offset = to_usize(be_i32(&entry[20..24]), &tag)?;
+                data_offset
+                    .checked_add(data_size)
+                    .and_then(|end| bytes.get(data_offset..end))
+                    .ok_or_else(|| Error::OutOfBounds(tag.clone()))?
+                    .to_vec()
+            };
+
+            entries.insert(
+                (name, number),
+                Entry {
+                    element_size,
+                    num_elements,
+                    data,
+                },
+            );
+        }
+
+        Ok(Abif { entries })
+    }
+
+    /// Get the raw bytes of a tagged entry
+    pub fn bytes(&self, name: &str, number: i32) -> Option<&[u8]> {
+        self.entries
+            .get(&(name.to_string(), number))
+            .map(|entry| entry.data.as_slice())
+    }
+
+    /// Get a tagged entry as an array of big endian shorts
+    pub fn shorts(&self, name: &str, number: i32) -> Option<Vec<i16>> {
+        let entry = self.entries.get(&(name.to_string(), number))?;
+        if entry.element_size != 2 {
+            return None;
+        }
+
+        Some(
+            entry
+                .data
+                .chunks_exact(2)
+                .take(entry.num_elements)
+                .map(be_i16)
+                .collect(),
+        )
+    }
+
+    /// The order of the bases in the analyzed data channels. Defaults to GATC
+    pub fn base_order(&self) -> String {
+        self.bytes("FWO_", 1)
+            .map(|bytes| String::from_utf8_lossy(bytes).to_string())
+            .unwrap_or_else(|| "GATC".to_string())
+    }
+
+    /// The base calls, preferring the edited calls in PBAS2 over the ones made by the basecaller
+    pub fn base_calls(&self) -> Option<String> {
+        self.bytes("PBAS", 2)
+            .or_else(|| self.bytes("PBAS", 1))
+            .map(|bytes| String::from_utf8_lossy(bytes).to_string())
+    }
+
+    /// The phred quality score of each base call, stored as a char array in PCON2
+    pub fn quality_scores(&self) -> Option<Vec<i16>> {
+        self.bytes("PCON", 2)
+            .map(|bytes| bytes.iter().map(|&score| score as i16).collect())
+    }
+
+    /// The scan number of the peak of each base call, preferring the edited locations in PLOC2
+    pub fn peak_locations(&self) -> Option<Vec<i16>> {
+        self.shorts("PLOC", 2).or_else(|| self.shorts("PLOC", 1))
+    }
+
+    pub fn a_analyzed(&self) -> Option<Vec<i16>> {
+        self.analyzed('A')
+    }
+
+    pub fn c_analyzed(&self) -> Option<Vec<i16>> {
+        self.analyzed('C')
+    }
+
+    pub fn g_analyzed(&self) -> Option<Vec<i16>> {
+        self.analyzed('G')
+    }
+
+    pub fn t_analyzed(&self) -> Option<Vec<i16>> {
+        self.analyzed('T')
+    }
+
+    /// The analyzed data for a base, stored in DATA9 to DATA12 in the base order
+    fn analyzed(&self, base: char) -> Option<Vec<i16>> {
+        let channel = self.base_order().chars().position(|b| b == base)?;
+        self.shorts("DATA", 9 + channel as i32)
+    }
+}
+
+
+/// Sizes and offsets are stored as signed integers, anything negative is a corrupt file
+fn to_usize(value: i32, tag: &str) -> Result<usize, Error> {
+    usize::try_from(value).map_err(|_| Error::OutOfBounds(tag.to_string()))
+}
+
+fn be_i16(bytes: &[u8]) -> i16 {
+    i16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+fn be_i32(bytes: &[u8]) -> i32 {
+    i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build an ABIF file with the entries stored after the header and the directory at the end
+    fn abif(entries: &[(&str, i32, i16, &[u8])]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut directory = Vec::new();
+
+        for (name, number, element_size, bytes) in entries {
+            let num_elements = bytes.len() as i32 / *element_size as i32;
+
+            directory.extend_from_slice(name.as_bytes());
+            directory.extend_from_slice(&number.to_be_bytes());
+            directory.extend_from_slice(&2i16.to_be_bytes());
+            directory.extend_from_slice(&element_size.to_be_bytes());
+            directory.extend_from_slice(&num_elements.to_be_bytes());
+            directory.extend_from_slice(&(bytes.len() as i32).to_be_bytes());
+
+            if bytes.len() <= 4 {
+                let mut inline = bytes.to_vec();
+                inline.resize(4, 0);
+                directory.extend_from_slice(&inline);
+            }
+            else {
+                directory.extend_from_slice(&(128 + data.len() as i32).to_be_bytes());
+                data.extend_from_slice(bytes);
+            }
+            directory.extend_from_slice(&0i32.to_be_bytes());
+        }
+
+        let mut file = Vec::new();
+        file.extend_from_slice(b"ABIF");
+        file.extend_from_slice(&101i16.to_be_bytes());
+        root(&mut file, entries.len() as i32, 128 + data.len() as i32);
+        file.resize(128, 0);
+        file.extend_from_slice(&data);
+        file.extend_from_slice(&directory);
+        file
+    }
+
+    /// The directory entry pointing to the list of tagged entries
+    fn root(file: &mut Vec<u8>, num_elements: i32, offset: i32) {
+        file.extend_from_slice(b"tdir");
+        file.extend_from_slice(&1i32.to_be_bytes());
+        file.extend_from_slice(&1023i16.to_be_bytes());
+        file.extend_from_slice(&28i16.to_be_bytes());
+        file.extend_from_slice(&num_elements.to_be_bytes());
+        file.extend_from_slice(&(num_elements * 28).to_be_bytes());
+        file.extend_from_slice(&offset.to_be_bytes());
+        file.extend_from_slice(&0i32.to_be_bytes());
+    }
+
+    fn shorts(values: &[i16]) -> Vec<u8> {
+        values.iter().flat_map(|value| value.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn parses_inline_and_offset_entries() {
+        let data = shorts(&[1, -2, 300]);
+        let file = abif(&[("FWO_", 1, 1, b"ACGT"), ("DATA", 9, 2, &data)]);
+        let abif = Abif::parse(&file).unwrap();
+
+        assert_eq!(abif.bytes("FWO_", 1), Some(b"ACGT".as_slice()));
+        assert_eq!(abif.shorts("DATA", 9), Some(vec![1, -2, 300]));
+        assert_eq!(abif.bytes("DATA", 10), None);
+    }
+
+    #[test]
+    fn rejects_files_without_the_magic() {
+        assert!(matches!(Abif::parse(b""), Err(Error::InvalidMagic)));
+        assert!(matches!(Abif::parse(b"FIBA0000"), Err(Error::InvalidMagic)));
+    }
+
+    #[test]
+    fn rejects_a_truncated_header() {
+        let file = abif(&[]);
+        assert!(matches!(Abif::parse(&file[0..20]), Err(Error::OutOfBounds(_))));
+    }
+
+    #[test]
+    fn rejects_a_truncated_directory() {
+        let file = abif(&[("PBAS", 1, 1, b"ACGTACGT")]);
+        assert!(matches!(Abif::parse(&file[..file.len() - 1]), Err(Error::OutOfBounds(tag)) if tag == "directory"));
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let mut file = abif(&[("PBAS", 1, 1, b"ACGTACGT")]);
+        // move the data past the end of the file
+        let offset = file.len() - 8;
+        file[offset..offset + 4].copy_from_slice(&(i32::MAX - 4).to_be_bytes());
+        assert!(matches!(Abif::parse(&file), Err(Error::OutOfBounds(tag)) if tag == "PBAS1"));
+    }
+
+    #[test]
+    fn rejects_negative_sizes() {
+        let mut file = abif(&[("PBAS", 1, 1, b"ACGTACGT")]);
+        let size = file.len() - 12;
+        file[size..size + 4].copy_from_slice(&(-1i32).to_be_bytes());
+        assert!(matches!(Abif::parse(&file), Err(Error::OutOfBounds(tag)) if tag == "PBAS1"));
+
+        let mut file = abif(&[]);
+        file[18..22].copy_from_slice(&(-1i32).to_be_bytes());
+        assert!(matches!(Abif::parse(&file), Err(Error::OutOfBounds(tag)) if tag == "root"));
+    }
+
+    #[test]
+    fn rejects_directories_past_the_end_of_the_file() {
+        let mut file = abif(&[]);
+        file[18..22].copy_from_slice(&i32::MAX.to_be_bytes());
+        file[26..30].copy_from_slice(&i32::MAX.to_be_bytes());
+        assert!(matches!(Abif::parse(&file), Err(Error::OutOfBounds(tag)) if tag == "directory"));
+    }
+}
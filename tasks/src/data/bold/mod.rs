@@ -1,3 +1,4 @@
+pub mod abif;
 pub mod traces;
 
 use std::path::PathBuf;
@@ -19,7 +20,15 @@ pub enum Command {
         input: String,
         /// The path to the directory containing the downloaded trace files
         traces: String,
-    }
+    },
+
+    /// Import the chromatograms, base calls and quality scores of trace files into the database
+    ImportTraces {
+        /// The BOLD csv file with trace links
+        input: String,
+        /// The path to the directory containing the downloaded trace files
+        traces: String,
+    },
 }
 
 pub fn process_command(command: &Command) {
@@ -32,5 +41,9 @@ pub fn process_command(command: &Command) {
             PathBuf::from(input),
             PathBuf::from(traces),
         ).unwrap(),
+        Command::ImportTraces { input, traces } => traces::import(
+            PathBuf::from(input),
+            PathBuf::from(traces),
+        ).unwrap(),
     }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::{path::PathBuf, fs::File};
 use std::io::prelude::*;
 
@@ -10,7 +10,9 @@ use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use tracing::{info, error};
 
+use arga_core::models::SequenceTrace;
 use arga_core::schema;
+use super::abif;
 use crate::data::Error;
 use crate::data::ncbi::biosamples::{Progress, MultiProgressIterator};
 
@@ -48,6 +50,9 @@ struct Abif {
     c_analyzed: Option<String>,
     g_analyzed: Option<String>,
     t_analyzed: Option<String>,
+    base_calls: Option<String>,
+    quality_scores: Option<String>,
+    peak_locations: Option<String>,
 }
 
 impl From<abif::Abif> for Abif {
@@ -57,10 +62,32 @@ impl From<abif::Abif> for Abif {
             c_analyzed: value.c_analyzed().map(|arr| arr.iter().map(|&v| v.to_string()).collect::<Vec<String>>().join(",")),
             g_analyzed: value.g_analyzed().map(|arr| arr.iter().map(|&v| v.to_string()).collect::<Vec<String>>().join(",")),
             t_analyzed: value.t_analyzed().map(|arr| arr.iter().map(|&v| v.to_string()).collect::<Vec<String>>().join(",")),
+            base_calls: value.base_calls(),
+            quality_scores: value.quality_scores().map(|arr| arr.iter().map(|&v| v.to_string()).collect::<Vec<String>>().join(",")),
+            peak_locations: value.peak_locations().map(|arr| arr.iter().map(|&v| v.to_string()).collect::<Vec<String>>().join(",")),
         }
     }
 }
 
+fn sequence_trace(trace: &Trace, abif: abif::Abif, sequence_run_id: Option<String>) -> SequenceTrace {
+    let array = |values: Option<Vec<i16>>| values.map(|arr| arr.into_iter().map(Some).collect());
+
+    SequenceTrace {
+        trace_id: trace.id.clone(),
+        process_id: trace.processid.clone(),
+        sequence_run_id,
+        trace_name: trace.name.clone(),
+        trace_link: trace.link.clone(),
+        a_analyzed: array(abif.a_analyzed()),
+        c_analyzed: array(abif.c_analyzed()),
+        g_analyzed: array(abif.g_analyzed()),
+        t_analyzed: array(abif.t_analyzed()),
+        base_calls: abif.base_calls(),
+        quality_scores: array(abif.quality_scores()),
+        peak_locations: array(abif.peak_locations()),
+    }
+}
+
 
 pub fn name_map(pool: &mut PgPool) -> Result<NameMap, Error> {
     use schema::names::dsl::*;
@@ -196,6 +223,90 @@ pub fn process(input: PathBuf, dir: PathBuf) -> Result<(), Error> {
 }
 
 
+/// Parse the trace files and import the chromatograms into the database.
+///
+/// Traces are linked to the sequence run with the same BOLD process id as its run id. Traces
+/// that are imported before their runs aren't linked and need to be imported again.
+pub fn import(input: PathBuf, dir: PathBuf) -> Result<(), Error> {
+    info!(?input, ?dir, "Importing all trace files");
+
+    let traces = extract_traces(&input)?;
+
+    let url = arga_core::get_database_url();
+    let manager = ConnectionManager::<PgConnection>::new(url);
+    let pool = Pool::builder().build(manager)?;
+    let mut conn = pool.get()?;
+
+    let runs = sequence_run_map(&mut conn, &traces)?;
+
+    let bars = Progress::new();
+    let parse_bar = bars.add("Parsing", traces.len());
+    let mut total = 0;
+    let mut unlinked = 0;
+
+    for chunk in traces.chunks(10_000) {
+        let results: Vec<SequenceTrace> = chunk
+            .into_par_iter()
+            .progress_with(parse_bar.clone())
+            .filter_map(|trace| match parse_trace(trace, &dir) {
+                Ok(abif) => Some(sequence_trace(trace, abif, runs.get(&trace.processid).cloned())),
+                Err(err) => {
+                    error!(?err, ?trace, "Failed to parse trace file");
+                    None
+                }
+            })
+            .collect();
+
+        total += results.len();
+        unlinked += results.iter().filter(|trace| trace.sequence_run_id.is_none()).count();
+
+        for batch in results.chunks(500) {
+            insert_traces(&mut conn, batch)?;
+        }
+    }
+
+    info!(total, unlinked, "Importing trace files finished");
+    Ok(())
+}
+
+/// Replace the traces so that reimporting a trace updates it
+fn insert_traces(conn: &mut PgConnection, traces: &[SequenceTrace]) -> Result<(), Error> {
+    use schema::sequence_traces::dsl::*;
+
+    let ids: Vec<&String> = traces.iter().map(|trace| &trace.trace_id).collect();
+
+    conn.transaction(|conn| {
+        diesel::delete(sequence_traces.filter(trace_id.eq_any(ids))).execute(conn)?;
+        diesel::insert_into(sequence_traces).values(traces).execute(conn)?;
+        Ok::<(), Error>(())
+    })
+}
+
+/// Get the entity id of the sequence runs for every process id in the traces
+fn sequence_run_map(conn: &mut PgConnection, traces: &[Trace]) -> Result<HashMap<String, String>, Error> {
+    use schema::sequence_runs::dsl::*;
+
+    let process_ids: Vec<String> = traces
+        .iter()
+        .map(|trace| trace.processid.clone())
+        .collect::<HashSet<String>>()
+        .into_iter()
+        .collect();
+
+    let mut map = HashMap::new();
+    for chunk in process_ids.chunks(10_000) {
+        let runs = sequence_runs
+            .filter(sequence_run_id.eq_any(chunk))
+            .select((sequence_run_id, entity_id))
+            .load::<(String, String)>(conn)?;
+        map.extend(runs);
+    }
+
+    info!(runs = map.len(), processes = process_ids.len(), "Sequence runs linked to traces");
+    Ok(map)
+}
+
+
 fn parse_trace(trace: &Trace, dir: &PathBuf) -> Result<abif::Abif, Error> {
     let path = dir.join(&trace.processid).join(&trace.id).join(&trace.name);
     let file = File::open(path)?;
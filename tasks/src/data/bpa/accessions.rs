@@ -1,7 +1,9 @@
-use std::path::PathBuf;
+use std::path::Path;
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use super::validation::Validator;
 use crate::data::Error;
 
 
@@ -18,6 +20,8 @@ struct Record {
     specimen_id: Option<String>,
 
     sample_id: Option<String>,
+    scientific_name: Option<String>,
+    species_name: Option<String>,
 
     type_status: Option<String>,
     institution_name: Option<String>,
@@ -28,15 +32,16 @@ struct Record {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct AccessionEvent {
-    id: String,
-    specimen_id: String,
-    accession: String,
-    material_sample_ids: String,
-    type_status: Option<String>,
-    institution_code: Option<String>,
-    event_date: Option<String>,
-    accessioned_by: Option<String>,
-    quality: Option<String>,
+    pub id: String,
+    pub specimen_id: String,
+    pub accession: String,
+    pub material_sample_ids: String,
+    pub scientific_name: Option<String>,
+    pub type_status: Option<String>,
+    pub institution_code: Option<String>,
+    pub event_date: Option<NaiveDate>,
+    pub accessioned_by: Option<String>,
+    pub quality: Option<String>,
 }
 
 impl AccessionEvent {
@@ -49,10 +54,21 @@ impl AccessionEvent {
     }
 }
 
-pub fn normalise(path: &PathBuf) -> Result<(), Error> {
-    let mut reader = csv::Reader::from_path(&path)?;
+pub fn normalise(path: &Path, validator: &mut Validator) -> Result<(), Error> {
     let mut writer = csv::Writer::from_path("accessions.csv")?;
 
+    for event in read(path, validator)? {
+        writer.serialize(event)?;
+    }
+
+    Ok(())
+}
+
+/// Read and validate the accession events in a BPA csv file
+pub fn read(path: &Path, validator: &mut Validator) -> Result<Vec<AccessionEvent>, Error> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut events = Vec::new();
+
     for row in reader.deserialize() {
         let record: Record = row?;
 
@@ -64,6 +80,7 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
             .unwrap_or(record.id.clone());
 
         let accession = specimen_id.clone();
+        let mut check = validator.record("accessions", &record.id, &specimen_id);
 
         // let accession = record.voucher_herbarium_catalog_number.as_ref()
         //     .or(record.voucher_herbarium_record_number.as_ref())
@@ -87,17 +104,18 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
             specimen_id,
             accession,
             material_sample_ids: vouchers.join(" | ").to_string(),
+            scientific_name: record.scientific_name.or(record.species_name),
             type_status: record.type_status,
             institution_code: record.institution_name,
-            event_date: record.voucher_herbarium_event_date,
+            event_date: check.date("voucher_herbarium_event_date", &record.voucher_herbarium_event_date),
             accessioned_by: record.voucher_herbarium_recorded_by,
             quality: record.sample_quality,
         };
 
         if event.has_data() {
-            writer.serialize(event)?;
+            events.push(event);
         }
     }
 
-    Ok(())
+    Ok(events)
 }
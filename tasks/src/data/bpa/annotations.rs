@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 
@@ -31,7 +31,7 @@ impl AnnotationEvent {
     }
 }
 
-pub fn normalise(path: &PathBuf) -> Result<(), Error> {
+pub fn normalise(path: &Path) -> Result<(), Error> {
     let mut reader = csv::Reader::from_path(&path)?;
     let mut writer = csv::Writer::from_path("annotations.csv")?;
 
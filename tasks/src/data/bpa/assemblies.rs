@@ -1,7 +1,9 @@
-use std::path::PathBuf;
+use std::path::Path;
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use super::validation::Validator;
 use crate::data::Error;
 
 
@@ -22,7 +24,7 @@ struct Record {
 pub struct AssemblyEvent {
     id: String,
     sequence_id: String,
-    event_date: Option<String>,
+    event_date: Option<NaiveDate>,
     assembly_type: Option<String>,
     version_status: Option<String>,
     name: Option<String>,
@@ -39,7 +41,7 @@ impl AssemblyEvent {
     }
 }
 
-pub fn normalise(path: &PathBuf) -> Result<(), Error> {
+pub fn normalise(path: &Path, validator: &mut Validator) -> Result<(), Error> {
     let mut reader = csv::Reader::from_path(&path)?;
     let mut writer = csv::Writer::from_path("assemblies.csv")?;
 
@@ -53,10 +55,11 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
 
         let version_status = record.assembly_method_version.or(record.assembly_method_version_or_date);
 
+        let mut check = validator.record("assemblies", &record.id, &sequence_id);
         let event = AssemblyEvent {
             id: record.id,
             sequence_id,
-            event_date: record.assembly_date,
+            event_date: check.date("assembly_date", &record.assembly_date),
             assembly_type: record.assembly_method,
             version_status,
             name: record.assembly_name,
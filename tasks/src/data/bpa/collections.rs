@@ -1,7 +1,9 @@
-use std::path::PathBuf;
+use std::path::Path;
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use super::validation::{Location, Validator};
 use crate::data::Error;
 
 
@@ -92,77 +94,88 @@ struct Record {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct CollectionEvent {
-    id: String,
-    record_id: String,
-    material_sample_id: Option<String>,
-    r#type: Option<String>,
-    record_title_text: Option<String>,
-    sex: Option<String>,
-    genotypic_sex: Option<String>,
-    phenotypic_sex: Option<String>,
-    life_stage: Option<String>,
-    degree_of_establishment: Option<String>,
-    organism_id: Option<String>,
-    organism_remarks: Option<String>,
-    field_number: Option<String>,
-    collection_date: Option<String>,
-    habitat: Option<String>,
-    event_remarks: Option<String>,
-    location: Option<String>,
-    location_id: Option<String>,
-    country: Option<String>,
-    state: Option<String>,
-    location_text: Option<String>,
-    geo_loc_name: Option<String>,
-    elevation: Option<f64>,
-    depth: Option<f64>,
-    minimum_depth_in_meters: Option<f64>,
-    maximum_depth_in_meters: Option<f64>,
-    location_notes: Option<String>,
-    latitude: Option<f64>,
-    longitude: Option<f64>,
-    coordinate_uncertainty_in_metres: Option<String>,
-    lat_lon: Option<String>,
-    location_info_restricted: Option<String>,
-    location_generalisation: Option<String>,
-    type_status: Option<String>,
-    identified_by: Option<String>,
-    date_identified: Option<String>,
-    taxon_id: Option<String>,
-    scientific_name: Option<String>,
-    kingdom: Option<String>,
-    phylum: Option<String>,
-    class: Option<String>,
-    order: Option<String>,
-    family: Option<String>,
-    genus: Option<String>,
-    infraspecicific_epithet: Option<String>,
-    scientific_name_authorship: Option<String>,
-    vernacular_name: Option<String>,
-    taxon_remarks: Option<String>,
-    subspecies: Option<String>,
-    env_broad_scale: Option<String>,
-    env_local_scale: Option<String>,
-    env_medium: Option<String>,
-    isolate: Option<String>,
-    host_common_name: Option<String>,
-    host_family: Option<String>,
-    host_scientific_name: Option<String>,
-    host_location: Option<String>,
-    host_age: Option<String>,
-    host_sex: Option<String>,
-    host_state: Option<String>,
-    host_disease_outcome: Option<String>,
-    nagoya_protocol_compliance: Option<String>,
-    nagoya_protocol_permit_number: Option<String>,
-    collection_permit: Option<String>,
-    collected_by: Option<String>,
+    pub id: String,
+    pub record_id: String,
+    pub material_sample_id: Option<String>,
+    pub r#type: Option<String>,
+    pub record_title_text: Option<String>,
+    pub sex: Option<String>,
+    pub genotypic_sex: Option<String>,
+    pub phenotypic_sex: Option<String>,
+    pub life_stage: Option<String>,
+    pub degree_of_establishment: Option<String>,
+    pub organism_id: Option<String>,
+    pub organism_remarks: Option<String>,
+    pub field_number: Option<String>,
+    pub collection_date: Option<NaiveDate>,
+    pub habitat: Option<String>,
+    pub event_remarks: Option<String>,
+    pub location: Option<String>,
+    pub location_id: Option<String>,
+    pub country: Option<String>,
+    pub state: Option<String>,
+    pub location_text: Option<String>,
+    pub geo_loc_name: Option<String>,
+    pub elevation: Option<f64>,
+    pub depth: Option<f64>,
+    pub minimum_depth_in_meters: Option<f64>,
+    pub maximum_depth_in_meters: Option<f64>,
+    pub location_notes: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub coordinate_uncertainty_in_metres: Option<String>,
+    pub lat_lon: Option<String>,
+    pub location_info_restricted: Option<String>,
+    pub location_generalisation: Option<String>,
+    pub type_status: Option<String>,
+    pub identified_by: Option<String>,
+    pub date_identified: Option<NaiveDate>,
+    pub taxon_id: Option<String>,
+    pub scientific_name: Option<String>,
+    pub kingdom: Option<String>,
+    pub phylum: Option<String>,
+    pub class: Option<String>,
+    pub order: Option<String>,
+    pub family: Option<String>,
+    pub genus: Option<String>,
+    pub infraspecicific_epithet: Option<String>,
+    pub scientific_name_authorship: Option<String>,
+    pub vernacular_name: Option<String>,
+    pub taxon_remarks: Option<String>,
+    pub subspecies: Option<String>,
+    pub env_broad_scale: Option<String>,
+    pub env_local_scale: Option<String>,
+    pub env_medium: Option<String>,
+    pub isolate: Option<String>,
+    pub host_common_name: Option<String>,
+    pub host_family: Option<String>,
+    pub host_scientific_name: Option<String>,
+    pub host_location: Option<String>,
+    pub host_age: Option<String>,
+    pub host_sex: Option<String>,
+    pub host_state: Option<String>,
+    pub host_disease_outcome: Option<String>,
+    pub nagoya_protocol_compliance: Option<String>,
+    pub nagoya_protocol_permit_number: Option<String>,
+    pub collection_permit: Option<String>,
+    pub collected_by: Option<String>,
 }
 
-pub fn normalise(path: &PathBuf) -> Result<(), Error> {
-    let mut reader = csv::Reader::from_path(&path)?;
+pub fn normalise(path: &Path, validator: &mut Validator) -> Result<(), Error> {
     let mut writer = csv::Writer::from_path("collections.csv")?;
 
+    for event in read(path, validator)? {
+        writer.serialize(event)?;
+    }
+
+    Ok(())
+}
+
+/// Read and validate the collection events in a BPA csv file
+pub fn read(path: &Path, validator: &mut Validator) -> Result<Vec<CollectionEvent>, Error> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut events = Vec::new();
+
     for row in reader.deserialize() {
         let record: Record = row?;
 
@@ -180,8 +193,6 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
         let collection_date = record.collection_date.or(record.living_collections_event_date);
         let state = record.state_or_territory.or(record.state_or_region);
         let location_notes = record.location_notes.or(record.sample_site_location_description);
-        let latitude = record.decimal_latitude_public.or(record.latitude);
-        let longitude = record.decimal_longitude_public.or(record.longitude);
         let identified_by = record.identified_by.or(record.id_vetting_by);
         let scientific_name = record.scientific_name.or(record.species_name);
         let taxon_remarks = record.scientific_name_notes.or(record.species_complex);
@@ -195,6 +206,16 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
         // let material_sample_id = record.specimen_id.or(record.tissue_number).or(record.voucher_or_tissue_number);
         // let record_id = material_sample_id.clone().or(record.sample_id).unwrap_or(record.id.clone());
 
+        let mut check = validator.record("collections", &record.id, &record_id);
+        let location = Location {
+            latitude_public: record.decimal_latitude_public,
+            longitude_public: record.decimal_longitude_public,
+            latitude: record.latitude,
+            longitude: record.longitude,
+            restricted: record.location_info_restricted.clone(),
+        };
+        let coordinates = check.coordinates(&location);
+
         let event = CollectionEvent {
             id: record.id.clone(),
             record_id,
@@ -209,7 +230,7 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
             organism_id: None,
             organism_remarks: record.ancillary_notes,
             field_number: record.collector_sample_id,
-            collection_date,
+            collection_date: check.date("collection_date", &collection_date),
             habitat: record.habitat,
             event_remarks: record.collection_method,
             location: record.collection_location,
@@ -218,20 +239,21 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
             state,
             location_text: record.location_text,
             geo_loc_name: record.geo_loc_name,
-            elevation: parse_f64(record.elev),
-            depth: parse_f64(record.depth),
-            minimum_depth_in_meters: parse_f64(record.depth_upper),
-            maximum_depth_in_meters: parse_f64(record.depth_lower),
+            elevation: check.number("elev", &record.elev),
+            depth: check.number("depth", &record.depth),
+            minimum_depth_in_meters: check.number("depth_upper", &record.depth_upper),
+            maximum_depth_in_meters: check.number("depth_lower", &record.depth_lower),
             location_notes,
-            latitude: parse_f64(latitude),
-            longitude: parse_f64(longitude),
+            latitude: coordinates.map(|(latitude, _)| latitude),
+            longitude: coordinates.map(|(_, longitude)| longitude),
             coordinate_uncertainty_in_metres: record.coord_uncertainty_metres,
-            lat_lon: record.lat_lon,
+            // the combined field can have the precise location so only keep it when unrestricted
+            lat_lon: record.lat_lon.filter(|_| !location.is_restricted()),
             location_info_restricted: record.location_info_restricted,
             location_generalisation: record.location_generalisation,
             type_status: record.type_status,
             identified_by,
-            date_identified: record.id_vetting_date,
+            date_identified: check.date("id_vetting_date", &record.id_vetting_date),
             taxon_id: record.taxon_id,
             scientific_name,
             kingdom: record.kingdom,
@@ -263,16 +285,8 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
             collected_by,
         };
 
-        writer.serialize(event)?;
+        events.push(event);
     }
 
-    Ok(())
-}
-
-
-fn parse_f64(value: Option<String>) -> Option<f64> {
-    match value {
-        Some(v) => str::parse::<f64>(&v).ok(),
-        None => None,
-    }
+    Ok(events)
 }
@@ -1,7 +1,9 @@
-use std::path::PathBuf;
+use std::path::Path;
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use super::validation::Validator;
 use crate::data::Error;
 
 
@@ -43,7 +45,7 @@ struct Record {
 pub struct DepositionEvent {
     id: String,
     sequence_id: String,
-    event_date: Option<String>,
+    event_date: Option<NaiveDate>,
     material_sample_id: Option<String>,
     rights_holder: Option<String>,
     access_rights: Option<String>,
@@ -80,7 +82,7 @@ impl DepositionEvent {
 }
 
 
-pub fn normalise(path: &PathBuf) -> Result<(), Error> {
+pub fn normalise(path: &Path, validator: &mut Validator) -> Result<(), Error> {
     let mut reader = csv::Reader::from_path(&path)?;
     let mut writer = csv::Writer::from_path("depositions.csv")?;
 
@@ -104,10 +106,11 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
             None => None,
         };
 
+        let mut check = validator.record("depositions", &record.id, &sequence_id);
         let event = DepositionEvent {
             id: record.id,
             sequence_id,
-            event_date,
+            event_date: check.date("date_submission", &event_date),
             material_sample_id: record.bpa_sample_id,
             rights_holder: record.owner_org,
             access_rights: record.access_rights,
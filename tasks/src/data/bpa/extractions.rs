@@ -1,7 +1,9 @@
-use std::path::PathBuf;
+use std::path::Path;
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use super::validation::Validator;
 use crate::data::Error;
 
 
@@ -17,6 +19,8 @@ struct Record {
     voucher_herbarium_catalog_number: Option<String>,
     sample_id: Option<String>,
     specimen_id: Option<String>,
+    scientific_name: Option<String>,
+    species_name: Option<String>,
 
     dna_extraction_date: Option<String>,
     genomic_material_preparation_date: Option<String>,
@@ -46,25 +50,37 @@ struct Record {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct DnaExtractionEvent {
-    id: String,
-    record_id: String,
-    subsample_id: String,
-    event_date: Option<String>,
-    concentration: Option<String>,
-    concentration_method: Option<String>,
-    absorbance_260_230: Option<String>,
-    absorbance_260_280: Option<String>,
-    dna_extraction_method: Option<String>,
-    extracted_by: Option<String>,
-    preservation_type: Option<String>,
-    preparation_type: Option<String>,
-    quality: Option<String>,
+    pub id: String,
+    pub record_id: String,
+    pub subsample_id: String,
+    pub scientific_name: Option<String>,
+    pub event_date: Option<NaiveDate>,
+    pub concentration: Option<f64>,
+    pub concentration_method: Option<String>,
+    pub absorbance_260_230: Option<f64>,
+    pub absorbance_260_280: Option<f64>,
+    pub dna_extraction_method: Option<String>,
+    pub extracted_by: Option<String>,
+    pub preservation_type: Option<String>,
+    pub preparation_type: Option<String>,
+    pub quality: Option<String>,
 }
 
-pub fn normalise(path: &PathBuf) -> Result<(), Error> {
-    let mut reader = csv::Reader::from_path(&path)?;
+pub fn normalise(path: &Path, validator: &mut Validator) -> Result<(), Error> {
     let mut writer = csv::Writer::from_path("extractions.csv")?;
 
+    for event in read(path, validator)? {
+        writer.serialize(event)?;
+    }
+
+    Ok(())
+}
+
+/// Read and validate the DNA extraction events in a BPA csv file
+pub fn read(path: &Path, validator: &mut Validator) -> Result<Vec<DnaExtractionEvent>, Error> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut events = Vec::new();
+
     for row in reader.deserialize() {
         let record: Record = row?;
 
@@ -104,16 +120,18 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
             .or(record.dna_extraction_protocol);
 
         let extracted_by = record.dna_extracted_by.or(record.material_extracted_by);
+        let mut check = validator.record("extractions", &record.id, &record_id);
 
         let event = DnaExtractionEvent {
             id: record.id,
             record_id,
             subsample_id,
-            event_date,
-            concentration,
+            scientific_name: record.scientific_name.or(record.species_name),
+            event_date: check.date("dna_extraction_date", &event_date),
+            concentration: check.number("dna_concentration", &concentration),
             concentration_method: record.dna_concentration_method,
-            absorbance_260_230: record.absorbance_260_230_ratio,
-            absorbance_260_280: record.absorbance_260_280_ratio,
+            absorbance_260_230: check.number("absorbance_260_230_ratio", &record.absorbance_260_230_ratio),
+            absorbance_260_280: check.number("absorbance_260_280_ratio", &record.absorbance_260_280_ratio),
             dna_extraction_method,
             extracted_by,
             preservation_type: record.preservation_type,
@@ -121,8 +139,8 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
             quality: record.sample_quality,
         };
 
-        writer.serialize(event)?;
+        events.push(event);
     }
 
-    Ok(())
+    Ok(events)
 }
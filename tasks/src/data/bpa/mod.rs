@@ -6,10 +6,22 @@ pub mod sequences;
 pub mod assemblies;
 pub mod annotations;
 pub mod depositions;
+pub mod operations;
+pub mod validation;
 
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
+use arga_core::crdt::Version;
+use arga_core::models::DatasetVersion;
+use diesel::*;
+use tracing::info;
+
+use self::operations::SpecimenOperations;
+use self::validation::Validator;
 use super::Error;
+use crate::data::oplogger::{create_dataset_version, get_pool};
+use crate::data::pipeline::Pipeline;
 
 
 #[derive(clap::Subcommand)]
@@ -18,24 +30,116 @@ pub enum Command {
     Normalise {
         /// The BPA csv file
         input: String,
+
+        /// The CSV file to write the validation problems of every record into
+        #[arg(long, default_value = "validation.csv")]
+        report: PathBuf,
+    },
+
+    /// Validate a BPA csv file and import the specimens into the operation logs under a new dataset version
+    Import {
+        dataset_id: String,
+        version: String,
+        created_at: String,
+
+        /// The BPA csv file
+        input: String,
+
+        /// The CSV file to write the validation problems of every record into
+        #[arg(long, default_value = "validation.csv")]
+        report: PathBuf,
     },
 }
 
 pub fn process_command(command: &Command) {
     match command {
-        Command::Normalise { input } => normalise(PathBuf::from(input)).unwrap(),
+        Command::Normalise { input, report } => normalise(PathBuf::from(input), report).unwrap(),
+        Command::Import { dataset_id, version, created_at, input, report } => {
+            let dataset_version = create_dataset_version(dataset_id, version, created_at).unwrap();
+            let mut pipeline = Pipeline::import();
+            import(PathBuf::from(input), dataset_version, report, &mut pipeline).unwrap();
+            pipeline.report();
+        }
     }
 }
 
-fn normalise(path: PathBuf) -> Result<(), Error> {
-    collections::normalise(&path)?;
-    accessions::normalise(&path)?;
-    subsamples::normalise(&path)?;
-    extractions::normalise(&path)?;
-    sequences::normalise(&path)?;
-    assemblies::normalise(&path)?;
+fn normalise(path: PathBuf, report: &Path) -> Result<(), Error> {
+    let mut validator = Validator::default();
+
+    collections::normalise(&path, &mut validator)?;
+    accessions::normalise(&path, &mut validator)?;
+    subsamples::normalise(&path, &mut validator)?;
+    extractions::normalise(&path, &mut validator)?;
+    sequences::normalise(&path, &mut validator)?;
+    assemblies::normalise(&path, &mut validator)?;
     annotations::normalise(&path)?;
-    depositions::normalise(&path)?;
+    depositions::normalise(&path, &mut validator)?;
+
+    validator.write(report)
+}
+
+/// Import the specimens, subsamples and extractions in a BPA csv file into the operation logs.
+///
+/// A BPA file has a row for every dataset so the same specimen can appear many times. Only the
+/// first event for an entity is imported which matches the precedence of the normalised CSV files.
+pub fn import(
+    path: PathBuf,
+    dataset_version: DatasetVersion,
+    report: &Path,
+    pipeline: &mut Pipeline,
+) -> Result<(), Error> {
+    info!(?path, "Reading BPA records");
+    let mut validator = Validator::default();
+
+    let collections = collections::read(&path, &mut validator)?;
+    let accessions = accessions::read(&path, &mut validator)?;
+    let subsamples = subsamples::read(&path, &mut validator)?;
+    let extractions = extractions::read(&path, &mut validator)?;
+
+    validator.write(report)?;
+
+    let mut operations = SpecimenOperations::default();
+    let mut last_version = Version::new();
+
+    let mut seen = HashSet::new();
+    for event in collections.iter().filter(|event| seen.insert(event.record_id.clone())) {
+        last_version = operations.push_collection(event, dataset_version.id, last_version);
+    }
+
+    let mut seen = HashSet::new();
+    for event in accessions.iter().filter(|event| seen.insert(event.specimen_id.clone())) {
+        last_version = operations.push_accession(event, dataset_version.id, last_version);
+    }
+
+    let mut seen = HashSet::new();
+    for event in subsamples.iter().filter(|event| seen.insert(event.record_id.clone())) {
+        last_version = operations.push_subsample(event, dataset_version.id, last_version);
+    }
+
+    let mut seen = HashSet::new();
+    for event in extractions.iter().filter(|event| seen.insert(event.record_id.clone())) {
+        last_version = operations.push_extraction(event, dataset_version.id, last_version);
+    }
+
+    info!(
+        collections = operations.collections.len(),
+        accessions = operations.accessions.len(),
+        subsamples = operations.subsamples.len(),
+        extractions = operations.extractions.len(),
+        "Importing BPA operations"
+    );
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    conn.transaction(|conn| {
+        pipeline.insert(conn, &operations.collections)?;
+        pipeline.insert(conn, &operations.accessions)?;
+        pipeline.insert(conn, &operations.subsamples)?;
+        pipeline.insert(conn, &operations.extractions)?;
+        Ok::<(), Error>(())
+    })?;
 
+    info!("Importing BPA records finished");
     Ok(())
 }
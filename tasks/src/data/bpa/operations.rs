@@ -0,0 +1,237 @@
+use arga_core::crdt::{DataFrame, Version};
+use arga_core::models::{
+    entity_hash,
+    AccessionEventAtom,
+    AccessionEventOperation,
+    CollectionEventAtom,
+    CollectionEventOperation,
+    ExtractionAtom,
+    ExtractionOperation,
+    SubsampleAtom,
+    SubsampleOperation,
+};
+use uuid::Uuid;
+
+use super::accessions::AccessionEvent;
+use super::collections::CollectionEvent;
+use super::extractions::DnaExtractionEvent;
+use super::subsamples::SubsampleEvent;
+
+
+/// The operations for the specimens in a BPA file.
+///
+/// The entity ids are derived from the same ids that the normalised events use to link to each other
+/// so that importing a newer version of the BPA metadata updates the existing entities. Specimens use
+/// the voucher or `specimen_id` falling back to the `sample_id`, subsamples append the tissue number to
+/// the specimen id, and extractions use the BPA `sample_id`.
+#[derive(Debug, Default)]
+pub struct SpecimenOperations {
+    pub collections: Vec<CollectionEventOperation>,
+    pub accessions: Vec<AccessionEventOperation>,
+    pub subsamples: Vec<SubsampleOperation>,
+    pub extractions: Vec<ExtractionOperation>,
+}
+
+impl SpecimenOperations {
+    /// Add the operations for a collection event and return the last version used
+    pub fn push_collection(
+        &mut self,
+        event: &CollectionEvent,
+        dataset_version_id: Uuid,
+        last_version: Version,
+    ) -> Version {
+        let mut frame = DataFrame::create(entity_hash(&event.record_id), dataset_version_id, last_version);
+        frame.push(CollectionEventAtom::SpecimenId(event.record_id.clone()));
+
+        if let Some(value) = &event.scientific_name {
+            frame.push(CollectionEventAtom::ScientificName(value.clone()));
+        }
+        if let Some(value) = &event.field_number {
+            frame.push(CollectionEventAtom::FieldCollectingId(value.clone()));
+        }
+        if let Some(date) = event.collection_date {
+            frame.push(CollectionEventAtom::EventDate(date));
+        }
+        if let Some(value) = &event.collected_by {
+            frame.push(CollectionEventAtom::CollectedBy(value.clone()));
+        }
+        if let Some(value) = &event.event_remarks {
+            frame.push(CollectionEventAtom::CollectionRemarks(value.clone()));
+        }
+        if let Some(value) = &event.identified_by {
+            frame.push(CollectionEventAtom::IdentifiedBy(value.clone()));
+        }
+        if let Some(date) = event.date_identified {
+            frame.push(CollectionEventAtom::IdentifiedDate(date));
+        }
+        if let Some(value) = &event.taxon_remarks {
+            frame.push(CollectionEventAtom::IdentificationRemarks(value.clone()));
+        }
+        if let Some(value) = event.location_text.as_ref().or(event.location.as_ref()) {
+            frame.push(CollectionEventAtom::Locality(value.clone()));
+        }
+        if let Some(value) = &event.country {
+            frame.push(CollectionEventAtom::Country(value.clone()));
+        }
+        if let Some(value) = &event.state {
+            frame.push(CollectionEventAtom::StateProvince(value.clone()));
+        }
+        // the BPA location generalisation only describes how the public coordinates were derived
+        // and there is no atom for it, so it stays in the normalised CSV but isn't imported
+        if let (Some(latitude), Some(longitude)) = (event.latitude, event.longitude) {
+            frame.push(CollectionEventAtom::Latitude(latitude));
+            frame.push(CollectionEventAtom::Longitude(longitude));
+        }
+        if let Some(value) = event.elevation {
+            frame.push(CollectionEventAtom::Elevation(value));
+        }
+        if let Some(value) = event.depth.or(event.minimum_depth_in_meters) {
+            frame.push(CollectionEventAtom::Depth(value));
+        }
+        if let Some(value) = &event.habitat {
+            frame.push(CollectionEventAtom::Habitat(value.clone()));
+        }
+        if let Some(value) = &event.env_broad_scale {
+            frame.push(CollectionEventAtom::EnvironmentBroadScale(value.clone()));
+        }
+        if let Some(value) = &event.env_local_scale {
+            frame.push(CollectionEventAtom::EnvironmentLocalScale(value.clone()));
+        }
+        if let Some(value) = &event.env_medium {
+            frame.push(CollectionEventAtom::EnvironmentMedium(value.clone()));
+        }
+        if let Some(value) = &event.host_scientific_name {
+            frame.push(CollectionEventAtom::SpecificHost(value.clone()));
+        }
+        if let Some(value) = &event.isolate {
+            frame.push(CollectionEventAtom::Isolate(value.clone()));
+        }
+        if let Some(value) = &event.location_notes {
+            frame.push(CollectionEventAtom::FieldNotes(value.clone()));
+        }
+
+        let last_version = frame.last_version();
+        self.collections.extend(frame.collect::<CollectionEventOperation>());
+        last_version
+    }
+
+    /// Add the operations for an accession event and return the last version used
+    pub fn push_accession(
+        &mut self,
+        event: &AccessionEvent,
+        dataset_version_id: Uuid,
+        last_version: Version,
+    ) -> Version {
+        let mut frame = DataFrame::create(entity_hash(&event.specimen_id), dataset_version_id, last_version);
+        frame.push(AccessionEventAtom::SpecimenId(event.specimen_id.clone()));
+        frame.push(AccessionEventAtom::CollectionRepositoryId(event.accession.clone()));
+
+        if let Some(value) = &event.scientific_name {
+            frame.push(AccessionEventAtom::ScientificName(value.clone()));
+        }
+        if let Some(date) = event.event_date {
+            frame.push(AccessionEventAtom::EventDate(date));
+        }
+        if let Some(value) = &event.type_status {
+            frame.push(AccessionEventAtom::TypeStatus(value.clone()));
+        }
+        if let Some(value) = &event.institution_code {
+            frame.push(AccessionEventAtom::InstitutionName(value.clone()));
+        }
+        if let Some(value) = &event.accessioned_by {
+            frame.push(AccessionEventAtom::AccessionedBy(value.clone()));
+        }
+        if !event.material_sample_ids.is_empty() {
+            frame.push(AccessionEventAtom::OtherCatalogNumbers(event.material_sample_ids.clone()));
+        }
+
+        let last_version = frame.last_version();
+        self.accessions.extend(frame.collect::<AccessionEventOperation>());
+        last_version
+    }
+
+    /// Add the operations for a subsample event and return the last version used
+    pub fn push_subsample(
+        &mut self,
+        event: &SubsampleEvent,
+        dataset_version_id: Uuid,
+        last_version: Version,
+    ) -> Version {
+        let mut frame = DataFrame::create(entity_hash(&event.record_id), dataset_version_id, last_version);
+        frame.push(SubsampleAtom::SubsampleId(event.record_id.clone()));
+        frame.push(SubsampleAtom::SpecimenId(event.specimen_id.clone()));
+
+        if let Some(value) = &event.scientific_name {
+            frame.push(SubsampleAtom::ScientificName(value.clone()));
+        }
+        if let Some(date) = event.event_date {
+            frame.push(SubsampleAtom::EventDate(date));
+        }
+        if let Some(value) = &event.material_sample_id {
+            frame.push(SubsampleAtom::Name(value.clone()));
+        }
+        if let Some(value) = event.preparation_type.as_ref().or(event.material_sample_type.as_ref()) {
+            frame.push(SubsampleAtom::SampleType(value.clone()));
+        }
+        if let Some(value) = &event.subsampled_by {
+            frame.push(SubsampleAtom::Custodian(value.clone()));
+        }
+        if let Some(value) = &event.preservation_type {
+            frame.push(SubsampleAtom::PreservationMethod(value.clone()));
+        }
+        if let Some(value) = &event.preservation_temperature {
+            frame.push(SubsampleAtom::PreservationTemperature(value.clone()));
+        }
+
+        let last_version = frame.last_version();
+        self.subsamples.extend(frame.collect::<SubsampleOperation>());
+        last_version
+    }
+
+    /// Add the operations for a DNA extraction event and return the last version used
+    pub fn push_extraction(
+        &mut self,
+        event: &DnaExtractionEvent,
+        dataset_version_id: Uuid,
+        last_version: Version,
+    ) -> Version {
+        let mut frame = DataFrame::create(entity_hash(&event.record_id), dataset_version_id, last_version);
+        frame.push(ExtractionAtom::ExtractId(event.record_id.clone()));
+        frame.push(ExtractionAtom::SubsampleId(event.subsample_id.clone()));
+
+        if let Some(value) = &event.scientific_name {
+            frame.push(ExtractionAtom::ScientificName(value.clone()));
+        }
+        if let Some(date) = event.event_date {
+            frame.push(ExtractionAtom::EventDate(date));
+        }
+        if let Some(value) = &event.extracted_by {
+            frame.push(ExtractionAtom::ExtractedBy(value.clone()));
+        }
+        if let Some(value) = &event.dna_extraction_method {
+            frame.push(ExtractionAtom::ExtractionMethod(value.clone()));
+        }
+        if let Some(value) = event.concentration {
+            frame.push(ExtractionAtom::Concentration(value));
+        }
+        if let Some(value) = &event.concentration_method {
+            frame.push(ExtractionAtom::ConcentrationMethod(value.clone()));
+        }
+        if let Some(value) = event.absorbance_260_230 {
+            frame.push(ExtractionAtom::Absorbance260230Ratio(value));
+        }
+        if let Some(value) = event.absorbance_260_280 {
+            frame.push(ExtractionAtom::Absorbance260280Ratio(value));
+        }
+        if let Some(value) = &event.preservation_type {
+            frame.push(ExtractionAtom::PreservationType(value.clone()));
+        }
+        if let Some(value) = &event.preparation_type {
+            frame.push(ExtractionAtom::PreparationType(value.clone()));
+        }
+
+        let last_version = frame.last_version();
+        self.extractions.extend(frame.collect::<ExtractionOperation>());
+        last_version
+    }
+}
@@ -1,7 +1,9 @@
-use std::path::PathBuf;
+use std::path::Path;
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use super::validation::Validator;
 use crate::data::Error;
 
 
@@ -61,7 +63,7 @@ pub struct SequencingEvent {
     material_sample_ids: String,
     dataset_ids: String,
     institution_code: Option<String>,
-    event_date: Option<String>,
+    event_date: Option<NaiveDate>,
     location_id: Option<String>,
     target_gene: Option<String>,
     seq_meth: Option<String>,
@@ -78,7 +80,7 @@ pub struct SequencingEvent {
     estimated_size: Option<String>,
 }
 
-pub fn normalise(path: &PathBuf) -> Result<(), Error> {
+pub fn normalise(path: &Path, validator: &mut Validator) -> Result<(), Error> {
     let mut reader = csv::Reader::from_path(&path)?;
     let mut writer = csv::Writer::from_path("sequences.csv")?;
 
@@ -116,6 +118,7 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
             .or(record.conversion_software)
             .or(record.analysis_software);
 
+        let mut check = validator.record("sequences", &record.id, &sequence_id);
         let event = SequencingEvent {
             id: record.id,
             sequence_id,
@@ -123,7 +126,7 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
             material_sample_ids: sample_ids.join(" | ").to_string(),
             dataset_ids: dataset_ids.join(" | ").to_string(),
             institution_code: record.facility_project_code,
-            event_date: record.run_date,
+            event_date: check.date("run_date", &record.run_date),
             location_id: record.library_location,
             target_gene: record.target,
             seq_meth,
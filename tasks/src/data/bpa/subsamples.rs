@@ -1,7 +1,9 @@
-use std::path::PathBuf;
+use std::path::Path;
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use super::validation::Validator;
 use crate::data::Error;
 
 
@@ -16,6 +18,8 @@ struct Record {
     voucher_or_tissue_number: Option<String>,
     voucher_number: Option<String>,
     voucher_herbarium_catalog_number: Option<String>,
+    scientific_name: Option<String>,
+    species_name: Option<String>,
 
     tissue_collection: Option<String>,
     tissue_preservation: Option<String>,
@@ -27,24 +31,36 @@ struct Record {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct SubsampleEvent {
-    id: String,
-    record_id: String,
-    specimen_id: String,
-    material_sample_id: Option<String>,
-    event_date: Option<String>,
-
-    subsampled_by: Option<String>,
-    preservation_type: Option<String>,
-    preservation_temperature: Option<String>,
-    preservation_date_begin: Option<String>,
-    preparation_type: Option<String>,
-    material_sample_type: Option<String>,
+    pub id: String,
+    pub record_id: String,
+    pub specimen_id: String,
+    pub material_sample_id: Option<String>,
+    pub scientific_name: Option<String>,
+    pub event_date: Option<NaiveDate>,
+
+    pub subsampled_by: Option<String>,
+    pub preservation_type: Option<String>,
+    pub preservation_temperature: Option<String>,
+    pub preservation_date_begin: Option<NaiveDate>,
+    pub preparation_type: Option<String>,
+    pub material_sample_type: Option<String>,
 }
 
-pub fn normalise(path: &PathBuf) -> Result<(), Error> {
-    let mut reader = csv::Reader::from_path(&path)?;
+pub fn normalise(path: &Path, validator: &mut Validator) -> Result<(), Error> {
     let mut writer = csv::Writer::from_path("subsamples.csv")?;
 
+    for event in read(path, validator)? {
+        writer.serialize(event)?;
+    }
+
+    Ok(())
+}
+
+/// Read and validate the subsample events in a BPA csv file
+pub fn read(path: &Path, validator: &mut Validator) -> Result<Vec<SubsampleEvent>, Error> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut events = Vec::new();
+
     for row in reader.deserialize() {
         let record: Record = row?;
 
@@ -67,22 +83,25 @@ pub fn normalise(path: &PathBuf) -> Result<(), Error> {
             None => specimen_id.clone(),
         };
 
+        let mut check = validator.record("subsamples", &record.id, &record_id);
+
         let event = SubsampleEvent {
             id: record.id.clone(),
             record_id,
             specimen_id,
             material_sample_id,
-            event_date: record.sample_submission_date,
+            scientific_name: record.scientific_name.or(record.species_name),
+            event_date: check.date("sample_submission_date", &record.sample_submission_date),
             subsampled_by: record.tissue_collection,
             preservation_type: record.tissue_preservation,
             preservation_temperature: record.tissue_preservation_temperature,
-            preservation_date_begin: record.preservation_date_begin,
+            preservation_date_begin: check.date("preservation_date_begin", &record.preservation_date_begin),
             preparation_type: record.tissue,
             material_sample_type: record.sample_collection_type,
         };
 
-        writer.serialize(event)?;
+        events.push(event);
     }
 
-    Ok(())
+    Ok(events)
 }
@@ -0,0 +1,303 @@
+use std::path::Path;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use tracing::info;
+
+use crate::data::utils::{non_empty, parse_date, DateError};
+use crate::data::Error;
+
+
+/// A problem found with a field when normalising a BPA record
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Problem {
+    /// The value isn't a date in any of the formats BPA uses
+    InvalidDate,
+    /// The date only has a year or a year and month
+    PartialDate,
+    /// The value isn't a number
+    InvalidNumber,
+    LatitudeOutOfRange,
+    LongitudeOutOfRange,
+    /// Only one of the latitude or longitude has a value
+    IncompleteCoordinates,
+    /// Both coordinates are zero which is almost always a placeholder
+    NullIsland,
+    /// The location is restricted but there are no generalised coordinates so the
+    /// precise coordinates were dropped
+    RestrictedLocation,
+}
+
+
+/// A row in the validation report
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    /// The BPA package id of the record
+    pub id: String,
+    /// The id of the record being normalised, used to derive the entity id
+    pub record_id: String,
+    /// The event being normalised, such as `collections`
+    pub event: &'static str,
+    pub field: &'static str,
+    pub value: String,
+    pub problem: Problem,
+}
+
+
+/// Collects the problems found while normalising the records of a BPA file.
+///
+/// Invalid values are never written out or imported, instead they are recorded
+/// here so that the report can be sent back to the data custodian.
+#[derive(Debug, Default)]
+pub struct Validator {
+    pub issues: Vec<Issue>,
+}
+
+impl Validator {
+    pub fn record<'a>(&'a mut self, event: &'static str, id: &str, record_id: &str) -> RecordValidator<'a> {
+        RecordValidator {
+            validator: self,
+            event,
+            id: id.to_string(),
+            record_id: record_id.to_string(),
+        }
+    }
+
+    /// Write the issues into a CSV file with one row for every problem found
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let mut writer = csv::Writer::from_path(path)?;
+        for issue in &self.issues {
+            writer.serialize(issue)?;
+        }
+        writer.flush()?;
+
+        info!(issues = self.issues.len(), ?path, "Validation report written");
+        Ok(())
+    }
+}
+
+
+/// Validates the fields of a single record
+pub struct RecordValidator<'a> {
+    validator: &'a mut Validator,
+    event: &'static str,
+    id: String,
+    record_id: String,
+}
+
+impl RecordValidator<'_> {
+    fn issue(&mut self, field: &'static str, value: &str, problem: Problem) {
+        self.validator.issues.push(Issue {
+            id: self.id.clone(),
+            record_id: self.record_id.clone(),
+            event: self.event,
+            field,
+            value: value.to_string(),
+            problem,
+        });
+    }
+
+    /// Parse a date with the shared BPA date parser
+    pub fn date(&mut self, field: &'static str, value: &Option<String>) -> Option<NaiveDate> {
+        let value = non_empty(value)?;
+        match parse_date(&value) {
+            Ok(date) => Some(date),
+            Err(err) => {
+                self.issue(field, &value, err.into());
+                None
+            }
+        }
+    }
+
+    pub fn number(&mut self, field: &'static str, value: &Option<String>) -> Option<f64> {
+        let value = non_empty(value)?;
+        match value.parse::<f64>() {
+            Ok(number) if number.is_finite() => Some(number),
+            _ => {
+                self.issue(field, &value, Problem::InvalidNumber);
+                None
+            }
+        }
+    }
+
+    /// Get the coordinates that can be published for a record.
+    ///
+    /// BPA publishes generalised coordinates in the `decimal_*_public` fields for samples
+    /// with a restricted location. When the location is restricted only those coordinates
+    /// are used, otherwise they are preferred over the precise ones if they exist.
+    pub fn coordinates(&mut self, location: &Location) -> Option<(f64, f64)> {
+        let public = (non_empty(&location.latitude_public), non_empty(&location.longitude_public));
+
+        let (latitude_field, longitude_field, latitude, longitude) = match public {
+            (None, None) if location.is_restricted() => {
+                if let Some(value) = non_empty(&location.latitude).or(non_empty(&location.longitude)) {
+                    self.issue("latitude", &value, Problem::RestrictedLocation);
+                }
+                return None;
+            }
+            (None, None) => ("latitude", "longitude", non_empty(&location.latitude), non_empty(&location.longitude)),
+            (latitude, longitude) => ("decimal_latitude_public", "decimal_longitude_public", latitude, longitude),
+        };
+
+        let (latitude, longitude) = match (latitude, longitude) {
+            (Some(latitude), Some(longitude)) => (latitude, longitude),
+            (Some(value), None) | (None, Some(value)) => {
+                self.issue(latitude_field, &value, Problem::IncompleteCoordinates);
+                return None;
+            }
+            (None, None) => return None,
+        };
+
+        let lat = self.number(latitude_field, &Some(latitude.clone()));
+        let lon = self.number(longitude_field, &Some(longitude.clone()));
+        let (lat, lon) = (lat?, lon?);
+
+        if !(-90.0..=90.0).contains(&lat) {
+            self.issue(latitude_field, &latitude, Problem::LatitudeOutOfRange);
+            return None;
+        }
+        if !(-180.0..=180.0).contains(&lon) {
+            self.issue(longitude_field, &longitude, Problem::LongitudeOutOfRange);
+            return None;
+        }
+        if lat == 0.0 && lon == 0.0 {
+            self.issue(latitude_field, &format!("{latitude} {longitude}"), Problem::NullIsland);
+            return None;
+        }
+
+        Some((lat, lon))
+    }
+}
+
+
+/// The location fields of a BPA record
+#[derive(Debug, Default)]
+pub struct Location {
+    pub latitude_public: Option<String>,
+    pub longitude_public: Option<String>,
+    pub latitude: Option<String>,
+    pub longitude: Option<String>,
+    pub restricted: Option<String>,
+}
+
+impl Location {
+    /// BPA uses a mix of yes/no values and free text explaining the restriction
+    pub fn is_restricted(&self) -> bool {
+        match non_empty(&self.restricted) {
+            Some(value) => !matches!(value.to_lowercase().as_str(), "no" | "n" | "false" | "none" | "unrestricted"),
+            None => false,
+        }
+    }
+}
+
+
+impl From<DateError> for Problem {
+    fn from(err: DateError) -> Self {
+        match err {
+            DateError::Partial => Problem::PartialDate,
+            DateError::Invalid => Problem::InvalidDate,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(latitude: &str, longitude: &str) -> Location {
+        Location {
+            latitude: Some(latitude.to_string()),
+            longitude: Some(longitude.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn problems(validator: &Validator) -> Vec<(&'static str, String)> {
+        validator
+            .issues
+            .iter()
+            .map(|issue| (issue.field, format!("{:?}", issue.problem)))
+            .collect()
+    }
+
+    #[test]
+    fn dates_report_partial_and_invalid_values() {
+        let mut validator = Validator::default();
+        let mut check = validator.record("collections", "1", "R1");
+
+        let date = NaiveDate::from_ymd_opt(2020, 3, 4);
+        assert_eq!(check.date("collection_date", &Some("04/03/2020".to_string())), date);
+        assert_eq!(check.date("collection_date", &Some("2020-03".to_string())), None);
+        assert_eq!(check.date("collection_date", &Some("yesterday".to_string())), None);
+        assert_eq!(check.date("collection_date", &Some(" ".to_string())), None);
+
+        assert_eq!(problems(&validator), vec![
+            ("collection_date", "PartialDate".to_string()),
+            ("collection_date", "InvalidDate".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn precise_coordinates_are_used_when_unrestricted() {
+        let mut validator = Validator::default();
+        let mut check = validator.record("collections", "1", "R1");
+
+        assert_eq!(check.coordinates(&location("-33.86", "151.2")), Some((-33.86, 151.2)));
+        assert!(validator.issues.is_empty());
+    }
+
+    #[test]
+    fn public_coordinates_are_preferred() {
+        let mut validator = Validator::default();
+        let mut check = validator.record("collections", "1", "R1");
+
+        let location = Location {
+            latitude_public: Some("-33.9".to_string()),
+            longitude_public: Some("151.2".to_string()),
+            restricted: Some("yes".to_string()),
+            ..location("-33.8688", "151.2093")
+        };
+        assert_eq!(check.coordinates(&location), Some((-33.9, 151.2)));
+        assert!(validator.issues.is_empty());
+    }
+
+    #[test]
+    fn restricted_coordinates_without_public_ones_are_dropped() {
+        let mut validator = Validator::default();
+        let mut check = validator.record("collections", "1", "R1");
+
+        let location = Location {
+            restricted: Some("Location restricted at the request of the custodian".to_string()),
+            ..location("-33.8688", "151.2093")
+        };
+        assert_eq!(check.coordinates(&location), None);
+        assert_eq!(problems(&validator), vec![("latitude", "RestrictedLocation".to_string())]);
+    }
+
+    #[test]
+    fn invalid_coordinates_are_reported() {
+        let mut validator = Validator::default();
+        let mut check = validator.record("collections", "1", "R1");
+
+        assert_eq!(check.coordinates(&location("91", "151.2")), None);
+        assert_eq!(check.coordinates(&location("-33.86", "181")), None);
+        assert_eq!(check.coordinates(&location("0", "0")), None);
+        assert_eq!(check.coordinates(&location("north", "151.2")), None);
+
+        let incomplete = Location {
+            latitude: Some("-33.86".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(check.coordinates(&incomplete), None);
+
+        assert_eq!(problems(&validator), vec![
+            ("latitude", "LatitudeOutOfRange".to_string()),
+            ("longitude", "LongitudeOutOfRange".to_string()),
+            ("latitude", "NullIsland".to_string()),
+            ("latitude", "InvalidNumber".to_string()),
+            ("latitude", "IncompleteCoordinates".to_string()),
+        ]);
+    }
+}
@@ -1,8 +1,12 @@
+pub mod backbones;
 pub mod bpa;
 pub mod ncbi;
-// pub mod bold;
-// pub mod oplogger;
-// pub mod plazi;
+pub mod bold;
+pub mod oplogger;
+pub mod pipeline;
+pub mod plazi;
+pub mod sensitive;
+pub mod utils;
 
 #[derive(clap::Subcommand)]
 pub enum Command {
@@ -12,13 +16,21 @@ pub enum Command {
     /// Extra processing for BPA datasets
     #[command(subcommand)]
     Bpa(bpa::Command),
-    // Extra processing for BOLD datasets
-    // #[command(subcommand)]
-    // Bold(bold::Command),
-    // #[command(subcommand)]
-    // Plazi(plazi::Command),
-    // #[command(subcommand)]
-    // Oplog(oplogger::Command),
+    /// Extra processing for BOLD datasets
+    #[command(subcommand)]
+    Bold(bold::Command),
+    /// Extra processing for Plazi datasets
+    #[command(subcommand)]
+    Plazi(plazi::Command),
+    /// Import and reduce operation logs
+    #[command(subcommand)]
+    Oplog(oplogger::Command),
+    /// Manage the rules generalising the locations of sensitive collections
+    #[command(subcommand)]
+    Sensitive(sensitive::Command),
+    /// Manage the taxonomic backbones used to classify names
+    #[command(subcommand)]
+    Backbones(backbones::Command),
 }
 
 pub fn process_command(command: &Command) {
@@ -27,9 +39,11 @@ pub fn process_command(command: &Command) {
     match command {
         Command::Ncbi(cmd) => ncbi::process_command(cmd),
         Command::Bpa(cmd) => bpa::process_command(cmd),
-        // Command::Bold(cmd) => bold::process_command(cmd),
-        // Command::Plazi(cmd) => plazi::process_command(cmd),
-        // Command::Oplog(cmd) => oplogger::process_command(cmd),
+        Command::Bold(cmd) => bold::process_command(cmd),
+        Command::Plazi(cmd) => plazi::process_command(cmd),
+        Command::Oplog(cmd) => oplogger::process_command(cmd),
+        Command::Sensitive(cmd) => sensitive::process_command(cmd),
+        Command::Backbones(cmd) => backbones::process_command(cmd),
     }
 }
 
@@ -42,8 +56,8 @@ pub enum Error {
     Database(diesel::result::Error),
     Pool(diesel::r2d2::PoolError),
     Parsing(ParseError),
-    // Http(ureq::Error),
-    // Abif(abif::Error),
+    Http(ureq::Error),
+    Abif(bold::abif::Error),
 }
 
 impl From<std::io::Error> for Error {
@@ -76,6 +90,12 @@ impl From<diesel::r2d2::PoolError> for Error {
     }
 }
 
+impl From<ParseError> for Error {
+    fn from(value: ParseError) -> Self {
+        Self::Parsing(value)
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ParseError {
     #[error("invalid value: {0}")]
@@ -89,16 +109,19 @@ pub enum ParseError {
 
     #[error("invalid structure: {0}")]
     InvalidStructure(String),
+
+    #[error("unexpected element: {0}")]
+    UnexpectedElement(String),
 }
 
-// impl From<ureq::Error> for Error {
-//     fn from(value: ureq::Error) -> Self {
-//         Self::Http(value)
-//     }
-// }
-
-// impl From<abif::Error> for Error {
-//     fn from(value: abif::Error) -> Self {
-//         Self::Abif(value)
-//     }
-// }
+impl From<ureq::Error> for Error {
+    fn from(value: ureq::Error) -> Self {
+        Self::Http(value)
+    }
+}
+
+impl From<bold::abif::Error> for Error {
+    fn from(value: bold::abif::Error) -> Self {
+        Self::Abif(value)
+    }
+}
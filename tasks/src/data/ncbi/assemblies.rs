@@ -0,0 +1,339 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use arga_core::crdt::Version;
+use arga_core::models::DatasetVersion;
+use diesel::*;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use super::operations::AssemblyOperations;
+use super::report_unlinked_biosamples;
+use crate::data::oplogger::get_pool;
+use crate::data::pipeline::Pipeline;
+use crate::data::utils::non_placeholder;
+use crate::data::Error;
+
+
+/// The format of an assembly metadata file
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum AssemblyFormat {
+    /// The tab separated assembly_summary.txt files from the NCBI genomes FTP
+    Summary,
+    /// The assembly data report JSON Lines file from the NCBI datasets tool
+    Datasets,
+}
+
+
+/// An assembly from either of the NCBI assembly formats
+#[derive(Debug, Default, Clone)]
+pub struct AssemblyRecord {
+    pub accession: String,
+    pub biosample: Option<String>,
+    pub scientific_name: Option<String>,
+
+    pub name: Option<String>,
+    pub submitter: Option<String>,
+    pub release_date: Option<String>,
+    pub url: Option<String>,
+
+    pub assembly_type: Option<String>,
+    pub level: Option<String>,
+    pub representation: Option<String>,
+    pub method: Option<String>,
+    pub coverage: Option<String>,
+    pub completeness: Option<String>,
+
+    pub size: Option<i64>,
+    pub size_ungapped: Option<i64>,
+    pub gc_percent: Option<f64>,
+    pub replicons: Option<i32>,
+    pub scaffolds: Option<i32>,
+    pub contigs: Option<i32>,
+    pub chromosomes: Option<i32>,
+    pub organelles: Option<i32>,
+    pub component_sequences: Option<i32>,
+    pub contig_n50: Option<i32>,
+    pub contig_l50: Option<i32>,
+    pub scaffold_n50: Option<i32>,
+    pub scaffold_l50: Option<i32>,
+}
+
+
+/// A row in an assembly_summary.txt file. Older files don't have the statistics columns
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SummaryRow {
+    assembly_accession: String,
+    biosample: Option<String>,
+    organism_name: Option<String>,
+    asm_name: Option<String>,
+    asm_submitter: Option<String>,
+    seq_rel_date: Option<String>,
+    ftp_path: Option<String>,
+    assembly_type: Option<String>,
+    assembly_level: Option<String>,
+    genome_rep: Option<String>,
+    genome_size: Option<String>,
+    genome_size_ungapped: Option<String>,
+    gc_percent: Option<String>,
+    replicon_count: Option<String>,
+    scaffold_count: Option<String>,
+    contig_count: Option<String>,
+}
+
+impl From<SummaryRow> for AssemblyRecord {
+    fn from(row: SummaryRow) -> Self {
+        AssemblyRecord {
+            accession: row.assembly_accession,
+            biosample: row.biosample,
+            scientific_name: row.organism_name,
+            name: row.asm_name,
+            submitter: row.asm_submitter,
+            release_date: row.seq_rel_date,
+            url: row.ftp_path,
+            assembly_type: row.assembly_type,
+            level: row.assembly_level,
+            representation: row.genome_rep,
+            size: number(&row.genome_size),
+            size_ungapped: number(&row.genome_size_ungapped),
+            gc_percent: number(&row.gc_percent),
+            replicons: number(&row.replicon_count),
+            scaffolds: number(&row.scaffold_count),
+            contigs: number(&row.contig_count),
+            ..Default::default()
+        }
+    }
+}
+
+
+/// A line in an assembly data report from the NCBI datasets tool
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataReport {
+    accession: String,
+    #[serde(default)]
+    organism: ReportOrganism,
+    #[serde(default)]
+    assembly_info: ReportAssemblyInfo,
+    #[serde(default)]
+    assembly_stats: ReportAssemblyStats,
+    checkm_info: Option<ReportCheckm>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportOrganism {
+    organism_name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportAssemblyInfo {
+    assembly_name: Option<String>,
+    assembly_level: Option<String>,
+    assembly_type: Option<String>,
+    assembly_method: Option<String>,
+    submitter: Option<String>,
+    release_date: Option<String>,
+    biosample: Option<ReportBioSample>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportBioSample {
+    accession: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportAssemblyStats {
+    total_sequence_length: Option<ReportValue>,
+    total_ungapped_length: Option<ReportValue>,
+    gc_percent: Option<ReportValue>,
+    number_of_component_sequences: Option<ReportValue>,
+    number_of_contigs: Option<ReportValue>,
+    number_of_scaffolds: Option<ReportValue>,
+    number_of_organelles: Option<ReportValue>,
+    total_number_of_chromosomes: Option<ReportValue>,
+    contig_n50: Option<ReportValue>,
+    contig_l50: Option<ReportValue>,
+    scaffold_n50: Option<ReportValue>,
+    scaffold_l50: Option<ReportValue>,
+    genome_coverage: Option<ReportValue>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportCheckm {
+    completeness: Option<ReportValue>,
+}
+
+/// The data reports use strings for large numbers and numbers for everything else
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum ReportValue {
+    Number(serde_json::Number),
+    Text(String),
+}
+
+impl ReportValue {
+    fn text(&self) -> String {
+        match self {
+            ReportValue::Number(number) => number.to_string(),
+            ReportValue::Text(text) => text.clone(),
+        }
+    }
+}
+
+impl From<DataReport> for AssemblyRecord {
+    fn from(report: DataReport) -> Self {
+        let info = report.assembly_info;
+        let stats = report.assembly_stats;
+        let value = |value: &Option<ReportValue>| value.as_ref().map(|value| value.text());
+
+        AssemblyRecord {
+            url: Some(format!("https://www.ncbi.nlm.nih.gov/datasets/genome/{}/", report.accession)),
+            accession: report.accession,
+            biosample: info.biosample.and_then(|biosample| biosample.accession),
+            scientific_name: report.organism.organism_name,
+            name: info.assembly_name,
+            submitter: info.submitter,
+            release_date: info.release_date,
+            assembly_type: info.assembly_type,
+            level: info.assembly_level,
+            representation: None,
+            method: info.assembly_method,
+            coverage: value(&stats.genome_coverage),
+            completeness: report.checkm_info.and_then(|checkm| value(&checkm.completeness)),
+            size: number(&value(&stats.total_sequence_length)),
+            size_ungapped: number(&value(&stats.total_ungapped_length)),
+            gc_percent: number(&value(&stats.gc_percent)),
+            replicons: None,
+            scaffolds: number(&value(&stats.number_of_scaffolds)),
+            contigs: number(&value(&stats.number_of_contigs)),
+            chromosomes: number(&value(&stats.total_number_of_chromosomes)),
+            organelles: number(&value(&stats.number_of_organelles)),
+            component_sequences: number(&value(&stats.number_of_component_sequences)),
+            contig_n50: number(&value(&stats.contig_n50)),
+            contig_l50: number(&value(&stats.contig_l50)),
+            scaffold_n50: number(&value(&stats.scaffold_n50)),
+            scaffold_l50: number(&value(&stats.scaffold_l50)),
+        }
+    }
+}
+
+
+/// Import the assemblies in an NCBI assembly file into the operation logs.
+///
+/// Any biosample the assemblies reference that hasn't been imported yet is written to the report.
+pub fn import(
+    input: PathBuf,
+    format: AssemblyFormat,
+    dataset_version: DatasetVersion,
+    report: &Path,
+    pipeline: &mut Pipeline,
+) -> Result<(), Error> {
+    info!(?input, ?format, "Reading assemblies");
+    let records = match format {
+        AssemblyFormat::Summary => read_summary(&input)?,
+        AssemblyFormat::Datasets => read_data_report(&input)?,
+    };
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    let biosamples: HashSet<String> = records.iter().filter_map(|record| non_placeholder(&record.biosample)).collect();
+    let unlinked = report_unlinked_biosamples(&mut conn, &biosamples, report)?;
+    if unlinked > 0 {
+        warn!(unlinked, ?report, "Assemblies reference biosamples that haven't been imported");
+    }
+
+    info!(total = records.len(), "Importing assemblies");
+    let mut last_version = Version::new();
+    let mut libraries = HashSet::new();
+
+    for chunk in records.chunks(100_000) {
+        let mut operations = AssemblyOperations::default();
+
+        for record in chunk {
+            let include_library = match &record.biosample {
+                Some(biosample) => libraries.insert(biosample.clone()),
+                None => false,
+            };
+            last_version = operations.push(record, include_library, dataset_version.id, last_version);
+        }
+
+        conn.transaction(|conn| {
+            pipeline.insert(conn, &operations.libraries)?;
+            pipeline.insert(conn, &operations.assemblies)?;
+            pipeline.insert(conn, &operations.depositions)?;
+            Ok::<(), Error>(())
+        })?;
+    }
+
+    info!(total = records.len(), "Importing assemblies finished");
+    Ok(())
+}
+
+
+/// Read an assembly_summary.txt file.
+///
+/// The column names are in the last of the comment lines at the top of the file
+/// so we find them first and then read the rest of the file as a tab separated file.
+fn read_summary(path: &Path) -> Result<Vec<AssemblyRecord>, Error> {
+    let mut header = None;
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        match line.strip_prefix('#') {
+            Some(comment) => header = Some(comment.trim().to_string()),
+            None => break,
+        }
+    }
+
+    let header = header.unwrap_or_default();
+    let headers = csv::StringRecord::from(header.split('\t').collect::<Vec<&str>>());
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .comment(Some(b'#'))
+        .has_headers(false)
+        .flexible(true)
+        .quoting(false)
+        .from_path(path)?;
+    reader.set_headers(headers);
+
+    let mut records = Vec::new();
+    for row in reader.deserialize::<SummaryRow>() {
+        records.push(row?.into());
+    }
+
+    Ok(records)
+}
+
+/// Read an assembly data report where every line is a JSON object
+fn read_data_report(path: &Path) -> Result<Vec<AssemblyRecord>, Error> {
+    let mut records = Vec::new();
+
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<DataReport>(&line) {
+            Ok(report) => records.push(report.into()),
+            Err(err) => warn!(?err, "Skipping invalid data report line"),
+        }
+    }
+
+    Ok(records)
+}
+
+
+fn number<T: FromStr>(value: &Option<String>) -> Option<T> {
+    non_placeholder(value).and_then(|value| value.parse::<T>().ok())
+}
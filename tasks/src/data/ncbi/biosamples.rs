@@ -2,6 +2,9 @@ use std::{path::PathBuf, collections::HashMap};
 
 // use arga_core::models::{Specimen, Dataset, NameList, NameListType};
 // use arga_core::{schema, models};
+use arga_core::crdt::Version;
+use arga_core::models::DatasetVersion;
+use diesel::*;
 use memmap2::Mmap;
 use quick_xml::{Reader, events::{Event, BytesStart}};
 use serde::Serialize;
@@ -9,9 +12,12 @@ use tracing::info;
 use rayon::prelude::*;
 use indicatif::{ProgressBar, ProgressStyle, ParallelProgressIterator, MultiProgress, ProgressIterator};
 
-use crate::data::Error;
+use crate::data::{Error, ParseError};
+use crate::data::oplogger::get_pool;
+use crate::data::pipeline::Pipeline;
 
-use super::name_matcher::NameRecord;
+use super::name_matcher::{match_names, NameRecord};
+use super::operations::BioSampleOperations;
 
 
 pub struct Progress {
@@ -229,6 +235,18 @@ struct DataDepositionEvent {
 }
 
 
+/// A name that couldn't be matched or an item that couldn't be parsed when importing biosamples.
+/// Failed items have no name and are reported by their byte offset in the file instead
+#[derive(Serialize, Debug, Clone)]
+struct SkippedSamples {
+    scientific_name: Option<String>,
+    taxonomy_id: Option<String>,
+    samples: usize,
+    offset: Option<usize>,
+    error: Option<String>,
+}
+
+
 #[derive(Debug)]
 enum BioSampleState {
     Root,
@@ -275,6 +293,28 @@ pub fn convert(input: PathBuf, out: PathBuf) -> Result<(), Error> {
 }
 
 
+/// Import a biosamples XML directly into the operation logs.
+///
+/// Samples are linked to names by their taxonomy name and any sample that can't be
+/// matched is skipped and its name written to the report instead. Items that fail to
+/// parse are also skipped and written to the report with their offset and error.
+pub fn import(
+    input: PathBuf,
+    dataset_version: DatasetVersion,
+    report: PathBuf,
+    pipeline: &mut Pipeline,
+) -> Result<(), Error> {
+    info!(?input, "Memory mapping file");
+    let file = std::fs::File::open(input)?;
+    let mmap = unsafe { Mmap::map(&file)? };
+
+    let analysed = analyse_biosamples(mmap)?;
+    import_biosamples(analysed, dataset_version, report, pipeline)?;
+
+    Ok(())
+}
+
+
 pub fn summarise(input: PathBuf) -> Result<(), Error> {
     info!(?input, "Memory mapping file");
     let file = std::fs::File::open(input)?;
@@ -374,43 +414,41 @@ fn convert_biosamples(analysed: AnalysedBiosamples, out: PathBuf) -> Result<(),
 
     for chunk in analysed.offsets.chunks(1_000_000) {
         // parse the body of each BioSample item in parallel
-        let samples: Vec<BioSample> = chunk
+        let samples = chunk
             .into_par_iter()
             .progress_with(parse_bar.clone())
-            .map(|(start, end)| {
-                process_item(&analysed.mmap, *start, *end).expect("Failed to process item")
-            })
-            .collect();
+            .map(|(start, end)| process_item(&analysed.mmap, *start, *end))
+            .collect::<Result<Vec<BioSample>, Error>>()?;
 
-        let events = extract_collection_events(&samples, &bars).unwrap();
+        let events = extract_collection_events(&samples, &bars)?;
         for record in events.into_iter().multiprogress_with(&bars, "Persisting collection events") {
             collection_writer.serialize(record)?;
         }
-        let events = extract_accession_events(&samples, &bars).unwrap();
+        let events = extract_accession_events(&samples, &bars)?;
         for record in events.into_iter().multiprogress_with(&bars, "Persisting accession events") {
             accession_writer.serialize(record)?;
         }
-        let events = extract_subsample_events(&samples, &bars).unwrap();
+        let events = extract_subsample_events(&samples, &bars)?;
         for record in events.into_iter().multiprogress_with(&bars, "Persisting subsample events") {
             subsample_writer.serialize(record)?;
         }
-        let events = extract_extraction_events(&samples, &bars).unwrap();
+        let events = extract_extraction_events(&samples, &bars)?;
         for record in events.into_iter().multiprogress_with(&bars, "Persisting extraction events") {
             extraction_writer.serialize(record)?;
         }
-        let events = extract_sequencing_events(&samples, &bars).unwrap();
+        let events = extract_sequencing_events(&samples, &bars)?;
         for record in events.into_iter().multiprogress_with(&bars, "Persisting sequencing events") {
             sequencing_writer.serialize(record)?;
         }
-        let events = extract_assembly_events(&samples, &bars).unwrap();
+        let events = extract_assembly_events(&samples, &bars)?;
         for record in events.into_iter().multiprogress_with(&bars, "Persisting assembly events") {
             assembly_writer.serialize(record)?;
         }
-        let events = extract_annotation_events(&samples, &bars).unwrap();
+        let events = extract_annotation_events(&samples, &bars)?;
         for record in events.into_iter().multiprogress_with(&bars, "Persisting annotation events") {
             annotation_writer.serialize(record)?;
         }
-        let events = extract_data_accession_events(&samples, &bars).unwrap();
+        let events = extract_data_accession_events(&samples, &bars)?;
         for record in events.into_iter().multiprogress_with(&bars, "Persisting data accession events") {
             data_accession_writer.serialize(record)?;
         }
@@ -423,10 +461,116 @@ fn convert_biosamples(analysed: AnalysedBiosamples, out: PathBuf) -> Result<(),
 }
 
 
+fn import_biosamples(
+    analysed: AnalysedBiosamples,
+    dataset_version: DatasetVersion,
+    report: PathBuf,
+    pipeline: &mut Pipeline,
+) -> Result<(), Error> {
+    let total = analysed.offsets.len() as u64;
+    info!(items=total, "Importing biosample file");
+
+    let mut pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    let bars = Progress::new();
+    let parse_bar = bars.add("Parsing", analysed.offsets.len());
+
+    let mut last_version = Version::new();
+    let mut unmatched: HashMap<String, SkippedSamples> = HashMap::new();
+    let mut failed: Vec<SkippedSamples> = Vec::new();
+    let mut imported = 0;
+
+    for chunk in analysed.offsets.chunks(100_000) {
+        // parse the body of each BioSample item in parallel
+        let results: Vec<(usize, Result<BioSample, Error>)> = chunk
+            .into_par_iter()
+            .progress_with(parse_bar.clone())
+            .map(|(start, end)| (*start, process_item(&analysed.mmap, *start, *end)))
+            .collect();
+
+        // a malformed item shouldn't abort the whole import so we report it and carry on
+        let mut samples = Vec::with_capacity(results.len());
+        for (offset, result) in results {
+            match result {
+                Ok(sample) => samples.push(sample),
+                Err(err) => failed.push(SkippedSamples {
+                    scientific_name: None,
+                    taxonomy_id: None,
+                    samples: 1,
+                    offset: Some(offset),
+                    error: Some(format!("{err:?}")),
+                }),
+            }
+        }
+
+        let records: Vec<NameRecord> = samples.iter().map(|sample| NameRecord {
+            scientific_name: sample.taxonomy_name.clone().unwrap_or_default(),
+            canonical_name: None,
+        }).collect();
+        let names = match_names(&records, &mut pool);
+
+        let mut operations = BioSampleOperations::default();
+
+        for sample in samples.iter().multiprogress_with(&bars, "Extracting operations") {
+            let name = sample.taxonomy_name.clone().unwrap_or_default();
+
+            match names.get(&name) {
+                Some(matched) => {
+                    last_version = operations.push(sample, &matched.scientific_name, dataset_version.id, last_version);
+                    imported += 1;
+                }
+                None => {
+                    let entry = unmatched.entry(name.clone()).or_insert_with(|| SkippedSamples {
+                        scientific_name: Some(name),
+                        taxonomy_id: sample.taxonomy_id.clone(),
+                        samples: 0,
+                        offset: None,
+                        error: None,
+                    });
+                    entry.samples += 1;
+                }
+            }
+        }
+
+        conn.transaction(|conn| {
+            pipeline.insert(conn, &operations.organisms)?;
+            pipeline.insert(conn, &operations.collections)?;
+            pipeline.insert(conn, &operations.accessions)?;
+            pipeline.insert(conn, &operations.subsamples)?;
+            pipeline.insert(conn, &operations.extractions)?;
+            Ok::<(), Error>(())
+        })?;
+    }
+
+    parse_bar.finish();
+
+    // the most common names first since they have the biggest impact when matched
+    let mut unmatched: Vec<SkippedSamples> = unmatched.into_values().collect();
+    unmatched.sort_by(|a, b| b.samples.cmp(&a.samples).then_with(|| a.scientific_name.cmp(&b.scientific_name)));
+
+    let mut writer = csv::Writer::from_path(report)?;
+    for skipped in unmatched.iter().chain(failed.iter()) {
+        writer.serialize(skipped)?;
+    }
+    writer.flush()?;
+
+    info!(imported, unmatched=unmatched.len(), failed=failed.len(), "Finished importing");
+    Ok(())
+}
+
+
+/// The taxonomy name of a sample, which every converted event is keyed by
+fn taxonomy_name(sample: &BioSample) -> Result<String, Error> {
+    let name = sample.taxonomy_name.clone();
+    name.ok_or_else(|| ParseError::NotFound(format!("taxonomy name for {}", sample.accession)).into())
+}
+
+
 fn extract_collection_events(samples: &Vec<BioSample>, bars: &Progress) -> Result<Vec<CollectionEvent>, Error> {
     let records = samples.par_iter().multiprogress_with(bars, "Extracting collection events").map(|sample| {
-        CollectionEvent {
-            scientific_name: sample.taxonomy_name.clone().unwrap(),
+        Ok(CollectionEvent {
+            scientific_name: taxonomy_name(sample)?,
             record_id: sample.accession.clone(),
             sex: sample.get_attribute("sex"),
             life_stage: sample.get_attribute("developmental-stage"),
@@ -442,15 +586,15 @@ fn extract_collection_events(samples: &Vec<BioSample>, bars: &Progress) -> Resul
             host_spec_range: sample.get_attribute("host-taxid"),
             strain: sample.get_attribute("strain"),
             isolate: sample.get_attribute("isolate"),
-        }
-    }).collect();
+        })
+    }).collect::<Result<Vec<_>, Error>>()?;
     Ok(records)
 }
 
 fn extract_accession_events(samples: &Vec<BioSample>, bars: &Progress) -> Result<Vec<AccessionEvent>, Error> {
     let records = samples.par_iter().multiprogress_with(bars, "Extracting accession events").map(|sample| {
-        AccessionEvent {
-            scientific_name: sample.taxonomy_name.clone().unwrap(),
+        Ok(AccessionEvent {
+            scientific_name: taxonomy_name(sample)?,
             record_id: sample.accession.clone(),
             accession: sample.accession.clone(),
             taxon_id: sample.taxonomy_id.clone(),
@@ -461,37 +605,37 @@ fn extract_accession_events(samples: &Vec<BioSample>, bars: &Progress) -> Result
             name: sample.owner.clone(),
             alternate_name: sample.owner_code.clone(),
             relation_to_type_material: sample.get_attribute("type-material"),
-        }
-    }).collect();
+        })
+    }).collect::<Result<Vec<_>, Error>>()?;
     Ok(records)
 }
 
 fn extract_subsample_events(samples: &Vec<BioSample>, bars: &Progress) -> Result<Vec<SubsampleEvent>, Error> {
     let records = samples.par_iter().multiprogress_with(bars, "Extracting subsample events").map(|sample| {
-        SubsampleEvent {
-            scientific_name: sample.taxonomy_name.clone().unwrap(),
+        Ok(SubsampleEvent {
+            scientific_name: taxonomy_name(sample)?,
             record_id: sample.accession.clone(),
             preparation_type: sample.get_attribute("tissue"),
-        }
-    }).collect();
+        })
+    }).collect::<Result<Vec<_>, Error>>()?;
     Ok(records)
 }
 
 fn extract_extraction_events(samples: &Vec<BioSample>, bars: &Progress) -> Result<Vec<ExtractionEvent>, Error> {
     let records = samples.par_iter().multiprogress_with(bars, "Extracting subsample events").map(|sample| {
-        ExtractionEvent {
-            scientific_name: sample.taxonomy_name.clone().unwrap(),
+        Ok(ExtractionEvent {
+            scientific_name: taxonomy_name(sample)?,
             record_id: sample.accession.clone(),
             measurement_method: sample.get_attribute("nucleic-acid-extraction"),
-        }
-    }).collect();
+        })
+    }).collect::<Result<Vec<_>, Error>>()?;
     Ok(records)
 }
 
 fn extract_sequencing_events(samples: &Vec<BioSample>, bars: &Progress) -> Result<Vec<SequencingEvent>, Error> {
     let records = samples.par_iter().multiprogress_with(bars, "Extracting sequencing events").map(|sample| {
-        SequencingEvent {
-            scientific_name: sample.taxonomy_name.clone().unwrap(),
+        Ok(SequencingEvent {
+            scientific_name: taxonomy_name(sample)?,
             record_id: sample.accession.clone(),
             measurement_method: sample.get_attribute("sequencing-method"),
             resource_id: sample.sra.clone(),
@@ -501,38 +645,38 @@ fn extract_sequencing_events(samples: &Vec<BioSample>, bars: &Progress) -> Resul
             relationship_according_to: Some("NCBI-Biosample".to_string()),
             estimated_size: sample.get_attribute("estimated-size"),
             seq_meth: sample.get_attribute("sequencing-method"),
-        }
-    }).collect();
+        })
+    }).collect::<Result<Vec<_>, Error>>()?;
     Ok(records)
 }
 
 fn extract_assembly_events(samples: &Vec<BioSample>, bars: &Progress) -> Result<Vec<AssemblyEvent>, Error> {
     let records = samples.par_iter().multiprogress_with(bars, "Extracting assembly events").map(|sample| {
-        AssemblyEvent {
-            scientific_name: sample.taxonomy_name.clone().unwrap(),
+        Ok(AssemblyEvent {
+            scientific_name: taxonomy_name(sample)?,
             record_id: sample.accession.clone(),
             assembly_name: sample.get_attribute("assembly"),
-        }
-    }).collect();
+        })
+    }).collect::<Result<Vec<_>, Error>>()?;
     Ok(records)
 }
 
 fn extract_annotation_events(samples: &Vec<BioSample>, bars: &Progress) -> Result<Vec<AnnotationEvent>, Error> {
     let records = samples.par_iter().multiprogress_with(bars, "Extracting annotation events").map(|sample| {
-        AnnotationEvent {
-            scientific_name: sample.taxonomy_name.clone().unwrap(),
+        Ok(AnnotationEvent {
+            scientific_name: taxonomy_name(sample)?,
             record_id: sample.accession.clone(),
             num_replicons: sample.get_attribute("num_replicons"),
             sop: sample.get_attribute("sop"),
-        }
-    }).collect();
+        })
+    }).collect::<Result<Vec<_>, Error>>()?;
     Ok(records)
 }
 
 fn extract_data_accession_events(samples: &Vec<BioSample>, bars: &Progress) -> Result<Vec<DataDepositionEvent>, Error> {
     let records = samples.par_iter().multiprogress_with(bars, "Extracting data accession events").map(|sample| {
-        DataDepositionEvent {
-            scientific_name: sample.taxonomy_name.clone().unwrap(),
+        Ok(DataDepositionEvent {
+            scientific_name: taxonomy_name(sample)?,
             record_id: sample.accession.clone(),
             accession: sample.accession.clone(),
             material_sample_id: Some(sample.accession.clone()),
@@ -545,8 +689,8 @@ fn extract_data_accession_events(samples: &Vec<BioSample>, bars: &Progress) -> R
             record_submission_date: sample.submission_date.clone(),
             record_last_update_date: sample.last_update.clone(),
             record_title_text: sample.title.clone(),
-        }
-    }).collect();
+        })
+    }).collect::<Result<Vec<_>, Error>>()?;
     Ok(records)
 }
 
@@ -561,23 +705,21 @@ fn summarise_biosamples(analysed: AnalysedBiosamples) -> Result<HashMap<String,
     let summaries = analysed.offsets.chunks(1_000_000).map(|chunk| {
         let mut attrs: HashMap<String, usize> = HashMap::new();
 
-        let samples: Vec<BioSample> = chunk
+        let samples = chunk
             .into_par_iter()
             .progress_with(transform_bar.clone())
-            .map(|(start, end)| {
-                process_item(&analysed.mmap, *start, *end).expect("Failed to process item")
-            })
-            .collect();
+            .map(|(start, end)| process_item(&analysed.mmap, *start, *end))
+            .collect::<Result<Vec<BioSample>, Error>>()?;
 
         for item in samples {
             for attr in item.attributes {
-                let name = attr.harmonized_name.or_else(|| Some(attr.name)).unwrap();
+                let name = attr.harmonized_name.unwrap_or(attr.name);
                 attrs.entry(name).and_modify(|counter| *counter += 1).or_insert(1);
             }
         };
 
-        attrs
-    }).collect::<Vec<HashMap<String, usize>>>();
+        Ok(attrs)
+    }).collect::<Result<Vec<HashMap<String, usize>>, Error>>()?;
 
     transform_bar.finish();
     info!("Finished summarising");
@@ -609,7 +751,8 @@ fn process_item(mmap: &memmap2::Mmap, start: usize, end: usize) -> Result<BioSam
         state = match (state, reader.read_event_into(&mut buf)?) {
             // Attributes
             (State::Attributes, Event::Start(e)) if e.local_name().as_ref() == b"Attribute" => State::Attribute(Attribute {
-                name: parse_attribute(&reader, &e, "attribute_name")?.unwrap(),
+                name: parse_attribute(&reader, &e, "attribute_name")?
+                    .ok_or_else(|| ParseError::NotFound("attribute_name".to_string()))?,
                 harmonized_name: parse_attribute(&reader, &e, "harmonized_name")?,
                 value: None,
             }),
@@ -731,7 +874,9 @@ fn process_item(mmap: &memmap2::Mmap, start: usize, end: usize) -> Result<BioSam
 
 
             (State::Root, Event::Eof) => break,
-            (state, Event::Eof) => panic!("Unexpected end of file. Last state: {state:?}"),
+            (state, Event::Eof) => {
+                return Err(ParseError::InvalidStructure(format!("unexpected end of item. Last state: {state:?}")).into())
+            }
             (state, _) => state,
         };
     }
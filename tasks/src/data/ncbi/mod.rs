@@ -1,7 +1,19 @@
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use arga_core::models::entity_hash;
+use arga_core::schema;
+use diesel::*;
+
+use crate::data::Error;
+use crate::data::oplogger::create_dataset_version;
+use crate::data::pipeline::Pipeline;
 
 pub mod name_matcher;
+pub mod assemblies;
 pub mod biosamples;
+pub mod operations;
+pub mod sra;
 
 
 #[derive(clap::Subcommand)]
@@ -15,6 +27,51 @@ pub enum Command {
         out: String,
     },
 
+    /// Import a biosamples XML directly into the operation logs under a new dataset version
+    ImportBiosamples {
+        dataset_id: String,
+        version: String,
+        created_at: String,
+
+        /// The biosamples XML file
+        input: String,
+
+        /// The CSV file to write the names that couldn't be matched into
+        #[arg(long, default_value = "biosamples_unmatched.csv")]
+        report: PathBuf,
+    },
+
+    /// Import an assembly summary or datasets report as assemblies, libraries and depositions
+    ImportAssemblies {
+        dataset_id: String,
+        version: String,
+        created_at: String,
+
+        /// The assembly_summary.txt or assembly_data_report.jsonl file
+        input: String,
+
+        #[arg(long, value_enum, default_value_t = assemblies::AssemblyFormat::Summary)]
+        format: assemblies::AssemblyFormat,
+
+        /// The CSV file to write the biosamples that haven't been imported yet into
+        #[arg(long, default_value = "assemblies_unlinked.csv")]
+        report: PathBuf,
+    },
+
+    /// Import an SRA run info CSV as libraries and sequence runs
+    ImportSraRuns {
+        dataset_id: String,
+        version: String,
+        created_at: String,
+
+        /// The run info CSV file
+        input: String,
+
+        /// The CSV file to write the biosamples that haven't been imported yet into
+        #[arg(long, default_value = "sra_unlinked.csv")]
+        report: PathBuf,
+    },
+
     /// Summarise a biosamples XML file
     SummariseBiosamples {
         /// The biosamples XML file
@@ -25,6 +82,81 @@ pub enum Command {
 pub fn process_command(command: &Command) {
     match command {
         Command::ConvertBiosamples { input, out } => biosamples::convert(PathBuf::from(input), PathBuf::from(out)).unwrap(),
+        Command::ImportBiosamples { dataset_id, version, created_at, input, report } => {
+            let dataset_version = create_dataset_version(dataset_id, version, created_at).unwrap();
+            let mut pipeline = Pipeline::import();
+            biosamples::import(PathBuf::from(input), dataset_version, report.clone(), &mut pipeline).unwrap();
+            pipeline.report();
+        }
+        Command::ImportAssemblies { dataset_id, version, created_at, input, format, report } => {
+            let dataset_version = create_dataset_version(dataset_id, version, created_at).unwrap();
+            let mut pipeline = Pipeline::import();
+            assemblies::import(PathBuf::from(input), *format, dataset_version, report, &mut pipeline).unwrap();
+            pipeline.report();
+        }
+        Command::ImportSraRuns { dataset_id, version, created_at, input, report } => {
+            let dataset_version = create_dataset_version(dataset_id, version, created_at).unwrap();
+            let mut pipeline = Pipeline::import();
+            sra::import(PathBuf::from(input), dataset_version, report, &mut pipeline).unwrap();
+            pipeline.report();
+        }
         Command::SummariseBiosamples { input } => biosamples::summarise(PathBuf::from(input)).unwrap(),
     }
 }
+
+
+/// A biosample referenced by an assembly or run that hasn't been imported yet
+#[derive(serde::Serialize, Debug)]
+struct UnlinkedBioSample {
+    biosample: String,
+}
+
+
+/// Find the biosample accessions that don't have an extraction imported yet.
+///
+/// Assemblies and runs link to the biosample extract by accession so anything imported
+/// before its biosample won't be linked until the biosample is imported.
+fn unlinked_biosamples(conn: &mut PgConnection, accessions: &HashSet<String>) -> Result<Vec<String>, Error> {
+    use schema::extraction_logs::dsl::*;
+
+    let mut unlinked = Vec::new();
+    let accessions: Vec<&String> = accessions.iter().collect();
+
+    for chunk in accessions.chunks(10_000) {
+        let hashes: Vec<String> = chunk.iter().map(|accession| entity_hash(accession)).collect();
+        let found: HashSet<String> = extraction_logs
+            .filter(entity_id.eq_any(&hashes))
+            .select(entity_id)
+            .distinct()
+            .load::<String>(conn)?
+            .into_iter()
+            .collect();
+
+        for (accession, hash) in chunk.iter().zip(hashes.iter()) {
+            if !found.contains(hash) {
+                unlinked.push(accession.to_string());
+            }
+        }
+    }
+
+    unlinked.sort();
+    Ok(unlinked)
+}
+
+/// Write the biosamples that haven't been imported yet into the report so that they can
+/// be imported before the assemblies or runs linking to them are imported again
+fn report_unlinked_biosamples(
+    conn: &mut PgConnection,
+    accessions: &HashSet<String>,
+    report: &Path,
+) -> Result<usize, Error> {
+    let unlinked = unlinked_biosamples(conn, accessions)?;
+
+    let mut writer = csv::Writer::from_path(report)?;
+    for biosample in unlinked.iter() {
+        writer.serialize(UnlinkedBioSample { biosample: biosample.clone() })?;
+    }
+    writer.flush()?;
+
+    Ok(unlinked.len())
+}
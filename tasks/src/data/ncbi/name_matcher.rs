@@ -4,10 +4,11 @@ use diesel::*;
 use diesel::r2d2::{Pool, ConnectionManager};
 use rayon::prelude::*;
 use serde::Deserialize;
-use tracing::info;
+use tracing::{debug, error, info};
 use uuid::Uuid;
 
-use arga_core::schema;
+use arga_core::models::Name;
+use arga_core::names::matching::{self, AcceptedName, Matcher, NameQuery};
 use crate::data::Error;
 
 type PgPool = Pool<ConnectionManager<PgConnection>>;
@@ -28,36 +29,83 @@ pub struct NameMatch {
 }
 
 
+/// Only matches this likely are used when importing. Fuzzy matches are left for the
+/// match report so that data isn't attached to a misspelt name without a review.
+const IMPORT_CONFIDENCE: f64 = 0.85;
+
+
+/// Match names in bulk with the core name matcher, following synonyms to their accepted names
+pub fn resolve_names(
+    names: &[String],
+    pool: &mut PgPool,
+) -> Result<Vec<(NameQuery, Vec<matching::NameMatch>)>, Error> {
+    let mut conn = pool.get()?;
+
+    let queries: Vec<NameQuery> = names.iter().map(|name| NameQuery::new(name)).collect();
+    let candidates = matching::candidates_query(&queries).load::<Name>(&mut conn)?;
+    let matcher = Matcher::new(candidates);
+
+    let mut results: Vec<(NameQuery, Vec<matching::NameMatch>)> = queries
+        .into_par_iter()
+        .map(|query| {
+            let matches = matcher.match_name(&query);
+            (query, matches)
+        })
+        .collect();
+
+    let name_ids = results
+        .iter()
+        .flat_map(|(_, matches)| matches.iter().map(|name_match| name_match.name.id))
+        .collect();
+
+    let accepted: HashMap<Uuid, Name> = matching::accepted_names_query(name_ids)
+        .load::<AcceptedName>(&mut conn)?
+        .into_iter()
+        .map(|accepted| accepted.into())
+        .collect();
+
+    for (_, matches) in results.iter_mut() {
+        matching::follow_synonyms(matches, &accepted);
+    }
+
+    Ok(results)
+}
+
+
+/// Match the records to names, keyed by the scientific name of the record.
+///
+/// Names that differ in their authorship or the gender endings of their epithets are
+/// matched as well as exact matches, but ambiguous and fuzzy matches are skipped.
 pub fn match_names(records: &Vec<NameRecord>, pool: &mut PgPool) -> HashMap<String, NameMatch> {
-    use schema::names;
     info!(total=records.len(), "Matching names");
 
-    // get 50,000 possible matches in parallel. this can speed up the matching significantly
-    // since our main limit here is the parameter limit in postgres
-    let matched: Vec<Result<Vec<NameMatch>, Error>> = records.par_chunks(50_000).map(|chunk| {
-        let mut conn = pool.get()?;
-        let all_names: Vec<&String> = chunk.iter().map(|row| &row.scientific_name).collect();
-
-        let results = names::table
-            .select((names::id, names::scientific_name, names::canonical_name))
-            .filter(names::scientific_name.eq_any(&all_names))
-            .or_filter(names::canonical_name.eq_any(&all_names))
-            .load::<NameMatch>(&mut conn)?;
+    // match 10,000 names at a time. common genera and epithets can still pull in a lot of
+    // candidates for each name, so keep the batches small enough to load them all in memory
+    let matched: Vec<Result<Vec<(String, NameMatch)>, Error>> = records.par_chunks(10_000).map(|chunk| {
+        let names: Vec<String> = chunk.iter().map(|row| row.scientific_name.clone()).collect();
+        let mut pool = pool.clone();
+
+        let mut results = Vec::with_capacity(chunk.len());
+        for (query, matches) in resolve_names(&names, &mut pool)? {
+            match matching::best_match(&matches) {
+                Some(best) if best.confidence >= IMPORT_CONFIDENCE => results.push((query.verbatim, NameMatch {
+                    id: best.name.id,
+                    scientific_name: best.name.scientific_name.clone(),
+                    canonical_name: best.name.canonical_name.clone(),
+                })),
+                Some(best) => debug!(name=query.verbatim, candidate=best.name.scientific_name, "Skipping fuzzy match"),
+                None => {}
+            }
+        }
 
-        Ok::<Vec<NameMatch>, Error>(results)
+        Ok::<Vec<(String, NameMatch)>, Error>(results)
     }).collect();
 
     let mut map: HashMap<String, NameMatch> = HashMap::new();
-
-    // for every bulk name match result from the database we insert it into a map
-    // that can be referenced either by scientific name or canonical name as some
-    // scenarios only have canonical names available
     for chunk in matched {
-        if let Ok(names) = chunk {
-            for name_match in names {
-                map.insert(name_match.canonical_name.clone(), name_match.clone());
-                map.insert(name_match.scientific_name.clone(), name_match);
-            }
+        match chunk {
+            Ok(names) => map.extend(names),
+            Err(err) => error!(?err, "Failed to match names"),
         }
     }
 
@@ -0,0 +1,584 @@
+use arga_core::crdt::{DataFrame, Version};
+use arga_core::models::{
+    entity_hash,
+    AccessionEventAtom,
+    AccessionEventOperation,
+    AssemblyAtom,
+    AssemblyOperation,
+    CollectionEventAtom,
+    CollectionEventOperation,
+    DepositionAtom,
+    DepositionOperation,
+    ExtractionAtom,
+    ExtractionOperation,
+    LibraryAtom,
+    LibraryOperation,
+    OrganismAtom,
+    OrganismOperation,
+    SequenceRunAtom,
+    SequenceRunOperation,
+    SubsampleAtom,
+    SubsampleOperation,
+};
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::data::utils::{self, non_placeholder};
+use super::assemblies::AssemblyRecord;
+use super::biosamples::BioSample;
+use super::sra::RunInfo;
+
+
+/// The operations for a batch of biosamples.
+///
+/// A biosample describes a single organism that was collected and registered with NCBI
+/// so the biosample accession is used as the specimen, organism, subsample and extract id.
+/// The subsample and extraction are always created, even when the sample doesn't record the
+/// tissue or extraction method, because the libraries of SRA experiments and assemblies link
+/// to the biosample through its extract.
+#[derive(Debug, Default)]
+pub struct BioSampleOperations {
+    pub collections: Vec<CollectionEventOperation>,
+    pub organisms: Vec<OrganismOperation>,
+    pub accessions: Vec<AccessionEventOperation>,
+    pub subsamples: Vec<SubsampleOperation>,
+    pub extractions: Vec<ExtractionOperation>,
+}
+
+impl BioSampleOperations {
+    /// Add the operations for a biosample matched to a name and return the last version used
+    pub fn push(
+        &mut self,
+        sample: &BioSample,
+        scientific_name: &str,
+        dataset_version_id: Uuid,
+        last_version: Version,
+    ) -> Version {
+        let accession = &sample.accession;
+        let entity_id = entity_hash(accession);
+
+        // organism
+        let mut frame = DataFrame::create(entity_id.clone(), dataset_version_id, last_version);
+        frame.push(OrganismAtom::OrganismId(accession.clone()));
+        frame.push(OrganismAtom::ScientificName(scientific_name.to_string()));
+
+        if let Some(value) = attribute(sample, "sex") {
+            frame.push(OrganismAtom::Sex(value));
+        }
+        if let Some(value) = attribute(sample, "developmental-stage") {
+            frame.push(OrganismAtom::LifeStage(value));
+        }
+        if let Some(value) = attribute(sample, "biome") {
+            frame.push(OrganismAtom::Biome(value));
+        }
+
+        let last_version = frame.last_version();
+        self.organisms.extend(frame.collect::<OrganismOperation>());
+
+        // collection event
+        let mut frame = DataFrame::create(entity_id.clone(), dataset_version_id, last_version);
+        frame.push(CollectionEventAtom::SpecimenId(accession.clone()));
+        frame.push(CollectionEventAtom::OrganismId(accession.clone()));
+        frame.push(CollectionEventAtom::ScientificName(scientific_name.to_string()));
+
+        if let Some(date) = attribute(sample, "collection-date").and_then(|date| parse_date(&date)) {
+            frame.push(CollectionEventAtom::EventDate(date));
+        }
+        if let Some(value) = attribute(sample, "collected-by") {
+            frame.push(CollectionEventAtom::CollectedBy(value));
+        }
+        if let Some(value) = attribute(sample, "identified-by") {
+            frame.push(CollectionEventAtom::IdentifiedBy(value));
+        }
+        if let Some(value) = attribute(sample, "geo-loc-name") {
+            // locations are formatted as `country: locality` with an optional locality
+            if let Some((country, _)) = value.split_once(':') {
+                frame.push(CollectionEventAtom::Country(country.trim().to_string()));
+            }
+            frame.push(CollectionEventAtom::Locality(value));
+        }
+        if let Some((latitude, longitude)) = attribute(sample, "lat-lon").and_then(|value| parse_lat_lon(&value)) {
+            frame.push(CollectionEventAtom::Latitude(latitude));
+            frame.push(CollectionEventAtom::Longitude(longitude));
+        }
+        if let Some(value) = attribute(sample, "env-broad-scale") {
+            frame.push(CollectionEventAtom::EnvironmentBroadScale(value));
+        }
+        if let Some(value) = attribute(sample, "env-local-scale") {
+            frame.push(CollectionEventAtom::EnvironmentLocalScale(value));
+        }
+        if let Some(value) = attribute(sample, "env-medium") {
+            frame.push(CollectionEventAtom::EnvironmentMedium(value));
+        }
+        if let Some(value) = attribute(sample, "host") {
+            frame.push(CollectionEventAtom::SpecificHost(value));
+        }
+        if let Some(value) = attribute(sample, "strain") {
+            frame.push(CollectionEventAtom::Strain(value));
+        }
+        if let Some(value) = attribute(sample, "isolate") {
+            frame.push(CollectionEventAtom::Isolate(value));
+        }
+
+        let last_version = frame.last_version();
+        self.collections.extend(frame.collect::<CollectionEventOperation>());
+
+        // accession event
+        let mut frame = DataFrame::create(entity_id.clone(), dataset_version_id, last_version);
+        frame.push(AccessionEventAtom::SpecimenId(accession.clone()));
+        frame.push(AccessionEventAtom::ScientificName(scientific_name.to_string()));
+
+        if let Some(date) = sample.submission_date.as_deref().and_then(parse_date) {
+            frame.push(AccessionEventAtom::EventDate(date));
+        }
+        if let Some(value) = attribute(sample, "specimen-voucher") {
+            frame.push(AccessionEventAtom::CollectionRepositoryId(value));
+        }
+        if let Some(value) = attribute(sample, "type-material") {
+            frame.push(AccessionEventAtom::TypeStatus(value));
+        }
+        if let Some(value) = non_placeholder(&sample.owner) {
+            frame.push(AccessionEventAtom::InstitutionName(value));
+        }
+        if let Some(value) = non_placeholder(&sample.owner_code) {
+            frame.push(AccessionEventAtom::InstitutionCode(value));
+        }
+
+        let last_version = frame.last_version();
+        self.accessions.extend(frame.collect::<AccessionEventOperation>());
+
+        // subsample
+        let mut frame = DataFrame::create(entity_id.clone(), dataset_version_id, last_version);
+        frame.push(SubsampleAtom::SubsampleId(accession.clone()));
+        frame.push(SubsampleAtom::SpecimenId(accession.clone()));
+        frame.push(SubsampleAtom::ScientificName(scientific_name.to_string()));
+
+        if let Some(tissue) = attribute(sample, "tissue") {
+            frame.push(SubsampleAtom::SampleType(tissue));
+        }
+
+        let last_version = frame.last_version();
+        self.subsamples.extend(frame.collect::<SubsampleOperation>());
+
+        // extraction
+        let mut frame = DataFrame::create(entity_id, dataset_version_id, last_version);
+        frame.push(ExtractionAtom::ExtractId(accession.clone()));
+        frame.push(ExtractionAtom::SubsampleId(accession.clone()));
+        frame.push(ExtractionAtom::ScientificName(scientific_name.to_string()));
+
+        if let Some(method) = attribute(sample, "nucleic-acid-extraction") {
+            frame.push(ExtractionAtom::ExtractionMethod(method));
+        }
+
+        let last_version = frame.last_version();
+        self.extractions.extend(frame.collect::<ExtractionOperation>());
+        last_version
+    }
+}
+
+
+
+/// The operations for a batch of genome assemblies.
+///
+/// NCBI doesn't record which sequencing libraries an assembly was built from so every
+/// assembly is linked to a library representing all the sequencing of its biosample.
+/// That library uses the biosample accession as its id and the biosample extract and, like
+/// the libraries of SRA runs, is only added for the first assembly of a biosample in an import.
+///
+/// The libraries of the SRA experiments for the biosample share the same extract, so the
+/// assembly reaches the sequencing it was built from through the extract that every
+/// biosample import creates.
+#[derive(Debug, Default)]
+pub struct AssemblyOperations {
+    pub libraries: Vec<LibraryOperation>,
+    pub assemblies: Vec<AssemblyOperation>,
+    pub depositions: Vec<DepositionOperation>,
+}
+
+impl AssemblyOperations {
+    /// Add the operations for an assembly and return the last version used
+    pub fn push(
+        &mut self,
+        record: &AssemblyRecord,
+        include_library: bool,
+        dataset_version_id: Uuid,
+        last_version: Version,
+    ) -> Version {
+        let accession = &record.accession;
+        let entity_id = entity_hash(accession);
+        let scientific_name = non_placeholder(&record.scientific_name);
+        let release_date = record.release_date.as_deref().and_then(parse_date);
+        let mut last_version = last_version;
+
+        // library
+        if let Some(biosample) = non_placeholder(&record.biosample).filter(|_| include_library) {
+            let mut frame = DataFrame::create(entity_hash(&biosample), dataset_version_id, last_version);
+            frame.push(LibraryAtom::LibraryId(biosample.clone()));
+            frame.push(LibraryAtom::ExtractId(biosample));
+
+            if let Some(name) = &scientific_name {
+                frame.push(LibraryAtom::ScientificName(name.clone()));
+            }
+
+            last_version = frame.last_version();
+            self.libraries.extend(frame.collect::<LibraryOperation>());
+        }
+
+        // assembly
+        let mut frame = DataFrame::create(entity_id.clone(), dataset_version_id, last_version);
+        frame.push(AssemblyAtom::AssemblyId(accession.clone()));
+
+        if let Some(biosample) = non_placeholder(&record.biosample) {
+            frame.push(AssemblyAtom::LibraryId(biosample));
+        }
+        if let Some(name) = &scientific_name {
+            frame.push(AssemblyAtom::ScientificName(name.clone()));
+        }
+        if let Some(date) = release_date {
+            frame.push(AssemblyAtom::EventDate(date));
+        }
+        if let Some(value) = non_placeholder(&record.name) {
+            frame.push(AssemblyAtom::Name(value));
+        }
+        if let Some(value) = non_placeholder(&record.submitter) {
+            frame.push(AssemblyAtom::Facility(value));
+        }
+        if let Some(value) = non_placeholder(&record.assembly_type) {
+            frame.push(AssemblyAtom::Type(value));
+        }
+        if let Some(value) = non_placeholder(&record.level) {
+            frame.push(AssemblyAtom::Level(value));
+        }
+        if let Some(value) = non_placeholder(&record.representation) {
+            frame.push(AssemblyAtom::Representation(value));
+        }
+        if let Some(value) = non_placeholder(&record.method) {
+            frame.push(AssemblyAtom::Method(value));
+        }
+        if let Some(value) = non_placeholder(&record.coverage) {
+            frame.push(AssemblyAtom::Coverage(value));
+        }
+        if let Some(value) = non_placeholder(&record.completeness) {
+            frame.push(AssemblyAtom::Completeness(value));
+        }
+        if let Some(value) = record.size {
+            frame.push(AssemblyAtom::Size(value));
+        }
+        if let Some(value) = record.size_ungapped {
+            frame.push(AssemblyAtom::SizeUngapped(value));
+        }
+        if let Some(value) = record.gc_percent {
+            frame.push(AssemblyAtom::GuanineCytosinePercent(value));
+        }
+        if let Some(value) = record.replicons {
+            frame.push(AssemblyAtom::NumberOfReplicons(value));
+        }
+        if let Some(value) = record.scaffolds {
+            frame.push(AssemblyAtom::NumberOfScaffolds(value));
+        }
+        if let Some(value) = record.contigs {
+            frame.push(AssemblyAtom::NumberOfContigs(value));
+        }
+        if let Some(value) = record.chromosomes {
+            frame.push(AssemblyAtom::NumberOfChromosomes(value));
+        }
+        if let Some(value) = record.organelles {
+            frame.push(AssemblyAtom::NumberOfOrganelles(value));
+        }
+        if let Some(value) = record.component_sequences {
+            frame.push(AssemblyAtom::NumberOfComponentSequences(value));
+        }
+        if let Some(value) = record.contig_n50 {
+            frame.push(AssemblyAtom::ContigN50(value));
+        }
+        if let Some(value) = record.contig_l50 {
+            frame.push(AssemblyAtom::ContigL50(value));
+        }
+        if let Some(value) = record.scaffold_n50 {
+            frame.push(AssemblyAtom::ScaffoldN50(value));
+        }
+        if let Some(value) = record.scaffold_l50 {
+            frame.push(AssemblyAtom::ScaffoldL50(value));
+        }
+
+        let last_version = frame.last_version();
+        self.assemblies.extend(frame.collect::<AssemblyOperation>());
+
+        // deposition
+        let mut frame = DataFrame::create(entity_id, dataset_version_id, last_version);
+        frame.push(DepositionAtom::AssemblyId(accession.clone()));
+
+        if let Some(date) = release_date {
+            frame.push(DepositionAtom::EventDate(date));
+        }
+        if let Some(value) = non_placeholder(&record.url) {
+            frame.push(DepositionAtom::Url(value));
+        }
+        if let Some(value) = non_placeholder(&record.submitter) {
+            frame.push(DepositionAtom::Institution(value));
+        }
+
+        let last_version = frame.last_version();
+        self.depositions.extend(frame.collect::<DepositionOperation>());
+        last_version
+    }
+}
+
+
+/// The operations for a batch of SRA runs.
+///
+/// Every SRA experiment is a library prepared from a biosample and can be sequenced
+/// in multiple runs. The library is only added for the first run of an experiment in an import.
+#[derive(Debug, Default)]
+pub struct RunOperations {
+    pub libraries: Vec<LibraryOperation>,
+    pub runs: Vec<SequenceRunOperation>,
+}
+
+impl RunOperations {
+    /// Add the operations for a run and return the last version used
+    pub fn push(
+        &mut self,
+        run: &RunInfo,
+        include_library: bool,
+        dataset_version_id: Uuid,
+        last_version: Version,
+    ) -> Version {
+        let scientific_name = non_placeholder(&run.scientific_name);
+        let mut last_version = last_version;
+
+        // library
+        if include_library {
+            let mut frame = DataFrame::create(entity_hash(&run.experiment), dataset_version_id, last_version);
+            frame.push(LibraryAtom::LibraryId(run.experiment.clone()));
+
+            if let Some(biosample) = non_placeholder(&run.biosample) {
+                frame.push(LibraryAtom::ExtractId(biosample));
+            }
+            if let Some(name) = &scientific_name {
+                frame.push(LibraryAtom::ScientificName(name.clone()));
+            }
+            if let Some(value) = non_placeholder(&run.library_layout) {
+                frame.push(LibraryAtom::Layout(value));
+            }
+            if let Some(value) = non_placeholder(&run.library_selection) {
+                frame.push(LibraryAtom::Selection(value));
+            }
+            if let Some(value) = non_placeholder(&run.library_source) {
+                frame.push(LibraryAtom::Source(value));
+            }
+            if let Some(value) = non_placeholder(&run.library_strategy) {
+                frame.push(LibraryAtom::Strategy(value));
+            }
+            if let Some(value) = non_placeholder(&run.insert_size).filter(|size| size != "0") {
+                frame.push(LibraryAtom::InsertSize(value));
+            }
+
+            last_version = frame.last_version();
+            self.libraries.extend(frame.collect::<LibraryOperation>());
+        }
+
+        // sequence run
+        let mut frame = DataFrame::create(entity_hash(&run.run), dataset_version_id, last_version);
+        frame.push(SequenceRunAtom::SequenceRunId(run.run.clone()));
+        frame.push(SequenceRunAtom::LibraryId(run.experiment.clone()));
+        frame.push(SequenceRunAtom::SraRunAccession(run.run.clone()));
+
+        if let Some(name) = scientific_name {
+            frame.push(SequenceRunAtom::ScientificName(name));
+        }
+        if let Some(date) = run.release_date.as_deref().and_then(parse_date) {
+            frame.push(SequenceRunAtom::EventDate(date));
+        }
+        if let Some(value) = non_placeholder(&run.center_name) {
+            frame.push(SequenceRunAtom::Facility(value));
+        }
+        if let Some(value) = non_placeholder(&run.platform) {
+            frame.push(SequenceRunAtom::Platform(value));
+        }
+        if let Some(value) = non_placeholder(&run.model) {
+            frame.push(SequenceRunAtom::InstrumentOrMethod(value));
+        }
+
+        let last_version = frame.last_version();
+        self.runs.extend(frame.collect::<SequenceRunOperation>());
+        last_version
+    }
+}
+
+fn attribute(sample: &BioSample, name: &str) -> Option<String> {
+    non_placeholder(&sample.get_attribute(name))
+}
+
+/// Parse a date from any of the NCBI databases. Partial dates can't be represented so they are skipped
+pub(super) fn parse_date(value: &str) -> Option<NaiveDate> {
+    utils::parse_date(value).ok()
+}
+
+/// Parse a `lat-lon` attribute in the `12.34 S 56.78 E` format recommended by NCBI
+/// or as plain signed decimal degrees
+fn parse_lat_lon(value: &str) -> Option<(f64, f64)> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+
+    match parts.as_slice() {
+        [latitude, lat_dir, longitude, lon_dir] => {
+            let latitude = latitude.parse::<f64>().ok()?;
+            let longitude = longitude.parse::<f64>().ok()?;
+
+            let latitude = match *lat_dir {
+                "N" => latitude,
+                "S" => -latitude,
+                _ => return None,
+            };
+            let longitude = match *lon_dir {
+                "E" => longitude,
+                "W" => -longitude,
+                _ => return None,
+            };
+
+            Some((latitude, longitude))
+        }
+        [latitude, longitude] => Some((latitude.parse().ok()?, longitude.parse().ok()?)),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn biosample() -> BioSample {
+        BioSample {
+            accession: "SAMN00000001".to_string(),
+            taxonomy_name: Some("Felis catus".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn extract_ids(libraries: &[LibraryOperation]) -> Vec<String> {
+        libraries
+            .iter()
+            .filter_map(|op| match &op.atom {
+                LibraryAtom::ExtractId(id) => Some(id.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn biosamples_always_have_an_extract() {
+        let mut operations = BioSampleOperations::default();
+        operations.push(&biosample(), "Felis catus", Uuid::new_v4(), Version::new());
+
+        let extraction = &operations.extractions;
+        assert!(extraction.iter().all(|op| op.entity_id == entity_hash("SAMN00000001")));
+        assert!(extraction.iter().any(|op| op.atom == ExtractionAtom::ExtractId("SAMN00000001".to_string())));
+        assert!(extraction.iter().any(|op| op.atom == ExtractionAtom::SubsampleId("SAMN00000001".to_string())));
+        assert!(!extraction.iter().any(|op| matches!(op.atom, ExtractionAtom::ExtractionMethod(_))));
+
+        let subsample = &operations.subsamples;
+        assert!(subsample.iter().any(|op| op.atom == SubsampleAtom::SubsampleId("SAMN00000001".to_string())));
+        assert!(!subsample.iter().any(|op| matches!(op.atom, SubsampleAtom::SampleType(_))));
+    }
+
+    #[test]
+    fn assemblies_link_to_the_biosample_library() {
+        let record = AssemblyRecord {
+            accession: "GCA_000000001.1".to_string(),
+            biosample: Some("SAMN00000001".to_string()),
+            ..Default::default()
+        };
+
+        let mut operations = AssemblyOperations::default();
+        operations.push(&record, true, Uuid::new_v4(), Version::new());
+
+        let library = &operations.libraries;
+        assert!(library.iter().all(|op| op.entity_id == entity_hash("SAMN00000001")));
+        assert!(library.iter().any(|op| op.atom == LibraryAtom::LibraryId("SAMN00000001".to_string())));
+        assert_eq!(extract_ids(library), vec!["SAMN00000001".to_string()]);
+
+        let assembly = &operations.assemblies;
+        assert!(assembly.iter().any(|op| op.atom == AssemblyAtom::LibraryId("SAMN00000001".to_string())));
+
+        // the library is only added for the first assembly of a biosample
+        let mut operations = AssemblyOperations::default();
+        operations.push(&record, false, Uuid::new_v4(), Version::new());
+        assert!(operations.libraries.is_empty());
+        assert!(!operations.assemblies.is_empty());
+    }
+
+    #[test]
+    fn runs_share_the_extract_of_assemblies() {
+        let run = RunInfo {
+            run: "SRR0000001".to_string(),
+            experiment: "SRX0000001".to_string(),
+            biosample: Some("SAMN00000001".to_string()),
+            ..Default::default()
+        };
+        let record = AssemblyRecord {
+            accession: "GCA_000000001.1".to_string(),
+            biosample: Some("SAMN00000001".to_string()),
+            ..Default::default()
+        };
+
+        let mut runs = RunOperations::default();
+        runs.push(&run, true, Uuid::new_v4(), Version::new());
+        let mut assemblies = AssemblyOperations::default();
+        assemblies.push(&record, true, Uuid::new_v4(), Version::new());
+
+        let mut biosamples = BioSampleOperations::default();
+        biosamples.push(&biosample(), "Felis catus", Uuid::new_v4(), Version::new());
+        let extract = ExtractionAtom::ExtractId("SAMN00000001".to_string());
+        assert!(biosamples.extractions.iter().any(|op| op.atom == extract));
+
+        assert_eq!(extract_ids(&runs.libraries), extract_ids(&assemblies.libraries));
+        assert!(runs.libraries.iter().all(|op| op.entity_id == entity_hash("SRX0000001")));
+        assert!(runs.runs.iter().any(|op| op.atom == SequenceRunAtom::LibraryId("SRX0000001".to_string())));
+    }
+
+    #[test]
+    fn parses_directional_coordinates() {
+        assert_eq!(parse_lat_lon("12.5 N 130.25 E"), Some((12.5, 130.25)));
+        assert_eq!(parse_lat_lon("12.5 S 130.25 W"), Some((-12.5, -130.25)));
+        assert_eq!(parse_lat_lon("0 N 0 E"), Some((0.0, 0.0)));
+    }
+
+    #[test]
+    fn parses_decimal_coordinates() {
+        assert_eq!(parse_lat_lon("-33.86 151.2"), Some((-33.86, 151.2)));
+        assert_eq!(parse_lat_lon("  -33.86   151.2 "), Some((-33.86, 151.2)));
+    }
+
+    #[test]
+    fn rejects_invalid_coordinates() {
+        assert_eq!(parse_lat_lon("12.5 X 130.25 E"), None);
+        assert_eq!(parse_lat_lon("12.5 N 130.25 Q"), None);
+        assert_eq!(parse_lat_lon("north N 130.25 E"), None);
+        assert_eq!(parse_lat_lon("12.5 N 130.25"), None);
+        assert_eq!(parse_lat_lon("12.5"), None);
+        assert_eq!(parse_lat_lon("not collected"), None);
+        assert_eq!(parse_lat_lon(""), None);
+    }
+
+    #[test]
+    fn parses_ncbi_dates() {
+        assert_eq!(parse_date("2020-03-04"), Some(date(2020, 3, 4)));
+        assert_eq!(parse_date("2020/03/04"), Some(date(2020, 3, 4)));
+        assert_eq!(parse_date("04-Mar-2020"), Some(date(2020, 3, 4)));
+        assert_eq!(parse_date("2020-03-04T10:20:30.000"), Some(date(2020, 3, 4)));
+    }
+
+    #[test]
+    fn rejects_partial_and_invalid_dates() {
+        assert_eq!(parse_date("2020"), None);
+        assert_eq!(parse_date("2020-03"), None);
+        assert_eq!(parse_date("2020-13-01"), None);
+        assert_eq!(parse_date("missing"), None);
+        assert_eq!(parse_date(""), None);
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use arga_core::crdt::Version;
+use arga_core::models::DatasetVersion;
+use diesel::*;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use super::operations::RunOperations;
+use super::report_unlinked_biosamples;
+use crate::data::oplogger::get_pool;
+use crate::data::pipeline::Pipeline;
+use crate::data::utils::non_placeholder;
+use crate::data::Error;
+
+
+/// A run in an SRA run info CSV, such as the ones from `efetch -db sra -format runinfo`
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct RunInfo {
+    #[serde(rename = "Run")]
+    pub run: String,
+    #[serde(rename = "Experiment")]
+    pub experiment: String,
+    #[serde(rename = "BioSample")]
+    pub biosample: Option<String>,
+    #[serde(rename = "ScientificName")]
+    pub scientific_name: Option<String>,
+    #[serde(rename = "ReleaseDate")]
+    pub release_date: Option<String>,
+    #[serde(rename = "LibraryStrategy")]
+    pub library_strategy: Option<String>,
+    #[serde(rename = "LibrarySelection")]
+    pub library_selection: Option<String>,
+    #[serde(rename = "LibrarySource")]
+    pub library_source: Option<String>,
+    #[serde(rename = "LibraryLayout")]
+    pub library_layout: Option<String>,
+    #[serde(rename = "InsertSize")]
+    pub insert_size: Option<String>,
+    #[serde(rename = "Platform")]
+    pub platform: Option<String>,
+    #[serde(rename = "Model")]
+    pub model: Option<String>,
+    #[serde(rename = "CenterName")]
+    pub center_name: Option<String>,
+}
+
+
+/// Import the runs in an SRA run info CSV into the operation logs.
+///
+/// Any biosample the runs reference that hasn't been imported yet is written to the report.
+pub fn import(
+    input: PathBuf,
+    dataset_version: DatasetVersion,
+    report: &Path,
+    pipeline: &mut Pipeline,
+) -> Result<(), Error> {
+    info!(?input, "Reading SRA runs");
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(&input)?;
+
+    let mut runs = Vec::new();
+    for row in reader.deserialize::<RunInfo>() {
+        let run = row?;
+        // files fetched in batches repeat the header for every batch
+        if run.run != "Run" && !run.run.is_empty() {
+            runs.push(run);
+        }
+    }
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    let biosamples: HashSet<String> = runs.iter().filter_map(|run| non_placeholder(&run.biosample)).collect();
+    let unlinked = report_unlinked_biosamples(&mut conn, &biosamples, report)?;
+    if unlinked > 0 {
+        warn!(unlinked, ?report, "Runs reference biosamples that haven't been imported");
+    }
+
+    info!(total = runs.len(), "Importing SRA runs");
+    let mut last_version = Version::new();
+    let mut libraries = HashSet::new();
+
+    for chunk in runs.chunks(100_000) {
+        let mut operations = RunOperations::default();
+
+        for run in chunk {
+            let include_library = libraries.insert(run.experiment.clone());
+            last_version = operations.push(run, include_library, dataset_version.id, last_version);
+        }
+
+        conn.transaction(|conn| {
+            pipeline.insert(conn, &operations.libraries)?;
+            pipeline.insert(conn, &operations.runs)?;
+            Ok::<(), Error>(())
+        })?;
+    }
+
+    info!(total = runs.len(), "Importing SRA runs finished");
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use arga_core::crdt::hlc::{HybridTimestamp, MAX_LOGICAL};
+use arga_core::models::logs::snapshots;
+use arga_core::models::logs::OperationLogTable;
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+use crate::data::oplogger::get_pool;
+use crate::data::{Error, ParseError};
+
+
+/// Snapshot every entity in a log table and archive the superseded operations.
+pub fn compact<T: OperationLogTable>(watermark: HybridTimestamp) -> Result<(), Error> {
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    info!(table = T::table_name(), %watermark, "Compacting operations");
+    let compaction = snapshots::compact::<T>(&mut conn, watermark)?;
+
+    info!(
+        table = T::table_name(),
+        entities = compaction.entities,
+        snapshots = compaction.snapshots,
+        archived = compaction.archived,
+        "Compaction finished"
+    );
+    Ok(())
+}
+
+/// Check that every parent id in a log table can still be found after compaction.
+pub fn verify<T: OperationLogTable>() -> Result<(), Error> {
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    let verification = snapshots::verify_chain::<T>(&mut conn)?;
+
+    for operation_id in verification.dangling.iter() {
+        warn!(table = T::table_name(), %operation_id, "Parent operation not found");
+    }
+
+    info!(
+        table = T::table_name(),
+        operations = verification.operations,
+        archived = verification.archived,
+        dangling = verification.dangling.len(),
+        valid = verification.is_valid(),
+        "Verification finished"
+    );
+    Ok(())
+}
+
+
+/// Parse a compaction watermark.
+///
+/// The watermark can either be a raw hybrid timestamp as stored in the operation_id
+/// column or an RFC 3339 date time. A date time includes every operation created
+/// within the same millisecond. Defaults to the current time when not specified.
+pub fn parse_watermark(value: Option<&str>) -> Result<HybridTimestamp, ParseError> {
+    let datetime = match value {
+        None => Utc::now(),
+        Some(value) => match value.parse::<u64>() {
+            Ok(timestamp) => return Ok(HybridTimestamp::new(timestamp)),
+            Err(_) => DateTime::parse_from_rfc3339(value)?.to_utc(),
+        },
+    };
+
+    let timestamp: HybridTimestamp = datetime.into();
+    Ok(HybridTimestamp::with_logical(timestamp.as_u64(), MAX_LOGICAL))
+}
@@ -1,16 +1,22 @@
+pub mod compaction;
 pub mod nomenclatural_acts;
+pub mod reducer;
+pub mod rollback;
 pub mod specimens;
 pub mod taxa;
 
 use std::path::PathBuf;
 
-use arga_core::models::DatasetVersion;
+use arga_core::crdt::hlc::HybridTimestamp;
+use arga_core::models::logs::OperationLogTable;
+use arga_core::models::{self, DatasetVersion};
 use arga_core::schema;
 use chrono::{DateTime, Utc};
 use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::*;
 use uuid::Uuid;
 
+use super::pipeline::Pipeline;
 use super::Error;
 
 type PgPool = Pool<ConnectionManager<PgConnection>>;
@@ -25,6 +31,22 @@ pub enum Command {
     /// Reduce operation logs into entity objects
     #[command(subcommand)]
     Reduce(ReduceCommand),
+
+    /// Snapshot the entities in an operation log and archive superseded operations
+    Compact {
+        #[arg(value_enum)]
+        log: LogType,
+        /// Compact operations up to and including this hybrid timestamp or RFC 3339 date time.
+        /// Defaults to now
+        #[arg(long)]
+        watermark: Option<String>,
+    },
+
+    /// Verify that every operation in a log can still be traced to its parent
+    Verify {
+        #[arg(value_enum)]
+        log: LogType,
+    },
 }
 
 #[derive(clap::Subcommand)]
@@ -52,6 +74,19 @@ pub enum ImportCommand {
         created_at: String,
         path: PathBuf,
     },
+
+    /// Extract operations for any log from a CSV with columns named after the log atoms
+    Log {
+        /// The operation log to import into
+        #[arg(value_enum)]
+        log: LogType,
+        /// The column with the unique value used to derive the entity id
+        entity_column: String,
+        dataset_id: String,
+        version: String,
+        created_at: String,
+        path: PathBuf,
+    },
 }
 
 #[derive(clap::Subcommand)]
@@ -61,6 +96,36 @@ pub enum ReduceCommand {
     NomenclaturalActs,
     Specimens,
     CollectionEvents,
+
+    /// Reduce any operation log into entities with a column for every atom
+    Log {
+        #[arg(value_enum)]
+        log: LogType,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum LogType {
+    Taxa,
+    TaxonomicActs,
+    NomenclaturalActs,
+    Publications,
+    Sequences,
+    Specimens,
+    CollectionEvents,
+    Organisms,
+    AccessionEvents,
+    Tissues,
+    Subsamples,
+    Extractions,
+    Libraries,
+    SequenceRuns,
+    Assemblies,
+    Annotations,
+    Depositions,
+    Agents,
+    Projects,
+    DataProducts,
 }
 
 
@@ -72,23 +137,47 @@ pub fn process_command(cmd: &Command) {
                 version,
                 created_at,
                 path,
-            } => taxa::process(path.clone(), create_dataset_version(dataset_id, version, created_at).unwrap()).unwrap(),
+            } => {
+                let dataset_version = create_dataset_version(dataset_id, version, created_at).unwrap();
+                let mut pipeline = Pipeline::import();
+                taxa::process(path.clone(), dataset_version, &mut pipeline).unwrap();
+                pipeline.report();
+            }
             ImportCommand::NomenclaturalActs {
                 dataset_id,
                 version,
                 created_at,
                 path,
-            } => nomenclatural_acts::process(
-                path.clone(),
-                create_dataset_version(dataset_id, version, created_at).unwrap(),
-            )
-            .unwrap(),
+            } => {
+                let dataset_version = create_dataset_version(dataset_id, version, created_at).unwrap();
+                let mut pipeline = Pipeline::import();
+                nomenclatural_acts::process(path.clone(), dataset_version, &mut pipeline).unwrap();
+                pipeline.report();
+            }
             ImportCommand::Specimens {
                 dataset_id,
                 version,
                 created_at,
                 path,
-            } => specimens::process(path.clone(), create_dataset_version(dataset_id, version, created_at).unwrap())
+            } => {
+                let dataset_version = create_dataset_version(dataset_id, version, created_at).unwrap();
+                let mut pipeline = Pipeline::import();
+                specimens::process(path.clone(), dataset_version, &mut pipeline).unwrap();
+                pipeline.report();
+            }
+            ImportCommand::Log {
+                log,
+                entity_column,
+                dataset_id,
+                version,
+                created_at,
+                path,
+            } => log
+                .dispatch(ImportLog {
+                    path: path.clone(),
+                    entity_column,
+                    version: create_dataset_version(dataset_id, version, created_at).unwrap(),
+                })
                 .unwrap(),
         },
         Command::Reduce(cmd) => match cmd {
@@ -97,7 +186,84 @@ pub fn process_command(cmd: &Command) {
             ReduceCommand::NomenclaturalActs => nomenclatural_acts::reduce().unwrap(),
             ReduceCommand::Specimens => specimens::reduce_specimens().unwrap(),
             ReduceCommand::CollectionEvents => specimens::reduce_collections().unwrap(),
+            ReduceCommand::Log { log } => log.dispatch(ReduceLog).unwrap(),
         },
+        Command::Compact { log, watermark } => {
+            let watermark = compaction::parse_watermark(watermark.as_deref()).unwrap();
+            log.dispatch(CompactLog(watermark)).unwrap()
+        }
+        Command::Verify { log } => log.dispatch(VerifyLog).unwrap(),
+    }
+}
+
+/// A task that can be run against any operation log table.
+///
+/// Implementing this and using `LogType::dispatch` avoids having to match every
+/// log type to its operation struct for each generic task.
+trait LogTask {
+    fn run<T: OperationLogTable>(self) -> Result<(), Error>;
+}
+
+impl LogType {
+    fn dispatch<Task: LogTask>(self, task: Task) -> Result<(), Error> {
+        match self {
+            LogType::Taxa => task.run::<models::TaxonOperation>(),
+            LogType::TaxonomicActs => task.run::<models::TaxonomicActOperation>(),
+            LogType::NomenclaturalActs => task.run::<models::NomenclaturalActOperation>(),
+            LogType::Publications => task.run::<models::PublicationOperation>(),
+            LogType::Sequences => task.run::<models::SequenceOperation>(),
+            LogType::Specimens => task.run::<models::logs::SpecimenOperation>(),
+            LogType::CollectionEvents => task.run::<models::CollectionEventOperation>(),
+            LogType::Organisms => task.run::<models::OrganismOperation>(),
+            LogType::AccessionEvents => task.run::<models::AccessionEventOperation>(),
+            LogType::Tissues => task.run::<models::TissueOperation>(),
+            LogType::Subsamples => task.run::<models::SubsampleOperation>(),
+            LogType::Extractions => task.run::<models::ExtractionOperation>(),
+            LogType::Libraries => task.run::<models::LibraryOperation>(),
+            LogType::SequenceRuns => task.run::<models::SequenceRunOperation>(),
+            LogType::Assemblies => task.run::<models::AssemblyOperation>(),
+            LogType::Annotations => task.run::<models::AnnotationOperation>(),
+            LogType::Depositions => task.run::<models::DepositionOperation>(),
+            LogType::Agents => task.run::<models::AgentOperation>(),
+            LogType::Projects => task.run::<models::ProjectOperation>(),
+            LogType::DataProducts => task.run::<models::DataProductOperation>(),
+        }
+    }
+}
+
+struct ImportLog<'a> {
+    path: PathBuf,
+    entity_column: &'a str,
+    version: DatasetVersion,
+}
+
+impl LogTask for ImportLog<'_> {
+    fn run<T: OperationLogTable>(self) -> Result<(), Error> {
+        reducer::import_csv::<T>(self.path, self.entity_column, self.version)
+    }
+}
+
+struct ReduceLog;
+
+impl LogTask for ReduceLog {
+    fn run<T: OperationLogTable>(self) -> Result<(), Error> {
+        reducer::reduce::<T>()
+    }
+}
+
+struct CompactLog(HybridTimestamp);
+
+impl LogTask for CompactLog {
+    fn run<T: OperationLogTable>(self) -> Result<(), Error> {
+        compaction::compact::<T>(self.0)
+    }
+}
+
+struct VerifyLog;
+
+impl LogTask for VerifyLog {
+    fn run<T: OperationLogTable>(self) -> Result<(), Error> {
+        compaction::verify::<T>()
     }
 }
 
@@ -108,7 +274,7 @@ pub fn get_pool() -> Result<PgPool, Error> {
     Ok(pool)
 }
 
-fn create_dataset_version(dataset_id: &str, version: &str, created_at: &str) -> Result<DatasetVersion, Error> {
+pub fn create_dataset_version(dataset_id: &str, version: &str, created_at: &str) -> Result<DatasetVersion, Error> {
     use schema::dataset_versions;
 
     let pool = get_pool()?;
@@ -128,7 +294,7 @@ fn create_dataset_version(dataset_id: &str, version: &str, created_at: &str) ->
     Ok(dataset_version)
 }
 
-fn find_database_id(dataset_id: &str) -> Result<Uuid, Error> {
+pub fn find_database_id(dataset_id: &str) -> Result<Uuid, Error> {
     use schema::datasets::dsl::*;
 
     let pool = get_pool()?;
@@ -1,25 +1,23 @@
-use std::collections::HashMap;
 use std::path::PathBuf;
 
-use arga_core::crdt::lww::Map;
+use arga_core::crdt::lww::{reduce_entities, Map};
 use arga_core::crdt::{Frame, Version};
+use arga_core::models::logs::{Action, OperationLogTable};
 use arga_core::models::{
-    Action,
     DatasetVersion,
     NomenclaturalActAtom,
     NomenclaturalActOperation,
     NomenclaturalActType,
     TaxonomicStatus,
 };
-use arga_core::schema;
 use bigdecimal::BigDecimal;
 // use chrono::{DateTime, Utc};
-use diesel::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use xxhash_rust::xxh3::Xxh3;
 
 use crate::data::oplogger::get_pool;
+use crate::data::pipeline::Pipeline;
 use crate::data::{Error, ParseError};
 
 // fn parse_date_time(value: &str) -> Result<DateTime<Utc>, ParseError> {
@@ -208,83 +206,25 @@ impl NomenclaturalActs {
     }
 }
 
-pub fn process(path: PathBuf, dataset_version: DatasetVersion) -> Result<(), Error> {
+pub fn process(path: PathBuf, dataset_version: DatasetVersion, pipeline: &mut Pipeline) -> Result<(), Error> {
     let acts = NomenclaturalActs {
         path,
         dataset_version_id: dataset_version.id,
     };
-    let records = acts.original_descriptions()?;
-    let reduced = reduce_operations(records)?;
 
-    import_operations(reduced)?;
-    Ok(())
+    pipeline.merge(acts.original_descriptions()?)
 }
 
 pub fn reduce() -> Result<(), Error> {
     reduce_acts()
 }
 
-fn import_operations(records: Vec<NomenclaturalActOperation>) -> Result<(), Error> {
-    use schema::nomenclatural_act_logs::dsl::*;
-
-    let pool = get_pool()?;
-    let mut conn = pool.get()?;
-
-    for chunk in records.chunks(1000) {
-        diesel::insert_into(nomenclatural_act_logs)
-            .values(chunk)
-            .execute(&mut conn)?;
-    }
-
-    Ok(())
-}
-
-fn merge_operations(
-    records: Vec<NomenclaturalActOperation>,
-) -> Result<HashMap<String, Vec<NomenclaturalActOperation>>, Error> {
-    use schema::nomenclatural_act_logs::dsl::*;
-
+fn reduce_acts() -> Result<(), Error> {
     let pool = get_pool()?;
     let mut conn = pool.get()?;
 
-    let operations = nomenclatural_act_logs
-        .order(operation_id.asc())
-        .load::<NomenclaturalActOperation>(&mut conn)?;
-
-    let mut grouped_ops: HashMap<String, Vec<NomenclaturalActOperation>> = HashMap::new();
-    for op in operations.into_iter() {
-        grouped_ops.entry(op.entity_id.clone()).or_default().push(op)
-    }
-
-    for op in records.into_iter() {
-        grouped_ops.entry(op.entity_id.clone()).or_default().push(op)
-    }
-
-    Ok(grouped_ops)
-}
-
-fn reduce_operations(records: Vec<NomenclaturalActOperation>) -> Result<Vec<NomenclaturalActOperation>, Error> {
-    let entities = merge_operations(records)?;
-    let mut merged_ops = Vec::new();
-
-    for (key, ops) in entities.into_iter() {
-        let mut map = Map::new(key);
-        let reduced = map.reduce(&ops);
-        merged_ops.extend(reduced);
-    }
-
-    Ok(merged_ops)
-}
-
-fn reduce_acts() -> Result<(), Error> {
-    let entities = merge_operations(vec![])?;
-    let mut acts = Vec::new();
-
-    for (key, ops) in entities.into_iter() {
-        let mut map = Map::new(key);
-        map.reduce(&ops);
-        acts.push(NomenclaturalAct::from(map));
-    }
+    let ops = NomenclaturalActOperation::load_all(&mut conn)?;
+    let acts = reduce_entities(ops).into_iter().map(NomenclaturalAct::from);
 
     let mut writer = csv::Writer::from_writer(std::io::stdout());
 
@@ -0,0 +1,205 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use arga_core::crdt::hlc::HybridTimestamp;
+use arga_core::crdt::lww::{self, Entity};
+use arga_core::crdt::{DataFrame, Version};
+use arga_core::models::logs::{snapshots, AtomVariants, OperationLogTable};
+use arga_core::models::{entity_hash, DatasetVersion};
+use diesel::PgConnection;
+use tracing::info;
+
+use crate::data::oplogger::get_pool;
+use crate::data::{Error, ParseError};
+
+
+fn merge_operations<T: OperationLogTable>(
+    conn: &mut PgConnection,
+    existing: Vec<T>,
+    operations: Vec<T>,
+) -> Result<(), Error> {
+    info!(table = T::table_name(), "Reducing operations");
+    let reduced = lww::reduce_operations(existing, operations);
+
+    info!(table = T::table_name(), total = reduced.len(), "Importing operations");
+    for chunk in reduced.chunks(1000) {
+        T::insert_all(conn, chunk)?;
+    }
+
+    Ok(())
+}
+
+/// Get a version that is ordered after every existing operation of the imported entities.
+///
+/// The log tables can contain operations from other importers and machines whose
+/// clocks might be ahead of ours, so we merge the last operation id into our clock
+/// to make sure new operations are always reduced after the existing ones. Operations
+/// are only ever reduced with the other operations of their entity so those are the
+/// only ones that need to be ordered before.
+fn next_version<T: OperationLogTable>(existing: &[T]) -> Result<Version, Error> {
+    let version = Version::new();
+
+    match existing.iter().map(|op| op.id()).max() {
+        Some(id) => {
+            let remote = HybridTimestamp::try_from(id).map_err(|err| ParseError::InvalidValue(err.to_string()))?;
+            Ok(version.receive(remote.into()))
+        }
+        None => Ok(version),
+    }
+}
+
+/// Reduce all operations in a log table and output the entities as CSV to stdout.
+pub fn reduce<T: OperationLogTable>() -> Result<(), Error> {
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    // start from the latest snapshots so compacted logs don't need to reduce every operation
+    let entities: Vec<Entity> = snapshots::reduce_from_snapshots::<T>(&mut conn)?
+        .into_iter()
+        .map(Entity::from)
+        .collect();
+
+    // not every entity will have every atom so we use all the fields
+    // available across the entities as the header
+    let mut fields = BTreeSet::new();
+    for entity in entities.iter() {
+        fields.extend(entity.fields.keys().cloned());
+    }
+
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+
+    let mut header = vec!["entity_id".to_string()];
+    header.extend(fields.iter().cloned());
+    writer.write_record(&header)?;
+
+    for entity in entities {
+        let mut record = vec![entity.entity_id];
+
+        for field in fields.iter() {
+            let value = match entity.fields.get(field) {
+                None | Some(serde_json::Value::Null) => String::new(),
+                Some(serde_json::Value::String(value)) => value.clone(),
+                Some(value) => value.to_string(),
+            };
+            record.push(value);
+        }
+
+        writer.write_record(&record)?;
+    }
+
+    Ok(())
+}
+
+/// Import a CSV as operations for any log table.
+///
+/// Every column is mapped to the atom variant with the same name, so a `scientific_name`
+/// column becomes a `ScientificName` atom. Columns that don't match an atom are ignored.
+/// The entity id is derived from the value in the `entity_column`.
+pub fn import_csv<T: OperationLogTable>(
+    path: PathBuf,
+    entity_column: &str,
+    dataset_version: DatasetVersion,
+) -> Result<(), Error> {
+    let mut reader = csv::Reader::from_path(&path)?;
+    let headers = reader.headers()?.clone();
+
+    let entity_idx = headers
+        .iter()
+        .position(|header| header == entity_column)
+        .ok_or(ParseError::NotFound(entity_column.to_string()))?;
+
+    // match the columns to the atom variants ahead of time
+    let variants = T::Atom::variant_names();
+    let columns: Vec<Option<String>> = headers
+        .iter()
+        .map(|header| {
+            let variant = to_pascal_case(header);
+            variants.contains(&variant.as_str()).then_some(variant)
+        })
+        .collect();
+
+    for (header, column) in headers.iter().zip(columns.iter()) {
+        if column.is_none() {
+            info!(table = T::table_name(), column = header, "Ignoring column without a matching atom");
+        }
+    }
+
+    let mut rows: Vec<(String, Vec<T::Atom>)> = Vec::new();
+    for row in reader.records() {
+        let row = row?;
+        let entity_id = row.get(entity_idx).unwrap_or_default();
+
+        let mut atoms = Vec::new();
+        for (value, column) in row.iter().zip(columns.iter()) {
+            if let (Some(variant), false) = (column, value.is_empty()) {
+                atoms.push(parse_atom::<T::Atom>(variant, value)?);
+            }
+        }
+        rows.push((entity_hash(entity_id), atoms));
+    }
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    // only the operations of the imported entities are needed to find what changed, which
+    // keeps the cost of an import to the size of the file rather than the whole log
+    info!(table = T::table_name(), "Loading operations");
+    let entity_ids: Vec<String> = rows
+        .iter()
+        .map(|(entity_id, _)| entity_id.clone())
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect();
+
+    let mut existing = Vec::new();
+    for chunk in entity_ids.chunks(10_000) {
+        existing.extend(T::load_entities(&mut conn, chunk)?);
+    }
+
+    let mut last_version = next_version(&existing)?;
+    let mut operations: Vec<T> = Vec::new();
+
+    for (entity_id, atoms) in rows {
+        let mut frame = DataFrame::create(entity_id, dataset_version.id, last_version);
+        for atom in atoms {
+            frame.push(atom);
+        }
+
+        last_version = frame.last_version();
+        operations.extend(frame.collect::<T>());
+    }
+
+    merge_operations(&mut conn, existing, operations)
+}
+
+
+/// Parse a value into the specified atom variant.
+///
+/// Atoms are deserialized as an externally tagged enum so we first try the value as a string
+/// and then fallback to parsing it as JSON to support numbers and booleans.
+fn parse_atom<A: serde::de::DeserializeOwned>(variant: &str, value: &str) -> Result<A, ParseError> {
+    let mut object = serde_json::Map::new();
+    object.insert(variant.to_string(), serde_json::Value::String(value.to_string()));
+
+    if let Ok(atom) = serde_json::from_value(serde_json::Value::Object(object.clone())) {
+        return Ok(atom);
+    }
+
+    let parsed = serde_json::from_str(value).map_err(|_| ParseError::InvalidValue(format!("{variant}: {value}")))?;
+    object.insert(variant.to_string(), parsed);
+    serde_json::from_value(serde_json::Value::Object(object))
+        .map_err(|_| ParseError::InvalidValue(format!("{variant}: {value}")))
+}
+
+fn to_pascal_case(value: &str) -> String {
+    value
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
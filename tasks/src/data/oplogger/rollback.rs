@@ -0,0 +1,183 @@
+use arga_core::models::logs::{DeletionReason, OperationLogTable};
+use arga_core::schema;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use diesel::sql_types::{Bool, Text, Uuid as SqlUuid};
+use diesel::*;
+use tracing::info;
+use uuid::Uuid;
+
+use super::{get_pool, LogTask, LogType};
+use crate::data::{Error, ParseError};
+
+
+#[derive(QueryableByName)]
+struct Exists {
+    #[diesel(sql_type = Bool)]
+    exists: bool,
+}
+
+
+/// Remove every operation imported under a dataset version along with the version itself.
+///
+/// A version can only be rolled back while none of its operations have been compacted
+/// since the snapshots would still include them. Operations imported after the version
+/// can have parents in it, so the logs should be verified and reduced again afterwards.
+///
+/// Only the latest version of a dataset can be rolled back. Imports only log the operations
+/// that change an entity, so a later version asserting the same values as an earlier one has
+/// nothing logged for them and relies on the operations of the earlier version. Later versions
+/// have to be rolled back first.
+pub fn rollback(dataset_id: &str, version: &str) -> Result<(), Error> {
+    use schema::{dataset_versions, datasets};
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    let versions = dataset_versions::table
+        .inner_join(datasets::table)
+        .filter(datasets::global_id.eq(dataset_id))
+        .select((dataset_versions::id, dataset_versions::version, dataset_versions::imported_at))
+        .load::<(Uuid, String, DateTime<Utc>)>(&mut conn)?;
+
+    let version_ids = latest_version_ids(&versions, version).map_err(|err| match err {
+        ParseError::NotFound(_) => ParseError::NotFound(format!("{dataset_id} {version}")),
+        err => err,
+    })?;
+
+    conn.transaction(|conn| {
+        for dataset_version_id in version_ids.iter() {
+            for log in LogType::value_variants() {
+                log.dispatch(CheckCompacted { conn, dataset_version_id })?;
+            }
+            for log in LogType::value_variants() {
+                log.dispatch(DeleteVersion { conn, dataset_version_id })?;
+            }
+
+            diesel::delete(dataset_versions::table.filter(dataset_versions::id.eq(dataset_version_id))).execute(conn)?;
+            info!(?dataset_version_id, "Dataset version removed");
+        }
+        Ok::<(), Error>(())
+    })?;
+
+    info!("Rollback finished. Verify and reduce the logs to update the entities");
+    Ok(())
+}
+
+
+/// Get the ids of a version if it is the latest version of the dataset.
+///
+/// The versions are every version of a single dataset along with when they were imported.
+fn latest_version_ids(versions: &[(Uuid, String, DateTime<Utc>)], version: &str) -> Result<Vec<Uuid>, ParseError> {
+    let matched: Vec<&(Uuid, String, DateTime<Utc>)> = versions.iter().filter(|(_, name, _)| name == version).collect();
+    let Some(imported_at) = matched.iter().map(|(_, _, imported_at)| imported_at).max() else {
+        return Err(ParseError::NotFound(version.to_string()));
+    };
+
+    let later = versions
+        .iter()
+        .filter(|(_, name, later_imported_at)| name != version && later_imported_at > imported_at)
+        .map(|(_, name, _)| name.as_str())
+        .collect::<Vec<&str>>();
+
+    match later.is_empty() {
+        true => Ok(matched.into_iter().map(|(id, _, _)| *id).collect()),
+        false => Err(ParseError::InvalidValue(format!(
+            "version {version} was imported before {}, which must be rolled back first",
+            later.join(", ")
+        ))),
+    }
+}
+
+
+struct CheckCompacted<'a> {
+    conn: &'a mut PgConnection,
+    dataset_version_id: &'a Uuid,
+}
+
+impl LogTask for CheckCompacted<'_> {
+    fn run<T: OperationLogTable>(self) -> Result<(), Error> {
+        let query = format!(
+            "SELECT EXISTS (
+                 SELECT 1 FROM operation_log_archives
+                 WHERE log_table = $1 AND dataset_version_id = $2
+             ) OR EXISTS (
+                 SELECT 1 FROM {table} logs
+                 JOIN operation_log_snapshots snapshots
+                 ON snapshots.log_table = $1 AND snapshots.entity_id = logs.entity_id
+                 WHERE logs.dataset_version_id = $2 AND snapshots.watermark >= logs.operation_id
+             ) AS exists",
+            table = T::table_name(),
+        );
+
+        let compacted = sql_query(query)
+            .bind::<Text, _>(T::table_name())
+            .bind::<SqlUuid, _>(self.dataset_version_id)
+            .get_result::<Exists>(self.conn)?;
+
+        match compacted.exists {
+            true => Err(ParseError::InvalidValue(format!(
+                "{} has compacted operations from dataset version {}",
+                T::table_name(),
+                self.dataset_version_id
+            ))
+            .into()),
+            false => Ok(()),
+        }
+    }
+}
+
+struct DeleteVersion<'a> {
+    conn: &'a mut PgConnection,
+    dataset_version_id: &'a Uuid,
+}
+
+impl LogTask for DeleteVersion<'_> {
+    fn run<T: OperationLogTable>(self) -> Result<(), Error> {
+        let deleted = T::delete_dataset_version(self.conn, self.dataset_version_id, DeletionReason::Rollback)?;
+        if deleted > 0 {
+            info!(table = T::table_name(), deleted, "Operations removed");
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn version(name: &str, day: u32) -> (Uuid, String, DateTime<Utc>) {
+        (Uuid::new_v4(), name.to_string(), Utc.with_ymd_and_hms(2024, 6, day, 0, 0, 0).unwrap())
+    }
+
+    #[test]
+    fn rolls_back_the_latest_version() {
+        let versions = vec![version("v1", 1), version("v2", 2)];
+        assert_eq!(latest_version_ids(&versions, "v2").unwrap(), vec![versions[1].0]);
+    }
+
+    #[test]
+    fn refuses_to_roll_back_an_earlier_version() {
+        let versions = vec![version("v1", 1), version("v2", 2)];
+        assert!(matches!(latest_version_ids(&versions, "v1"), Err(ParseError::InvalidValue(_))));
+    }
+
+    #[test]
+    fn rolls_back_two_versions_latest_first() {
+        let mut versions = vec![version("v1", 1), version("v2", 2)];
+
+        let latest = latest_version_ids(&versions, "v2").unwrap();
+        versions.retain(|(id, _, _)| !latest.contains(id));
+
+        assert_eq!(latest_version_ids(&versions, "v1").unwrap(), vec![versions[0].0]);
+    }
+
+    #[test]
+    fn reports_missing_versions() {
+        let versions = vec![version("v1", 1)];
+        assert!(matches!(latest_version_ids(&versions, "v3"), Err(ParseError::NotFound(_))));
+    }
+}
@@ -1,32 +1,44 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
-use arga_core::crdt::lww::Map;
-use arga_core::crdt::{Frame, Version};
+use arga_core::crdt::lww::{Map, reduce_entities};
+use arga_core::crdt::{DataFrame, Frame, Version};
+use arga_core::models::logs::{Action, OperationLogTable};
 use arga_core::models::{
-    Action,
     CollectionEventAtom,
     CollectionEventOperation,
     DatasetVersion,
+    LocationGeneralisation,
+    OrganismAtom,
+    OrganismOperation,
+    SensitiveLocation,
     SpecimenAtom,
     SpecimenOperation,
 };
-use arga_core::schema;
 use bigdecimal::BigDecimal;
-use diesel::*;
+use chrono::{NaiveDate, NaiveTime};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 use uuid::Uuid;
 use xxhash_rust::xxh3::Xxh3;
 
-use crate::data::oplogger::get_pool;
 use crate::data::Error;
-
-
-#[derive(Debug, Clone, Deserialize)]
+use crate::data::oplogger::get_pool;
+use crate::data::pipeline::Pipeline;
+use crate::data::sensitive::{self, Rules};
+use crate::data::utils;
+
+
+/// A row in a specimen CSV.
+///
+/// Columns that don't have a matching atom in the specimen, collection event or organism
+/// logs are not imported. These are `canonical_name`, `material_sample_id`, `details`,
+/// `record_number`, `verbatim_lat_long`, `establishment_means`, `degree_of_establishment`,
+/// `pathway`, `occurrence_status`, `ref_biomaterial` and `source_mat_id`.
+#[derive(Debug, Clone, Default, Deserialize)]
 struct Record {
     record_id: String,
     scientific_name: Option<String>,
-    // canonical_name: Option<String>,
     type_status: Option<String>,
     institution_name: Option<String>,
     institution_code: Option<String>,
@@ -36,8 +48,6 @@ struct Record {
     identified_by: Option<String>,
     identified_date: Option<String>,
     organism_id: Option<String>,
-    material_sample_id: Option<String>,
-    details: Option<String>,
     remarks: Option<String>,
     identification_remarks: Option<String>,
 
@@ -50,7 +60,6 @@ struct Record {
     municipality: Option<String>,
     latitude: Option<f64>,
     longitude: Option<f64>,
-    // verbatim_lat_long: Option<String>,
     elevation: Option<f64>,
     depth: Option<f64>,
     elevation_accuracy: Option<f64>,
@@ -62,68 +71,69 @@ struct Record {
     event_time: Option<String>,
     field_number: Option<String>,
     field_notes: Option<String>,
-    record_number: Option<String>,
     individual_count: Option<String>,
     organism_quantity: Option<String>,
     organism_quantity_type: Option<String>,
+    preparation: Option<String>,
+    other_catalog_numbers: Option<String>,
+
+    // organism block
     sex: Option<String>,
     genotypic_sex: Option<String>,
     phenotypic_sex: Option<String>,
     life_stage: Option<String>,
     reproductive_condition: Option<String>,
     behavior: Option<String>,
-    establishment_means: Option<String>,
-    degree_of_establishment: Option<String>,
-    pathway: Option<String>,
-    occurrence_status: Option<String>,
-    preparation: Option<String>,
-    other_catalog_numbers: Option<String>,
+
     env_broad_scale: Option<String>,
     env_local_scale: Option<String>,
     env_medium: Option<String>,
     habitat: Option<String>,
-    ref_biomaterial: Option<String>,
-    source_mat_id: Option<String>,
     specific_host: Option<String>,
     strain: Option<String>,
     isolate: Option<String>,
 }
 
+impl Record {
+    fn has_organism(&self) -> bool {
+        self.sex.is_some()
+            || self.genotypic_sex.is_some()
+            || self.phenotypic_sex.is_some()
+            || self.life_stage.is_some()
+            || self.reproductive_condition.is_some()
+            || self.behavior.is_some()
+    }
+
+    /// The organism the specimen was taken from. Records without an organism id but with
+    /// organism details describe a single organism so the record id is used instead
+    fn organism_id(&self) -> Option<String> {
+        match &self.organism_id {
+            Some(organism_id) => Some(organism_id.clone()),
+            None if self.has_organism() => Some(self.record_id.clone()),
+            None => None,
+        }
+    }
+}
+
+
 #[derive(Clone, Debug, Default, Serialize)]
 pub struct Specimen {
     entity_id: String,
-    record_id: String,
+    specimen_id: String,
     scientific_name: Option<String>,
-    canonical_name: Option<String>,
 
-    material_sample_id: Option<String>,
-    organism_id: Option<String>,
+    event_date: Option<String>,
+    event_time: Option<String>,
 
     institution_name: Option<String>,
     institution_code: Option<String>,
-    collection_code: Option<String>,
-    recorded_by: Option<String>,
-    identified_by: Option<String>,
-    identified_date: Option<String>,
+    collection_repository_id: Option<String>,
+    collection_repository_code: Option<String>,
 
     type_status: Option<String>,
-    locality: Option<String>,
-    country: Option<String>,
-    country_code: Option<String>,
-    state_province: Option<String>,
-    county: Option<String>,
-    municipality: Option<String>,
-    latitude: Option<f64>,
-    longitude: Option<f64>,
-    elevation: Option<f64>,
-    depth: Option<f64>,
-    elevation_accuracy: Option<f64>,
-    depth_accuracy: Option<f64>,
-    location_source: Option<String>,
-
-    details: Option<String>,
-    remarks: Option<String>,
-    identification_remarks: Option<String>,
+    preparation: Option<String>,
+    other_catalog_numbers: Option<String>,
+    disposition: Option<String>,
 }
 
 impl From<Map<SpecimenAtom>> for Specimen {
@@ -136,34 +146,18 @@ impl From<Map<SpecimenAtom>> for Specimen {
         for val in value.atoms.into_values() {
             match val {
                 SpecimenAtom::Empty => {}
-                SpecimenAtom::EntityId(_) => {}
-                SpecimenAtom::RecordId(value) => specimen.record_id = value,
-                SpecimenAtom::MaterialSampleId(value) => specimen.material_sample_id = Some(value),
-                SpecimenAtom::OrganismId(value) => specimen.organism_id = Some(value),
+                SpecimenAtom::SpecimenId(value) => specimen.specimen_id = value,
                 SpecimenAtom::ScientificName(value) => specimen.scientific_name = Some(value),
+                SpecimenAtom::EventDate(value) => specimen.event_date = Some(value),
+                SpecimenAtom::EventTime(value) => specimen.event_time = Some(value),
                 SpecimenAtom::InstitutionName(value) => specimen.institution_name = Some(value),
                 SpecimenAtom::InstitutionCode(value) => specimen.institution_code = Some(value),
-                SpecimenAtom::CollectionCode(value) => specimen.collection_code = Some(value),
-                SpecimenAtom::RecordedBy(value) => specimen.recorded_by = Some(value),
-                SpecimenAtom::IdentifiedBy(value) => specimen.identified_by = Some(value),
-                SpecimenAtom::IdentifiedDate(value) => specimen.identified_date = Some(value),
+                SpecimenAtom::CollectionRepositoryId(value) => specimen.collection_repository_id = Some(value),
+                SpecimenAtom::CollectionRepositoryCode(value) => specimen.collection_repository_code = Some(value),
                 SpecimenAtom::TypeStatus(value) => specimen.type_status = Some(value),
-                SpecimenAtom::Locality(value) => specimen.locality = Some(value),
-                SpecimenAtom::Country(value) => specimen.country = Some(value),
-                SpecimenAtom::CountryCode(value) => specimen.country_code = Some(value),
-                SpecimenAtom::StateProvince(value) => specimen.state_province = Some(value),
-                SpecimenAtom::County(value) => specimen.county = Some(value),
-                SpecimenAtom::Municipality(value) => specimen.municipality = Some(value),
-                SpecimenAtom::Latitude(value) => specimen.latitude = Some(value),
-                SpecimenAtom::Longitude(value) => specimen.longitude = Some(value),
-                SpecimenAtom::Elevation(value) => specimen.elevation = Some(value),
-                SpecimenAtom::Depth(value) => specimen.depth = Some(value),
-                SpecimenAtom::ElevationAccuracy(value) => specimen.elevation_accuracy = Some(value),
-                SpecimenAtom::DepthAccuracy(value) => specimen.depth_accuracy = Some(value),
-                SpecimenAtom::LocationSource(value) => specimen.location_source = Some(value),
-                SpecimenAtom::Details(value) => specimen.details = Some(value),
-                SpecimenAtom::Remarks(value) => specimen.remarks = Some(value),
-                SpecimenAtom::IdentificationRemarks(value) => specimen.identification_remarks = Some(value),
+                SpecimenAtom::Preparation(value) => specimen.preparation = Some(value),
+                SpecimenAtom::OtherCatalogNumbers(value) => specimen.other_catalog_numbers = Some(value),
+                SpecimenAtom::Disposition(value) => specimen.disposition = Some(value),
             }
         }
 
@@ -174,43 +168,47 @@ impl From<Map<SpecimenAtom>> for Specimen {
 #[derive(Clone, Debug, Default, Serialize)]
 pub struct CollectionEvent {
     entity_id: String,
-    specimen_id: String,
+    field_collecting_id: Option<String>,
+    specimen_id: Option<String>,
+    organism_id: Option<String>,
+    scientific_name: Option<String>,
 
-    event_date: Option<String>,
-    event_time: Option<String>,
+    event_date: Option<NaiveDate>,
+    event_time: Option<NaiveTime>,
     collected_by: Option<String>,
+    collection_remarks: Option<String>,
 
-    field_number: Option<String>,
-    catalog_number: Option<String>,
-    record_number: Option<String>,
-    individual_count: Option<String>,
-    organism_quantity: Option<String>,
-    organism_quantity_type: Option<String>,
-    sex: Option<String>,
-    genotypic_sex: Option<String>,
-    phenotypic_sex: Option<String>,
-    life_stage: Option<String>,
-    reproductive_condition: Option<String>,
-    behavior: Option<String>,
-    establishment_means: Option<String>,
-    degree_of_establishment: Option<String>,
-    pathway: Option<String>,
-    occurrence_status: Option<String>,
-    preparation: Option<String>,
-    other_catalog_numbers: Option<String>,
+    identified_by: Option<String>,
+    identified_date: Option<NaiveDate>,
+    identification_remarks: Option<String>,
+
+    locality: Option<String>,
+    country: Option<String>,
+    country_code: Option<String>,
+    state_province: Option<String>,
+    county: Option<String>,
+    municipality: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    elevation: Option<f64>,
+    depth: Option<f64>,
+    elevation_accuracy: Option<f64>,
+    depth_accuracy: Option<f64>,
+    location_source: Option<String>,
 
+    preparation: Option<String>,
     env_broad_scale: Option<String>,
     env_local_scale: Option<String>,
     env_medium: Option<String>,
     habitat: Option<String>,
-    ref_biomaterial: Option<String>,
-    source_mat_id: Option<String>,
     specific_host: Option<String>,
+    individual_count: Option<String>,
+    organism_quantity: Option<String>,
+    organism_quantity_type: Option<String>,
     strain: Option<String>,
     isolate: Option<String>,
-
     field_notes: Option<String>,
-    remarks: Option<String>,
+    location_generalisation: Option<LocationGeneralisation>,
 }
 
 impl From<Map<CollectionEventAtom>> for CollectionEvent {
@@ -225,39 +223,42 @@ impl From<Map<CollectionEventAtom>> for CollectionEvent {
         for val in value.atoms.into_values() {
             match val {
                 Empty => {}
-                SpecimenId(value) => event.specimen_id = value,
+                FieldCollectingId(value) => event.field_collecting_id = Some(value),
+                SpecimenId(value) => event.specimen_id = Some(value),
+                ScientificName(value) => event.scientific_name = Some(value),
                 EventDate(value) => event.event_date = Some(value),
                 EventTime(value) => event.event_time = Some(value),
                 CollectedBy(value) => event.collected_by = Some(value),
-                FieldNumber(value) => event.field_number = Some(value),
-                CatalogNumber(value) => event.catalog_number = Some(value),
-                RecordNumber(value) => event.record_number = Some(value),
-                IndividualCount(value) => event.individual_count = Some(value),
-                OrganismQuantity(value) => event.organism_quantity = Some(value),
-                OrganismQuantityType(value) => event.organism_quantity_type = Some(value),
-                Sex(value) => event.sex = Some(value),
-                GenotypicSex(value) => event.genotypic_sex = Some(value),
-                PhenotypicSex(value) => event.phenotypic_sex = Some(value),
-                LifeStage(value) => event.life_stage = Some(value),
-                ReproductiveCondition(value) => event.reproductive_condition = Some(value),
-                Behavior(value) => event.behavior = Some(value),
-                EstablishmentMeans(value) => event.establishment_means = Some(value),
-                DegreeOfEstablishment(value) => event.degree_of_establishment = Some(value),
-                Pathway(value) => event.pathway = Some(value),
-                OccurrenceStatus(value) => event.occurrence_status = Some(value),
+                CollectionRemarks(value) => event.collection_remarks = Some(value),
+                IdentifiedBy(value) => event.identified_by = Some(value),
+                IdentifiedDate(value) => event.identified_date = Some(value),
+                IdentificationRemarks(value) => event.identification_remarks = Some(value),
+                OrganismId(value) => event.organism_id = Some(value),
+                Locality(value) => event.locality = Some(value),
+                Country(value) => event.country = Some(value),
+                CountryCode(value) => event.country_code = Some(value),
+                StateProvince(value) => event.state_province = Some(value),
+                County(value) => event.county = Some(value),
+                Municipality(value) => event.municipality = Some(value),
+                Latitude(value) => event.latitude = Some(value),
+                Longitude(value) => event.longitude = Some(value),
+                Elevation(value) => event.elevation = Some(value),
+                Depth(value) => event.depth = Some(value),
+                ElevationAccuracy(value) => event.elevation_accuracy = Some(value),
+                DepthAccuracy(value) => event.depth_accuracy = Some(value),
+                LocationSource(value) => event.location_source = Some(value),
                 Preparation(value) => event.preparation = Some(value),
-                OtherCatalogNumbers(value) => event.other_catalog_numbers = Some(value),
-                EnvBroadScale(value) => event.env_broad_scale = Some(value),
-                EnvLocalScale(value) => event.env_local_scale = Some(value),
-                EnvMedium(value) => event.env_medium = Some(value),
+                EnvironmentBroadScale(value) => event.env_broad_scale = Some(value),
+                EnvironmentLocalScale(value) => event.env_local_scale = Some(value),
+                EnvironmentMedium(value) => event.env_medium = Some(value),
                 Habitat(value) => event.habitat = Some(value),
-                RefBiomaterial(value) => event.ref_biomaterial = Some(value),
-                SourceMatId(value) => event.source_mat_id = Some(value),
                 SpecificHost(value) => event.specific_host = Some(value),
+                IndividualCount(value) => event.individual_count = Some(value),
+                OrganismQuantity(value) => event.organism_quantity = Some(value),
+                OrganismQuantityType(value) => event.organism_quantity_type = Some(value),
                 Strain(value) => event.strain = Some(value),
                 Isolate(value) => event.isolate = Some(value),
                 FieldNotes(value) => event.field_notes = Some(value),
-                Remarks(value) => event.remarks = Some(value),
             }
         }
 
@@ -341,14 +342,8 @@ impl Specimens {
             let hash = hasher.digest();
 
             let mut frame = SpecimenFrame::create(self.dataset_version_id, hash.to_string(), last_version);
-            frame.push(SpecimenAtom::RecordId(record.record_id));
+            frame.push(SpecimenAtom::SpecimenId(record.record_id));
 
-            if let Some(value) = record.material_sample_id {
-                frame.push(SpecimenAtom::MaterialSampleId(value));
-            }
-            if let Some(value) = record.organism_id {
-                frame.push(SpecimenAtom::OrganismId(value));
-            }
             if let Some(value) = record.scientific_name {
                 frame.push(SpecimenAtom::ScientificName(value));
             }
@@ -358,68 +353,20 @@ impl Specimens {
             if let Some(value) = record.institution_code {
                 frame.push(SpecimenAtom::InstitutionCode(value));
             }
-            if let Some(value) = record.collection_code {
-                frame.push(SpecimenAtom::CollectionCode(value));
-            }
-            // if let Some(value) = record.recorded_by {
-            //     frame.push(SpecimenAtom::RecordedBy(value));
-            // }
-            if let Some(value) = record.identified_by {
-                frame.push(SpecimenAtom::IdentifiedBy(value));
+            if let Some(value) = record.catalog_number {
+                frame.push(SpecimenAtom::CollectionRepositoryId(value));
             }
-            if let Some(value) = record.identified_date {
-                frame.push(SpecimenAtom::IdentifiedDate(value));
+            if let Some(value) = record.collection_code {
+                frame.push(SpecimenAtom::CollectionRepositoryCode(value));
             }
             if let Some(value) = record.type_status {
                 frame.push(SpecimenAtom::TypeStatus(value));
             }
-            if let Some(value) = record.locality {
-                frame.push(SpecimenAtom::Locality(value));
-            }
-            if let Some(value) = record.country {
-                frame.push(SpecimenAtom::Country(value));
-            }
-            if let Some(value) = record.country_code {
-                frame.push(SpecimenAtom::CountryCode(value));
-            }
-            if let Some(value) = record.state_province {
-                frame.push(SpecimenAtom::StateProvince(value));
-            }
-            if let Some(value) = record.county {
-                frame.push(SpecimenAtom::County(value));
-            }
-            if let Some(value) = record.municipality {
-                frame.push(SpecimenAtom::Municipality(value));
-            }
-            if let Some(value) = record.latitude {
-                frame.push(SpecimenAtom::Latitude(value));
-            }
-            if let Some(value) = record.longitude {
-                frame.push(SpecimenAtom::Longitude(value));
-            }
-            if let Some(value) = record.elevation {
-                frame.push(SpecimenAtom::Elevation(value));
-            }
-            if let Some(value) = record.depth {
-                frame.push(SpecimenAtom::Depth(value));
-            }
-            if let Some(value) = record.elevation_accuracy {
-                frame.push(SpecimenAtom::ElevationAccuracy(value));
-            }
-            if let Some(value) = record.depth_accuracy {
-                frame.push(SpecimenAtom::DepthAccuracy(value));
-            }
-            if let Some(value) = record.location_source {
-                frame.push(SpecimenAtom::LocationSource(value));
-            }
-            if let Some(value) = record.details {
-                frame.push(SpecimenAtom::Details(value));
-            }
-            if let Some(value) = record.remarks {
-                frame.push(SpecimenAtom::Remarks(value));
+            if let Some(value) = record.preparation {
+                frame.push(SpecimenAtom::Preparation(value));
             }
-            if let Some(value) = record.identification_remarks {
-                frame.push(SpecimenAtom::IdentificationRemarks(value));
+            if let Some(value) = record.other_catalog_numbers {
+                frame.push(SpecimenAtom::OtherCatalogNumbers(value));
             }
 
             last_version = frame.frame.current;
@@ -504,92 +451,104 @@ impl CollectionEvents {
             let hash = hasher.digest();
 
             let mut frame = CollectionEventFrame::create(self.dataset_version_id, hash.to_string(), last_version);
+            if let Some(value) = record.organism_id() {
+                frame.push(OrganismId(value));
+            }
             frame.push(SpecimenId(record.record_id));
 
-            if let Some(value) = record.event_date {
+            if let Some(value) = record.scientific_name {
+                frame.push(ScientificName(value));
+            }
+            if let Some(value) = record.event_date.as_deref().and_then(parse_date) {
                 frame.push(EventDate(value));
             }
-            if let Some(value) = record.event_time {
+            if let Some(value) = record.event_time.as_deref().and_then(parse_time) {
                 frame.push(EventTime(value));
             }
             if let Some(value) = record.collected_by {
                 frame.push(CollectedBy(value));
             }
-            if let Some(value) = record.field_number {
-                frame.push(FieldNumber(value));
+            if let Some(value) = record.remarks {
+                frame.push(CollectionRemarks(value));
             }
-            if let Some(value) = record.catalog_number {
-                frame.push(CatalogNumber(value));
+            if let Some(value) = record.identified_by {
+                frame.push(IdentifiedBy(value));
             }
-            if let Some(value) = record.record_number {
-                frame.push(RecordNumber(value));
+            if let Some(value) = record.identified_date.as_deref().and_then(parse_date) {
+                frame.push(IdentifiedDate(value));
             }
-            if let Some(value) = record.individual_count {
-                frame.push(IndividualCount(value));
+            if let Some(value) = record.identification_remarks {
+                frame.push(IdentificationRemarks(value));
             }
-            if let Some(value) = record.organism_quantity {
-                frame.push(OrganismQuantity(value));
+            if let Some(value) = record.field_number {
+                frame.push(FieldCollectingId(value));
             }
-            if let Some(value) = record.organism_quantity_type {
-                frame.push(OrganismQuantityType(value));
+            if let Some(value) = record.locality {
+                frame.push(Locality(value));
             }
-            if let Some(value) = record.sex {
-                frame.push(Sex(value));
+            if let Some(value) = record.country {
+                frame.push(Country(value));
             }
-            if let Some(value) = record.genotypic_sex {
-                frame.push(GenotypicSex(value));
+            if let Some(value) = record.country_code {
+                frame.push(CountryCode(value));
             }
-            if let Some(value) = record.phenotypic_sex {
-                frame.push(PhenotypicSex(value));
+            if let Some(value) = record.state_province {
+                frame.push(StateProvince(value));
             }
-            if let Some(value) = record.life_stage {
-                frame.push(LifeStage(value));
+            if let Some(value) = record.county {
+                frame.push(County(value));
             }
-            if let Some(value) = record.reproductive_condition {
-                frame.push(ReproductiveCondition(value));
+            if let Some(value) = record.municipality {
+                frame.push(Municipality(value));
             }
-            if let Some(value) = record.behavior {
-                frame.push(Behavior(value));
+            if let Some(value) = record.latitude {
+                frame.push(Latitude(value));
             }
-            if let Some(value) = record.establishment_means {
-                frame.push(EstablishmentMeans(value));
+            if let Some(value) = record.longitude {
+                frame.push(Longitude(value));
+            }
+            if let Some(value) = record.elevation {
+                frame.push(Elevation(value));
+            }
+            if let Some(value) = record.depth {
+                frame.push(Depth(value));
             }
-            if let Some(value) = record.degree_of_establishment {
-                frame.push(DegreeOfEstablishment(value));
+            if let Some(value) = record.elevation_accuracy {
+                frame.push(ElevationAccuracy(value));
             }
-            if let Some(value) = record.pathway {
-                frame.push(Pathway(value));
+            if let Some(value) = record.depth_accuracy {
+                frame.push(DepthAccuracy(value));
             }
-            if let Some(value) = record.occurrence_status {
-                frame.push(OccurrenceStatus(value));
+            if let Some(value) = record.location_source {
+                frame.push(LocationSource(value));
             }
             if let Some(value) = record.preparation {
                 frame.push(Preparation(value));
             }
-            if let Some(value) = record.other_catalog_numbers {
-                frame.push(OtherCatalogNumbers(value));
-            }
             if let Some(value) = record.env_broad_scale {
-                frame.push(EnvBroadScale(value));
+                frame.push(EnvironmentBroadScale(value));
             }
             if let Some(value) = record.env_local_scale {
-                frame.push(EnvLocalScale(value));
+                frame.push(EnvironmentLocalScale(value));
             }
             if let Some(value) = record.env_medium {
-                frame.push(EnvMedium(value));
+                frame.push(EnvironmentMedium(value));
             }
             if let Some(value) = record.habitat {
                 frame.push(Habitat(value));
             }
-            if let Some(value) = record.ref_biomaterial {
-                frame.push(RefBiomaterial(value));
-            }
-            if let Some(value) = record.source_mat_id {
-                frame.push(SourceMatId(value));
-            }
             if let Some(value) = record.specific_host {
                 frame.push(SpecificHost(value));
             }
+            if let Some(value) = record.individual_count {
+                frame.push(IndividualCount(value));
+            }
+            if let Some(value) = record.organism_quantity {
+                frame.push(OrganismQuantity(value));
+            }
+            if let Some(value) = record.organism_quantity_type {
+                frame.push(OrganismQuantityType(value));
+            }
             if let Some(value) = record.strain {
                 frame.push(Strain(value));
             }
@@ -599,9 +558,6 @@ impl CollectionEvents {
             if let Some(value) = record.field_notes {
                 frame.push(FieldNotes(value));
             }
-            if let Some(value) = record.remarks {
-                frame.push(Remarks(value));
-            }
 
             last_version = frame.frame.current;
             operations.extend(frame.frame.operations);
@@ -612,161 +568,231 @@ impl CollectionEvents {
 }
 
 
-pub fn process(path: PathBuf, dataset_version: DatasetVersion) -> Result<(), Error> {
-    use schema::{collection_event_logs, specimen_logs};
+pub struct Organisms {
+    pub path: PathBuf,
+    pub dataset_version_id: Uuid,
+}
+
+impl Organisms {
+    pub fn organisms(&self) -> Result<Vec<OrganismOperation>, Error> {
+        let mut last_version = Version::new();
+        let mut operations: Vec<OrganismOperation> = Vec::new();
+
+        for row in csv::Reader::from_path(&self.path)?.deserialize() {
+            let record: Record = row?;
+            if let Some((version, organism)) = organism_operations(&record, self.dataset_version_id, last_version) {
+                last_version = version;
+                operations.extend(organism);
+            }
+        }
+
+        Ok(operations)
+    }
+}
+
+/// Get the operations for the organism of a record, if it has any organism details
+fn organism_operations(
+    record: &Record,
+    dataset_version_id: Uuid,
+    last_version: Version,
+) -> Option<(Version, Vec<OrganismOperation>)> {
+    if !record.has_organism() {
+        return None;
+    }
+
+    let organism_id = record.organism_id()?;
+    let mut hasher = Xxh3::new();
+    hasher.update(organism_id.as_bytes());
+
+    let mut frame = DataFrame::create(hasher.digest().to_string(), dataset_version_id, last_version);
+    frame.push(OrganismAtom::OrganismId(organism_id));
+
+    if let Some(value) = &record.scientific_name {
+        frame.push(OrganismAtom::ScientificName(value.clone()));
+    }
+    if let Some(value) = &record.sex {
+        frame.push(OrganismAtom::Sex(value.clone()));
+    }
+    if let Some(value) = &record.genotypic_sex {
+        frame.push(OrganismAtom::GenotypicSex(value.clone()));
+    }
+    if let Some(value) = &record.phenotypic_sex {
+        frame.push(OrganismAtom::PhenotypicSex(value.clone()));
+    }
+    if let Some(value) = &record.life_stage {
+        frame.push(OrganismAtom::LifeStage(value.clone()));
+    }
+    if let Some(value) = &record.reproductive_condition {
+        frame.push(OrganismAtom::ReproductiveCondition(value.clone()));
+    }
+    if let Some(value) = &record.behavior {
+        frame.push(OrganismAtom::Behavior(value.clone()));
+    }
 
+    Some((frame.last_version(), frame.collect::<OrganismOperation>()))
+}
+
+
+pub fn process(path: PathBuf, dataset_version: DatasetVersion, pipeline: &mut Pipeline) -> Result<(), Error> {
     let specimens = Specimens {
         path: path.clone(),
         dataset_version_id: dataset_version.id,
     };
 
     let collections = CollectionEvents {
-        path,
+        path: path.clone(),
         dataset_version_id: dataset_version.id,
     };
 
-    let pool = get_pool()?;
-    let mut conn = pool.get()?;
-
-    let specimen_ops = specimen_logs::table
-        .order(specimen_logs::operation_id.asc())
-        .load::<SpecimenOperation>(&mut conn)?;
-
-    let records = specimens.specimens()?;
-    let reduced = reduce_operations(specimen_ops, records)?;
-    import_specimens(reduced)?;
-
-
-    let collection_ops = collection_event_logs::table
-        .order(collection_event_logs::operation_id.asc())
-        .load::<CollectionEventOperation>(&mut conn)?;
-
-    let records = collections.events()?;
-    let reduced = reduce_operations(collection_ops, records)?;
-    import_collections(reduced)?;
+    let organisms = Organisms {
+        path,
+        dataset_version_id: dataset_version.id,
+    };
 
+    pipeline.merge(specimens.specimens()?)?;
+    pipeline.merge(collections.events()?)?;
+    pipeline.merge(organisms.organisms()?)?;
     Ok(())
 }
 
-fn import_specimens(records: Vec<SpecimenOperation>) -> Result<(), Error> {
-    use schema::specimen_logs::dsl::*;
 
+pub fn reduce_specimens() -> Result<(), Error> {
     let pool = get_pool()?;
     let mut conn = pool.get()?;
 
-    for chunk in records.chunks(1000) {
-        diesel::insert_into(specimen_logs)
-            .values(chunk)
-            .on_conflict_do_nothing()
-            .execute(&mut conn)?;
+    let ops = SpecimenOperation::load_all(&mut conn)?;
+    let specimens = reduce_entities(ops).into_iter().map(Specimen::from);
+
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+
+    for specimen in specimens {
+        writer.serialize(specimen)?;
     }
 
     Ok(())
 }
 
-fn import_collections(records: Vec<CollectionEventOperation>) -> Result<(), Error> {
-    use schema::collection_event_logs::dsl::*;
-
+pub fn reduce_collections() -> Result<(), Error> {
     let pool = get_pool()?;
     let mut conn = pool.get()?;
 
-    for chunk in records.chunks(1000) {
-        diesel::insert_into(collection_event_logs)
-            .values(chunk)
-            .on_conflict_do_nothing()
-            .execute(&mut conn)?;
-    }
-
-    Ok(())
-}
+    let ops = CollectionEventOperation::load_all(&mut conn)?;
+    let rules = Rules::load(&mut conn)?;
 
-fn merge_ops<T, A>(existing_ops: Vec<T>, new_ops: Vec<T>) -> Result<HashMap<String, Vec<T>>, Error>
-where
-    T: arga_core::models::LogOperation<A>,
-{
-    let mut grouped: HashMap<String, Vec<T>> = HashMap::new();
-
-    for op in existing_ops.into_iter() {
-        grouped.entry(op.entity_id().clone()).or_default().push(op);
+    // dataset rules apply to any event with an operation from one of the dataset's versions
+    let mut dataset_versions: HashMap<String, HashSet<Uuid>> = HashMap::new();
+    for op in ops.iter() {
+        dataset_versions
+            .entry(op.entity_id.clone())
+            .or_default()
+            .insert(op.dataset_version_id);
     }
 
-    for op in new_ops.into_iter() {
-        grouped.entry(op.entity_id().clone()).or_default().push(op);
-    }
+    let collections = reduce_entities(ops).into_iter().map(CollectionEvent::from);
 
-    Ok(grouped)
-}
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    let mut locations = Vec::new();
+    let no_versions = HashSet::new();
+
+    for mut collection in collections {
+        let versions = dataset_versions.get(&collection.entity_id).unwrap_or(&no_versions);
+
+        // the precise coordinates are never exported, only saved for administrators
+        if let Some(generalisation) = rules.find(collection.scientific_name.as_deref(), versions) {
+            locations.push(SensitiveLocation {
+                entity_id: collection.entity_id.clone(),
+                latitude: collection.latitude,
+                longitude: collection.longitude,
+            });
+
+            (collection.latitude, collection.longitude) =
+                generalisation.generalise(collection.latitude, collection.longitude);
+            collection.location_generalisation = Some(generalisation);
+        }
 
-fn reduce_operations<T, A>(existing_ops: Vec<T>, new_ops: Vec<T>) -> Result<Vec<T>, Error>
-where
-    A: ToString + Clone + PartialEq,
-    T: arga_core::models::LogOperation<A> + Clone,
-{
-    let entities = merge_ops(existing_ops, new_ops)?;
-    let mut merged = Vec::new();
-
-    for (key, ops) in entities.into_iter() {
-        let mut map = Map::new(key);
-        let reduced = map.reduce(&ops);
-        merged.extend(reduced);
+        writer.serialize(collection)?;
     }
 
-    Ok(merged)
+    sensitive::save_locations(&mut conn, &locations)
 }
 
 
-pub fn reduce_specimens() -> Result<(), Error> {
-    use schema::specimen_logs::dsl::*;
-
-    let pool = get_pool()?;
-    let mut conn = pool.get()?;
-
-    let ops = specimen_logs
-        .order(operation_id.asc())
-        .load::<SpecimenOperation>(&mut conn)?;
-
-    let entities = merge_ops(ops, vec![])?;
-    let mut specimens = Vec::new();
-
-    for (key, ops) in entities.into_iter() {
-        let mut map = Map::new(key);
-        map.reduce(&ops);
-        specimens.push(Specimen::from(map));
+/// Parse a date with the shared date parser, logging the dates that are skipped
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    match utils::parse_date(value) {
+        Ok(date) => Some(date),
+        Err(err) => {
+            warn!(value, ?err, "Skipping invalid date");
+            None
+        }
     }
+}
 
-    let mut writer = csv::Writer::from_writer(std::io::stdout());
-
-    for specimen in specimens {
-        writer.serialize(specimen)?;
+/// Parse a time in the HH:MM:SS format, allowing the seconds to be omitted
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    let time = NaiveTime::parse_from_str(value, "%H:%M:%S").or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"));
+    match time {
+        Ok(time) => Some(time),
+        Err(err) => {
+            warn!(value, ?err, "Skipping invalid time");
+            None
+        }
     }
-
-    Ok(())
 }
 
-pub fn reduce_collections() -> Result<(), Error> {
-    use schema::collection_event_logs::dsl::*;
 
-    let pool = get_pool()?;
-    let mut conn = pool.get()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atoms(operations: &[OrganismOperation]) -> Vec<OrganismAtom> {
+        operations.iter().map(|op| op.atom.clone()).collect()
+    }
 
-    let ops = collection_event_logs
-        .order(operation_id.asc())
-        .load::<CollectionEventOperation>(&mut conn)?;
+    #[test]
+    fn organism_details_are_imported() {
+        let record = Record {
+            record_id: "MV-1".to_string(),
+            organism_id: Some("ORG-1".to_string()),
+            scientific_name: Some("Felis catus".to_string()),
+            sex: Some("female".to_string()),
+            life_stage: Some("adult".to_string()),
+            behavior: Some("nocturnal".to_string()),
+            ..Default::default()
+        };
 
-    let entities = merge_ops(ops, vec![])?;
-    let mut collections = Vec::new();
+        let (_, operations) = organism_operations(&record, Uuid::nil(), Version::new()).unwrap();
+        let atoms = atoms(&operations);
 
-    for (key, ops) in entities.into_iter() {
-        let mut map = Map::new(key);
-        map.reduce(&ops);
-        collections.push(CollectionEvent::from(map));
+        assert!(atoms.contains(&OrganismAtom::OrganismId("ORG-1".to_string())));
+        assert!(atoms.contains(&OrganismAtom::ScientificName("Felis catus".to_string())));
+        assert!(atoms.contains(&OrganismAtom::Sex("female".to_string())));
+        assert!(atoms.contains(&OrganismAtom::LifeStage("adult".to_string())));
+        assert!(atoms.contains(&OrganismAtom::Behavior("nocturnal".to_string())));
     }
 
-    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    #[test]
+    fn organisms_without_an_id_use_the_record_id() {
+        let record = Record {
+            record_id: "MV-1".to_string(),
+            sex: Some("male".to_string()),
+            ..Default::default()
+        };
 
-    for collection in collections {
-        writer.serialize(collection)?;
+        let (_, operations) = organism_operations(&record, Uuid::nil(), Version::new()).unwrap();
+        assert!(atoms(&operations).contains(&OrganismAtom::OrganismId("MV-1".to_string())));
+        assert_eq!(record.organism_id(), Some("MV-1".to_string()));
     }
 
-    Ok(())
+    #[test]
+    fn records_without_organism_details_have_no_organism() {
+        let record = Record {
+            record_id: "MV-1".to_string(),
+            organism_id: Some("ORG-1".to_string()),
+            ..Default::default()
+        };
+
+        assert!(organism_operations(&record, Uuid::nil(), Version::new()).is_none());
+        assert_eq!(record.organism_id(), Some("ORG-1".to_string()));
+    }
 }
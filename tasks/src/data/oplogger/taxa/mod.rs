@@ -1,18 +1,15 @@
-use std::collections::HashMap;
 use std::path::PathBuf;
 
-use arga_core::crdt::lww::Map;
+use arga_core::crdt::lww::{group_by_entity, reduce_entities, Map};
 use arga_core::crdt::{Frame, Version};
+use arga_core::models::logs::{Action, LogOperationDataset, OperationLogTable};
 use arga_core::models::{
-    Action,
     DatasetVersion,
-    LogOperationDataset,
     TaxonAtom,
     TaxonOperation,
     TaxonOperationWithDataset,
     TaxonomicActAtom,
     TaxonomicActOperation,
-    TaxonomicActType,
     TaxonomicRank,
     TaxonomicStatus,
 };
@@ -25,6 +22,7 @@ use uuid::Uuid;
 use xxhash_rust::xxh3::Xxh3;
 
 use crate::data::oplogger::get_pool;
+use crate::data::pipeline::Pipeline;
 use crate::data::{Error, ParseError};
 
 
@@ -107,6 +105,7 @@ impl From<Map<TaxonAtom>> for Taxon {
             match val {
                 Empty => {}
                 EntityId(_value) => {}
+                DatasetId(value) => taxon.dataset_id = Some(value),
                 TaxonId(value) => taxon.taxon_id = value,
                 ParentTaxon(value) => taxon.parent_taxon = Some(value),
                 ScientificName(value) => taxon.scientific_name = value,
@@ -140,7 +139,6 @@ pub struct TaxonomicAct {
     entity_id: String,
     taxon: String,
     accepted_taxon: Option<String>,
-    act: Option<TaxonomicActType>,
     publication: Option<String>,
     publication_date: Option<String>,
     source_url: Option<String>,
@@ -159,11 +157,11 @@ impl From<Map<TaxonomicActAtom>> for TaxonomicAct {
             match val {
                 Empty => {}
                 EntityId(_) => {}
+                DatasetId(_) => {}
                 Publication(value) => act.publication = Some(value),
                 PublicationDate(value) => act.publication_date = Some(value),
                 Taxon(value) => act.taxon = value,
                 AcceptedTaxon(value) => act.accepted_taxon = Some(value),
-                Act(value) => act.act = Some(value),
                 SourceUrl(value) => act.source_url = Some(value),
                 CreatedAt(_) => {}
                 UpdatedAt(_) => {}
@@ -268,18 +266,18 @@ impl Taxa {
         let mut operations: Vec<TaxonomicActOperation> = Vec::new();
 
         for record in records.into_iter() {
-            let act = match record.taxonomic_status {
-                TaxonomicStatus::Synonym => Some(TaxonomicActType::Synonym),
-                TaxonomicStatus::Homonym => Some(TaxonomicActType::Homonym),
-                TaxonomicStatus::Unaccepted => Some(TaxonomicActType::Unaccepted),
-                TaxonomicStatus::NomenclaturalSynonym => Some(TaxonomicActType::NomenclaturalSynonym),
-                TaxonomicStatus::TaxonomicSynonym => Some(TaxonomicActType::TaxonomicSynonym),
-                TaxonomicStatus::ReplacedSynonym => Some(TaxonomicActType::ReplacedSynonym),
-                _ => None,
-            };
+            let is_synonym = matches!(
+                record.taxonomic_status,
+                TaxonomicStatus::Synonym
+                    | TaxonomicStatus::Homonym
+                    | TaxonomicStatus::Unaccepted
+                    | TaxonomicStatus::NomenclaturalSynonym
+                    | TaxonomicStatus::TaxonomicSynonym
+                    | TaxonomicStatus::ReplacedSynonym
+            );
 
             // skip anything that isn't a synonym
-            if act.is_none() {
+            if !is_synonym {
                 continue;
             }
 
@@ -294,9 +292,6 @@ impl Taxa {
             let mut frame = TaxonomicActFrame::create(self.dataset_version_id, hash.to_string(), last_version);
             frame.push(Taxon(record.scientific_name));
 
-            if let Some(value) = act {
-                frame.push(Act(value));
-            }
             if let Some(value) = record.accepted_usage_taxon {
                 frame.push(AcceptedTaxon(value));
             }
@@ -319,110 +314,21 @@ impl Taxa {
 }
 
 
-pub fn process(path: PathBuf, dataset_version: DatasetVersion) -> Result<(), Error> {
-    use schema::{taxa_logs, taxonomic_act_logs};
-
+pub fn process(path: PathBuf, dataset_version: DatasetVersion, pipeline: &mut Pipeline) -> Result<(), Error> {
     let taxa = Taxa {
         path: path.clone(),
         dataset_version_id: dataset_version.id,
     };
 
-    let pool = get_pool()?;
-    let mut conn = pool.get()?;
-
-    info!("Loading taxon operations");
-    let taxon_ops = taxa_logs::table
-        .order(taxa_logs::operation_id.asc())
-        .load::<TaxonOperation>(&mut conn)?;
-
-    info!("Reducing taxon operations");
-    let records = taxa.taxa()?;
-    let reduced = reduce_operations(taxon_ops, records)?;
-
-    info!("Importing taxon operations");
-    import_taxa(reduced)?;
-
-    info!("Loading taxonomic act operations");
-    let taxon_act_ops = taxonomic_act_logs::table
-        .order(taxonomic_act_logs::operation_id.asc())
-        .load::<TaxonomicActOperation>(&mut conn)?;
+    info!("Processing taxon operations");
+    pipeline.merge(taxa.taxa()?)?;
 
-    info!("Reducing taxonomic act operations");
-    let records = taxa.acts()?;
-    let reduced = reduce_operations(taxon_act_ops, records)?;
-
-    info!("Importing taxonomic act operations");
-    import_acts(reduced)?;
+    info!("Processing taxonomic act operations");
+    pipeline.merge(taxa.acts()?)?;
 
     Ok(())
 }
 
-fn import_taxa(records: Vec<TaxonOperation>) -> Result<(), Error> {
-    use schema::taxa_logs::dsl::*;
-
-    let pool = get_pool()?;
-    let mut conn = pool.get()?;
-
-    for chunk in records.chunks(1000) {
-        diesel::insert_into(taxa_logs)
-            .values(chunk)
-            .on_conflict_do_nothing()
-            .execute(&mut conn)?;
-    }
-
-    Ok(())
-}
-
-fn import_acts(records: Vec<TaxonomicActOperation>) -> Result<(), Error> {
-    use schema::taxonomic_act_logs::dsl::*;
-
-    let pool = get_pool()?;
-    let mut conn = pool.get()?;
-
-    for chunk in records.chunks(1000) {
-        diesel::insert_into(taxonomic_act_logs)
-            .values(chunk)
-            .on_conflict_do_nothing()
-            .execute(&mut conn)?;
-    }
-
-    Ok(())
-}
-
-fn merge_ops<T, A>(existing_ops: Vec<T>, new_ops: Vec<T>) -> Result<HashMap<String, Vec<T>>, Error>
-where
-    T: arga_core::models::LogOperation<A>,
-{
-    let mut grouped: HashMap<String, Vec<T>> = HashMap::new();
-
-    for op in existing_ops.into_iter() {
-        grouped.entry(op.entity_id().clone()).or_default().push(op);
-    }
-
-    for op in new_ops.into_iter() {
-        grouped.entry(op.entity_id().clone()).or_default().push(op);
-    }
-
-    Ok(grouped)
-}
-
-fn reduce_operations<T, A>(existing_ops: Vec<T>, new_ops: Vec<T>) -> Result<Vec<T>, Error>
-where
-    A: ToString + Clone + PartialEq,
-    T: arga_core::models::LogOperation<A> + Clone,
-{
-    let entities = merge_ops(existing_ops, new_ops)?;
-    let mut merged = Vec::new();
-
-    for (key, ops) in entities.into_iter() {
-        let mut map = Map::new(key);
-        let reduced = map.reduce(&ops);
-        merged.extend(reduced);
-    }
-
-    Ok(merged)
-}
-
 
 pub fn reduce() -> Result<(), Error> {
     use schema::taxa_logs::dsl::*;
@@ -435,9 +341,10 @@ pub fn reduce() -> Result<(), Error> {
         .inner_join(dataset_versions::table.on(dataset_version_id.eq(dataset_versions::id)))
         .inner_join(datasets::table.on(dataset_versions::dataset_id.eq(datasets::id)))
         .order(operation_id.asc())
+        .select(TaxonOperationWithDataset::as_select())
         .load::<TaxonOperationWithDataset>(&mut conn)?;
 
-    let entities = merge_ops(ops, vec![])?;
+    let entities = group_by_entity(ops);
     let mut taxa = Vec::new();
 
     for (key, ops) in entities.into_iter() {
@@ -474,28 +381,16 @@ pub fn reduce() -> Result<(), Error> {
 }
 
 pub fn reduce_acts() -> Result<(), Error> {
-    use schema::taxonomic_act_logs::dsl::*;
-
     let pool = get_pool()?;
     let mut conn = pool.get()?;
 
-    let ops = taxonomic_act_logs
-        .order(operation_id.asc())
-        .load::<TaxonomicActOperation>(&mut conn)?;
-
-    let entities = merge_ops(ops, vec![])?;
-    let mut taxa = Vec::new();
-
-    for (key, ops) in entities.into_iter() {
-        let mut map = Map::new(key);
-        map.reduce(&ops);
-        taxa.push(TaxonomicAct::from(map));
-    }
+    let ops = TaxonomicActOperation::load_all(&mut conn)?;
+    let acts = reduce_entities(ops).into_iter().map(TaxonomicAct::from);
 
     let mut writer = csv::Writer::from_writer(std::io::stdout());
 
-    for taxon in taxa {
-        writer.serialize(taxon)?;
+    for act in acts {
+        writer.serialize(act)?;
     }
 
     Ok(())
@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use arga_core::crdt::lww;
+use arga_core::models::logs::{LogOperation, OperationLogTable};
+use bigdecimal::BigDecimal;
+use diesel::{PgConnection, QueryResult};
+use tracing::info;
+
+use crate::data::oplogger::get_pool;
+use crate::data::Error;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Insert the operations into the log tables
+    Import,
+    /// Only count the entities the operations would create or update
+    DryRun,
+}
+
+
+/// The entities affected by an import in a single log table
+#[derive(Debug, Clone, Default)]
+pub struct TableSummary {
+    pub operations: usize,
+    pub creates: usize,
+    pub updates: usize,
+}
+
+
+/// The destination of the operations produced by an importer.
+///
+/// Importers hand every batch of operations to the pipeline instead of inserting them
+/// directly which lets the same importer run as a dry run. An entity is counted as an
+/// update when the log table already has operations for it, and as a create otherwise.
+pub struct Pipeline {
+    mode: Mode,
+    summary: BTreeMap<&'static str, TableSummary>,
+    entities: HashMap<&'static str, HashSet<String>>,
+}
+
+impl Pipeline {
+    pub fn new(mode: Mode) -> Pipeline {
+        Pipeline {
+            mode,
+            summary: BTreeMap::new(),
+            entities: HashMap::new(),
+        }
+    }
+
+    pub fn import() -> Pipeline {
+        Pipeline::new(Mode::Import)
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.mode == Mode::DryRun
+    }
+
+    pub fn summary(&self) -> &BTreeMap<&'static str, TableSummary> {
+        &self.summary
+    }
+
+    /// Insert the operations into their log table, or only count them in a dry run
+    pub fn insert<T: OperationLogTable>(&mut self, conn: &mut PgConnection, operations: &[T]) -> Result<(), Error> {
+        let seen = self.entities.entry(T::table_name()).or_default();
+        let entities: Vec<String> = operations
+            .iter()
+            .map(|op| op.entity_id())
+            .filter(|entity_id| !seen.contains(*entity_id))
+            .cloned()
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        let mut existing = 0;
+        for chunk in entities.chunks(10_000) {
+            existing += T::existing_entities(conn, chunk)?.len();
+        }
+        seen.extend(entities.iter().cloned());
+
+        let summary = self.summary.entry(T::table_name()).or_default();
+        summary.operations += operations.len();
+        summary.updates += existing;
+        summary.creates += entities.len() - existing;
+
+        if self.mode == Mode::Import {
+            insert_operations(conn, operations)?;
+        }
+
+        Ok(())
+    }
+
+    /// Merge operations into their log table.
+    ///
+    /// The new operations are reduced along with the existing operations in the log table
+    /// so that only operations that actually change the state of an entity get inserted
+    /// and counted in the summary.
+    pub fn merge<T: OperationLogTable>(&mut self, operations: Vec<T>) -> Result<(), Error> {
+        let pool = get_pool()?;
+        let mut conn = pool.get()?;
+
+        // only the operations of the merged entities are needed to find what changed
+        info!(table = T::table_name(), "Loading operations");
+        let entity_ids: Vec<String> = operations
+            .iter()
+            .map(|op| op.entity_id().clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        let mut existing = Vec::new();
+        for chunk in entity_ids.chunks(10_000) {
+            existing.extend(T::load_entities(&mut conn, chunk)?);
+        }
+
+        info!(table = T::table_name(), "Reducing operations");
+        let changes = changed_operations(existing, operations);
+
+        info!(table = T::table_name(), total = changes.len(), "Importing operations");
+        self.insert(&mut conn, &changes)
+    }
+
+    /// Log the entities created and updated in every log table
+    pub fn report(&self) {
+        for (table, summary) in self.summary.iter() {
+            info!(
+                table,
+                operations = summary.operations,
+                creates = summary.creates,
+                updates = summary.updates,
+                dry_run = self.is_dry_run(),
+                "Import summary"
+            );
+        }
+    }
+}
+
+
+/// Reduce the new operations with the existing ones and keep only the new operations that change an atom.
+///
+/// The reduction also returns the existing operations that still determine the state of an
+/// entity, but they are already in the log table and would inflate the summary counts.
+pub fn changed_operations<T, A>(existing: Vec<T>, operations: Vec<T>) -> Vec<T>
+where
+    A: ToString + Clone + PartialEq,
+    T: LogOperation<A> + Clone,
+{
+    let existing_ids: HashSet<BigDecimal> = existing.iter().map(|op| op.id().clone()).collect();
+    lww::reduce_operations(existing, operations)
+        .into_iter()
+        .filter(|op| !existing_ids.contains(op.id()))
+        .collect()
+}
+
+
+/// Insert the operations into their log table in batches small enough for a single statement
+pub fn insert_operations<T: OperationLogTable>(conn: &mut PgConnection, operations: &[T]) -> QueryResult<()> {
+    for chunk in operations.chunks(1000) {
+        T::insert_all(conn, chunk)?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use arga_core::models::logs::Action;
+    use arga_core::models::{OrganismAtom, OrganismOperation};
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn op(id: u64, entity_id: &str, atom: OrganismAtom) -> OrganismOperation {
+        OrganismOperation {
+            operation_id: BigDecimal::from(id),
+            parent_id: BigDecimal::from(id),
+            entity_id: entity_id.to_string(),
+            dataset_version_id: Uuid::nil(),
+            action: Action::Update,
+            atom,
+        }
+    }
+
+    fn ids(ops: &[OrganismOperation]) -> Vec<u64> {
+        let mut ids: Vec<u64> = ops
+            .iter()
+            .map(|op| op.operation_id.to_string().parse().unwrap())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn remerging_an_unchanged_batch_has_no_changes() {
+        let existing = vec![
+            op(1, "a", OrganismAtom::Sex("female".to_string())),
+            op(2, "a", OrganismAtom::LifeStage("adult".to_string())),
+            op(3, "b", OrganismAtom::Sex("male".to_string())),
+        ];
+        let batch = vec![
+            op(10, "a", OrganismAtom::Sex("female".to_string())),
+            op(11, "a", OrganismAtom::LifeStage("adult".to_string())),
+            op(12, "b", OrganismAtom::Sex("male".to_string())),
+        ];
+
+        assert!(changed_operations(existing.clone(), existing.clone()).is_empty());
+        assert!(changed_operations(existing, batch).is_empty());
+    }
+
+    #[test]
+    fn only_keeps_the_new_operations_that_change_an_atom() {
+        let existing = vec![
+            op(1, "a", OrganismAtom::Sex("female".to_string())),
+            op(2, "a", OrganismAtom::LifeStage("adult".to_string())),
+            op(3, "b", OrganismAtom::Sex("male".to_string())),
+        ];
+        let batch = vec![
+            op(10, "a", OrganismAtom::Sex("female".to_string())),
+            op(11, "a", OrganismAtom::LifeStage("juvenile".to_string())),
+            op(12, "c", OrganismAtom::Sex("male".to_string())),
+        ];
+
+        assert_eq!(ids(&changed_operations(existing, batch)), vec![11, 12]);
+    }
+}
@@ -0,0 +1,321 @@
+use arga_core::crdt::{DataFrame, Version};
+use arga_core::models::{
+    AccessionEventAtom,
+    AccessionEventOperation,
+    CollectionEventAtom,
+    CollectionEventOperation,
+    OrganismAtom,
+    OrganismOperation,
+    SpecimenAtom,
+    SpecimenOperation,
+    entity_hash,
+};
+use uuid::Uuid;
+
+use super::treatments::MaterialsCitation;
+use crate::data::utils::{non_empty, parse_date};
+
+
+/// The operations for the specimens cited in treatments.
+///
+/// Every citation becomes an organism and a specimen with a collection event. Citations that
+/// identify a registered specimen, either by a specimen code or a type status, also get an
+/// accession event since that is where the type status of a specimen is recorded.
+///
+/// All of them use the scientific name of the treatment so that they link to the same name as
+/// the nomenclatural act, and the organism records the publication the act was made in. Like
+/// the other specimen logs they are reduced with `tasks oplog reduce log` into the organisms,
+/// specimens, collection_events and accession_events tables, where an accession event with a
+/// type status makes the specimen one of the `Taxon.typeSpecimens` of its name.
+#[derive(Debug, Default)]
+pub struct MaterialOperations {
+    pub organisms: Vec<OrganismOperation>,
+    pub specimens: Vec<SpecimenOperation>,
+    pub collections: Vec<CollectionEventOperation>,
+    pub accessions: Vec<AccessionEventOperation>,
+}
+
+/// The treatment that cited the material
+pub struct CitingTreatment<'a> {
+    pub uri: &'a str,
+    pub publication_entity_id: &'a str,
+    /// The name the treatment is about
+    pub scientific_name: Option<&'a str>,
+}
+
+
+impl MaterialOperations {
+    /// Add the operations for the nth material citation in a treatment and return the last version used
+    pub fn push(
+        &mut self,
+        citation: &MaterialsCitation,
+        index: usize,
+        treatment: &CitingTreatment,
+        dataset_version_id: Uuid,
+        last_version: Version,
+    ) -> Version {
+        let specimen_id = citation.specimen_id(treatment.uri, index);
+        let entity_id = entity_hash(&specimen_id);
+
+        let institution_code = non_empty(&citation.collection_code);
+        let specimen_code = non_empty(&citation.specimen_code);
+        let type_status = non_empty(&citation.type_status);
+
+        // organism. a cited specimen is the only record of the organism it came from
+        let mut frame = DataFrame::create(entity_id.clone(), dataset_version_id, last_version);
+        frame.push(OrganismAtom::OrganismId(specimen_id.clone()));
+        frame.push(OrganismAtom::PublicationId(treatment.publication_entity_id.to_string()));
+
+        if let Some(name) = treatment.scientific_name {
+            frame.push(OrganismAtom::ScientificName(name.to_string()));
+        }
+
+        let last_version = frame.last_version();
+        self.organisms.extend(frame.collect::<OrganismOperation>());
+
+        // specimen
+        let mut frame = DataFrame::create(entity_id.clone(), dataset_version_id, last_version);
+        frame.push(SpecimenAtom::SpecimenId(specimen_id.clone()));
+
+        if let Some(name) = treatment.scientific_name {
+            frame.push(SpecimenAtom::ScientificName(name.to_string()));
+        }
+        if let Some(value) = &institution_code {
+            frame.push(SpecimenAtom::InstitutionCode(value.clone()));
+        }
+        if let Some(value) = &specimen_code {
+            frame.push(SpecimenAtom::CollectionRepositoryId(value.clone()));
+        }
+        if let Some(value) = &type_status {
+            frame.push(SpecimenAtom::TypeStatus(value.clone()));
+        }
+
+        let last_version = frame.last_version();
+        self.specimens.extend(frame.collect::<SpecimenOperation>());
+
+        // collection event
+        let mut frame = DataFrame::create(entity_id.clone(), dataset_version_id, last_version);
+        frame.push(CollectionEventAtom::SpecimenId(specimen_id.clone()));
+        frame.push(CollectionEventAtom::OrganismId(specimen_id.clone()));
+
+        if let Some(name) = treatment.scientific_name {
+            frame.push(CollectionEventAtom::ScientificName(name.to_string()));
+        }
+        if let Some(date) = non_empty(&citation.collecting_date).and_then(|date| parse_date(&date).ok()) {
+            frame.push(CollectionEventAtom::EventDate(date));
+        }
+        if let Some(value) = non_empty(&citation.collector_name) {
+            frame.push(CollectionEventAtom::CollectedBy(value));
+        }
+        if let Some(value) = non_empty(&citation.location) {
+            frame.push(CollectionEventAtom::Locality(value));
+        }
+        if let Some(value) = non_empty(&citation.country) {
+            frame.push(CollectionEventAtom::Country(value));
+        }
+        if let Some(value) = non_empty(&citation.state_province) {
+            frame.push(CollectionEventAtom::StateProvince(value));
+        }
+        if let Some(value) = non_empty(&citation.county) {
+            frame.push(CollectionEventAtom::County(value));
+        }
+        if let Some(value) = non_empty(&citation.municipality) {
+            frame.push(CollectionEventAtom::Municipality(value));
+        }
+        if let Some(value) = number(&citation.latitude) {
+            frame.push(CollectionEventAtom::Latitude(value));
+        }
+        if let Some(value) = number(&citation.longitude) {
+            frame.push(CollectionEventAtom::Longitude(value));
+        }
+        if let Some(value) = number(&citation.elevation) {
+            frame.push(CollectionEventAtom::Elevation(value));
+        }
+        if let Some(value) = non_empty(&citation.specimen_count) {
+            frame.push(CollectionEventAtom::IndividualCount(value));
+        }
+
+        let last_version = frame.last_version();
+        self.collections.extend(frame.collect::<CollectionEventOperation>());
+
+        if specimen_code.is_none() && type_status.is_none() {
+            return last_version;
+        }
+
+        // accession event
+        let mut frame = DataFrame::create(entity_id, dataset_version_id, last_version);
+        frame.push(AccessionEventAtom::SpecimenId(specimen_id));
+
+        if let Some(name) = treatment.scientific_name {
+            frame.push(AccessionEventAtom::ScientificName(name.to_string()));
+        }
+        if let Some(value) = institution_code {
+            frame.push(AccessionEventAtom::InstitutionCode(value));
+        }
+        if let Some(value) = specimen_code {
+            frame.push(AccessionEventAtom::CollectionRepositoryId(value));
+        }
+        if let Some(value) = type_status {
+            frame.push(AccessionEventAtom::TypeStatus(value));
+        }
+
+        let last_version = frame.last_version();
+        self.accessions.extend(frame.collect::<AccessionEventOperation>());
+        last_version
+    }
+}
+
+
+impl MaterialsCitation {
+    /// A globally unique id for the cited specimen.
+    ///
+    /// The same specimen is often cited by multiple treatments so we prefer the institution
+    /// and specimen code when available. Otherwise the citation itself is the specimen.
+    pub fn specimen_id(&self, treatment_uri: &str, index: usize) -> String {
+        match (non_empty(&self.collection_code), non_empty(&self.specimen_code)) {
+            (Some(institution), Some(code)) => format!("{institution}:{code}"),
+            _ => match (non_empty(&self.http_uri), non_empty(&self.id)) {
+                (Some(uri), _) => uri,
+                (None, Some(id)) => format!("{treatment_uri}#{id}"),
+                (None, None) => format!("{treatment_uri}#{index}"),
+            },
+        }
+    }
+}
+
+
+fn number(attribute: &Option<String>) -> Option<f64> {
+    non_empty(attribute).and_then(|value| value.parse::<f64>().ok())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    const TREATMENT: &str = "http://treatment.plazi.org/id/03A1";
+
+    fn type_citation() -> MaterialsCitation {
+        MaterialsCitation {
+            id: Some("M1".to_string()),
+            specimen_code: Some(" 12345 ".to_string()),
+            collection_code: Some("AM".to_string()),
+            type_status: Some("holotype".to_string()),
+            collector_name: Some("J. Smith".to_string()),
+            collecting_date: Some("1990-05-20".to_string()),
+            country: Some("Australia".to_string()),
+            latitude: Some("-33.86".to_string()),
+            longitude: Some("".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn treatment(scientific_name: Option<&str>) -> CitingTreatment<'_> {
+        CitingTreatment {
+            uri: TREATMENT,
+            publication_entity_id: "pub-1",
+            scientific_name,
+        }
+    }
+
+    fn push(citation: &MaterialsCitation) -> MaterialOperations {
+        let mut operations = MaterialOperations::default();
+        operations.push(citation, 0, &treatment(Some("Felis catus")), Uuid::nil(), Version::new());
+        operations
+    }
+
+    #[test]
+    fn specimen_id_prefers_the_institution_and_code() {
+        let mut citation = type_citation();
+        assert_eq!(citation.specimen_id(TREATMENT, 2), "AM:12345");
+
+        citation.specimen_code = Some(" ".to_string());
+        citation.http_uri = Some("http://example.org/specimen/1".to_string());
+        assert_eq!(citation.specimen_id(TREATMENT, 2), "http://example.org/specimen/1");
+
+        citation.http_uri = None;
+        assert_eq!(citation.specimen_id(TREATMENT, 2), format!("{TREATMENT}#M1"));
+
+        citation.id = None;
+        assert_eq!(citation.specimen_id(TREATMENT, 2), format!("{TREATMENT}#2"));
+    }
+
+    #[test]
+    fn every_log_uses_the_same_entity() {
+        let operations = push(&type_citation());
+        let entity_id = entity_hash("AM:12345");
+
+        assert!(operations.organisms.iter().all(|op| op.entity_id == entity_id));
+        assert!(operations.specimens.iter().all(|op| op.entity_id == entity_id));
+        assert!(operations.collections.iter().all(|op| op.entity_id == entity_id));
+        assert!(operations.accessions.iter().all(|op| op.entity_id == entity_id));
+    }
+
+    #[test]
+    fn organism_links_to_the_publication() {
+        let operations = push(&type_citation());
+        let atoms: Vec<&OrganismAtom> = operations.organisms.iter().map(|op| &op.atom).collect();
+
+        assert!(atoms.contains(&&OrganismAtom::OrganismId("AM:12345".to_string())));
+        assert!(atoms.contains(&&OrganismAtom::PublicationId("pub-1".to_string())));
+        assert!(atoms.contains(&&OrganismAtom::ScientificName("Felis catus".to_string())));
+    }
+
+    #[test]
+    fn citation_details_are_mapped_to_the_collection_event() {
+        let operations = push(&type_citation());
+        let atoms: Vec<&CollectionEventAtom> = operations.collections.iter().map(|op| &op.atom).collect();
+
+        assert!(atoms.contains(&&CollectionEventAtom::OrganismId("AM:12345".to_string())));
+        assert!(atoms.contains(&&CollectionEventAtom::CollectedBy("J. Smith".to_string())));
+        assert!(atoms.contains(&&CollectionEventAtom::Country("Australia".to_string())));
+        assert!(atoms.contains(&&CollectionEventAtom::Latitude(-33.86)));
+        assert!(atoms.contains(&&CollectionEventAtom::EventDate(NaiveDate::from_ymd_opt(1990, 5, 20).unwrap())));
+
+        // blank attributes are skipped rather than imported as empty values
+        assert!(
+            !atoms
+                .iter()
+                .any(|atom| matches!(atom, CollectionEventAtom::Longitude(_)))
+        );
+    }
+
+    #[test]
+    fn type_status_is_recorded_on_the_accession() {
+        let operations = push(&type_citation());
+        let atoms: Vec<&AccessionEventAtom> = operations.accessions.iter().map(|op| &op.atom).collect();
+
+        assert!(atoms.contains(&&AccessionEventAtom::TypeStatus("holotype".to_string())));
+        assert!(atoms.contains(&&AccessionEventAtom::CollectionRepositoryId("12345".to_string())));
+        assert!(atoms.contains(&&AccessionEventAtom::InstitutionCode("AM".to_string())));
+    }
+
+    #[test]
+    fn unregistered_specimens_have_no_accession() {
+        let citation = MaterialsCitation {
+            collector_name: Some("J. Smith".to_string()),
+            ..Default::default()
+        };
+        let operations = push(&citation);
+
+        assert!(!operations.specimens.is_empty());
+        assert!(!operations.collections.is_empty());
+        assert!(operations.accessions.is_empty());
+    }
+
+    #[test]
+    fn versions_increase_across_logs() {
+        let mut operations = MaterialOperations::default();
+        let last = operations.push(&type_citation(), 0, &treatment(None), Uuid::nil(), Version::new());
+        operations.push(&type_citation(), 1, &treatment(None), Uuid::nil(), last);
+
+        let mut ids: Vec<_> = operations.organisms.iter().map(|op| op.operation_id.clone()).collect();
+        ids.extend(operations.specimens.iter().map(|op| op.operation_id.clone()));
+        let total = ids.len();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), total);
+    }
+}
@@ -1,4 +1,6 @@
 pub mod formatting;
+pub mod materials;
+pub mod publications;
 pub mod treatments;
 
 use std::path::PathBuf;
@@ -10,18 +12,25 @@ use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::*;
 use uuid::Uuid;
 
-use super::Error;
+use super::{Error, ParseError};
 
 type PgPool = Pool<ConnectionManager<PgConnection>>;
 
 
 #[derive(clap::Subcommand)]
 pub enum Command {
+    /// Import the nomenclatural acts, publications and treatment text from a directory of treatment XML files
     ImportTreatments {
         dataset_id: String,
         version: String,
         created_at: String,
         path: PathBuf,
+        /// The file recording every imported file. Rerun with the same file to resume an import
+        #[arg(long, default_value = "plazi_treatments.checkpoint")]
+        checkpoint: PathBuf,
+        /// The CSV file to write the files that failed to parse into
+        #[arg(long, default_value = "plazi_treatments_errors.csv")]
+        report: PathBuf,
     },
 }
 
@@ -32,8 +41,11 @@ pub fn process_command(command: &Command) {
             version,
             created_at,
             path,
+            checkpoint,
+            report,
         } => {
-            treatments::import(path.clone(), create_dataset_version(dataset_id, version, created_at).unwrap()).unwrap()
+            let dataset_version = find_or_create_dataset_version(dataset_id, version, created_at).unwrap();
+            treatments::import(path.clone(), dataset_version, checkpoint.clone(), report.clone()).unwrap()
         }
     }
 }
@@ -46,18 +58,35 @@ pub fn get_pool() -> Result<PgPool, Error> {
     Ok(pool)
 }
 
-fn create_dataset_version(dataset_id: &str, version: &str, created_at: &str) -> Result<DatasetVersion, Error> {
+/// Get the dataset version created at the same time or create it if it doesn't exist.
+///
+/// This allows a resumed import to continue adding operations to the same version.
+fn find_or_create_dataset_version(dataset_id: &str, version: &str, created_at: &str) -> Result<DatasetVersion, Error> {
     use schema::dataset_versions;
 
     let pool = get_pool()?;
     let mut conn = pool.get()?;
 
+    let dataset_uuid = find_database_id(dataset_id)?;
+    let created_at = DateTime::parse_from_rfc3339(created_at).map_err(ParseError::from)?.to_utc();
+
+    let existing = dataset_versions::table
+        .filter(dataset_versions::dataset_id.eq(dataset_uuid))
+        .filter(dataset_versions::created_at.eq(created_at))
+        .select(DatasetVersion::as_select())
+        .get_result(&mut conn)
+        .optional()?;
+
+    if let Some(dataset_version) = existing {
+        return Ok(dataset_version);
+    }
+
     let dataset_version = diesel::insert_into(dataset_versions::table)
         .values(DatasetVersion {
             id: Uuid::new_v4(),
-            dataset_id: find_database_id(&dataset_id)?,
+            dataset_id: dataset_uuid,
             version: version.to_string(),
-            created_at: DateTime::parse_from_rfc3339(&created_at).unwrap().to_utc(),
+            created_at,
             imported_at: Utc::now(),
         })
         .returning(DatasetVersion::as_select())
@@ -0,0 +1,241 @@
+use std::str::FromStr;
+
+use arga_core::crdt::{DataFrame, Version};
+use arga_core::models::{PublicationAtom, PublicationOperation, PublicationType};
+use uuid::Uuid;
+
+use super::formatting::Classification;
+use super::treatments::Document;
+use crate::data::utils::{non_empty, non_empty_str};
+
+
+/// The operations for the publications that treatments were extracted from.
+///
+/// Most of the bibliographic metadata comes from the MODS record in the document. The
+/// document attributes are only used when the record is missing the title, authors or date.
+#[derive(Debug, Default)]
+pub struct PublicationOperations {
+    pub publications: Vec<PublicationOperation>,
+}
+
+impl PublicationOperations {
+    /// Add the operations for the publication of a document and return the last version used
+    pub fn push(
+        &mut self,
+        entity_id: String,
+        document: &Document,
+        dataset_version_id: Uuid,
+        last_version: Version,
+    ) -> Version {
+        let mods = &document.mods;
+        let mut frame = DataFrame::create(entity_id, dataset_version_id, last_version);
+
+        let title = non_empty(&mods.title).or_else(|| non_empty_str(&document.title));
+        let authors = match mods.authors.is_empty() {
+            true => non_empty_str(&document.authors).into_iter().collect(),
+            false => mods.authors.clone(),
+        };
+        let year = non_empty(&mods.date_issued)
+            .or_else(|| non_empty(&mods.part_date))
+            .or_else(|| non_empty_str(&document.date_issued))
+            .and_then(|date| parse_year(&date));
+        let pages = match (non_empty(&mods.page_start), non_empty(&mods.page_end)) {
+            (Some(start), Some(end)) if start != end => Some(format!("{start}-{end}")),
+            (Some(start), _) => Some(start),
+            (None, end) => end,
+        };
+        let publication_type = non_empty(&mods.classification).and_then(|value| publication_type(&value));
+
+        let citation = format_citation(&Reference {
+            authors: &authors,
+            year,
+            title: title.as_deref(),
+            journal: non_empty(&mods.journal).as_deref(),
+            volume: non_empty(&mods.volume).as_deref(),
+            issue: non_empty(&mods.issue).as_deref(),
+            pages: pages.as_deref(),
+            doi: non_empty(&mods.doi).as_deref(),
+        });
+
+        if let Some(value) = title {
+            frame.push(PublicationAtom::Title(value));
+        }
+        if !authors.is_empty() {
+            frame.push(PublicationAtom::Authors(authors));
+        }
+        if let Some(value) = year {
+            frame.push(PublicationAtom::PublishedYear(value));
+        }
+        if let Some(value) = non_empty(&mods.publisher) {
+            frame.push(PublicationAtom::Publisher(value));
+        }
+        if let Some(value) = non_empty(&mods.doi) {
+            frame.push(PublicationAtom::Doi(value));
+        }
+        if let Some(value) = non_empty(&mods.url) {
+            frame.push(PublicationAtom::SourceUrl(value));
+        }
+        if let Some(value) = publication_type {
+            frame.push(PublicationAtom::Type(value));
+        }
+        if let Some(value) = non_empty(&mods.journal) {
+            frame.push(PublicationAtom::Journal(value));
+        }
+        if let Some(value) = non_empty(&mods.volume) {
+            frame.push(PublicationAtom::Volume(value));
+        }
+        if let Some(value) = non_empty(&mods.issue) {
+            frame.push(PublicationAtom::Issue(value));
+        }
+        if let Some(value) = pages {
+            frame.push(PublicationAtom::Pages(value));
+        }
+        if let Some(value) = citation {
+            frame.push(PublicationAtom::Citation(value));
+        }
+
+        let last_version = frame.last_version();
+        self.publications.extend(frame.collect::<PublicationOperation>());
+        last_version
+    }
+}
+
+
+struct Reference<'a> {
+    authors: &'a [String],
+    year: Option<i32>,
+    title: Option<&'a str>,
+    journal: Option<&'a str>,
+    volume: Option<&'a str>,
+    issue: Option<&'a str>,
+    pages: Option<&'a str>,
+    doi: Option<&'a str>,
+}
+
+/// Format a reference as a citation, eg. `Smith, J. & Doe, J. (2020) Title. Journal 12(3): 1-20`
+fn format_citation(reference: &Reference) -> Option<String> {
+    let title = reference.title?;
+    let mut citation = String::new();
+
+    if !reference.authors.is_empty() {
+        citation.push_str(&reference.authors.join(" & "));
+        citation.push(' ');
+    }
+    if let Some(year) = reference.year {
+        citation.push_str(&format!("({year}) "));
+    }
+
+    citation.push_str(title.trim_end_matches('.'));
+    citation.push('.');
+
+    if let Some(journal) = reference.journal {
+        citation.push(' ');
+        citation.push_str(journal);
+
+        if let Some(volume) = reference.volume {
+            citation.push_str(&format!(" {volume}"));
+        }
+        if let Some(issue) = reference.issue {
+            citation.push_str(&format!("({issue})"));
+        }
+        if let Some(pages) = reference.pages {
+            citation.push_str(&format!(": {pages}"));
+        }
+    }
+
+    if let Some(doi) = reference.doi {
+        citation.push_str(&format!(". https://doi.org/{doi}"));
+    }
+
+    Some(citation)
+}
+
+/// Get the publication type from a MODS classification. Proceedings are a collection of
+/// papers rather than a single publication so we leave them untyped
+fn publication_type(classification: &str) -> Option<PublicationType> {
+    match Classification::from_str(classification).ok()? {
+        Classification::Book => Some(PublicationType::Book),
+        Classification::BookChapter => Some(PublicationType::BookChapter),
+        Classification::JournalArticle => Some(PublicationType::JournalArticle),
+        Classification::JournalVolume => Some(PublicationType::JournalVolume),
+        Classification::ProceedingsPaper => Some(PublicationType::ProceedingsPaper),
+        Classification::Url => Some(PublicationType::Url),
+        Classification::Proceedings => None,
+    }
+}
+
+/// Get the year from a date that is either just the year or starts with it
+fn parse_year(date: &str) -> Option<i32> {
+    date.get(0..4).and_then(|year| year.parse::<i32>().ok())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference<'a>(authors: &'a [String], title: Option<&'a str>) -> Reference<'a> {
+        Reference {
+            authors,
+            year: None,
+            title,
+            journal: None,
+            volume: None,
+            issue: None,
+            pages: None,
+            doi: None,
+        }
+    }
+
+    #[test]
+    fn formats_a_full_journal_citation() {
+        let authors = vec!["Smith, J.".to_string(), "Doe, J.".to_string()];
+        let citation = format_citation(&Reference {
+            year: Some(2020),
+            journal: Some("Zootaxa"),
+            volume: Some("12"),
+            issue: Some("3"),
+            pages: Some("1-20"),
+            doi: Some("10.11646/zootaxa.1"),
+            ..reference(&authors, Some("A new species."))
+        });
+
+        assert_eq!(
+            citation.as_deref(),
+            Some("Smith, J. & Doe, J. (2020) A new species. Zootaxa 12(3): 1-20. https://doi.org/10.11646/zootaxa.1")
+        );
+    }
+
+    #[test]
+    fn a_title_is_required() {
+        let authors = vec!["Smith, J.".to_string()];
+        assert_eq!(format_citation(&reference(&authors, None)), None);
+    }
+
+    #[test]
+    fn missing_parts_are_left_out() {
+        assert_eq!(format_citation(&reference(&[], Some("Title"))).as_deref(), Some("Title."));
+
+        let citation = format_citation(&Reference {
+            year: Some(1999),
+            pages: Some("5"),
+            ..reference(&[], Some("Title"))
+        });
+        assert_eq!(citation.as_deref(), Some("(1999) Title."));
+
+        let citation = format_citation(&Reference {
+            journal: Some("Zootaxa"),
+            issue: Some("2"),
+            ..reference(&[], Some("Title"))
+        });
+        assert_eq!(citation.as_deref(), Some("Title. Zootaxa(2)"));
+    }
+
+    #[test]
+    fn parses_the_year_from_partial_dates() {
+        assert_eq!(parse_year("2020"), Some(2020));
+        assert_eq!(parse_year("2020-05-01"), Some(2020));
+        assert_eq!(parse_year("May 2020"), None);
+        assert_eq!(parse_year("20"), None);
+    }
+}
@@ -1,24 +1,30 @@
-use std::io::BufRead;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use arga_core::crdt::{Frame, Version};
+use arga_core::models::logs::Action;
 use arga_core::models::{
-    Action,
+    entity_hash,
     DatasetVersion,
     NomenclaturalActAtom,
     NomenclaturalActOperation,
     NomenclaturalActType,
     NomenclaturalActTypeError,
 };
-use arga_core::schema;
+use arga_core::names::{self, ParsedName};
+use arga_core::{models, schema};
 use bigdecimal::BigDecimal;
 use diesel::*;
+use indicatif::{ProgressBar, ProgressStyle};
 use quick_xml::events::{BytesEnd, BytesStart, Event};
 use quick_xml::name::QName;
 use quick_xml::Reader;
+use rayon::prelude::*;
+use serde::Serialize;
 use tracing::info;
-use xxhash_rust::xxh3::Xxh3;
 
 use super::formatting::{
     BibCitation,
@@ -39,11 +45,18 @@ use super::formatting::{
     Uri,
     Uuid,
 };
-// use crate::data::oplogger::get_pool;
+use crate::data::plazi::get_pool;
 use crate::data::plazi::formatting::{Span, SpanStack};
+use crate::data::plazi::materials::{CitingTreatment, MaterialOperations};
+use crate::data::plazi::publications::PublicationOperations;
+use crate::data::pipeline::insert_operations;
 use crate::data::{Error, ParseError};
 
 
+/// The amount of files to parse in parallel and save in a single transaction
+const BATCH_SIZE: usize = 1000;
+
+
 /// Parse a section and it's hierarchy
 pub trait ParseSection<T>
 where
@@ -91,9 +104,32 @@ pub struct Document {
     // pub extent: Extent,
     // pub classification: Classification,
     // pub identifiers: Vec<Identifiers>,
+    pub mods: Mods,
     pub treatments: Vec<Treatment>,
 }
 
+/// The bibliographic metadata of the publication a document was extracted from.
+///
+/// Plazi includes a MODS record in every document describing the source publication
+/// which is more detailed than the document attributes, such as the journal and pages.
+#[derive(Debug, Default)]
+pub struct Mods {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub date_issued: Option<String>,
+    pub publisher: Option<String>,
+    pub journal: Option<String>,
+    pub volume: Option<String>,
+    pub issue: Option<String>,
+    /// The date of the journal issue, used when the record has no issue date
+    pub part_date: Option<String>,
+    pub page_start: Option<String>,
+    pub page_end: Option<String>,
+    pub doi: Option<String>,
+    pub url: Option<String>,
+    pub classification: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct Author {
     pub name: String,
@@ -370,46 +406,275 @@ pub struct Authority {
 #[derive(Debug)]
 pub struct Caption;
 
-#[derive(Debug)]
-pub struct MaterialsCitation;
+/// A specimen cited in a treatment.
+///
+/// Plazi marks up the details of the specimen as attributes on the citation element
+/// so we only need the start tag, the children are the verbatim citation text.
+#[derive(Debug, Default)]
+pub struct MaterialsCitation {
+    pub id: Option<String>,
+    pub http_uri: Option<String>,
+    pub gbif_occurrence_id: Option<String>,
+
+    pub specimen_code: Option<String>,
+    pub collection_code: Option<String>,
+    pub type_status: Option<String>,
+    pub specimen_count: Option<String>,
+
+    pub collector_name: Option<String>,
+    pub collecting_date: Option<String>,
+
+    pub country: Option<String>,
+    pub state_province: Option<String>,
+    pub county: Option<String>,
+    pub municipality: Option<String>,
+    pub location: Option<String>,
+    pub latitude: Option<String>,
+    pub longitude: Option<String>,
+    pub elevation: Option<String>,
+}
 
 #[derive(Debug)]
 pub struct Footnote;
 
 
-pub fn import(input_dir: PathBuf, dataset_version: DatasetVersion) -> Result<(), Error> {
+/// The contents of a treatment file
+struct ParsedFile {
+    documents: Vec<Document>,
+    /// The citations and text sections keyed by the uri of the treatment
+    contents: HashMap<String, TreatmentContent>,
+}
+
+/// The parts of a treatment that are gathered by scanning the whole file
+#[derive(Debug, Default)]
+pub struct TreatmentContent {
+    pub citations: Vec<MaterialsCitation>,
+    pub sections: Vec<TextSection>,
+}
+
+/// The plain text of a top level treatment section such as the description or diagnosis
+#[derive(Debug)]
+pub struct TextSection {
+    pub section_type: String,
+    pub text: String,
+}
+
+impl TextSection {
+    /// Append text to the section. Text is trimmed when parsed so we add the whitespace
+    /// back between words unless it's followed by punctuation
+    fn push_text(&mut self, text: &str) {
+        let punctuated = text.starts_with([',', '.', ';', ':', ')', ']']);
+        if !self.text.is_empty() && !self.text.ends_with(['\n', '(', '[']) && !punctuated {
+            self.text.push(' ');
+        }
+        self.text.push_str(text);
+    }
+}
+
+/// A file that couldn't be parsed during an import
+#[derive(Debug, Serialize)]
+pub struct FileError {
+    pub path: String,
+    /// The element that caused the failure if known
+    pub element: Option<String>,
+    pub reason: String,
+}
+
+impl FileError {
+    fn new(path: &Path, err: Error) -> FileError {
+        let element = match &err {
+            Error::Parsing(ParseError::UnexpectedElement(name)) => Some(name.clone()),
+            Error::Parsing(ParseError::NotFound(name)) => Some(name.clone()),
+            _ => None,
+        };
+
+        let reason = match err {
+            Error::Parsing(err) => err.to_string(),
+            err => format!("{err:?}"),
+        };
+
+        FileError {
+            path: path.to_string_lossy().to_string(),
+            element,
+            reason,
+        }
+    }
+}
+
+
+/// Import the nomenclatural acts from every treatment XML file in a directory.
+///
+/// Files are parsed in parallel and imported in batches. Once a batch is saved the
+/// files in it are appended to the checkpoint so that an interrupted import can be
+/// resumed by running it again with the same checkpoint. Files that fail to parse are
+/// written to the report instead of aborting the import and are retried on the next run.
+pub fn import(
+    input_dir: PathBuf,
+    dataset_version: DatasetVersion,
+    checkpoint: PathBuf,
+    report: PathBuf,
+) -> Result<(), Error> {
     info!("Enumerating files in '{input_dir:?}'");
     let files = xml_files(input_dir)?;
 
+    let processed = read_checkpoint(&checkpoint)?;
+    let pending: Vec<PathBuf> = files.into_iter().filter(|file| !processed.contains(file)).collect();
+    info!(pending = pending.len(), processed = processed.len(), "Importing XML files");
+
+    // when resuming the failures of the previous runs are kept and the header isn't repeated
+    let resuming = !processed.is_empty() && report.metadata().is_ok_and(|meta| meta.len() > 0);
+    let report_file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resuming)
+        .truncate(!resuming)
+        .open(&report)?;
+
+    let mut checkpoint = OpenOptions::new().create(true).append(true).open(&checkpoint)?;
+    let mut report = csv::WriterBuilder::new().has_headers(!resuming).from_writer(report_file);
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    let style = ProgressStyle::with_template("[{elapsed_precise}] {bar:40.cyan/blue} {human_pos}/{human_len} @ {per_sec}")
+        .unwrap();
+    let bar = ProgressBar::new(pending.len() as u64).with_style(style);
+
     let mut last_version = Version::new();
-    let mut operations: Vec<NomenclaturalActOperation> = Vec::new();
+    let mut imported = 0;
+    let mut failed = 0;
+
+    for chunk in pending.chunks(BATCH_SIZE) {
+        let results: Vec<(&PathBuf, Result<ParsedFile, FileError>)> =
+            chunk.par_iter().map(|file| (file, parse_file(file))).collect();
+
+        let mut operations: Vec<NomenclaturalActOperation> = Vec::new();
+        let mut materials = MaterialOperations::default();
+        let mut publications = PublicationOperations::default();
+        let mut texts = TreatmentTexts::default();
+        let mut parsed = Vec::new();
+
+        // build the operations in file order so that the versions are deterministic
+        for (file, result) in results {
+            let ParsedFile {
+                documents,
+                mut contents,
+            } = match result {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    report.serialize(err)?;
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            for document in documents {
+                // the acts and treatments link to the publication by the hash of its title
+                let publication_entity_id = entity_hash(&document.title);
+                last_version =
+                    publications.push(publication_entity_id.clone(), &document, dataset_version.id, last_version);
+
+                // specimens are linked to the act by the name the treatment is about
+                for treatment in document.treatments.iter() {
+                    let name = treatment.species_name();
+                    let scientific_name = name.as_ref().map(|name| name.scientific_name.as_str());
+                    let content = contents.remove(&treatment.http_uri).unwrap_or_default();
+
+                    let citing = CitingTreatment {
+                        uri: &treatment.http_uri,
+                        publication_entity_id: &publication_entity_id,
+                        scientific_name,
+                    };
+
+                    for (index, citation) in content.citations.iter().enumerate() {
+                        last_version = materials.push(citation, index, &citing, dataset_version.id, last_version);
+                    }
 
-    for (idx, file) in files.iter().enumerate() {
-        info!("Reading file {idx}: {file:?}");
-        println!("{}", file.as_os_str().to_string_lossy());
+                    texts.push(treatment, name.as_ref(), content.sections, &publication_entity_id, dataset_version.id);
+                }
 
-        for document in read_file(&file)? {
-            let mut hasher = Xxh3::new();
-            hasher.update(document.title.as_bytes());
-            let hash = hasher.digest();
+                let mut frame =
+                    NomenclaturalActFrame::create(dataset_version.id, publication_entity_id, last_version);
 
-            let mut frame = NomenclaturalActFrame::create(dataset_version.id, hash.to_string(), last_version);
+                for atom in Vec::<NomenclaturalActAtom>::from(document) {
+                    frame.push(atom);
+                }
 
-            for atom in Vec::<NomenclaturalActAtom>::from(document) {
-                frame.push(atom);
+                last_version = frame.frame.current;
+                operations.extend(frame.frame.operations);
             }
 
-            last_version = frame.frame.current;
-            operations.extend(frame.frame.operations);
+            parsed.push(file);
         }
-    }
 
-    import_operations(operations)?;
+        // a batch is only recorded in the checkpoint once all of it is saved. if the import
+        // stops before the checkpoint is written the batch is imported again on the next run,
+        // which is safe since the operations reduce to the same atoms and the texts are replaced
+        conn.transaction(|conn| {
+            insert_operations(conn, &operations)?;
+            insert_operations(conn, &publications.publications)?;
+            insert_operations(conn, &materials.organisms)?;
+            insert_operations(conn, &materials.specimens)?;
+            insert_operations(conn, &materials.collections)?;
+            insert_operations(conn, &materials.accessions)?;
+            texts.upsert(conn)
+        })?;
+
+        for file in parsed.iter() {
+            writeln!(checkpoint, "{}", file.to_string_lossy())?;
+        }
+        checkpoint.flush()?;
+        report.flush()?;
+
+        imported += parsed.len();
+        bar.inc(chunk.len() as u64);
+    }
 
-    info!("Importing {} XML files", files.len());
+    bar.finish();
+    info!(imported, failed, "Importing XML files finished");
     Ok(())
 }
 
+/// Get all the files that have already been imported
+fn read_checkpoint(path: &Path) -> Result<HashSet<PathBuf>, Error> {
+    let mut processed = HashSet::new();
+
+    if path.exists() {
+        for line in BufReader::new(File::open(path)?).lines() {
+            processed.insert(PathBuf::from(line?));
+        }
+    }
+
+    Ok(processed)
+}
+
+/// Parse a file into documents, converting parse errors and panics into a report entry
+fn parse_file(path: &PathBuf) -> Result<ParsedFile, FileError> {
+    let parse = || -> Result<ParsedFile, Error> {
+        Ok(ParsedFile {
+            documents: read_file(path)?,
+            contents: read_treatment_contents(path)?,
+        })
+    };
+
+    match std::panic::catch_unwind(parse) {
+        Ok(Ok(parsed)) => Ok(parsed),
+        Ok(Err(err)) => Err(FileError::new(path, err)),
+        Err(panic) => {
+            let reason = match panic.downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => panic.downcast_ref::<String>().cloned().unwrap_or_default(),
+            };
+
+            Err(FileError {
+                path: path.to_string_lossy().to_string(),
+                element: None,
+                reason: format!("panicked: {reason}"),
+            })
+        }
+    }
+}
+
 fn read_file(path: &PathBuf) -> Result<Vec<Document>, Error> {
     let mut documents = Vec::new();
 
@@ -429,24 +694,152 @@ fn read_file(path: &PathBuf) -> Result<Vec<Document>, Error> {
     Ok(documents)
 }
 
-fn parse_mods<T: BufRead>(reader: &mut Reader<T>) -> Result<(), Error> {
+/// Get the material citations and text sections of every treatment in a file keyed by the treatment uri.
+///
+/// Citations can appear in almost any section, most of which the treatment parser skips,
+/// so we scan the file for them separately rather than threading them through every section.
+/// The text of nested sections is included in the top level section containing them.
+fn read_treatment_contents(path: &PathBuf) -> Result<HashMap<String, TreatmentContent>, Error> {
+    let mut contents: HashMap<String, TreatmentContent> = HashMap::new();
+
+    let mut reader = Reader::from_file(path)?;
+    reader.trim_text(true);
+
     let mut buf = Vec::new();
+    let mut treatment_uri = None;
+    let mut section: Option<TextSection> = None;
+    let mut depth = 0;
 
-    // skip mods
     loop {
         match reader.read_event_into(&mut buf)? {
-            Event::End(e) if e.name().as_ref() == b"mods:mods" => break,
+            Event::Start(e) if start_eq(&e, "treatment") => {
+                treatment_uri = Some(parse_attribute(&reader, &e, "httpUri")?);
+            }
+            Event::End(e) if end_eq(&e, "treatment") => treatment_uri = None,
+
+            Event::Start(e) if start_eq(&e, "subSection") || start_eq(&e, "subSubSection") => match section {
+                Some(_) => depth += 1,
+                None => {
+                    section = Some(TextSection {
+                        section_type: parse_attribute_opt(&reader, &e, "type")?.unwrap_or_default(),
+                        text: String::new(),
+                    });
+                }
+            },
+            Event::End(e) if end_eq(&e, "subSection") || end_eq(&e, "subSubSection") => {
+                if depth > 0 {
+                    depth -= 1;
+                }
+                else if let (Some(mut finished), Some(uri)) = (section.take(), &treatment_uri) {
+                    finished.text = finished.text.trim().to_string();
+                    if !finished.text.is_empty() {
+                        contents.entry(uri.clone()).or_default().sections.push(finished);
+                    }
+                }
+            }
+
+            Event::Start(e) if start_eq(&e, "materialsCitation") => {
+                let citation = MaterialsCitation::from_attributes(&reader, &e)?;
+                if let Some(uri) = &treatment_uri {
+                    contents.entry(uri.clone()).or_default().citations.push(citation);
+                }
+            }
+
+            Event::End(e) if end_eq(&e, "paragraph") => {
+                if let Some(section) = &mut section {
+                    section.text.push('\n');
+                }
+            }
+            Event::Text(txt) => {
+                if let Some(section) = &mut section {
+                    section.push_text(&txt.unescape()?);
+                }
+            }
+
+            Event::Eof => break,
             _ => {}
-        }
+        };
     }
 
-    Ok(())
+    Ok(contents)
+}
+
+impl<T: BufRead> ParseSection<T> for Mods {
+    fn parse(reader: &mut Reader<T>, _event: &BytesStart) -> Result<Self, Error> {
+        let mut mods = Mods::default();
+
+        let mut buf = Vec::new();
+        // the path to the current element without the mods namespace. elements that are
+        // only distinguished by their type or unit attribute include it, eg. `detail[volume]`
+        let mut path: Vec<String> = Vec::new();
+
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::End(e) if end_eq(&e, "mods:mods") => break,
+
+                Event::Start(e) => {
+                    let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                    let qualifier = match parse_attribute_opt(reader, &e, "type")? {
+                        Some(value) => Some(value),
+                        None => parse_attribute_opt(reader, &e, "unit")?,
+                    };
+
+                    path.push(match qualifier {
+                        Some(qualifier) => format!("{name}[{qualifier}]"),
+                        None => name,
+                    });
+                }
+                Event::End(_) => {
+                    path.pop();
+                }
+
+                Event::Text(txt) => {
+                    let text = txt.unescape()?.trim().to_string();
+                    if text.is_empty() {
+                        continue;
+                    }
+
+                    let field = match path.join("/").as_str() {
+                        "titleInfo/title" => &mut mods.title,
+                        "originInfo/dateIssued" => &mut mods.date_issued,
+                        "originInfo/publisher" => &mut mods.publisher,
+                        "relatedItem[host]/titleInfo/title" => &mut mods.journal,
+                        "relatedItem[host]/part/detail[volume]/number" => &mut mods.volume,
+                        "relatedItem[host]/part/detail[issue]/number" => &mut mods.issue,
+                        "relatedItem[host]/part/date" => &mut mods.part_date,
+                        "relatedItem[host]/part/extent[page]/start" => &mut mods.page_start,
+                        "relatedItem[host]/part/extent[page]/end" => &mut mods.page_end,
+                        "identifier[DOI]" => &mut mods.doi,
+                        "location/url" => &mut mods.url,
+                        "classification" => &mut mods.classification,
+                        path if path.starts_with("name") && path.ends_with("/namePart") => {
+                            mods.authors.push(text);
+                            continue;
+                        }
+                        _ => continue,
+                    };
+
+                    // text with entities is split into multiple events
+                    match field {
+                        Some(value) => value.push_str(&text),
+                        None => *field = Some(text),
+                    }
+                }
+
+                Event::Eof => return Err(unexpected_element(&Event::Eof)),
+                _ => {}
+            }
+        }
+
+        Ok(mods)
+    }
 }
 
 
 impl<T: BufRead> ParseSection<T> for Document {
     fn parse(reader: &mut Reader<T>, event: &BytesStart) -> Result<Self, Error> {
         let mut treatments = Vec::new();
+        let mut mods = Mods::default();
 
         let mut buf = Vec::new();
 
@@ -454,7 +847,7 @@ impl<T: BufRead> ParseSection<T> for Document {
             match reader.read_event_into(&mut buf)? {
                 Event::End(e) if end_eq(&e, "document") => break,
 
-                Event::Start(e) if start_eq(&e, "mods:mods") => parse_mods(reader)?,
+                Event::Start(e) if start_eq(&e, "mods:mods") => mods = Mods::parse(reader, &e)?,
                 Event::Start(e) if start_eq(&e, "treatment") => treatments.push(Treatment::parse(reader, &e)?),
 
                 // (state, event) => panic!("Unknown element. current_state: {state:?}, event: {event:#?}"),
@@ -464,6 +857,7 @@ impl<T: BufRead> ParseSection<T> for Document {
 
         Ok(Document {
             treatments,
+            mods,
             treatment_id: parse_attribute(reader, event, "docId")?,
             title: parse_attribute(reader, event, "masterDocTitle")?,
             authors: parse_attribute(reader, event, "docAuthor")?,
@@ -701,7 +1095,7 @@ impl<T: BufRead> ParseSection<T> for Treatment {
                 // example: EF654433374BFFFC1F4C73A3FDF1FEDB.xml
                 Event::Text(_e) => continue,
 
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -1309,7 +1703,7 @@ impl<T: BufRead> ParseSection<T> for Nomenclature {
 
                 Event::End(e) if end_eq(&e, "subSubSection") => break,
                 Event::End(e) if end_eq(&e, "subSection") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -1500,7 +1894,7 @@ impl<T: BufRead> ParseSection<T> for TaxonomicName {
                     stack.commit_top();
                     break;
                 }
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -1636,7 +2030,7 @@ impl<T: BufRead> ParseFormat<T> for Citation {
                     stack.push(Span::text(&text));
                 }
                 Event::End(e) if end_eq(&e, "bibRefCitation") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -1718,7 +2112,7 @@ impl<T: BufRead> ParseFormat<T> for BibCitation {
                     stack.push(Span::text(&text));
                 }
                 Event::End(e) if end_eq(&e, "bibCitation") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -1820,7 +2214,7 @@ impl<T: BufRead> ParseFormat<T> for BibRef {
                     stack.push(Span::Text(text));
                 }
                 Event::End(e) if end_eq(&e, "bibRef") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -1869,7 +2263,7 @@ impl<T: BufRead> ParseSection<T> for TaxonomicNameLabel {
 
                 Event::Text(txt) => value = Some(txt.unescape()?.into_owned()),
                 Event::End(e) if end_eq(&e, "taxonomicNameLabel") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -1894,7 +2288,7 @@ impl<T: BufRead> ParseSection<T> for NormalizedToken {
 
                 Event::Text(txt) => value = Some(txt.unescape()?.into_owned()),
                 Event::End(e) if end_eq(&e, "normalizedToken") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -1937,7 +2331,7 @@ impl<T: BufRead> ParseFormat<T> for PageBreakToken {
                 Event::End(e) if end_eq(&e, "pageBreakToken") => {
                     break;
                 }
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -1969,7 +2363,7 @@ impl<T: BufRead> ParseSection<T> for PageStartToken {
 
                 Event::Text(txt) => value = Some(txt.unescape()?.into_owned()),
                 Event::End(e) if end_eq(&e, "pageStartToken") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -2003,7 +2397,7 @@ impl<T: BufRead> ParseSection<T> for Uuid {
 
                 Event::Text(txt) => value = Some(txt.unescape()?.into_owned()),
                 Event::End(e) if end_eq(&e, "uuid") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -2053,7 +2447,7 @@ impl<T: BufRead> ParseSection<T> for Authority {
                 Event::Text(txt) => value = Some(txt.unescape()?.into_owned()),
                 Event::End(e) if end_eq(&e, "authority") => break,
                 Event::End(e) if end_eq(&e, "authorityName") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -2092,7 +2486,9 @@ impl<T: BufRead> ParseSection<T> for Caption {
 }
 
 impl<T: BufRead> ParseSection<T> for MaterialsCitation {
-    fn parse(reader: &mut Reader<T>, _event: &BytesStart) -> Result<Self, Error> {
+    fn parse(reader: &mut Reader<T>, event: &BytesStart) -> Result<Self, Error> {
+        let citation = MaterialsCitation::from_attributes(reader, event)?;
+
         let mut buf = Vec::new();
         let mut depth = 0;
 
@@ -2112,7 +2508,7 @@ impl<T: BufRead> ParseSection<T> for MaterialsCitation {
             }
         }
 
-        Ok(MaterialsCitation)
+        Ok(citation)
     }
 }
 
@@ -2252,7 +2648,7 @@ impl<T: BufRead> ParseFormat<T> for Table {
 
                 Event::Text(txt) => stack.push(Span::text(&txt.unescape()?.into_owned())),
                 Event::End(e) if end_eq(&e, "table") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -2279,7 +2675,7 @@ impl<T: BufRead> ParseFormat<T> for Uri {
 
                 Event::Text(txt) => stack.push(Span::text(&txt.unescape()?.into_owned())),
                 Event::End(e) if end_eq(&e, "uri") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -2800,7 +3196,7 @@ impl<T: BufRead> ParseFormat<T> for FormattedValue {
 
                 Event::End(e) if end_eq(&e, "subSubSection") => break,
                 Event::End(e) if end_eq(&e, "subSection") => break,
-                event => panic!("Unknown element. event: {event:#?}"),
+                event => return Err(unexpected_element(&event)),
             }
         }
 
@@ -2809,19 +3205,101 @@ impl<T: BufRead> ParseFormat<T> for FormattedValue {
 }
 
 
-fn import_operations(operations: Vec<NomenclaturalActOperation>) -> Result<(), Error> {
-    use schema::nomenclatural_act_logs::dsl::*;
+/// The treatments and the text of their sections.
+///
+/// Unlike the other data in a treatment the text isn't versioned so every import replaces
+/// the sections of a treatment with the latest ones parsed.
+#[derive(Debug, Default)]
+struct TreatmentTexts {
+    treatments: Vec<models::Treatment>,
+    sections: Vec<models::TreatmentSection>,
+}
+
+impl TreatmentTexts {
+    fn push(
+        &mut self,
+        treatment: &Treatment,
+        name: Option<&SpeciesName>,
+        sections: Vec<TextSection>,
+        publication_entity_id: &str,
+        dataset_version_id: uuid::Uuid,
+    ) {
+        let entity_id = entity_hash(&treatment.http_uri);
+
+        // the same treatment can be in more than one file of a batch and postgres can't
+        // upsert a row twice in one statement, so the last one parsed replaces the others
+        if self.treatments.iter().any(|existing| existing.entity_id == entity_id) {
+            self.treatments.retain(|existing| existing.entity_id != entity_id);
+            self.sections.retain(|existing| existing.treatment_entity_id != entity_id);
+        }
 
-    let pool = get_pool()?;
-    let mut conn = pool.get()?;
+        for (position, section) in sections.into_iter().enumerate() {
+            self.sections.push(models::TreatmentSection {
+                treatment_entity_id: entity_id.clone(),
+                position: position as i32,
+                section_type: section.section_type,
+                text: section.text,
+            });
+        }
+
+        self.treatments.push(models::Treatment {
+            entity_id,
+            dataset_version_id,
+            publication_entity_id: publication_entity_id.to_string(),
+            treatment_uri: treatment.http_uri.clone(),
+            lsid: Some(treatment.lsid.clone()).filter(|lsid| !lsid.is_empty()),
+            scientific_name: name.map(|name| name.scientific_name.clone()),
+            canonical_name: name.map(|name| name.canonical_name.clone()),
+        });
+    }
+
+    fn upsert(&self, conn: &mut PgConnection) -> QueryResult<()> {
+        use diesel::upsert::excluded;
+        use schema::treatment_sections::dsl as sections;
+        use schema::treatments::dsl::*;
+
+        for chunk in self.treatments.chunks(1000) {
+            diesel::insert_into(treatments)
+                .values(chunk)
+                .on_conflict(entity_id)
+                .do_update()
+                .set((
+                    dataset_version_id.eq(excluded(dataset_version_id)),
+                    publication_entity_id.eq(excluded(publication_entity_id)),
+                    treatment_uri.eq(excluded(treatment_uri)),
+                    lsid.eq(excluded(lsid)),
+                    scientific_name.eq(excluded(scientific_name)),
+                    canonical_name.eq(excluded(canonical_name)),
+                ))
+                .execute(conn)?;
+
+            let ids: Vec<&String> = chunk.iter().map(|treatment| &treatment.entity_id).collect();
+            diesel::delete(sections::treatment_sections.filter(sections::treatment_entity_id.eq_any(ids)))
+                .execute(conn)?;
+        }
 
-    for chunk in operations.chunks(1000) {
-        diesel::insert_into(nomenclatural_act_logs)
-            .values(chunk)
-            .execute(&mut conn)?;
+        for chunk in self.sections.chunks(1000) {
+            diesel::insert_into(sections::treatment_sections)
+                .values(chunk)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+        }
+
+        Ok(())
     }
+}
 
-    Ok(())
+
+/// Describe an element that the parser doesn't know how to handle
+fn unexpected_element(event: &Event) -> Error {
+    let element = match event {
+        Event::Start(e) | Event::Empty(e) => String::from_utf8_lossy(e.name().as_ref()).to_string(),
+        Event::End(e) => format!("/{}", String::from_utf8_lossy(e.name().as_ref())),
+        Event::Eof => "EOF".to_string(),
+        event => format!("{event:?}"),
+    };
+
+    Error::Parsing(ParseError::UnexpectedElement(element))
 }
 
 
@@ -2880,7 +3358,7 @@ impl From<TaxonomicName> for Vec<NomenclaturalActAtom> {
 
         let mut operations = Vec::new();
 
-        match SpeciesName::try_from(value) {
+        match SpeciesName::try_from(&value) {
             Ok(name) => {
                 // match name.act {
                 //     Some(NomenclaturalActType::SpeciesNova) => operations.push(ActedOn("Biota".to_string())),
@@ -2919,6 +3397,42 @@ impl From<TaxonomicName> for Vec<NomenclaturalActAtom> {
 }
 
 
+impl Treatment {
+    /// The name of the taxon in the nomenclature section
+    pub fn species_name(&self) -> Option<SpeciesName> {
+        self.sections.iter().find_map(|section| match section {
+            Section::Nomenclature(Nomenclature { taxon: Some(taxon), .. }) => SpeciesName::try_from(taxon).ok(),
+            _ => None,
+        })
+    }
+}
+
+
+impl MaterialsCitation {
+    /// Get the specimen details from the attributes of a citation start tag
+    fn from_attributes<R>(reader: &Reader<R>, event: &BytesStart) -> Result<Self, Error> {
+        Ok(MaterialsCitation {
+            id: parse_attribute_opt(reader, event, "id")?,
+            http_uri: parse_attribute_opt(reader, event, "httpUri")?,
+            gbif_occurrence_id: parse_attribute_opt(reader, event, "ID-GBIF-Occurrence")?,
+            specimen_code: parse_attribute_opt(reader, event, "specimenCode")?,
+            collection_code: parse_attribute_opt(reader, event, "collectionCode")?,
+            type_status: parse_attribute_opt(reader, event, "typeStatus")?,
+            specimen_count: parse_attribute_opt(reader, event, "specimenCount")?,
+            collector_name: parse_attribute_opt(reader, event, "collectorName")?,
+            collecting_date: parse_attribute_opt(reader, event, "collectingDate")?,
+            country: parse_attribute_opt(reader, event, "country")?,
+            state_province: parse_attribute_opt(reader, event, "stateProvince")?,
+            county: parse_attribute_opt(reader, event, "county")?,
+            municipality: parse_attribute_opt(reader, event, "municipality")?,
+            location: parse_attribute_opt(reader, event, "location")?,
+            latitude: parse_attribute_opt(reader, event, "latitude")?,
+            longitude: parse_attribute_opt(reader, event, "longitude")?,
+            elevation: parse_attribute_opt(reader, event, "elevation")?,
+        })
+    }
+}
+
 impl TaxonomicName {
     pub fn full_name(&self) -> String {
         let mut name = match self.rank.as_ref().map(|s| s.as_str()) {
@@ -2968,39 +3482,82 @@ pub struct SpeciesName {
 }
 
 
-impl TryFrom<TaxonomicName> for SpeciesName {
+impl TryFrom<&TaxonomicName> for SpeciesName {
     type Error = SpeciesNameError;
 
-    fn try_from(value: TaxonomicName) -> Result<Self, Self::Error> {
+    fn try_from(value: &TaxonomicName) -> Result<Self, Self::Error> {
+        // fill in the parts of the name that weren't marked up from the verbatim name
+        let parsed = names::parse(&value.name.to_string());
+
+        let components = ParsedName {
+            genus: value
+                .genus
+                .clone()
+                .or_else(|| parsed.as_ref().map(|name| name.genus.clone()))
+                .ok_or(SpeciesNameError::MissingGenus)?,
+            subgenus: value.subgenus.clone().or_else(|| parsed.as_ref().and_then(|name| name.subgenus.clone())),
+            specific_epithet: Some(
+                value
+                    .species
+                    .clone()
+                    .or_else(|| parsed.as_ref().and_then(|name| name.specific_epithet.clone()))
+                    .ok_or(SpeciesNameError::MissingSpecificEpithet)?,
+            ),
+            infraspecific_epithet: value
+                .subspecies
+                .clone()
+                .or_else(|| parsed.as_ref().and_then(|name| name.infraspecific_epithet.clone())),
+            rank_marker: parsed.as_ref().and_then(|name| name.rank_marker),
+            hybrid: parsed.as_ref().and_then(|name| name.hybrid),
+            qualifier: None,
+            authorship: None,
+            basionym_authorship: None,
+        };
+
         // construct canonical name from parsed name, matched to a taxon
-        let genus = value.genus.ok_or(SpeciesNameError::MissingGenus)?;
-        let specific_epithet = value.species.ok_or(SpeciesNameError::MissingSpecificEpithet)?;
+        let canonical_name = components.canonical_name();
 
-        let canonical_name = match value.subgenus {
-            Some(subgenus) => format!("{} {subgenus} {}", genus, specific_epithet),
-            None => format!("{} {}", genus, specific_epithet),
+        // the authority attribute has both authorships when they weren't marked up separately
+        let (authorship, basionym_authorship) = match &value.authority {
+            Some(authority) => names::parse_authority(authority),
+            None => (None, None),
         };
 
-        let authority = match (value.authority_name, value.authority_year) {
-            (Some(name), Some(year)) => Some(AuthorityName {
-                name,
-                year: year.to_string(),
-            }),
+        let authority_name = value
+            .authority_name
+            .clone()
+            .or_else(|| authorship.clone().map(|auth| auth.authors));
+        let authority_year = value
+            .authority_year
+            .map(|year| year.to_string())
+            .or_else(|| authorship.and_then(|auth| auth.year));
+
+        let authority = match (authority_name, authority_year) {
+            (Some(name), Some(year)) => Some(AuthorityName { name, year }),
             _ => None,
         };
 
-        let basionym_authority = match (value.base_authority_name, value.base_authority_year) {
+        let base_authority_name = value
+            .base_authority_name
+            .clone()
+            .or_else(|| basionym_authorship.clone().map(|auth| auth.authors));
+        let base_authority_year = value
+            .base_authority_year
+            .clone()
+            .or_else(|| basionym_authorship.and_then(|auth| auth.year));
+
+        let basionym_authority = match (base_authority_name, base_authority_year) {
             (Some(name), Some(year)) => Some(AuthorityName { name, year }),
             _ => None,
         };
 
-        let act = match value.status {
+        let act = match &value.status {
             Some(status) => NomenclaturalActType::try_from(status.as_str()).ok(),
             None => None,
         };
         // let status = value.status.ok_or(SpeciesNameError::MissingStatus)?;
         // let act = NomenclaturalActType::try_from(status.as_str())?;
-        let rank = value.rank.ok_or(SpeciesNameError::MissingRank)?;
+        let rank = value.rank.clone().ok_or(SpeciesNameError::MissingRank)?;
 
         // construct scientific name
         let scientific_name = match (&authority, &basionym_authority) {
@@ -0,0 +1,384 @@
+use std::collections::{HashMap, HashSet};
+
+use arga_core::models::{LocationGeneralisation, SensitiveDataRule, SensitiveLocation};
+use arga_core::schema;
+use chrono::Utc;
+use diesel::*;
+use tracing::info;
+use uuid::Uuid;
+
+use super::{Error, ParseError};
+use crate::data::oplogger::get_pool;
+
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Generalisation {
+    /// Round the coordinates to one decimal place
+    TenthDegree,
+    /// Round the coordinates to a whole degree
+    OneDegree,
+    /// Don't publish the coordinates at all
+    Withhold,
+}
+
+impl From<Generalisation> for LocationGeneralisation {
+    fn from(value: Generalisation) -> Self {
+        match value {
+            Generalisation::TenthDegree => LocationGeneralisation::TenthDegree,
+            Generalisation::OneDegree => LocationGeneralisation::OneDegree,
+            Generalisation::Withhold => LocationGeneralisation::Withhold,
+        }
+    }
+}
+
+
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Add a rule generalising the collection locations of a species or dataset
+    AddRule {
+        /// The scientific or canonical name of the species
+        #[arg(long, required_unless_present = "dataset")]
+        name: Option<String>,
+
+        /// The global id of the dataset
+        #[arg(long)]
+        dataset: Option<String>,
+
+        #[arg(long, value_enum)]
+        generalisation: Generalisation,
+
+        /// Why the locations are sensitive
+        #[arg(long)]
+        reason: Option<String>,
+    },
+
+    /// Remove a sensitive data rule
+    RemoveRule { id: Uuid },
+
+    /// Generalise the loaded collection events with the current rules, restoring the
+    /// precise coordinates of events that no longer match any rule
+    Apply,
+}
+
+pub fn process_command(command: &Command) {
+    match command {
+        Command::AddRule {
+            name,
+            dataset,
+            generalisation,
+            reason,
+        } => add_rule(name.as_deref(), dataset.as_deref(), (*generalisation).into(), reason.clone()).unwrap(),
+        Command::RemoveRule { id } => remove_rule(id).unwrap(),
+        Command::Apply => apply().unwrap(),
+    }
+}
+
+
+/// Add a sensitive data rule.
+///
+/// A name can match more than one name record, such as homonyms in different kingdoms, in
+/// which case a rule is added for each of them to err on the side of withholding too much.
+fn add_rule(
+    name: Option<&str>,
+    dataset: Option<&str>,
+    generalisation: LocationGeneralisation,
+    reason: Option<String>,
+) -> Result<(), Error> {
+    use schema::{datasets, names, sensitive_data_rules};
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    let dataset_id = match dataset {
+        Some(dataset) => Some(
+            datasets::table
+                .filter(datasets::global_id.eq(dataset))
+                .select(datasets::id)
+                .get_result::<Uuid>(&mut conn)
+                .optional()?
+                .ok_or_else(|| ParseError::NotFound(dataset.to_string()))?,
+        ),
+        None => None,
+    };
+
+    let name_ids = match name {
+        Some(name) => {
+            let ids = names::table
+                .filter(names::scientific_name.eq(name).or(names::canonical_name.eq(name)))
+                .select(names::id)
+                .load::<Uuid>(&mut conn)?;
+
+            if ids.is_empty() {
+                return Err(ParseError::NotFound(name.to_string()).into());
+            }
+            ids.into_iter().map(Some).collect()
+        }
+        None => vec![None],
+    };
+
+    let rules: Vec<SensitiveDataRule> = name_ids
+        .into_iter()
+        .map(|name_id| SensitiveDataRule {
+            id: Uuid::new_v4(),
+            name_id,
+            dataset_id,
+            generalisation,
+            reason: reason.clone(),
+            created_at: Utc::now(),
+        })
+        .collect();
+
+    diesel::insert_into(sensitive_data_rules::table)
+        .values(&rules)
+        .execute(&mut conn)?;
+
+    for rule in rules {
+        info!(id = ?rule.id, name_id = ?rule.name_id, dataset_id = ?rule.dataset_id, "Sensitive data rule added");
+    }
+    info!("Run `apply` to generalise the loaded collection events");
+    Ok(())
+}
+
+fn remove_rule(id: &Uuid) -> Result<(), Error> {
+    use schema::sensitive_data_rules;
+
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    let removed = diesel::delete(sensitive_data_rules::table.filter(sensitive_data_rules::id.eq(id))).execute(&mut conn)?;
+    if removed == 0 {
+        return Err(ParseError::NotFound(id.to_string()).into());
+    }
+
+    info!(?id, "Sensitive data rule removed. Run `apply` to restore the loaded collection events");
+    Ok(())
+}
+
+
+/// Generalise the loaded collection events with the current rules.
+///
+/// The precise coordinates are moved into `sensitive_locations` before an event is first
+/// generalised and every later generalisation is derived from them, which means a rule can
+/// be relaxed or removed without reimporting the dataset. Dataset rules are matched through
+/// the operation logs as the reduced events don't record which dataset they came from.
+/// A rule with both a name and a dataset only matches events of that name in that dataset.
+fn apply() -> Result<(), Error> {
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    conn.transaction(|conn| {
+        sql_query(
+            "CREATE TEMPORARY TABLE sensitive_matches ON COMMIT DROP AS
+             SELECT entity_id, max(generalisation) AS generalisation FROM (
+                 SELECT collection_events.entity_id, rules.generalisation
+                 FROM collection_events
+                 JOIN sensitive_data_rules rules ON rules.name_id = collection_events.name_id
+                 WHERE rules.dataset_id IS NULL OR EXISTS (
+                     SELECT 1 FROM collection_event_logs logs
+                     JOIN dataset_versions ON dataset_versions.id = logs.dataset_version_id
+                     WHERE logs.entity_id = collection_events.entity_id
+                     AND dataset_versions.dataset_id = rules.dataset_id
+                 )
+                 UNION ALL
+                 SELECT logs.entity_id, rules.generalisation
+                 FROM collection_event_logs logs
+                 JOIN dataset_versions ON dataset_versions.id = logs.dataset_version_id
+                 JOIN sensitive_data_rules rules ON rules.dataset_id = dataset_versions.dataset_id
+                 WHERE rules.name_id IS NULL
+             ) matches
+             GROUP BY entity_id",
+        )
+        .execute(conn)?;
+
+        let restored = sql_query(
+            "UPDATE collection_events
+             SET latitude = sensitive_locations.latitude,
+                 longitude = sensitive_locations.longitude,
+                 location_generalisation = NULL
+             FROM sensitive_locations
+             WHERE sensitive_locations.entity_id = collection_events.entity_id
+             AND NOT EXISTS (SELECT 1 FROM sensitive_matches WHERE sensitive_matches.entity_id = collection_events.entity_id)",
+        )
+        .execute(conn)?;
+
+        // only remove the precise locations of loaded events as the reducer saves them before loading
+        sql_query(
+            "DELETE FROM sensitive_locations
+             WHERE EXISTS (SELECT 1 FROM collection_events WHERE collection_events.entity_id = sensitive_locations.entity_id)
+             AND NOT EXISTS (SELECT 1 FROM sensitive_matches WHERE sensitive_matches.entity_id = sensitive_locations.entity_id)",
+        )
+        .execute(conn)?;
+
+        sql_query(
+            "INSERT INTO sensitive_locations (entity_id, latitude, longitude)
+             SELECT collection_events.entity_id, collection_events.latitude, collection_events.longitude
+             FROM collection_events
+             JOIN sensitive_matches ON sensitive_matches.entity_id = collection_events.entity_id
+             WHERE collection_events.location_generalisation IS NULL
+             ON CONFLICT (entity_id) DO UPDATE SET latitude = excluded.latitude, longitude = excluded.longitude",
+        )
+        .execute(conn)?;
+
+        // rounding numerics matches the generalisation used when reducing, rounding floats rounds half to even
+        let generalised = sql_query(
+            "UPDATE collection_events
+             SET latitude = CASE
+                     WHEN sensitive_locations.longitude IS NULL THEN NULL
+                     WHEN sensitive_matches.generalisation = 'tenth_degree' THEN round(sensitive_locations.latitude::numeric, 1)::float8
+                     WHEN sensitive_matches.generalisation = 'one_degree' THEN round(sensitive_locations.latitude::numeric)::float8
+                 END,
+                 longitude = CASE
+                     WHEN sensitive_locations.latitude IS NULL THEN NULL
+                     WHEN sensitive_matches.generalisation = 'tenth_degree' THEN round(sensitive_locations.longitude::numeric, 1)::float8
+                     WHEN sensitive_matches.generalisation = 'one_degree' THEN round(sensitive_locations.longitude::numeric)::float8
+                 END,
+                 location_generalisation = sensitive_matches.generalisation
+             FROM sensitive_matches
+             JOIN sensitive_locations ON sensitive_locations.entity_id = sensitive_matches.entity_id
+             WHERE collection_events.entity_id = sensitive_matches.entity_id",
+        )
+        .execute(conn)?;
+
+        info!(restored, generalised, "Sensitive data rules applied");
+        Ok::<(), Error>(())
+    })
+}
+
+
+/// The sensitive data rules that apply when reducing collection events
+#[derive(Debug, Default)]
+pub struct Rules {
+    names: HashMap<String, LocationGeneralisation>,
+    dataset_versions: HashMap<Uuid, LocationGeneralisation>,
+    names_in_versions: HashMap<(String, Uuid), LocationGeneralisation>,
+}
+
+impl Rules {
+    /// Load every rule keyed by the names and dataset versions they match.
+    /// Rules with both a name and a dataset are keyed by both so that they only match when both do
+    pub fn load(conn: &mut PgConnection) -> Result<Rules, Error> {
+        use schema::{dataset_versions, names, sensitive_data_rules as rules};
+
+        let mut loaded = Rules::default();
+
+        let name_rules = rules::table
+            .inner_join(names::table)
+            .filter(rules::dataset_id.is_null())
+            .select((names::scientific_name, names::canonical_name, rules::generalisation))
+            .load::<(String, String, LocationGeneralisation)>(conn)?;
+
+        for (scientific_name, canonical_name, generalisation) in name_rules {
+            insert_strictest(&mut loaded.names, scientific_name, generalisation);
+            insert_strictest(&mut loaded.names, canonical_name, generalisation);
+        }
+
+        let dataset_rules = rules::table
+            .inner_join(dataset_versions::table.on(rules::dataset_id.eq(dataset_versions::dataset_id.nullable())))
+            .filter(rules::name_id.is_null())
+            .select((dataset_versions::id, rules::generalisation))
+            .load::<(Uuid, LocationGeneralisation)>(conn)?;
+
+        for (dataset_version_id, generalisation) in dataset_rules {
+            insert_strictest(&mut loaded.dataset_versions, dataset_version_id, generalisation);
+        }
+
+        let name_in_dataset_rules = rules::table
+            .inner_join(names::table)
+            .inner_join(dataset_versions::table.on(rules::dataset_id.eq(dataset_versions::dataset_id.nullable())))
+            .select((
+                names::scientific_name,
+                names::canonical_name,
+                dataset_versions::id,
+                rules::generalisation,
+            ))
+            .load::<(String, String, Uuid, LocationGeneralisation)>(conn)?;
+
+        for (scientific_name, canonical_name, dataset_version_id, generalisation) in name_in_dataset_rules {
+            insert_strictest(&mut loaded.names_in_versions, (scientific_name, dataset_version_id), generalisation);
+            insert_strictest(&mut loaded.names_in_versions, (canonical_name, dataset_version_id), generalisation);
+        }
+
+        Ok(loaded)
+    }
+
+    /// Get the strictest generalisation for an event with the name and dataset versions
+    pub fn find(&self, scientific_name: Option<&str>, versions: &HashSet<Uuid>) -> Option<LocationGeneralisation> {
+        let by_name = scientific_name.and_then(|name| self.names.get(name));
+        let by_dataset = versions.iter().filter_map(|version| self.dataset_versions.get(version));
+        let by_both = scientific_name.into_iter().flat_map(|name| {
+            versions
+                .iter()
+                .filter_map(move |version| self.names_in_versions.get(&(name.to_string(), *version)))
+        });
+        by_name.into_iter().chain(by_dataset).chain(by_both).max().copied()
+    }
+}
+
+fn insert_strictest<K: std::hash::Hash + Eq>(
+    rules: &mut HashMap<K, LocationGeneralisation>,
+    key: K,
+    generalisation: LocationGeneralisation,
+) {
+    rules
+        .entry(key)
+        .and_modify(|existing| *existing = (*existing).max(generalisation))
+        .or_insert(generalisation);
+}
+
+
+/// Save the precise coordinates of generalised collection events
+pub fn save_locations(conn: &mut PgConnection, locations: &[SensitiveLocation]) -> Result<(), Error> {
+    use diesel::upsert::excluded;
+    use schema::sensitive_locations::dsl::*;
+
+    for chunk in locations.chunks(1000) {
+        diesel::insert_into(sensitive_locations)
+            .values(chunk)
+            .on_conflict(entity_id)
+            .do_update()
+            .set((latitude.eq(excluded(latitude)), longitude.eq(excluded(longitude))))
+            .execute(conn)?;
+    }
+
+    info!(total = locations.len(), "Precise locations saved");
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_name_and_dataset_rules_independently() {
+        let version = Uuid::new_v4();
+        let mut rules = Rules::default();
+        rules.names.insert("Cacatua leadbeateri".to_string(), LocationGeneralisation::TenthDegree);
+        rules.dataset_versions.insert(version, LocationGeneralisation::OneDegree);
+
+        let versions = HashSet::from([version]);
+        let other = HashSet::from([Uuid::new_v4()]);
+
+        assert_eq!(rules.find(Some("Cacatua leadbeateri"), &other), Some(LocationGeneralisation::TenthDegree));
+        assert_eq!(rules.find(Some("Cacatua galerita"), &versions), Some(LocationGeneralisation::OneDegree));
+        assert_eq!(rules.find(Some("Cacatua leadbeateri"), &versions), Some(LocationGeneralisation::OneDegree));
+        assert_eq!(rules.find(None, &other), None);
+    }
+
+    #[test]
+    fn matches_rules_with_a_name_and_dataset_on_both() {
+        let version = Uuid::new_v4();
+        let mut rules = Rules::default();
+        rules
+            .names_in_versions
+            .insert(("Cacatua leadbeateri".to_string(), version), LocationGeneralisation::Withhold);
+
+        let versions = HashSet::from([version]);
+        let other = HashSet::from([Uuid::new_v4()]);
+
+        assert_eq!(rules.find(Some("Cacatua leadbeateri"), &versions), Some(LocationGeneralisation::Withhold));
+        assert_eq!(rules.find(Some("Cacatua leadbeateri"), &other), None);
+        assert_eq!(rules.find(Some("Cacatua galerita"), &versions), None);
+        assert_eq!(rules.find(None, &versions), None);
+    }
+}
@@ -0,0 +1,124 @@
+//! Helpers for cleaning up the values found in the datasets the importers read.
+
+use chrono::NaiveDate;
+
+
+/// Why a date couldn't be parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateError {
+    /// The date only has a year or a year and month which the operation logs can't represent
+    Partial,
+    /// The date isn't in any of the supported formats
+    Invalid,
+}
+
+
+/// Get the trimmed value, treating blanks as missing
+pub fn non_empty(value: &Option<String>) -> Option<String> {
+    value.as_deref().and_then(non_empty_str)
+}
+
+/// Get the trimmed value, treating blanks as missing
+pub fn non_empty_str(value: &str) -> Option<String> {
+    let value = value.trim();
+    match value.is_empty() {
+        true => None,
+        false => Some(value.to_string()),
+    }
+}
+
+/// Get the trimmed value, treating blanks and the placeholders datasets like NCBI use
+/// for missing values as missing
+pub fn non_placeholder(value: &Option<String>) -> Option<String> {
+    let value = non_empty(value)?;
+    match value.to_lowercase().as_str() {
+        "missing" | "not applicable" | "not collected" | "not provided" | "unknown" | "na" | "n/a" => None,
+        _ => Some(value),
+    }
+}
+
+/// Parse a date in any of the formats found in the datasets.
+///
+/// Most dates are YYYY-MM-DD, sometimes with a time, but older records use YYYY/MM/DD,
+/// DD/MM/YYYY, DD-MM-YYYY or DD-Mon-YYYY. Dates with only a year or month are partial
+/// since they can't be represented in the operation logs.
+pub fn parse_date(value: &str) -> Result<NaiveDate, DateError> {
+    let value = value.trim();
+    let date = value.split(['T', ' ']).next().unwrap_or(value);
+
+    for format in ["%Y-%m-%d", "%Y/%m/%d", "%d/%m/%Y", "%d-%b-%Y", "%d-%m-%Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(date, format) {
+            return Ok(date);
+        }
+    }
+
+    let parts: Vec<&str> = date.split(['-', '/']).collect();
+    let is_partial = match parts.as_slice() {
+        [year] => year.len() == 4 && year.parse::<i32>().is_ok(),
+        [first, second] => [first, second].iter().all(|part| part.parse::<u32>().is_ok()),
+        _ => false,
+    };
+
+    match is_partial {
+        true => Err(DateError::Partial),
+        false => Err(DateError::Invalid),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn blank_values_are_missing() {
+        assert_eq!(non_empty(&None), None);
+        assert_eq!(non_empty(&Some("".to_string())), None);
+        assert_eq!(non_empty(&Some("  \t".to_string())), None);
+        assert_eq!(non_empty(&Some(" value ".to_string())), Some("value".to_string()));
+    }
+
+    #[test]
+    fn placeholders_are_missing() {
+        assert_eq!(non_placeholder(&None), None);
+        assert_eq!(non_placeholder(&Some(" ".to_string())), None);
+        assert_eq!(non_placeholder(&Some("Not Collected".to_string())), None);
+        assert_eq!(non_placeholder(&Some("missing".to_string())), None);
+        assert_eq!(non_placeholder(&Some("N/A".to_string())), None);
+        assert_eq!(non_placeholder(&Some(" Nauru ".to_string())), Some("Nauru".to_string()));
+    }
+
+    #[test]
+    fn parses_every_date_format() {
+        assert_eq!(parse_date("2020-03-04"), Ok(date(2020, 3, 4)));
+        assert_eq!(parse_date("2020/03/04"), Ok(date(2020, 3, 4)));
+        assert_eq!(parse_date("04/03/2020"), Ok(date(2020, 3, 4)));
+        assert_eq!(parse_date("04-03-2020"), Ok(date(2020, 3, 4)));
+        assert_eq!(parse_date("04-Mar-2020"), Ok(date(2020, 3, 4)));
+    }
+
+    #[test]
+    fn ignores_the_time() {
+        assert_eq!(parse_date("2020-03-04T10:20:30Z"), Ok(date(2020, 3, 4)));
+        assert_eq!(parse_date(" 2020-03-04 10:20 "), Ok(date(2020, 3, 4)));
+    }
+
+    #[test]
+    fn partial_dates_are_distinguished() {
+        assert_eq!(parse_date("2020"), Err(DateError::Partial));
+        assert_eq!(parse_date("2020-03"), Err(DateError::Partial));
+        assert_eq!(parse_date("03/2020"), Err(DateError::Partial));
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        assert_eq!(parse_date(""), Err(DateError::Invalid));
+        assert_eq!(parse_date("spring 2020"), Err(DateError::Invalid));
+        assert_eq!(parse_date("2020-02-30"), Err(DateError::Invalid));
+        assert_eq!(parse_date("20"), Err(DateError::Invalid));
+    }
+}
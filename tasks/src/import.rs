@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+
+use arga_core::models::DatasetVersion;
+use chrono::{DateTime, Utc};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::data::ncbi::{assemblies, biosamples, sra};
+use crate::data::oplogger::{self, create_dataset_version, find_database_id, nomenclatural_acts, specimens, taxa};
+use crate::data::pipeline::{Mode, Pipeline};
+use crate::data::{bpa, Error, ParseError};
+
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Source {
+    /// A taxonomy CSV
+    Taxa,
+    /// A nomenclatural acts CSV
+    NomenclaturalActs,
+    /// A specimens and collection events CSV
+    Specimens,
+    /// A BPA CSV
+    Bpa,
+    /// An NCBI biosamples XML
+    NcbiBiosamples,
+    /// An NCBI assembly_summary.txt
+    NcbiAssemblySummary,
+    /// An NCBI datasets assembly_data_report.jsonl
+    NcbiAssemblyReport,
+    /// An NCBI SRA run info CSV
+    NcbiSraRuns,
+}
+
+
+pub struct ImportOptions<'a> {
+    pub source: Source,
+    pub path: PathBuf,
+    pub dataset_id: &'a str,
+    pub version: &'a str,
+    pub created_at: Option<&'a str>,
+    pub dry_run: bool,
+    pub report: PathBuf,
+}
+
+
+/// Import a file into the operation logs under a new dataset version.
+///
+/// A dry run goes through the same importer without creating the dataset version or
+/// inserting any operations so that the counts can be checked before committing to them.
+pub fn import(options: ImportOptions) {
+    tracing_subscriber::fmt().init();
+
+    if let Err(err) = run_import(options) {
+        error!(?err, "Import failed");
+        std::process::exit(1);
+    }
+}
+
+/// Remove every operation imported under a dataset version
+pub fn rollback(dataset_id: &str, version: &str) {
+    tracing_subscriber::fmt().init();
+
+    if let Err(err) = oplogger::rollback::rollback(dataset_id, version) {
+        error!(?err, "Rollback failed");
+        std::process::exit(1);
+    }
+}
+
+
+fn run_import(options: ImportOptions) -> Result<(), Error> {
+    let created_at = match options.created_at {
+        Some(created_at) => DateTime::parse_from_rfc3339(created_at).map_err(ParseError::from)?.to_utc(),
+        None => Utc::now(),
+    };
+
+    let (mut pipeline, dataset_version) = match options.dry_run {
+        true => {
+            let dataset_version = DatasetVersion {
+                id: Uuid::new_v4(),
+                dataset_id: find_database_id(options.dataset_id)?,
+                version: options.version.to_string(),
+                created_at,
+                imported_at: Utc::now(),
+            };
+            (Pipeline::new(Mode::DryRun), dataset_version)
+        }
+        false => {
+            let dataset_version =
+                create_dataset_version(options.dataset_id, options.version, &created_at.to_rfc3339())?;
+            (Pipeline::new(Mode::Import), dataset_version)
+        }
+    };
+
+    info!(source = ?options.source, path = ?options.path, dataset_version_id = ?dataset_version.id, "Importing");
+    let path = options.path;
+
+    match options.source {
+        Source::Taxa => taxa::process(path, dataset_version, &mut pipeline)?,
+        Source::NomenclaturalActs => nomenclatural_acts::process(path, dataset_version, &mut pipeline)?,
+        Source::Specimens => specimens::process(path, dataset_version, &mut pipeline)?,
+        Source::Bpa => bpa::import(path, dataset_version, &options.report, &mut pipeline)?,
+        Source::NcbiBiosamples => biosamples::import(path, dataset_version, options.report, &mut pipeline)?,
+        Source::NcbiAssemblySummary => assemblies::import(
+            path,
+            assemblies::AssemblyFormat::Summary,
+            dataset_version,
+            &options.report,
+            &mut pipeline,
+        )?,
+        Source::NcbiAssemblyReport => assemblies::import(
+            path,
+            assemblies::AssemblyFormat::Datasets,
+            dataset_version,
+            &options.report,
+            &mut pipeline,
+        )?,
+        Source::NcbiSraRuns => sra::import(path, dataset_version, &options.report, &mut pipeline)?,
+    }
+
+    pipeline.report();
+    Ok(())
+}
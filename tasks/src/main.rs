@@ -1,10 +1,14 @@
+use std::path::PathBuf;
+
 use clap::Parser;
 
 pub mod admin;
 pub mod data;
 pub mod dataset;
+pub mod import;
 pub mod reports;
 pub mod search;
+pub mod views;
 
 
 /// The ARGA backend
@@ -44,10 +48,47 @@ enum Commands {
         isolation_context: Vec<String>,
     },
 
+    /// Import a file into the operation logs under a new dataset version
+    Import {
+        #[arg(value_enum)]
+        source: import::Source,
+        /// The path to the file being imported
+        path: PathBuf,
+        /// The global ID of the dataset to import the data as
+        #[arg(long)]
+        dataset: String,
+        /// The version of the dataset being imported
+        #[arg(long)]
+        version: String,
+        /// When the dataset version was published as an RFC 3339 date time. Defaults to now
+        #[arg(long)]
+        created_at: Option<String>,
+        /// Count the entities that would be created or updated without importing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// The CSV file to write the records that couldn't be imported into
+        #[arg(long, default_value = "import_report.csv")]
+        report: PathBuf,
+    },
+
+    /// Remove every operation imported under the latest version of a dataset
+    Rollback {
+        /// The global ID of the dataset
+        #[arg(long)]
+        dataset: String,
+        /// The version of the dataset to remove
+        #[arg(long)]
+        version: String,
+    },
+
     /// Perform tasks on raw data sets
     #[command(subcommand)]
     Data(data::Command),
 
+    /// Manage the materialized views
+    #[command(subcommand)]
+    Views(views::Command),
+
     /// Create reports related to the database
     #[command(subcommand)]
     Reports(reports::Command),
@@ -62,8 +103,27 @@ fn main() {
     match &cli.command {
         Commands::CreateAdmin { name, email, password } => admin::create_admin(name, email, password),
         Commands::Search(command) => search::process_command(command),
+        Commands::Import {
+            source,
+            path,
+            dataset,
+            version,
+            created_at,
+            dry_run,
+            report,
+        } => import::import(import::ImportOptions {
+            source: *source,
+            path: path.clone(),
+            dataset_id: dataset,
+            version,
+            created_at: created_at.as_deref(),
+            dry_run: *dry_run,
+            report: report.clone(),
+        }),
+        Commands::Rollback { dataset, version } => import::rollback(dataset, version),
         Commands::Data(command) => data::process_command(command),
         Commands::Reports(command) => reports::process_command(command),
+        Commands::Views(command) => views::process_command(command),
         Commands::Dataset {
             worker,
             dataset,
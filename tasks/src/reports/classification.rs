@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use arga_core::comparison::{self, Conflict, ConflictKind, TaxonNode};
+use arga_core::schema::{datasets, taxa};
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::*;
+use tracing::info;
+use uuid::Uuid;
+
+use super::taxa::ReportFormat;
+use super::Error;
+
+
+/// Compare the classification of a dataset with another dataset.
+///
+/// Every taxon in the dataset is aligned by name with a taxon in the other dataset and
+/// reported when they disagree on the parent, rank, status or authorship, or when the other
+/// dataset doesn't have the taxon at all. The conflicts are ordered by name so that the
+/// disagreements about a taxon are next to each other.
+pub fn compare_report(
+    dataset: &str,
+    other_dataset: &str,
+    output: Option<PathBuf>,
+    format: ReportFormat,
+) -> Result<(), Error> {
+    let url = arga_core::get_database_url();
+    let manager = ConnectionManager::<PgConnection>::new(url);
+    let mut pool = Pool::builder().build(manager)?;
+
+    let taxa = classification_nodes(&mut pool, dataset)?;
+    let other_taxa = classification_nodes(&mut pool, other_dataset)?;
+
+    info!(dataset, total = taxa.len(), other_dataset, other_total = other_taxa.len(), "Comparing classifications");
+    let conflicts = comparison::compare(&taxa, &other_taxa);
+
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+
+    match format {
+        ReportFormat::Csv => write_csv(writer, &conflicts)?,
+        ReportFormat::Json => serde_json::to_writer_pretty(writer, &conflicts)?,
+    }
+
+    let mut totals: BTreeMap<ConflictKind, usize> = BTreeMap::new();
+    for conflict in &conflicts {
+        *totals.entry(conflict.kind).or_default() += 1;
+    }
+    for (kind, total) in totals {
+        info!(%kind, total, "Conflicts");
+    }
+
+    info!(total = conflicts.len(), "Comparing classifications finished");
+    Ok(())
+}
+
+
+fn write_csv(writer: Box<dyn Write>, conflicts: &[Conflict]) -> Result<(), Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "kind",
+        "canonical_name",
+        "rank",
+        "value",
+        "other_value",
+        "taxon_id",
+        "other_taxon_id",
+    ])?;
+
+    for conflict in conflicts {
+        writer.write_record([
+            conflict.kind.to_string(),
+            conflict.canonical_name.clone(),
+            conflict.rank.to_string(),
+            conflict.value.clone().unwrap_or_default(),
+            conflict.other_value.clone().unwrap_or_default(),
+            conflict.taxon_id.to_string(),
+            conflict.other_taxon_id.map(|id| id.to_string()).unwrap_or_default(),
+        ])?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+
+/// Get every taxon in a dataset with the name of their parent
+fn classification_nodes(
+    pool: &mut Pool<ConnectionManager<PgConnection>>,
+    global_id: &str,
+) -> Result<Vec<TaxonNode>, Error> {
+    let mut conn = pool.get()?;
+
+    let dataset_id = datasets::table
+        .filter(datasets::global_id.eq(global_id))
+        .select(datasets::id)
+        .get_result::<Uuid>(&mut conn)
+        .optional()?
+        .ok_or_else(|| Error::NotFound(global_id.to_string()))?;
+
+    let parent = diesel::alias!(taxa as parent);
+
+    let nodes = taxa::table
+        .left_join(parent.on(taxa::parent_id.eq(parent.field(taxa::id).nullable())))
+        .filter(taxa::dataset_id.eq(dataset_id))
+        .select((
+            taxa::id,
+            taxa::scientific_name,
+            taxa::canonical_name,
+            taxa::authorship,
+            taxa::rank,
+            taxa::status,
+            parent.field(taxa::canonical_name).nullable(),
+        ))
+        .load::<TaxonNode>(&mut conn)?;
+
+    Ok(nodes)
+}
@@ -1,3 +1,4 @@
+pub mod classification;
 pub mod taxa;
 
 use std::path::PathBuf;
@@ -5,26 +6,69 @@ use std::path::PathBuf;
 
 #[derive(clap::Subcommand)]
 pub enum Command {
-    /// Create a report showing taxon match success/failure
+    /// Create a report reconciling the names in a taxa CSV with the names in the database
     MatchTaxa {
         /// The taxa CSV file
         input: String,
-    }
+
+        /// Where to write the report. Defaults to stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// The format of the report
+        #[arg(long, value_enum, default_value_t = taxa::ReportFormat::Csv)]
+        format: taxa::ReportFormat,
+
+        /// Where to write the summary of match types by family as CSV. Defaults to stderr
+        #[arg(long)]
+        summary: Option<PathBuf>,
+    },
+
+    /// Create a report of where the classification of two datasets disagree
+    CompareClassifications {
+        /// The global id of the dataset to compare
+        dataset: String,
+
+        /// The global id of the dataset to compare it with
+        other_dataset: String,
+
+        /// Where to write the report. Defaults to stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// The format of the report
+        #[arg(long, value_enum, default_value_t = taxa::ReportFormat::Csv)]
+        format: taxa::ReportFormat,
+    },
 }
 
 pub fn process_command(command: &Command) {
     match command {
-        Command::MatchTaxa { input } => taxa::match_report(PathBuf::from(input)).unwrap(),
+        Command::MatchTaxa {
+            input,
+            output,
+            format,
+            summary,
+        } => taxa::match_report(PathBuf::from(input), output.clone(), *format, summary.clone()).unwrap(),
+        Command::CompareClassifications {
+            dataset,
+            other_dataset,
+            output,
+            format,
+        } => classification::compare_report(dataset, other_dataset, output.clone(), *format).unwrap(),
     }
 }
 
 
 #[derive(Debug)]
 pub enum Error {
+    NotFound(String),
     Io(std::io::Error),
     Csv(csv::Error),
     Database(diesel::result::Error),
     Pool(diesel::r2d2::PoolError),
+    Json(serde_json::Error),
+    Data(Box<crate::data::Error>),
 }
 
 impl From<std::io::Error> for Error {
@@ -50,3 +94,15 @@ impl From<diesel::r2d2::PoolError> for Error {
         Self::Pool(value)
     }
 }
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<crate::data::Error> for Error {
+    fn from(value: crate::data::Error) -> Self {
+        Self::Data(Box::new(value))
+    }
+}
@@ -1,12 +1,224 @@
-use std::{path::PathBuf, io};
-use diesel::{PgConnection, r2d2::{ConnectionManager, Pool}};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use arga_core::names::matching::{self, NameMatch, NameQuery};
+use arga_core::schema::{taxon_names, taxonomic_backbones};
+use arga_core::schema_gnl::species;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::*;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
 
-use crate::data::ncbi::name_matcher::{NameRecord, match_names};
 use super::Error;
+use crate::data::ncbi::name_matcher::resolve_names;
+
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+
+/// A name from the taxa CSV. The family is used to group the summary when it's provided
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InputRecord {
+    scientific_name: String,
+    family: Option<String>,
+}
+
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ReconciliationType {
+    Exact,
+    Canonical,
+    Stemmed,
+    Fuzzy,
+    Synonym,
+    Ambiguous,
+    None,
+}
+
+impl ReconciliationType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReconciliationType::Exact => "exact",
+            ReconciliationType::Canonical => "canonical",
+            ReconciliationType::Stemmed => "stemmed",
+            ReconciliationType::Fuzzy => "fuzzy",
+            ReconciliationType::Synonym => "synonym",
+            ReconciliationType::Ambiguous => "ambiguous",
+            ReconciliationType::None => "none",
+        }
+    }
+}
+
+impl From<matching::MatchType> for ReconciliationType {
+    fn from(value: matching::MatchType) -> Self {
+        match value {
+            matching::MatchType::Exact => ReconciliationType::Exact,
+            matching::MatchType::Canonical => ReconciliationType::Canonical,
+            matching::MatchType::Stemmed => ReconciliationType::Stemmed,
+            matching::MatchType::Fuzzy => ReconciliationType::Fuzzy,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct Classification {
+    kingdom: Option<String>,
+    phylum: Option<String>,
+    class: Option<String>,
+    order: Option<String>,
+    family: Option<String>,
+    genus: Option<String>,
+}
+
+impl From<serde_json::Value> for Classification {
+    fn from(value: serde_json::Value) -> Self {
+        let rank = |ranks: &[&str]| {
+            ranks
+                .iter()
+                .find_map(|rank| value.get(rank).and_then(|name| name.as_str()))
+                .map(|name| name.to_string())
+        };
+
+        // the botanical ranks are used instead when a taxon doesn't have the zoological ones
+        Classification {
+            kingdom: rank(&["kingdom", "regnum"]),
+            phylum: rank(&["phylum", "division"]),
+            class: rank(&["class", "classis"]),
+            order: rank(&["order", "ordo"]),
+            family: rank(&["family", "familia"]),
+            genus: rank(&["genus"]),
+        }
+    }
+}
 
 
-pub fn match_report(path: PathBuf) -> Result<(), Error> {
-    let mut records: Vec<NameRecord> = Vec::new();
+#[derive(Debug, Serialize)]
+struct Candidate {
+    name_id: Uuid,
+    scientific_name: String,
+    match_type: ReconciliationType,
+    confidence: f64,
+}
+
+impl From<&NameMatch> for Candidate {
+    fn from(value: &NameMatch) -> Self {
+        Candidate {
+            name_id: value.name.id,
+            scientific_name: value.name.scientific_name.clone(),
+            match_type: value.match_type.into(),
+            confidence: value.confidence,
+        }
+    }
+}
+
+
+/// How a name from the input was reconciled with the names in the database
+#[derive(Debug, Serialize)]
+struct Reconciliation {
+    input: String,
+    match_type: ReconciliationType,
+    confidence: Option<f64>,
+    name_id: Option<Uuid>,
+    matched_name: Option<String>,
+    accepted_name_id: Option<Uuid>,
+    accepted_name: Option<String>,
+    classification: Option<Classification>,
+    /// Every candidate when the best candidates are equally likely
+    candidates: Vec<Candidate>,
+}
+
+impl Reconciliation {
+    fn new(query: NameQuery, matches: &[NameMatch]) -> Reconciliation {
+        let mut reconciliation = Reconciliation {
+            input: query.verbatim,
+            match_type: ReconciliationType::None,
+            confidence: None,
+            name_id: None,
+            matched_name: None,
+            accepted_name_id: None,
+            accepted_name: None,
+            classification: None,
+            candidates: vec![],
+        };
+
+        match matching::best_match(matches) {
+            Some(best) => {
+                let accepted = best.accepted.as_ref().unwrap_or(&best.name);
+
+                reconciliation.match_type = match best.accepted {
+                    Some(_) => ReconciliationType::Synonym,
+                    None => best.match_type.into(),
+                };
+                reconciliation.confidence = Some(best.confidence);
+                reconciliation.name_id = Some(best.name.id);
+                reconciliation.matched_name = Some(best.name.scientific_name.clone());
+                reconciliation.accepted_name_id = Some(accepted.id);
+                reconciliation.accepted_name = Some(accepted.scientific_name.clone());
+            }
+            None if !matches.is_empty() => {
+                reconciliation.match_type = ReconciliationType::Ambiguous;
+                reconciliation.candidates = matches.iter().map(Candidate::from).collect();
+            }
+            None => {}
+        }
+
+        reconciliation
+    }
+}
+
+
+/// The match types of the names in a family
+#[derive(Debug, Default, Serialize)]
+struct FamilySummary {
+    family: String,
+    total: usize,
+    exact: usize,
+    canonical: usize,
+    stemmed: usize,
+    fuzzy: usize,
+    synonym: usize,
+    ambiguous: usize,
+    none: usize,
+}
+
+impl FamilySummary {
+    fn add(&mut self, match_type: ReconciliationType) {
+        self.total += 1;
+        match match_type {
+            ReconciliationType::Exact => self.exact += 1,
+            ReconciliationType::Canonical => self.canonical += 1,
+            ReconciliationType::Stemmed => self.stemmed += 1,
+            ReconciliationType::Fuzzy => self.fuzzy += 1,
+            ReconciliationType::Synonym => self.synonym += 1,
+            ReconciliationType::Ambiguous => self.ambiguous += 1,
+            ReconciliationType::None => self.none += 1,
+        }
+    }
+}
+
+
+/// Reconcile the names in a taxa CSV with the names in the database.
+///
+/// Every name gets its match type, the matched and accepted names along with the
+/// classification of the accepted name in the default backbone, and the candidates
+/// when it is ambiguous. A summary of the match types by family is written separately.
+pub fn match_report(
+    path: PathBuf,
+    output: Option<PathBuf>,
+    format: ReportFormat,
+    summary: Option<PathBuf>,
+) -> Result<(), Error> {
+    let mut records: Vec<InputRecord> = Vec::new();
     for row in csv::Reader::from_path(&path)?.deserialize() {
         records.push(row?);
     }
@@ -15,13 +227,288 @@ pub fn match_report(path: PathBuf) -> Result<(), Error> {
     let manager = ConnectionManager::<PgConnection>::new(url);
     let mut pool = Pool::builder().build(manager)?;
 
-    let mut writer = csv::Writer::from_writer(io::stdout());
-    let matched = match_names(&records, &mut pool);
+    info!(total = records.len(), "Reconciling names");
+    let mut reconciliations = Vec::with_capacity(records.len());
+
+    for chunk in records.chunks(10_000) {
+        let names: Vec<String> = chunk.iter().map(|record| record.scientific_name.clone()).collect();
+        let results = resolve_names(&names, &mut pool)?;
+
+        let mut chunk_reconciliations: Vec<Reconciliation> =
+            results.into_iter().map(|(query, matches)| Reconciliation::new(query, &matches)).collect();
 
-    for record in records {
-        let has_match = matched.contains_key(&record.scientific_name);
-        writer.write_record(&[&record.scientific_name, &has_match.to_string()])?;
+        let accepted_ids: Vec<Uuid> = chunk_reconciliations.iter().filter_map(|rec| rec.accepted_name_id).collect();
+        let classifications = classifications(&mut pool, &accepted_ids)?;
+
+        for reconciliation in chunk_reconciliations.iter_mut() {
+            if let Some(id) = &reconciliation.accepted_name_id {
+                reconciliation.classification = classifications.get(id).cloned();
+            }
+        }
+        reconciliations.extend(chunk_reconciliations);
     }
 
+    let families = family_summaries(&records, &reconciliations);
+
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+
+    match format {
+        ReportFormat::Csv => write_csv(writer, &reconciliations)?,
+        ReportFormat::Json => serde_json::to_writer_pretty(writer, &reconciliations)?,
+    }
+
+    let summary_writer: Box<dyn Write> = match summary {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stderr()),
+    };
+
+    let mut summary_writer = csv::Writer::from_writer(summary_writer);
+    for family in families.values() {
+        summary_writer.serialize(family)?;
+    }
+    summary_writer.flush()?;
+
+    info!(total = reconciliations.len(), families = families.len(), "Reconciling names finished");
     Ok(())
 }
+
+
+fn write_csv(writer: Box<dyn Write>, reconciliations: &[Reconciliation]) -> Result<(), Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "input",
+        "match_type",
+        "confidence",
+        "name_id",
+        "matched_name",
+        "accepted_name_id",
+        "accepted_name",
+        "kingdom",
+        "phylum",
+        "class",
+        "order",
+        "family",
+        "genus",
+        "candidates",
+    ])?;
+
+    for rec in reconciliations {
+        let class = rec.classification.clone().unwrap_or_default();
+
+        let candidates = format_candidates(&rec.candidates);
+
+        writer.write_record([
+            rec.input.clone(),
+            rec.match_type.as_str().to_string(),
+            rec.confidence.map(|confidence| format!("{confidence:.2}")).unwrap_or_default(),
+            rec.name_id.map(|id| id.to_string()).unwrap_or_default(),
+            rec.matched_name.clone().unwrap_or_default(),
+            rec.accepted_name_id.map(|id| id.to_string()).unwrap_or_default(),
+            rec.accepted_name.clone().unwrap_or_default(),
+            class.kingdom.unwrap_or_default(),
+            class.phylum.unwrap_or_default(),
+            class.class.unwrap_or_default(),
+            class.order.unwrap_or_default(),
+            class.family.unwrap_or_default(),
+            class.genus.unwrap_or_default(),
+            candidates,
+        ])?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+
+/// Candidates are listed as `name (match type confidence)` separated by a pipe
+fn format_candidates(candidates: &[Candidate]) -> String {
+    candidates
+        .iter()
+        .map(|candidate| {
+            let match_type = candidate.match_type.as_str();
+            format!("{} ({match_type} {:.2})", candidate.scientific_name, candidate.confidence)
+        })
+        .collect::<Vec<String>>()
+        .join(" | ")
+}
+
+
+/// Group the match types by the family in the input, falling back to the family of the match
+fn family_summaries(records: &[InputRecord], reconciliations: &[Reconciliation]) -> BTreeMap<String, FamilySummary> {
+    let mut families: BTreeMap<String, FamilySummary> = BTreeMap::new();
+    for (record, reconciliation) in records.iter().zip(reconciliations.iter()) {
+        let family = record
+            .family
+            .clone()
+            .or_else(|| reconciliation.classification.as_ref().and_then(|class| class.family.clone()))
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let entry = families.entry(family.clone()).or_insert_with(|| FamilySummary {
+            family,
+            ..Default::default()
+        });
+        entry.add(reconciliation.match_type);
+    }
+    families
+}
+
+
+/// Get the classification of the names from the taxa in the default backbone
+fn classifications(
+    pool: &mut Pool<ConnectionManager<PgConnection>>,
+    name_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Classification>, Error> {
+    let mut conn = pool.get()?;
+
+    let rows = taxon_names::table
+        .inner_join(species::table)
+        .inner_join(taxonomic_backbones::table.on(species::dataset_id.eq(taxonomic_backbones::dataset_id)))
+        .filter(taxonomic_backbones::is_default.eq(true))
+        .filter(taxon_names::name_id.eq_any(name_ids))
+        .select((taxon_names::name_id, species::classification))
+        .load::<(Uuid, serde_json::Value)>(&mut conn)?;
+
+    Ok(rows.into_iter().map(|(name_id, classification)| (name_id, classification.into())).collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use arga_core::models::Name;
+    use matching::MatchType;
+
+    use super::*;
+
+    fn name(scientific_name: &str) -> Name {
+        Name {
+            id: Uuid::new_v4(),
+            scientific_name: scientific_name.to_string(),
+            canonical_name: scientific_name.to_string(),
+            authorship: None,
+            entity_id: None,
+        }
+    }
+
+    fn name_match(name: Name, match_type: MatchType, confidence: f64) -> NameMatch {
+        NameMatch {
+            name,
+            match_type,
+            confidence,
+            accepted: None,
+        }
+    }
+
+    fn record(family: Option<&str>) -> InputRecord {
+        InputRecord {
+            scientific_name: "Eucalyptus globulus".to_string(),
+            family: family.map(String::from),
+        }
+    }
+
+    fn reconciliation(match_type: ReconciliationType, family: Option<&str>) -> Reconciliation {
+        let mut reconciliation = Reconciliation::new(NameQuery::new("Eucalyptus globulus"), &[]);
+        reconciliation.match_type = match_type;
+        reconciliation.classification = family.map(|family| Classification {
+            family: Some(family.to_string()),
+            ..Default::default()
+        });
+        reconciliation
+    }
+
+    #[test]
+    fn it_reconciles_the_best_match() {
+        let globulus = name("Eucalyptus globulus");
+        let matches = [name_match(globulus.clone(), MatchType::Stemmed, 0.9)];
+        let reconciliation = Reconciliation::new(NameQuery::new("Eucalyptus globula"), &matches);
+
+        assert_eq!(reconciliation.input, "Eucalyptus globula");
+        assert_eq!(reconciliation.match_type, ReconciliationType::Stemmed);
+        assert_eq!(reconciliation.confidence, Some(0.9));
+        assert_eq!(reconciliation.name_id, Some(globulus.id));
+        assert_eq!(reconciliation.accepted_name_id, Some(globulus.id));
+        assert!(reconciliation.candidates.is_empty());
+    }
+
+    #[test]
+    fn it_reconciles_synonyms_to_the_accepted_name() {
+        let synonym = name("Eucalyptus maidenii");
+        let accepted = name("Eucalyptus globulus");
+
+        let mut matched = name_match(synonym.clone(), MatchType::Exact, 1.0);
+        matched.accepted = Some(accepted.clone());
+        let reconciliation = Reconciliation::new(NameQuery::new("Eucalyptus maidenii"), &[matched]);
+
+        assert_eq!(reconciliation.match_type, ReconciliationType::Synonym);
+        assert_eq!(reconciliation.name_id, Some(synonym.id));
+        assert_eq!(reconciliation.matched_name.as_deref(), Some("Eucalyptus maidenii"));
+        assert_eq!(reconciliation.accepted_name_id, Some(accepted.id));
+        assert_eq!(reconciliation.accepted_name.as_deref(), Some("Eucalyptus globulus"));
+    }
+
+    #[test]
+    fn it_lists_candidates_when_ambiguous() {
+        let matches = [
+            name_match(name("Acacia dealbata"), MatchType::Fuzzy, 0.8),
+            name_match(name("Acacia decurrens"), MatchType::Fuzzy, 0.8),
+        ];
+        let reconciliation = Reconciliation::new(NameQuery::new("Acacia dealbta"), &matches);
+
+        assert_eq!(reconciliation.match_type, ReconciliationType::Ambiguous);
+        assert_eq!(reconciliation.confidence, None);
+        assert_eq!(reconciliation.name_id, None);
+        assert_eq!(reconciliation.candidates.len(), 2);
+    }
+
+    #[test]
+    fn it_reconciles_unmatched_names() {
+        let reconciliation = Reconciliation::new(NameQuery::new("Nonexistus name"), &[]);
+
+        assert_eq!(reconciliation.match_type, ReconciliationType::None);
+        assert_eq!(reconciliation.confidence, None);
+        assert_eq!(reconciliation.name_id, None);
+        assert_eq!(reconciliation.accepted_name_id, None);
+        assert!(reconciliation.candidates.is_empty());
+    }
+
+    #[test]
+    fn it_formats_candidates() {
+        let matches = [
+            name_match(name("Acacia dealbata"), MatchType::Fuzzy, 0.8),
+            name_match(name("Acacia decurrens"), MatchType::Stemmed, 0.756),
+        ];
+        let candidates: Vec<Candidate> = matches.iter().map(Candidate::from).collect();
+
+        assert_eq!(
+            format_candidates(&candidates),
+            "Acacia dealbata (fuzzy 0.80) | Acacia decurrens (stemmed 0.76)"
+        );
+        assert_eq!(format_candidates(&[]), "");
+    }
+
+    #[test]
+    fn it_groups_by_family_with_fallback() {
+        let records = [record(Some("Myrtaceae")), record(None), record(None), record(Some("Myrtaceae"))];
+        let reconciliations = [
+            reconciliation(ReconciliationType::Exact, Some("Fabaceae")),
+            reconciliation(ReconciliationType::Fuzzy, Some("Myrtaceae")),
+            reconciliation(ReconciliationType::None, None),
+            reconciliation(ReconciliationType::Synonym, None),
+        ];
+        let families = family_summaries(&records, &reconciliations);
+
+        assert_eq!(families.keys().collect::<Vec<_>>(), vec!["Myrtaceae", "unknown"]);
+
+        let myrtaceae = &families["Myrtaceae"];
+        assert_eq!(myrtaceae.total, 3);
+        assert_eq!(myrtaceae.exact, 1);
+        assert_eq!(myrtaceae.fuzzy, 1);
+        assert_eq!(myrtaceae.synonym, 1);
+
+        let unknown = &families["unknown"];
+        assert_eq!(unknown.total, 1);
+        assert_eq!(unknown.none, 1);
+    }
+}
@@ -4,6 +4,7 @@ mod specimen;
 mod taxon;
 
 use anyhow::Error;
+use arga_core::names;
 use arga_core::search::{DataType, SearchIndex};
 use chrono::NaiveTime;
 use diesel::r2d2::{ConnectionManager, Pool};
@@ -141,8 +142,14 @@ fn index_names(schema: &Schema, index: &Index) -> Result<(), Error> {
             if let Some(value) = &species.family {
                 doc.add_text(family, value);
             }
-            if let Some(value) = &species.genus {
-                doc.add_text(genus, value);
+            // not every classification has the genus so fall back to the one in the name
+            match &species.genus {
+                Some(value) => doc.add_text(genus, value),
+                None => {
+                    if let Some(name) = names::parse(&species.canonical_name) {
+                        doc.add_text(genus, name.genus);
+                    }
+                }
             }
 
             if let Some(value) = &species.regnum {
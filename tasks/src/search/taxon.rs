@@ -1,6 +1,6 @@
 use anyhow::Error;
 use arga_core::models::{ACCEPTED_NAMES, TaxonomicStatus};
-use arga_core::schema::datasets;
+use arga_core::schema::taxonomic_backbones;
 use arga_core::schema_gnl;
 use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::sql_types::{Nullable, Text, Varchar};
@@ -9,8 +9,6 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 
-pub const ALA_DATASET_ID: &str = "ARGA:TL:0001013";
-
 type PgPool = Pool<ConnectionManager<PgConnection>>;
 
 
@@ -46,7 +44,7 @@ pub fn get_species(pool: &PgPool) -> Result<Vec<SpeciesDoc>, Error> {
     let mut conn = pool.get()?;
 
     let docs = species::table
-        .inner_join(datasets::table.on(species::dataset_id.eq(datasets::id)))
+        .inner_join(taxonomic_backbones::table.on(species::dataset_id.eq(taxonomic_backbones::dataset_id)))
         // .left_join(synonyms::table)
         .select((
             species::id,
@@ -69,7 +67,7 @@ pub fn get_species(pool: &PgPool) -> Result<Vec<SpeciesDoc>, Error> {
             sql::<Nullable<Varchar>>("classification->>'familia'"),
         ))
         .filter(species::status.eq_any(&ACCEPTED_NAMES))
-        .filter(datasets::global_id.eq(ALA_DATASET_ID))
+        .filter(taxonomic_backbones::is_default.eq(true))
         .load::<SpeciesDoc>(&mut conn)?;
 
     Ok(docs)
@@ -0,0 +1,354 @@
+use std::collections::HashSet;
+use std::time::Instant;
+
+use diesel::sql_types::{Bool, Text};
+use diesel::*;
+use tracing::info;
+
+use crate::data::oplogger::get_pool;
+use crate::data::Error;
+
+
+#[derive(clap::Subcommand)]
+pub enum Command {
+    /// Refresh the materialized views in dependency order
+    Refresh {
+        /// Only refresh the views affected by changes to these entities
+        #[arg(long, value_enum)]
+        entity: Vec<EntityType>,
+    },
+
+    /// List the materialized views in the order they are refreshed
+    List {
+        /// Only list the views affected by changes to these entities
+        #[arg(long, value_enum)]
+        entity: Vec<EntityType>,
+    },
+}
+
+pub fn process_command(command: &Command) {
+    tracing_subscriber::fmt().init();
+
+    match command {
+        Command::Refresh { entity } => refresh(&affected_views(entity)).unwrap(),
+        Command::List { entity } => {
+            for view in affected_views(entity) {
+                println!("{}", view.name);
+            }
+        }
+    }
+}
+
+
+/// The entities that can change the data a materialized view is built from
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum EntityType {
+    Datasets,
+    Names,
+    Taxa,
+    NameAttributes,
+    VernacularNames,
+    Specimens,
+    CollectionEvents,
+    Organisms,
+    AccessionEvents,
+    Tissues,
+    Subsamples,
+    Extractions,
+    Sequences,
+    Libraries,
+    SequenceRuns,
+    Assemblies,
+    Annotations,
+    Depositions,
+    Agents,
+    Publications,
+    Projects,
+    DataProducts,
+}
+
+impl EntityType {
+    /// The tables that get written to when importing or reducing the entity
+    fn tables(&self) -> &'static [&'static str] {
+        match self {
+            EntityType::Datasets => &["datasets", "sources"],
+            EntityType::Names => &["names", "taxon_names"],
+            EntityType::Taxa => &["taxa", "taxon_names"],
+            EntityType::NameAttributes => &["name_attributes"],
+            EntityType::VernacularNames => &["vernacular_names"],
+            EntityType::Specimens => &["specimens"],
+            EntityType::CollectionEvents => &["collection_events", "collection_event_logs"],
+            EntityType::Organisms => &["organisms", "organism_logs"],
+            EntityType::AccessionEvents => &["accession_events", "accession_event_logs"],
+            EntityType::Tissues => &["tissues", "tissue_logs"],
+            EntityType::Subsamples => &["subsamples", "subsample_logs"],
+            EntityType::Extractions => &["dna_extracts", "extraction_logs"],
+            EntityType::Sequences => &[
+                "sequences",
+                "sequencing_events",
+                "assembly_events",
+                "annotation_events",
+                "deposition_events",
+            ],
+            EntityType::Libraries => &["libraries", "library_logs"],
+            EntityType::SequenceRuns => &["sequence_runs", "sequence_run_logs"],
+            EntityType::Assemblies => &["assemblies", "assembly_logs"],
+            EntityType::Annotations => &["annotations", "annotation_logs"],
+            EntityType::Depositions => &["depositions", "deposition_logs"],
+            EntityType::Agents => &["agents", "agent_logs"],
+            EntityType::Publications => &["publications", "publication_logs"],
+            EntityType::Projects => &["projects", "project_logs"],
+            EntityType::DataProducts => &["data_products", "data_product_logs"],
+        }
+    }
+}
+
+
+/// A materialized view and what it is built from.
+///
+/// The tables include the tables used by any plain views the materialized view selects
+/// from, such as `sequences` for `markers`, since those don't need refreshing themselves.
+pub struct View {
+    pub name: &'static str,
+    pub tables: &'static [&'static str],
+    pub views: &'static [&'static str],
+}
+
+const fn view(name: &'static str, tables: &'static [&'static str], views: &'static [&'static str]) -> View {
+    View { name, tables, views }
+}
+
+/// Every materialized view in `schema.sql`. Keep this in sync when adding a view
+pub const VIEWS: &[View] = &[
+    view(
+        "sequence_milestones",
+        &["sequences", "sequencing_events", "assembly_events", "annotation_events", "deposition_events", "taxon_names"],
+        &[],
+    ),
+    view(
+        "name_data_summaries",
+        &["names", "specimens", "sequences", "sequencing_events", "assembly_events", "annotation_events"],
+        &[],
+    ),
+    view("taxa_dag", &["taxa"], &[]),
+    view("taxa_dag_down", &["taxa"], &[]),
+    view("taxa_tree", &["taxa"], &["taxa_dag_down"]),
+    view("taxa_tree_stats", &["taxa", "taxon_names"], &["name_data_summaries", "taxa_tree"]),
+    view(
+        "species",
+        &["taxa", "taxon_names", "name_attributes", "vernacular_names"],
+        &["taxa_dag", "name_data_summaries"],
+    ),
+    view("taxon_classification", &[], &["taxa_dag"]),
+    view(
+        "specimen_stats",
+        &[
+            "specimens",
+            "subsamples",
+            "dna_extracts",
+            "sequences",
+            "sequencing_events",
+            "assembly_events",
+            "annotation_events",
+            "accession_events",
+        ],
+        &[],
+    ),
+    view(
+        "overview",
+        &[
+            "sequences",
+            "sequencing_events",
+            "assembly_events",
+            "annotation_events",
+            "deposition_events",
+            "specimens",
+            "sources",
+            "datasets",
+            "name_attributes",
+        ],
+        &[],
+    ),
+    view("collection_event_entities", &["collection_event_logs"], &[]),
+    view("organism_entities", &["organism_logs"], &[]),
+    view("tissue_entities", &["tissue_logs"], &[]),
+    view("accession_event_entities", &["accession_event_logs"], &[]),
+    view("subsample_entities", &["subsample_logs"], &[]),
+    view("extraction_entities", &["extraction_logs"], &[]),
+    view("agent_entities", &["agent_logs"], &[]),
+    view("publication_entities", &["publication_logs"], &[]),
+    view("library_entities", &["library_logs"], &[]),
+    view("sequence_run_entities", &["sequence_run_logs"], &[]),
+    view("assembly_entities", &["assembly_logs"], &[]),
+    view("data_product_entities", &["data_product_logs"], &[]),
+    view("annotation_entities", &["annotation_logs"], &[]),
+    view("deposition_entities", &["deposition_logs"], &[]),
+    view("project_entities", &["project_logs"], &[]),
+];
+
+
+/// Get the views affected by changes to the entities in the order they should be refreshed.
+///
+/// A view is affected when it selects from one of the entity tables or from another
+/// affected view. Every view is affected when no entities are specified.
+pub fn affected_views(entities: &[EntityType]) -> Vec<&'static View> {
+    let ordered = dependency_order();
+    if entities.is_empty() {
+        return ordered;
+    }
+
+    let tables: HashSet<&str> = entities.iter().flat_map(|entity| entity.tables()).copied().collect();
+    let mut affected: HashSet<&str> = HashSet::new();
+
+    // dependencies always come first so a single pass picks up every dependant
+    for view in ordered.iter() {
+        let stale = view.tables.iter().any(|table| tables.contains(table))
+            || view.views.iter().any(|name| affected.contains(name));
+
+        if stale {
+            affected.insert(view.name);
+        }
+    }
+
+    ordered.into_iter().filter(|view| affected.contains(view.name)).collect()
+}
+
+/// Sort the views so that every view comes after the views it selects from
+fn dependency_order() -> Vec<&'static View> {
+    let mut ordered: Vec<&View> = Vec::with_capacity(VIEWS.len());
+    let mut visited = HashSet::new();
+
+    fn visit(view: &'static View, visited: &mut HashSet<&'static str>, ordered: &mut Vec<&'static View>) {
+        if !visited.insert(view.name) {
+            return;
+        }
+        for dependency in view.views.iter().filter_map(|name| VIEWS.iter().find(|view| view.name == *name)) {
+            visit(dependency, visited, ordered);
+        }
+        ordered.push(view);
+    }
+
+    for view in VIEWS {
+        visit(view, &mut visited, &mut ordered);
+    }
+    ordered
+}
+
+
+#[derive(QueryableByName)]
+struct ViewState {
+    #[diesel(sql_type = Bool)]
+    populated: bool,
+    #[diesel(sql_type = Bool)]
+    unique_index: bool,
+}
+
+/// Refresh the views one after the other.
+///
+/// Views are refreshed concurrently when they have a unique index and have already been
+/// populated so that the server can keep reading from them during the refresh.
+pub fn refresh(views: &[&View]) -> Result<(), Error> {
+    let pool = get_pool()?;
+    let mut conn = pool.get()?;
+
+    let started = Instant::now();
+
+    for view in views {
+        let state = sql_query(
+            "SELECT
+                 pg_matviews.ispopulated AS populated,
+                 EXISTS (
+                     SELECT 1 FROM pg_index
+                     WHERE pg_index.indrelid = format('%I', pg_matviews.matviewname)::regclass
+                     AND pg_index.indisunique AND pg_index.indpred IS NULL
+                 ) AS unique_index
+             FROM pg_matviews
+             WHERE pg_matviews.matviewname = $1",
+        )
+        .bind::<Text, _>(view.name)
+        .get_result::<ViewState>(&mut conn)?;
+
+        let concurrently = state.populated && state.unique_index;
+        let query = match concurrently {
+            true => format!("REFRESH MATERIALIZED VIEW CONCURRENTLY {}", view.name),
+            false => format!("REFRESH MATERIALIZED VIEW {}", view.name),
+        };
+
+        let view_started = Instant::now();
+        sql_query(query).execute(&mut conn)?;
+        info!(view = view.name, concurrently, elapsed = ?view_started.elapsed(), "View refreshed");
+    }
+
+    info!(views = views.len(), elapsed = ?started.elapsed(), "Refresh finished");
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(views: &[&View]) -> Vec<&'static str> {
+        views.iter().map(|view| view.name).collect()
+    }
+
+    fn position(views: &[&View], name: &str) -> usize {
+        views.iter().position(|view| view.name == name).unwrap()
+    }
+
+    #[test]
+    fn orders_every_view_after_its_dependencies() {
+        let ordered = dependency_order();
+        assert_eq!(ordered.len(), VIEWS.len());
+
+        for view in &ordered {
+            for dependency in view.views {
+                assert!(
+                    position(&ordered, dependency) < position(&ordered, view.name),
+                    "{dependency} must be refreshed before {}",
+                    view.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_dependency_is_a_known_view() {
+        for view in VIEWS {
+            for dependency in view.views {
+                assert!(VIEWS.iter().any(|known| known.name == *dependency), "unknown view {dependency}");
+            }
+        }
+    }
+
+    #[test]
+    fn refreshes_every_view_without_entities() {
+        assert_eq!(affected_views(&[]).len(), VIEWS.len());
+    }
+
+    #[test]
+    fn refreshes_specimen_stats_after_accession_events() {
+        let affected = names(&affected_views(&[EntityType::AccessionEvents]));
+        assert_eq!(affected, vec!["specimen_stats", "accession_event_entities"]);
+    }
+
+    #[test]
+    fn refreshes_dependant_views_after_taxa() {
+        let affected = affected_views(&[EntityType::Taxa]);
+        let names = names(&affected);
+
+        for view in ["taxa_dag", "taxa_dag_down", "taxa_tree", "taxa_tree_stats", "species", "taxon_classification"] {
+            assert!(names.contains(&view), "{view} should be refreshed");
+        }
+        assert!(!names.contains(&"specimen_stats"));
+        assert!(position(&affected, "taxa_dag_down") < position(&affected, "taxa_tree"));
+        assert!(position(&affected, "taxa_tree") < position(&affected, "taxa_tree_stats"));
+        assert!(position(&affected, "taxa_dag") < position(&affected, "taxon_classification"));
+    }
+
+    #[test]
+    fn refreshes_only_the_entity_views_of_logs() {
+        let affected = names(&affected_views(&[EntityType::Organisms]));
+        assert_eq!(affected, vec!["organism_entities"]);
+    }
+}
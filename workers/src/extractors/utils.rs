@@ -1,22 +1,9 @@
 use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
-use once_cell::sync::Lazy;
-use regex::Regex;
 use serde::Deserialize;
 use tracing::info;
 
 use crate::error::{Error, ParseError};
 
-// regex features used:
-// - name capture groups (?<>)
-// - non-capture groups (?:)
-// - negative lookahead (?!)
-// const SCIENTIFIC_NAME_REGEX: &str = r"(?<genus>[A-Z][a-z]+) (?:\((?<subgenus>[A-Z][a-z]+)\) )?(?<epithet>[a-z]+ )(?<subepithet>(?!de|van|von|del|le)[a-z]+ )?(?<authority>.*)";
-
-// regex features used:
-// - name capture groups (?<>)
-// - non-capture groups (?:)
-const SCIENTIFIC_NAME_REGEX: &str = r"(?<genus>[A-Z][a-z]+) (?:\((?<subgenus>[A-Z][a-z]+)\) )?(?<epithet>[a-z]+ )(?<subepithet>[a-z]+ )?(?<authority>.*)";
-
 #[derive(Debug, Clone)]
 pub struct ScientificNameComponents {
     pub genus: String,
@@ -139,58 +126,18 @@ where
 }
 
 
+/// Decompose a binomial or trinomial scientific name with the name parser in arga-core
 pub fn decompose_scientific_name(scientific_name: &str) -> Option<ScientificNameComponents> {
-    // TODO: bubble regex creation failures
-    static RE: Lazy<Regex> = Lazy::new(|| Regex::new(SCIENTIFIC_NAME_REGEX).expect("Couldn't compile regex"));
-
-    if let Some(groups) = RE.captures(scientific_name) {
-        let genus = groups.name("genus");
-        let subgenus = groups.name("subgenus");
-        let epithet = groups.name("epithet");
-        let subepithet = groups.name("subepithet");
-        let authority = groups.name("authority");
-
-        // decompose the regex match into a struct
-        match (genus, epithet, authority) {
-            (Some(genus), Some(epithet), Some(authority)) => {
-                let mut subspecific_epithet: Option<String> = subepithet.map(|v| v.as_str().trim().into());
-                let mut authority = authority.as_str().trim().into();
-
-                // some authorities have a name like `van de Poll` which will be picked
-                // up by the regex as a subspecies. rather than introducing a dependency like
-                // fancy-regex that can do look-arounds allowing us to avoid matching subspecies
-                // with `van/von/de/del/le` prefixes we instead do it here in the code so that
-                // the guaranteed performance characteristics of the regex crate is retained.
-                //
-                // A big reason for this is the inability to trust the data being processed
-                // which has the potential to become a denial of service attack vector if we
-                // opt for look-around functionality.
-                if let Some(name) = &subspecific_epithet {
-                    match name.as_str() {
-                        // not subspecies, blank out and prepend to authority
-                        "van" | "von" | "de" | "del" | "le" => {
-                            authority = format!("{name} {authority}");
-                            subspecific_epithet = None;
-                        }
-                        // valid subspecies, do nothing
-                        _ => {}
-                    }
-                };
-
-                Some(ScientificNameComponents {
-                    genus: genus.as_str().trim().into(),
-                    subgenus: subgenus.map(|v| v.as_str().trim().into()),
-                    specific_epithet: epithet.as_str().trim().into(),
-                    subspecific_epithet,
-                    authority,
-                })
-            }
-            _ => None,
-        }
-    }
-    else {
-        None
-    }
+    let parsed = arga_core::names::parse(scientific_name)?;
+    let authority = parsed.authority().unwrap_or_default();
+
+    Some(ScientificNameComponents {
+        genus: parsed.genus,
+        subgenus: parsed.subgenus,
+        specific_epithet: parsed.specific_epithet?,
+        subspecific_epithet: parsed.infraspecific_epithet,
+        authority,
+    })
 }
 
 /// Read and deserialize the next million records